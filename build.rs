@@ -0,0 +1,97 @@
+//! Capturing the information about the build shown by --version, so that the
+//! bug reports from different builds can be told apart.
+use std::{
+    env,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    println!("cargo:rustc-env=MLESS_GIT_COMMIT={}", get_git_commit());
+    println!("cargo:rustc-env=MLESS_BUILD_DATE={}", get_build_date());
+    println!("cargo:rustc-env=MLESS_FEATURES={}", get_features());
+    println!(
+        "cargo:rustc-env=MLESS_TARGET={}",
+        env::var("TARGET").unwrap_or_else(|_| "unknown".to_string())
+    );
+}
+
+/// Get the short hash of the commit being built, with a "-dirty" suffix if
+/// there are uncommitted changes, or "unknown" when not building from a git
+/// repository, e.g. from a release tarball.
+fn get_git_commit() -> String {
+    let output = Command::new("git")
+        .args([
+            "describe",
+            "--always",
+            "--dirty",
+            "--abbrev=12",
+            "--exclude",
+            "*",
+        ])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Get the date of the build as YYYY-MM-DD, taken from SOURCE_DATE_EPOCH if
+/// it is set, so that reproducible builds stay reproducible.
+fn get_build_date() -> String {
+    let seconds = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.trim().parse().unwrap_or_default(),
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default(),
+    };
+
+    let (year, month, day) = get_civil_date(seconds / 86400);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Get the (year, month, day) of the day with the given number of days since
+/// 1970-01-01, see <https://howardhinnant.github.io/date_algorithms.html>.
+fn get_civil_date(days: u64) -> (u64, u64, u64) {
+    // Days since 0000-03-01, which makes leap days the last day of the year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = match shifted_month < 10 {
+        true => shifted_month + 3,
+        false => shifted_month - 9,
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Get the enabled cargo features, separated by commas, or "none".
+fn get_features() -> String {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    match features.is_empty() {
+        true => "none".to_string(),
+        false => features.join(", "),
+    }
+}
@@ -0,0 +1,32 @@
+"""Tests for selecting from the numbered list of the hits with --list-ui."""
+
+import pytest_tuitest as tt
+from utils import config_path, STATUS_OK
+
+
+@tt.with_stdin("test, test indeed")
+@tt.with_arguments(["--config", config_path("config_match_test.yaml"), "--list-ui"])
+def test_shows_hits_as_numbered_list(terminal):
+    """Verify that every hit is shown on its own line with its number."""
+    terminal.wait_for_stable_output()
+
+    first_line = terminal.get_string_at(0, 0, 7)
+    assert first_line == "1. test", f"Expected the first hit, found {first_line}"
+
+    second_line = terminal.get_string_at(1, 0, 7)
+    assert second_line == "2. test", f"Expected the second hit, found {second_line}"
+
+
+@tt.with_stdin("test, test indeed")
+@tt.with_arguments(["--config", config_path("config_match_test.yaml"), "--list-ui"])
+def test_selects_hit_with_typed_number(terminal):
+    """Verify that typing the number of a hit and Enter selects it."""
+    terminal.wait_for_stable_output()
+
+    terminal.send("2\r")
+
+    (status, stdout, stderr) = terminal.wait_for_finished()
+
+    assert status == STATUS_OK, "The proces unexpectedly failed"
+    assert stdout == "test", "Returned stdout not as expected"
+    assert stderr == "", "Expected empty stderr, got something"
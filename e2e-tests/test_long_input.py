@@ -20,3 +20,37 @@ def test_displays_first_page_of_long_text(terminal):
 
     last_line = terminal.get_string_at(4, 0, 1)
     assert last_line == "5", f"Expected 5 at the last line, found {last_line}"
+
+
+@tt.with_stdin("1\n2\n3\n4\n5\n6\n7\n8")
+@tt.with_terminal_size(10, 5)
+@tt.with_arguments(["--config", config_path("config_match_test.yaml")])
+def test_scrolls_long_text_by_line(terminal):
+    """Verify that Ctrl+e scrolls the long text by one line."""
+    terminal.wait_for_stable_output()
+
+    ctrl_e = "\x05"
+    terminal.send(ctrl_e)
+    terminal.wait_for_stable_output()
+
+    first_line = terminal.get_string_at(0, 0, 1)
+    assert first_line == "2", f"Expected 2 at the first line, found {first_line}"
+
+
+@tt.with_stdin("1\n2\n3\n4\n5\n6\n7\n8")
+@tt.with_terminal_size(10, 5)
+@tt.with_arguments(["--config", config_path("config_match_test.yaml")])
+def test_does_not_scroll_past_the_end(terminal):
+    """Verify that scrolling stops when the last line of the text is shown."""
+    terminal.wait_for_stable_output()
+
+    page_down = "\x1b[6~"
+    terminal.send(page_down)
+    terminal.send(page_down)
+    terminal.wait_for_stable_output()
+
+    first_line = terminal.get_string_at(0, 0, 1)
+    assert first_line == "4", f"Expected 4 at the first line, found {first_line}"
+
+    last_line = terminal.get_string_at(4, 0, 1)
+    assert last_line == "8", f"Expected 8 at the last line, found {last_line}"
@@ -2,7 +2,7 @@
 
 from pathlib import Path
 
-STATUS_ERROR = 255
+STATUS_ERROR = 1
 STATUS_OK = 0
 
 COLOR_RED = "\033[0;31m"
@@ -0,0 +1,99 @@
+//! Measuring how long the main steps of the selection take, see --bench.
+use std::{
+    io::BufReader,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    app::run::create_hint_generator,
+    configuration::{Config, ModeArgs},
+    error::RunError,
+    hints::HintTarget,
+    modes::{Mode, RegexMode},
+    pager::get_page,
+    rendering::{Renderer, TestScreen},
+};
+
+/// How many times every measured step is repeated.
+const ITERATIONS: u32 = 10;
+
+/// The number of rows of the screen the pages are computed and rendered for.
+const ROWS: usize = 50;
+
+/// The number of columns of the screen the pages are computed and rendered for.
+const COLS: usize = 200;
+
+/// Create an input with the given number of lines containing the kinds of
+/// text the default modes look for.
+fn create_input(lines: usize) -> String {
+    (0..lines)
+        .map(|line| {
+            format!(
+                "{line:>6} src/module_{}/file_{line}.rs:{line}:7 https://example.com/page/{line} \
+                 commit {:07x} \x1b[31mfailed\x1b[0m with some words around them\n",
+                line % 97,
+                line * 7919,
+            )
+        })
+        .collect()
+}
+
+/// Run the given step repeatedly and get the line reporting the average
+/// time it took.
+fn measure(name: &str, mut step: impl FnMut() -> Result<(), RunError>) -> Result<String, RunError> {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        step()?;
+    }
+    let average: Duration = start.elapsed() / ITERATIONS;
+
+    Ok(format!(
+        "{name:<32}{:>12.3} ms\n",
+        average.as_secs_f64() * 1000.0
+    ))
+}
+
+/// Measure the page computation, the matching with every mode of the
+/// config, the hint generation and the rendering without a terminal on
+/// an input with the given number of lines, and get the report.
+pub fn run_benchmarks(lines: usize, config: &Config) -> Result<String, RunError> {
+    let input = create_input(lines);
+    let hint_generator = create_hint_generator(config);
+    let mut report = format!("{lines} lines, {} bytes of input\n", input.len());
+
+    report += &measure("page", || {
+        get_page(&mut BufReader::new(input.as_bytes()), ROWS, COLS, true);
+        Ok(())
+    })?;
+    let page = get_page(&mut BufReader::new(input.as_bytes()), ROWS, COLS, true);
+
+    for mode in &config.modes {
+        let ModeArgs::RegexMode(args) = &mode.args else {
+            continue;
+        };
+        report += &measure(&format!("match input: {}", mode.name), || {
+            RegexMode::find_hits(&input, args, config).map(|_| ())
+        })?;
+    }
+
+    let targets = vec![HintTarget::default(); lines];
+    report += &measure(&format!("hints: {lines}"), || {
+        hint_generator.create_hints(&targets);
+        Ok(())
+    })?;
+
+    for mode in &config.modes {
+        let ModeArgs::RegexMode(args) = &mode.args else {
+            continue;
+        };
+        let mut renderer = Renderer::new(TestScreen::new(ROWS, COLS));
+        report += &measure(&format!("match and render page: {}", mode.name), || {
+            let mode = RegexMode::new(&page, args, hint_generator.as_ref(), config)?;
+
+            renderer.output.output.clear();
+            renderer.render(&page, &mode.get_draw_instructions(), config)
+        })?;
+    }
+
+    Ok(report)
+}
@@ -2,15 +2,17 @@
 
 use std::{
     env::{self, VarError},
-    fs::File,
+    fs,
     path::{Path, PathBuf},
 };
 
+use log::info;
+use serde_yaml::Value;
 use snafu::ResultExt;
 
 use crate::{
-    configuration::Config,
-    error::{ConfigOpenSnafu, ConfigParseSnafu, RunError},
+    configuration::{self, Config},
+    error::{ConfigMergeParseSnafu, ConfigOpenSnafu, ConfigParseSnafu, RunError},
 };
 
 /// Get the absolute path of the file in `$environment_variable_dir/$path` if
@@ -34,16 +36,8 @@ fn get_config_from(
     }
 }
 
-#[allow(
-    clippy::needless_match,
-    reason = "False positive https://github.com/rust-lang/rust-clippy/issues/13574"
-)]
-#[allow(
-    clippy::manual_map,
-    reason = "Using this to be extra explicit about the priority of configs"
-)]
-/// Implementation of [get_config_file_location] with additional arguments
-/// to make testing easier. See [get_config_file_location] for details.
+/// Implementation of [get_config_file_locations] with additional arguments
+/// to make testing easier. See [get_config_file_locations] for details.
 ///
 /// Arguments:
 ///  - `file_exists`: function to use to check if the file with the given path exists.
@@ -51,21 +45,33 @@ fn get_config_from(
 ///  - `get_env_var`: function to use to get the value of the the given environment variable.
 ///    Should return the value of the variable with the given name if the variable can be read
 ///    or an error otherwise.
-fn get_config_file_location_impl(
+fn get_config_file_locations_impl(
     file_exists: &dyn Fn(&str) -> bool,
     get_env_var: &dyn Fn(&str) -> Result<String, VarError>,
-) -> Option<PathBuf> {
+) -> Vec<PathBuf> {
     let get_config_from = |env, path| get_config_from(env, path, file_exists, get_env_var);
 
-    if let Some(path) = get_config_from("XDG_CONFIG_HOME", "mless/mless.yaml") {
-        Some(path)
-    } else if let Some(path) = get_config_from("HOME", ".config/mless/mless.yaml") {
-        Some(path)
-    } else if let Some(path) = get_config_from("HOME", ".mless.yaml") {
-        Some(path)
-    } else {
-        None
-    }
+    // The order here defines the priority of the configs
+    [
+        get_config_from("XDG_CONFIG_HOME", "mless/mless.yaml"),
+        get_config_from("HOME", ".config/mless/mless.yaml"),
+        get_config_from("HOME", ".mless.yaml"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Implementation of [get_config_file_location] with additional arguments
+/// to make testing easier. See [get_config_file_locations_impl] for details
+/// on the arguments.
+fn get_config_file_location_impl(
+    file_exists: &dyn Fn(&str) -> bool,
+    get_env_var: &dyn Fn(&str) -> Result<String, VarError>,
+) -> Option<PathBuf> {
+    get_config_file_locations_impl(file_exists, get_env_var)
+        .into_iter()
+        .next()
 }
 
 /// Get the config file location.
@@ -87,14 +93,47 @@ pub fn get_config_file_location() -> Option<PathBuf> {
     get_config_file_location_impl(&file_exists, &get_env_var)
 }
 
+/// Get the locations of all the config files that exist.
+///
+/// The same paths as in [get_config_file_location] are checked and
+/// the ones that exist are returned in the order of priority, highest
+/// priority first.
+pub fn get_config_file_locations() -> Vec<PathBuf> {
+    let file_exists = |path: &str| Path::new(&path).exists();
+    let get_env_var = |var_name: &str| env::var(var_name);
+
+    get_config_file_locations_impl(&file_exists, &get_env_var)
+}
+
+/// Get the line of the given config text where the given error was found,
+/// prefixed with its number and followed by a caret pointing to the column,
+/// or an empty text if the location of the error is not known.
+fn get_error_snippet(text: &str, error: &configuration::Error) -> String {
+    let Some(location) = error.location() else {
+        return String::new();
+    };
+    let Some(line) = text.lines().nth(location.line().saturating_sub(1)) else {
+        return String::new();
+    };
+
+    let number = location.line().to_string();
+    let margin = " ".repeat(number.len());
+    let caret_offset = " ".repeat(location.column().saturating_sub(1));
+
+    format!("\n{number} | {line}\n{margin} | {caret_offset}^")
+}
+
 /// Load the [Config] from the given path. If path is [None], the default
 /// value for [Config] is returned.
 pub fn load_config(path: Option<PathBuf>) -> Result<Config, RunError> {
     if let Some(path) = path {
-        let file = File::open(path.clone()) //
+        let text = fs::read_to_string(&path) //
             .context(ConfigOpenSnafu { path: path.clone() })?;
-        let config = Config::try_from(file) //
-            .context(ConfigParseSnafu { path })?;
+        let config = Config::try_from(text.as_str()) //
+            .with_context(|error| ConfigParseSnafu {
+                path,
+                snippet: get_error_snippet(&text, error),
+            })?;
 
         return Ok(config);
     }
@@ -104,6 +143,35 @@ pub fn load_config(path: Option<PathBuf>) -> Result<Config, RunError> {
     })
 }
 
+/// Load the [Config] by merging all the config files at the given paths.
+///
+/// The paths should be given in the order of priority, highest priority
+/// first. Values from the files with higher priority override the values
+/// from the files with lower priority. See [configuration::merge_values]
+/// for details.
+pub fn load_merged_config(paths: &[PathBuf]) -> Result<Config, RunError> {
+    let mut merged = Value::Null;
+
+    for path in paths.iter().rev() {
+        let text = fs::read_to_string(path) //
+            .context(ConfigOpenSnafu { path: path.clone() })?;
+        let value = configuration::read_config_value(&text) //
+            .with_context(|error| ConfigParseSnafu {
+                path: path.clone(),
+                snippet: get_error_snippet(&text, error),
+            })?;
+
+        info!("Merging config file {}", path.display());
+        configuration::merge_values(&mut merged, value);
+    }
+
+    if merged.is_null() {
+        return Ok(Config::default());
+    }
+
+    Config::try_from(merged).context(ConfigMergeParseSnafu {})
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,4 +250,52 @@ mod tests {
 
         assert!(config_path.is_none());
     }
+
+    #[test]
+    fn get_config_file_locations_returns_existing_files_in_priority_order() {
+        let existing_files = [
+            "/home/user/.mless.yaml",
+            "/home/user/.xdg_config/mless/mless.yaml",
+        ];
+        let fake_file_exists = |path: &str| existing_files.contains(&path);
+        let fake_get_env_var = |var_name: &str| match var_name {
+            "XDG_CONFIG_HOME" => Ok("/home/user/.xdg_config".to_string()),
+            "HOME" => Ok("/home/user".to_string()),
+            _ => Err(VarError::NotPresent),
+        };
+
+        let config_paths = get_config_file_locations_impl(&fake_file_exists, &fake_get_env_var);
+
+        assert_eq!(
+            config_paths,
+            [
+                PathBuf::from("/home/user/.xdg_config/mless/mless.yaml"),
+                PathBuf::from("/home/user/.mless.yaml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_snippet_points_to_error_location() {
+        let text = "hint_characters: asdf\nmax_hints_policy: frist\n";
+        let error = Config::try_from(text).unwrap_err();
+
+        let snippet = get_error_snippet(text, &error);
+
+        assert_eq!(
+            snippet,
+            "\n2 | max_hints_policy: frist\n  |                   ^"
+        );
+    }
+
+    #[test]
+    fn error_snippet_is_empty_without_location() {
+        let text = "hint_characters: asdf";
+        let error = configuration::Error::UnknownSetting {
+            name: "hint_characers".to_string(),
+            suggestion: String::new(),
+        };
+
+        assert_eq!(get_error_snippet(text, &error), "");
+    }
 }
@@ -0,0 +1,122 @@
+//! The overlay showing what the main loop is doing, for diagnosing e.g. why
+//! a regex does not match without digging through the log file.
+use std::time::Duration;
+
+use crossterm::event::Event;
+
+use crate::rendering::DrawInstruction;
+
+/// What the main loop did last, shown by [DebugOverlay::get_draw_instruction]
+/// while the overlay is shown.
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    /// Whether the overlay is drawn over everything else.
+    shown: bool,
+    /// The last event received from the terminal.
+    last_event: Option<Event>,
+    /// How long drawing the last screen took.
+    last_render: Option<Duration>,
+}
+
+impl DebugOverlay {
+    /// Show the overlay if it is hidden or hide it if it is shown.
+    pub fn toggle(&mut self) {
+        self.shown = !self.shown;
+    }
+
+    /// Check whether the overlay is drawn over everything else.
+    pub fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    /// Remember the given event as the last one received.
+    pub fn event_received(&mut self, event: &Event) {
+        self.last_event = Some(event.clone());
+    }
+
+    /// Remember how long drawing the last screen took.
+    pub fn rendered(&mut self, duration: Duration) {
+        self.last_render = Some(duration);
+    }
+
+    /// Get the entries of the overlay as (name, value) tuples, given the
+    /// name of the active mode, the number of its hits and the index of the
+    /// input line shown at the top of the screen.
+    pub fn get_entries(
+        &self,
+        mode_name: &str,
+        hit_count: usize,
+        first_line: usize,
+    ) -> Vec<(String, String)> {
+        let last_event = match &self.last_event {
+            Some(event) => format!("{event:?}"),
+            None => "none".to_string(),
+        };
+        let last_render = match self.last_render {
+            Some(duration) => format!("{:.3} ms", duration.as_secs_f64() * 1000.0),
+            None => "none".to_string(),
+        };
+
+        vec![
+            ("event".to_string(), last_event),
+            ("mode".to_string(), mode_name.to_string()),
+            ("hits".to_string(), hit_count.to_string()),
+            ("first line".to_string(), first_line.to_string()),
+            ("render".to_string(), last_render),
+        ]
+    }
+
+    /// Get the instruction drawing the overlay, see [DebugOverlay::get_entries].
+    pub fn get_draw_instruction(
+        &self,
+        mode_name: &str,
+        hit_count: usize,
+        first_line: usize,
+    ) -> DrawInstruction {
+        DrawInstruction::DebugOverlay(self.get_entries(mode_name, hit_count, first_line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn entries_show_last_event_and_render() {
+        let mut overlay = DebugOverlay::default();
+        let event = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
+        overlay.event_received(&event);
+        overlay.rendered(Duration::from_micros(1500));
+
+        let entries = overlay.get_entries("urls", 3, 10);
+
+        assert_eq!(entries[0], ("event".to_string(), format!("{event:?}")));
+        assert_eq!(entries[1], ("mode".to_string(), "urls".to_string()));
+        assert_eq!(entries[2], ("hits".to_string(), "3".to_string()));
+        assert_eq!(entries[3], ("first line".to_string(), "10".to_string()));
+        assert_eq!(entries[4], ("render".to_string(), "1.500 ms".to_string()));
+    }
+
+    #[test]
+    fn entries_show_none_before_anything_happens() {
+        let overlay = DebugOverlay::default();
+
+        let entries = overlay.get_entries("urls", 0, 0);
+
+        assert_eq!(entries[0].1, "none");
+        assert_eq!(entries[4].1, "none");
+    }
+
+    #[test]
+    fn toggle_switches_between_shown_and_hidden() {
+        let mut overlay = DebugOverlay::default();
+        assert!(!overlay.is_shown());
+
+        overlay.toggle();
+        assert!(overlay.is_shown());
+
+        overlay.toggle();
+        assert!(!overlay.is_shown());
+    }
+}
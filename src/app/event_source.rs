@@ -0,0 +1,321 @@
+//! Reading of the input events, optionally recording them to a file or
+//! replaying them from one.
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{poll, read, Event};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use crate::error::{
+    RecordingSnafu, ReplayOpenSnafu, ReplayParseSnafu, RunError, TerminalHandlingSnafu,
+};
+
+/// How often the main loop wakes up to check the followed input and the
+/// watched file for changes, while there are any.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What the main loop was woken up by, see [EventSource::wait].
+#[derive(Debug, PartialEq)]
+pub enum Wakeup {
+    /// An input event arrived.
+    Event(Event),
+    /// The requested timeout ran out before any event arrived.
+    Timeout,
+    /// It is time to check the followed input and the watched file.
+    Poll,
+}
+
+/// An input event together with the time it was received.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct RecordedEvent {
+    /// Milliseconds since the start of the recording.
+    time_ms: u64,
+    event: Event,
+}
+
+/// The source of the input events that drive the main loop.
+pub enum EventSource {
+    /// Read the events from the terminal, recording them to the file if given.
+    Terminal(Option<EventRecorder>),
+    /// Replay previously recorded events.
+    Replay(EventReplayer),
+}
+
+impl EventSource {
+    /// Get the next event, waiting at most `timeout` for it or indefinitely
+    /// if `timeout` is [None].
+    ///
+    /// Returns [None] if no event arrived in time.
+    pub fn next_event(&mut self, timeout: Option<Duration>) -> Result<Option<Event>, RunError> {
+        match self {
+            EventSource::Terminal(recorder) => {
+                if let Some(timeout) = timeout {
+                    let has_event = poll(timeout).context(TerminalHandlingSnafu {
+                        operation: "poll events",
+                    })?;
+                    if !has_event {
+                        return Ok(None);
+                    }
+                }
+
+                let event = read().context(TerminalHandlingSnafu {
+                    operation: "read event",
+                })?;
+
+                if let Some(recorder) = recorder {
+                    recorder.record(&event)?;
+                }
+
+                Ok(Some(event))
+            }
+            EventSource::Replay(replayer) => replayer.next_event(timeout),
+        }
+    }
+
+    /// Wait for whatever the main loop needs to handle next: the next event,
+    /// the end of the given `timeout`, e.g. of a key sequence, or the next
+    /// check of the input for changes if `polling` is true.
+    ///
+    /// The terminal events are only read here, on the thread of the main
+    /// loop, so that the commands run in the terminal while selecting get
+    /// the key presses meant for them.
+    pub fn wait(&mut self, timeout: Option<Duration>, polling: bool) -> Result<Wakeup, RunError> {
+        let wait = get_wait_time(timeout, polling);
+
+        match self.next_event(wait)? {
+            Some(event) => Ok(Wakeup::Event(event)),
+            None if timeout.is_some() && timeout <= wait => Ok(Wakeup::Timeout),
+            None => Ok(Wakeup::Poll),
+        }
+    }
+}
+
+/// Get how long to wait for the next event, given the time left until the
+/// requested timeout and whether the input needs to be checked for changes.
+fn get_wait_time(timeout: Option<Duration>, polling: bool) -> Option<Duration> {
+    match (timeout, polling) {
+        (Some(timeout), true) => Some(timeout.min(POLL_INTERVAL)),
+        (None, true) => Some(POLL_INTERVAL),
+        (timeout, false) => timeout,
+    }
+}
+
+/// Writes the received events to a file so that they can be replayed
+/// with [EventReplayer].
+pub struct EventRecorder {
+    file: File,
+    path: PathBuf,
+    start: Instant,
+}
+
+impl EventRecorder {
+    /// Create a recorder that writes to the file with the given path,
+    /// overwriting it if it exists.
+    pub fn create(path: &Path) -> Result<Self, RunError> {
+        info!("Recording events to {}", path.display());
+
+        let file = File::create(path).context(RecordingSnafu { path })?;
+
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            start: Instant::now(),
+        })
+    }
+
+    /// Append the given event to the recording.
+    ///
+    /// Every event is written as a separate YAML document, so that the
+    /// recording is usable even if the application does not exit cleanly.
+    fn record(&mut self, event: &Event) -> Result<(), RunError> {
+        let recorded = RecordedEvent {
+            time_ms: self.start.elapsed().as_millis() as u64,
+            event: event.clone(),
+        };
+
+        let document = serde_yaml::to_string(&recorded)
+            .map_err(std::io::Error::other)
+            .context(RecordingSnafu { path: &self.path })?;
+
+        write!(self.file, "---\n{document}").context(RecordingSnafu { path: &self.path })
+    }
+}
+
+/// Feeds back the events recorded with [EventRecorder], keeping the
+/// original timing between them.
+pub struct EventReplayer {
+    events: VecDeque<RecordedEvent>,
+    start: Instant,
+}
+
+impl EventReplayer {
+    /// Load the recording from the file with the given path.
+    pub fn load(path: &Path) -> Result<Self, RunError> {
+        info!("Replaying events from {}", path.display());
+
+        let recording = fs::read_to_string(path).context(ReplayOpenSnafu { path })?;
+        let events = parse_recording(&recording).context(ReplayParseSnafu { path })?;
+
+        Ok(Self {
+            events,
+            start: Instant::now(),
+        })
+    }
+
+    /// Get the next recorded event, see [EventSource::next_event].
+    fn next_event(&mut self, timeout: Option<Duration>) -> Result<Option<Event>, RunError> {
+        let Some(next) = self.events.front() else {
+            return Err(RunError::ReplayFinished);
+        };
+
+        let due = self.start + Duration::from_millis(next.time_ms);
+        let wait = due.saturating_duration_since(Instant::now());
+
+        match timeout {
+            Some(timeout) if timeout < wait => {
+                thread::sleep(timeout);
+                Ok(None)
+            }
+            _ => {
+                thread::sleep(wait);
+                let event = self.events.pop_front().map(|recorded| recorded.event);
+                debug!("Replaying event {event:?}");
+                Ok(event)
+            }
+        }
+    }
+}
+
+/// Parse the recording made by [EventRecorder].
+fn parse_recording(recording: &str) -> Result<VecDeque<RecordedEvent>, serde_yaml::Error> {
+    serde_yaml::Deserializer::from_str(recording)
+        .map(RecordedEvent::deserialize)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test]
+    fn recorded_events_can_be_parsed() {
+        let events = [
+            RecordedEvent {
+                time_ms: 10,
+                event: Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)),
+            },
+            RecordedEvent {
+                time_ms: 20,
+                event: Event::Resize(80, 24),
+            },
+        ];
+
+        let recording: String = events
+            .iter()
+            .map(|event| format!("---\n{}", serde_yaml::to_string(event).unwrap()))
+            .collect();
+
+        assert_eq!(parse_recording(&recording).unwrap(), events);
+    }
+
+    #[test]
+    fn replay_reports_timeout_before_next_event() {
+        let mut replayer = EventReplayer {
+            events: VecDeque::from([RecordedEvent {
+                time_ms: 60_000,
+                event: Event::Resize(80, 24),
+            }]),
+            start: Instant::now(),
+        };
+
+        let event = replayer.next_event(Some(Duration::from_millis(1))).unwrap();
+
+        assert!(event.is_none());
+    }
+
+    #[test_case(None, false, None; "when_nothing_is_awaited")]
+    #[test_case(Some(50), false, Some(50); "when_only_timeout_is_awaited")]
+    #[test_case(None, true, Some(100); "when_only_polling")]
+    #[test_case(Some(50), true, Some(50); "when_timeout_is_before_poll")]
+    #[test_case(Some(500), true, Some(100); "when_poll_is_before_timeout")]
+    fn get_wait_time_returns_expected_value(
+        timeout_ms: Option<u64>,
+        polling: bool,
+        expected_ms: Option<u64>,
+    ) {
+        let wait = get_wait_time(timeout_ms.map(Duration::from_millis), polling);
+
+        assert_eq!(wait, expected_ms.map(Duration::from_millis));
+    }
+
+    #[test_case(false, Wakeup::Timeout; "when_not_polling")]
+    #[test_case(true, Wakeup::Timeout; "when_timeout_is_before_poll")]
+    fn wait_reports_timeout_before_next_event(polling: bool, expected: Wakeup) {
+        let mut source = EventSource::Replay(EventReplayer {
+            events: VecDeque::from([RecordedEvent {
+                time_ms: 60_000,
+                event: Event::Resize(80, 24),
+            }]),
+            start: Instant::now(),
+        });
+
+        let wakeup = source
+            .wait(Some(Duration::from_millis(1)), polling)
+            .unwrap();
+
+        assert_eq!(wakeup, expected);
+    }
+
+    #[test]
+    fn wait_reports_poll_before_timeout() {
+        let mut source = EventSource::Replay(EventReplayer {
+            events: VecDeque::from([RecordedEvent {
+                time_ms: 60_000,
+                event: Event::Resize(80, 24),
+            }]),
+            start: Instant::now(),
+        });
+
+        let wakeup = source.wait(Some(Duration::from_secs(30)), true).unwrap();
+
+        assert_eq!(wakeup, Wakeup::Poll);
+    }
+
+    #[test]
+    fn wait_returns_event_that_arrives_in_time() {
+        let mut source = EventSource::Replay(EventReplayer {
+            events: VecDeque::from([RecordedEvent {
+                time_ms: 0,
+                event: Event::Resize(80, 24),
+            }]),
+            start: Instant::now(),
+        });
+
+        let wakeup = source.wait(Some(Duration::from_secs(30)), true).unwrap();
+
+        assert_eq!(wakeup, Wakeup::Event(Event::Resize(80, 24)));
+    }
+
+    #[test]
+    fn replay_returns_error_when_finished() {
+        let mut replayer = EventReplayer {
+            events: VecDeque::new(),
+            start: Instant::now(),
+        };
+
+        let result = replayer.next_event(None);
+
+        assert!(matches!(result, Err(RunError::ReplayFinished)));
+    }
+}
@@ -0,0 +1,49 @@
+//! Exiting when the main loop is stuck.
+use std::{fs::OpenOptions, process::exit, thread};
+
+use log::warn;
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM},
+    iterator::Signals,
+};
+use snafu::ResultExt;
+
+use crate::{
+    error::{RunError, SignalHandlerSnafu},
+    rendering::Renderer,
+};
+
+/// Start a thread that restores the terminal and exits as soon as the
+/// process receives SIGINT, SIGTERM, SIGHUP or SIGQUIT.
+///
+/// Since the terminal is in raw mode, pressing Ctrl-C is delivered to the
+/// main loop as a regular key press and does nothing if the main loop is
+/// stuck. The signals still arrive in that case, e.g. from `kill` or
+/// from closing the terminal window.
+pub fn start_force_quit_handler() -> Result<(), RunError> {
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM, SIGHUP, SIGQUIT]).context(SignalHandlerSnafu {})?;
+
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            warn!("Received signal {signal}, forcing the exit");
+
+            // The renderer of the main loop is not available here, but
+            // restoring the terminal does not depend on its state
+            match OpenOptions::new().append(true).open("/dev/tty") {
+                Ok(tty) => {
+                    let mut renderer = Renderer::new(tty);
+                    if let Err(error) = renderer.uninitialize_terminal() {
+                        warn!("Could not uninitialize the terminal: {error}");
+                    }
+                }
+                Err(error) => warn!("Could not open /dev/tty: {error}"),
+            }
+
+            // Conventional exit code for termination by a signal
+            exit(128 + signal);
+        }
+    });
+
+    Ok(())
+}
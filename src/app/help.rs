@@ -0,0 +1,177 @@
+//! Contents of the help dialog.
+use crate::{
+    configuration::{Config, KeySequence},
+    logging,
+};
+
+/// Get a readable name of the key with the given character.
+fn get_key_name(key: char) -> String {
+    match key {
+        ' ' => "Space".to_string(),
+        _ => key.to_string(),
+    }
+}
+
+/// Get the entries of the help dialog as (keys, description) tuples.
+///
+/// The keybindings of the mode with the given index are listed after the
+/// general ones, followed by all the available modes.
+pub fn get_help_entries(config: &Config, mode_index: Option<usize>) -> Vec<(String, String)> {
+    let mut entries = vec![
+        ("Ctrl+c".to_string(), "Exit".to_string()),
+        ("Esc".to_string(), "Clear typed keys or exit".to_string()),
+        (
+            "Backspace".to_string(),
+            "Erase the last typed key".to_string(),
+        ),
+        ("Enter".to_string(), "Select the focused hit".to_string()),
+        ("Ctrl+r".to_string(), "Match the hits again".to_string()),
+        ("Arrows".to_string(), "Move the focus".to_string()),
+        ("Ctrl+e Ctrl+y".to_string(), "Scroll by a line".to_string()),
+        ("PgDn PgUp".to_string(), "Scroll by a page".to_string()),
+        (
+            "Home End".to_string(),
+            "Scroll to the start or the end".to_string(),
+        ),
+    ];
+
+    if config.alt_output_command.is_some() {
+        entries.push((
+            "Alt+hint".to_string(),
+            "Send the selection to alt_output_command".to_string(),
+        ));
+    }
+    if config.hjkl_navigation {
+        entries.push(("h j k l".to_string(), "Move the focus".to_string()));
+    }
+    if config.mode_cycling {
+        entries.push(("Tab".to_string(), "Next mode".to_string()));
+        entries.push(("Shift+Tab".to_string(), "Previous mode".to_string()));
+    }
+    if let Some(key) = config.mode_selection_key {
+        entries.push((get_key_name(key), "Switch the mode".to_string()));
+    }
+    if let Some(key) = config.search_key {
+        entries.push((get_key_name(key), "Search the input".to_string()));
+    }
+    if let Some(key) = config.next_file_key {
+        entries.push((get_key_name(key), "Next file".to_string()));
+    }
+    if let Some(key) = config.previous_file_key {
+        entries.push((get_key_name(key), "Previous file".to_string()));
+    }
+    if let Some(key) = config.wrap_toggle_key {
+        entries.push((get_key_name(key), "Wrap or cut off long lines".to_string()));
+    }
+    if let Some(key) = config.next_hit_page_key {
+        entries.push((get_key_name(key), "Next page with hints".to_string()));
+    }
+    if let Some(key) = config.previous_hit_page_key {
+        entries.push((get_key_name(key), "Previous page with hints".to_string()));
+    }
+    if let Some(key) = config.refresh_key {
+        entries.push((get_key_name(key), "Run the input command again".to_string()));
+    }
+    if let Some(key) = config.select_key {
+        entries.push((get_key_name(key), "Start selecting when paging".to_string()));
+    }
+    if let Some(key) = config.collect_key {
+        entries.push((
+            get_key_name(key),
+            "Collect or output selections".to_string(),
+        ));
+    }
+    if let Some(key) = config.scroll_to_top_key {
+        entries.push((get_key_name(key), "Scroll to the start".to_string()));
+    }
+    if let Some(key) = config.scroll_to_bottom_key {
+        entries.push((get_key_name(key), "Scroll to the end".to_string()));
+    }
+    if let Some(key) = config.return_mode_key {
+        entries.push((get_key_name(key), "Previously active mode".to_string()));
+    }
+    if let Some(key) = config.abort_key {
+        entries.push((get_key_name(key), "Abort with exit code 2".to_string()));
+    }
+    if let Some(key) = config.peek_key {
+        entries.push((get_key_name(key), "Hide or show the hints".to_string()));
+    }
+    if let Some(key) = config.help_key {
+        entries.push((get_key_name(key), "Toggle this help".to_string()));
+    }
+    // The overlay only works while logging, see InputHandler::from_config
+    if let Some(key) = config.debug_overlay_key.filter(|_| logging::is_enabled()) {
+        entries.push((get_key_name(key), "Toggle the debug overlay".to_string()));
+    }
+
+    if let Some(mode) = mode_index.and_then(|index| config.modes.get(index)) {
+        let mut keybindings: Vec<(&KeySequence, &String)> = mode.keybindings.iter().collect();
+        keybindings.sort();
+
+        for (sequence, command) in keybindings {
+            entries.push((sequence.to_string(), command.clone()));
+        }
+    }
+
+    for mode in &config.modes {
+        entries.push((format!("Mode {}", mode.hotkey), mode.name.clone()));
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn has_entry(entries: &[(String, String)], keys: &str, description: &str) -> bool {
+        entries.iter().any(|(entry_keys, entry_description)| {
+            entry_keys == keys && entry_description == description
+        })
+    }
+
+    #[test]
+    fn help_entries_contain_keybindings_of_current_mode_only() {
+        let mut config = Config::default();
+        config.modes[0].keybindings =
+            HashMap::from([(KeySequence(vec!['x']), "select_all".to_string())]);
+
+        let entries = get_help_entries(&config, Some(0));
+        assert!(has_entry(&entries, "x", "select_all"));
+
+        let entries = get_help_entries(&config, None);
+        assert!(!has_entry(&entries, "x", "select_all"));
+    }
+
+    #[test]
+    fn help_entries_contain_all_modes() {
+        let config = Config::default();
+
+        let entries = get_help_entries(&config, None);
+
+        for mode in &config.modes {
+            assert!(has_entry(
+                &entries,
+                &format!("Mode {}", mode.hotkey),
+                &mode.name
+            ));
+        }
+    }
+
+    #[test]
+    fn help_entries_omit_disabled_keys() {
+        let config = Config {
+            mode_selection_key: None,
+            mode_cycling: false,
+            ..Config::default()
+        };
+
+        let entries = get_help_entries(&config, None);
+
+        assert!(!has_entry(&entries, "Space", "Switch the mode"));
+        assert!(!has_entry(&entries, "Tab", "Next mode"));
+        assert!(has_entry(&entries, "?", "Toggle this help"));
+    }
+}
@@ -0,0 +1,148 @@
+//! Preparing the input for showing and matching, including applying the
+//! [InputFilter]s from the config to it.
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::{
+    app::{input_text::OffsetMap, line_edits::resolve_line_edits},
+    configuration::InputFilter,
+};
+
+/// Resolve the cursor movements in each line of the given text, see
+/// [resolve_line_edits], and apply the given filters to it, keeping the
+/// line endings.
+///
+/// The carriage returns of CRLF line endings are removed on the way, so
+/// the prepared text comes with the offsets of its lines in the given text.
+pub fn prepare_input(filters: &[InputFilter], text: &str) -> (String, OffsetMap) {
+    if filters.is_empty() && !text.contains(['\r', '\x08', '\x1b']) {
+        return (text.to_string(), OffsetMap::default());
+    }
+
+    let mut prepared = String::with_capacity(text.len());
+    let mut offsets = OffsetMap::default();
+    let mut original = 0;
+    for line in text.split_inclusive('\n') {
+        let (content, ending) = match line.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (line, ""),
+        };
+
+        let filtered = filters
+            .iter()
+            .fold(resolve_line_edits(content), |line, filter| {
+                apply_filter(filter, &line)
+            });
+        prepared.push_str(&filtered);
+        prepared.push_str(ending);
+
+        original += line.len();
+        offsets.push(prepared.len(), original);
+    }
+
+    (prepared, offsets)
+}
+
+/// Apply the given filter to a single line without the line ending.
+fn apply_filter(filter: &InputFilter, line: &str) -> String {
+    match filter {
+        InputFilter::Replace { regex, with } => regex.replace_all(line, with).into_owned(),
+        InputFilter::Cut { fields, delimiter } => {
+            let (parts, separator): (Vec<&str>, &str) = match delimiter {
+                Some(delimiter) => (line.split(delimiter.as_str()).collect(), delimiter),
+                None => (line.split_whitespace().collect(), " "),
+            };
+
+            fields
+                .iter()
+                .filter_map(|field| field.checked_sub(1).and_then(|index| parts.get(index)))
+                .copied()
+                .collect::<Vec<&str>>()
+                .join(separator)
+        }
+        InputFilter::StripAnsi => get_ansi_regex().replace_all(line, "").into_owned(),
+    }
+}
+
+/// Get the regex matching the ANSI escape sequences, i.e. the control
+/// sequences (e.g. colors), the operating system commands (e.g. window
+/// titles) and the two character escapes.
+#[allow(clippy::unwrap_used, reason = "A literal that's known to be valid")]
+fn get_ansi_regex() -> &'static Regex {
+    static ANSI_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    ANSI_REGEX.get_or_init(|| {
+        Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)|\x1b[@-Z\\-_]")
+            .unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn cut(fields: &[usize], delimiter: Option<&str>) -> InputFilter {
+        InputFilter::Cut {
+            fields: fields.to_vec(),
+            delimiter: delimiter.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn filters_are_applied_to_each_line_in_order() {
+        let filters = [
+            InputFilter::Replace {
+                regex: Regex::new("^#").unwrap(),
+                with: String::new(),
+            },
+            cut(&[2], None),
+        ];
+
+        let (filtered, _) = prepare_input(&filters, "#a b\n#c d\nlast");
+
+        assert_eq!(filtered, "b\nd\n");
+    }
+
+    #[test]
+    fn line_edits_are_resolved_before_filters() {
+        let filters = [cut(&[1], None)];
+
+        let (prepared, _) = prepare_input(&filters, "10%\r100% done\r\nnext line\n");
+
+        assert_eq!(prepared, "100%\nnext\n");
+    }
+
+    #[test]
+    fn crlf_line_endings_are_mapped_to_original_offsets() {
+        let (prepared, offsets) = prepare_input(&[], "ab\r\ncd\r\nef");
+
+        assert_eq!(prepared, "ab\ncd\nef");
+        // The start of each line and the characters within it
+        assert_eq!(offsets.original_offset(3), 4);
+        assert_eq!(offsets.original_offset(4), 5);
+        assert_eq!(offsets.original_offset(6), 8);
+        assert_eq!(offsets.original_offset(7), 9);
+    }
+
+    #[test_case(&[1, 3], None, "one  two three", "one three"; "when_split_by_whitespace")]
+    #[test_case(&[2], Some(","), "a,b,c", "b"; "when_split_by_delimiter")]
+    #[test_case(&[3, 1], Some(":"), "a:b:c", "c:a"; "when_fields_are_reordered")]
+    #[test_case(&[0, 5], None, "a b", ""; "when_fields_do_not_exist")]
+    fn cut_keeps_expected_fields(
+        fields: &[usize],
+        delimiter: Option<&str>,
+        line: &str,
+        expected: &str,
+    ) {
+        assert_eq!(apply_filter(&cut(fields, delimiter), line), expected);
+    }
+
+    #[test_case("\x1b[1;31mred\x1b[0m", "red"; "when_text_has_colors")]
+    #[test_case("\x1b]0;title\x07text", "text"; "when_text_sets_title")]
+    #[test_case("\x1b[2Ktext", "text"; "when_text_clears_line")]
+    fn strip_ansi_removes_escape_sequences(line: &str, expected: &str) {
+        assert_eq!(apply_filter(&InputFilter::StripAnsi, line), expected);
+    }
+}
@@ -0,0 +1,177 @@
+//! Reading of the input that keeps arriving while the selection is running.
+use std::{
+    io::{BufRead, BufReader, Read},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use crate::app::input_text::from_utf8_keeping_offsets;
+
+/// Reads the input in a background thread and collects it until it is
+/// taken with [InputFollower::take_new_input].
+pub struct InputFollower {
+    receiver: Receiver<String>,
+    /// Whether the input ended and everything read from it was taken.
+    ended: bool,
+}
+
+impl InputFollower {
+    /// Start reading the given input in a background thread, until the
+    /// input ends or an error occurs.
+    ///
+    /// Any invalid UTF-8 in the input is replaced byte by byte, see
+    /// [from_utf8_keeping_offsets], so that binary or mixed-encoding input
+    /// can still be shown.
+    pub fn start(input: impl Read + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut reader = BufReader::new(input);
+
+            loop {
+                let mut line = vec![];
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) => {
+                        info!("The followed input ended");
+                        break;
+                    }
+                    Ok(_) => {
+                        let line = from_utf8_keeping_offsets(&line).into_owned();
+                        // The receiver is gone only when the selection is done
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(error) => {
+                        warn!("Could not read the followed input: {error}");
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            receiver,
+            ended: false,
+        }
+    }
+
+    /// Check whether the input ended and everything read from it was taken,
+    /// i.e. whether there is no point in checking for new input any more.
+    pub fn has_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Wait until the given number of lines arrives, the input ends or the
+    /// timeout (if any) runs out, whichever comes first, and get the lines
+    /// that arrived.
+    pub fn wait_for_lines(&mut self, line_count: usize, timeout: Option<Duration>) -> String {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut input = String::new();
+
+        for _ in 0..line_count {
+            let line = match deadline {
+                Some(deadline) => self
+                    .receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match line {
+                Ok(line) => input.push_str(&line),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.ended = true;
+                    break;
+                }
+            }
+        }
+
+        input
+    }
+
+    /// Wait until the input ends and get everything that arrived until then.
+    pub fn wait_for_end(&mut self) -> String {
+        let input = self.receiver.iter().collect();
+        self.ended = true;
+
+        input
+    }
+
+    /// Get all the input that arrived since the last call, if any.
+    pub fn take_new_input(&mut self) -> Option<String> {
+        let mut new_input = String::new();
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) => new_input.push_str(&line),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.ended = true;
+                    break;
+                }
+            }
+        }
+
+        match new_input.is_empty() {
+            true => None,
+            false => Some(new_input),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follower_collects_all_the_input() {
+        let mut follower = InputFollower::start("line 1\nline 2\nline 3".as_bytes());
+
+        let mut collected = String::new();
+        let start = Instant::now();
+        while collected.len() < 20 && start.elapsed() < Duration::from_secs(5) {
+            if let Some(new_input) = follower.take_new_input() {
+                collected.push_str(&new_input);
+            }
+        }
+
+        assert_eq!(collected, "line 1\nline 2\nline 3");
+    }
+
+    #[test]
+    fn wait_for_lines_returns_only_requested_lines() {
+        let mut follower = InputFollower::start("line 1\nline 2\nline 3".as_bytes());
+
+        let input = follower.wait_for_lines(2, Some(Duration::from_secs(5)));
+
+        assert_eq!(input, "line 1\nline 2\n");
+        assert!(!follower.has_ended());
+    }
+
+    #[test]
+    fn wait_for_end_returns_rest_of_the_input() {
+        let mut follower = InputFollower::start("line 1\nline 2\nline 3".as_bytes());
+        follower.wait_for_lines(1, None);
+
+        let input = follower.wait_for_end();
+
+        assert_eq!(input, "line 2\nline 3");
+        assert!(follower.has_ended());
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        let mut follower = InputFollower::start(&b"line 1\n\xff line 2\n"[..]);
+
+        let input = follower.wait_for_end();
+
+        assert_eq!(input, "line 1\n? line 2\n");
+    }
+}
@@ -0,0 +1,504 @@
+//! The text of the input, either read into memory or mapped from a file.
+use std::{borrow::Cow, collections::VecDeque, fs::File, str::Utf8Error};
+
+use log::{info, warn};
+use memmap2::Mmap;
+
+/// The text of the input that the selection is made from.
+pub enum InputText {
+    /// Text read into memory, which can keep growing while it is read.
+    Read(ReadText),
+    /// Text of a file mapped into memory, with the byte offsets of the
+    /// starts of its lines so that any line can be found without going
+    /// through the whole text.
+    Mapped { map: Mmap, line_starts: Vec<usize> },
+}
+
+impl InputText {
+    /// Create the text read into memory, prepared from the input with the
+    /// given offsets.
+    pub fn new(text: String, offsets: OffsetMap) -> Self {
+        InputText::Read(ReadText::new(text, offsets))
+    }
+
+    /// Map the given file into memory.
+    ///
+    /// Only files that are not expected to change while they are shown
+    /// should be mapped, e.g. not the watched or followed ones, since the
+    /// mapped text cannot grow and may end mless if the file is truncated,
+    /// see the safety comment below.
+    ///
+    /// Returns [None] if the file cannot be mapped or does not contain valid
+    /// UTF-8, in which case it should be read the usual way instead, which
+    /// replaces the invalid parts.
+    pub fn map_file(file: &File) -> Option<Self> {
+        // SAFETY: Nothing stops another process from changing the file while
+        // it is mapped, e.g. a log rotated in place. The text is therefore
+        // checked to be valid UTF-8 every time a part of it is used, so a
+        // changed file only changes what is shown. If another process
+        // truncates the file, reading the part that is gone raises SIGBUS,
+        // which ends mless. That is the only remaining risk, accepted in
+        // exchange for not copying huge files into memory.
+        let map = match unsafe { Mmap::map(file) } {
+            Ok(map) => map,
+            Err(error) => {
+                warn!("Could not map the file into memory: {error}");
+                return None;
+            }
+        };
+
+        let line_starts = match get_line_starts(&map) {
+            Ok(line_starts) => line_starts,
+            Err(error) => {
+                info!("Not mapping the file since it is not valid UTF-8: {error}");
+                return None;
+            }
+        };
+        info!("Mapped a file with {} lines", line_starts.len());
+
+        Some(Self::Mapped { map, line_starts })
+    }
+
+    /// Get the whole text.
+    ///
+    /// The mapped text is checked to still be valid UTF-8, since the file
+    /// may have been changed after it was mapped, in which case the invalid
+    /// bytes are replaced, see [from_utf8_keeping_offsets]. The text is only
+    /// copied if that happens.
+    pub fn to_str(&self) -> Cow<'_, str> {
+        match self {
+            InputText::Read(text) => Cow::Borrowed(text.as_str()),
+            InputText::Mapped { map, .. } => from_utf8_keeping_offsets(map),
+        }
+    }
+
+    /// Get the length of the text in bytes.
+    pub fn len(&self) -> usize {
+        match self {
+            InputText::Read(text) => text.as_str().len(),
+            InputText::Mapped { map, .. } => map.len(),
+        }
+    }
+
+    /// Check whether the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the byte offset of the start of the line with the given index,
+    /// or the length of the text if there is no such line.
+    pub fn line_start(&self, line: usize) -> usize {
+        match self {
+            InputText::Read(text) => text.line_start(line),
+            InputText::Mapped { map, line_starts } => {
+                line_starts.get(line).copied().unwrap_or(map.len())
+            }
+        }
+    }
+
+    /// Get the text of `count` lines starting at the line with the given
+    /// index, including their new lines, or an empty string if there are no
+    /// such lines.
+    ///
+    /// Only this part of the mapped text is checked to be valid UTF-8, the
+    /// same way as by [InputText::to_str].
+    pub fn lines(&self, first: usize, count: usize) -> Cow<'_, str> {
+        let start = self.line_start(first);
+        let end = self.line_start(first.saturating_add(count));
+
+        match self {
+            InputText::Read(text) => Cow::Borrowed(&text.as_str()[start..end]),
+            InputText::Mapped { map, .. } => from_utf8_keeping_offsets(&map[start..end]),
+        }
+    }
+
+    /// Get the number of lines in the text, counted the same way as by
+    /// [str::lines].
+    pub fn line_count(&self) -> usize {
+        match self {
+            InputText::Read(text) => text.line_starts.len(),
+            InputText::Mapped { line_starts, .. } => line_starts.len(),
+        }
+    }
+
+    /// Keep only the first `count` lines of the text, if it can be changed.
+    pub fn keep_first_lines(&mut self, count: usize) {
+        match self {
+            InputText::Read(text) => text.keep_first_lines(count),
+            InputText::Mapped { .. } => warn!("Cannot remove lines from a mapped file"),
+        }
+    }
+
+    /// Keep only the last `count` lines of the text, if it can be changed.
+    pub fn keep_last_lines(&mut self, count: usize) {
+        match self {
+            InputText::Read(text) => text.keep_last_lines(count),
+            InputText::Mapped { .. } => warn!("Cannot remove lines from a mapped file"),
+        }
+    }
+
+    /// Get the offset in the original input of the byte at the given offset
+    /// in the text, see [OffsetMap].
+    pub fn original_offset(&self, offset: usize) -> usize {
+        match self {
+            InputText::Read(text) => text.original_offset(offset),
+            InputText::Mapped { .. } => offset,
+        }
+    }
+
+    /// Append the given text, prepared from the input with the given
+    /// offsets, if the input can still grow.
+    pub fn push_str(&mut self, new_text: &str, new_offsets: &OffsetMap) {
+        match self {
+            InputText::Read(text) => text.push_str(new_text, new_offsets),
+            InputText::Mapped { .. } => warn!("Ignoring text appended to a mapped file"),
+        }
+    }
+}
+
+impl From<&str> for InputText {
+    fn from(text: &str) -> Self {
+        InputText::new(text.to_string(), OffsetMap::default())
+    }
+}
+
+impl Default for InputText {
+    fn default() -> Self {
+        InputText::from("")
+    }
+}
+
+/// Text read into memory, which keeps track of where its lines start as it
+/// grows, so that neither counting the lines nor finding one of them has to
+/// go through the whole text, e.g. when following an input with
+/// `--keep-lines`.
+pub struct ReadText {
+    /// The text, of which the part before [ReadText::start] is already
+    /// removed. That part is only dropped once it makes up half of the
+    /// text, so that removing the lines one by one does not move the rest
+    /// of the text every time.
+    text: String,
+    start: usize,
+    /// The offsets in [ReadText::text] of the starts of the lines that are
+    /// not removed.
+    line_starts: VecDeque<usize>,
+    /// The offsets in the input [ReadText::text] was prepared from.
+    offsets: OffsetMap,
+}
+
+impl ReadText {
+    fn new(text: String, offsets: OffsetMap) -> Self {
+        let mut read_text = Self {
+            text: String::new(),
+            start: 0,
+            line_starts: VecDeque::new(),
+            offsets: OffsetMap::default(),
+        };
+        read_text.push_str(&text, &offsets);
+
+        read_text
+    }
+
+    fn as_str(&self) -> &str {
+        &self.text[self.start..]
+    }
+
+    fn line_start(&self, line: usize) -> usize {
+        self.line_starts
+            .get(line)
+            .map_or(self.text.len(), |start| *start)
+            - self.start
+    }
+
+    fn original_offset(&self, offset: usize) -> usize {
+        self.offsets.original_offset(self.start + offset) - self.offsets.original_offset(self.start)
+    }
+
+    fn keep_first_lines(&mut self, count: usize) {
+        let end = self.start + self.line_start(count);
+
+        self.text.truncate(end);
+        self.line_starts.truncate(count);
+        self.offsets.truncate(end);
+    }
+
+    fn keep_last_lines(&mut self, count: usize) {
+        let removed_count = self.line_starts.len().saturating_sub(count);
+        self.start += self.line_start(removed_count);
+        self.line_starts.drain(..removed_count);
+
+        if self.start > self.text.len() / 2 {
+            self.text.drain(..self.start);
+            for line_start in &mut self.line_starts {
+                *line_start -= self.start;
+            }
+            self.offsets.remove_start(self.start);
+            self.start = 0;
+        }
+    }
+
+    fn push_str(&mut self, new_text: &str, new_offsets: &OffsetMap) {
+        let len = self.text.len();
+        let original = self.offsets.original_offset(len);
+        self.offsets.append(new_offsets, len, original);
+
+        // The new text continues the last line unless it ended with a new line
+        let continues_line = len > self.start && !self.text.ends_with('\n');
+        let new_lines = new_text
+            .split_inclusive('\n')
+            .scan(len, |start, line| {
+                let line_start = *start;
+                *start += line.len();
+                Some(line_start)
+            })
+            .skip(usize::from(continues_line));
+        self.line_starts.extend(new_lines);
+
+        self.text.push_str(new_text);
+    }
+}
+
+/// The mapping from the offsets in a text prepared from the input, e.g.
+/// with the carriage returns of CRLF line endings removed, back to the
+/// offsets in the input itself.
+///
+/// The start of each line is mapped exactly, and the rest of the line is
+/// mapped as if only its end changed, which holds for the line endings.
+/// The offsets within the lines changed by the input filters are therefore
+/// only approximate.
+#[derive(Debug, Default, PartialEq)]
+pub struct OffsetMap {
+    /// The offsets in the text from which the difference to the offsets
+    /// in the input changes, with the difference from there on, in order.
+    /// There are none if the text is the same as the input.
+    shifts: Vec<(usize, isize)>,
+}
+
+impl OffsetMap {
+    /// Record that the given offset in the text is the given offset in the
+    /// input. The offsets have to be recorded in order.
+    pub fn push(&mut self, offset: usize, original: usize) {
+        let shift = original as isize - offset as isize;
+        if shift != self.shift_at(offset) {
+            self.shifts.push((offset, shift));
+        }
+    }
+
+    /// Get the offset in the input of the byte at the given offset in the
+    /// text.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        offset.saturating_add_signed(self.shift_at(offset))
+    }
+
+    /// Get the difference between the offset in the input and the given
+    /// offset in the text.
+    fn shift_at(&self, offset: usize) -> isize {
+        let index = self.shifts.partition_point(|(start, _)| *start <= offset);
+
+        index.checked_sub(1).map_or(0, |index| self.shifts[index].1)
+    }
+
+    /// Add the mapping of a text appended at the given offset, which starts
+    /// at the given offset in the input.
+    fn append(&mut self, other: &OffsetMap, offset: usize, original: usize) {
+        self.push(offset, original);
+        for (start, shift) in &other.shifts {
+            let start = offset + start;
+            self.push(
+                start,
+                (original + start - offset).saturating_add_signed(*shift),
+            );
+        }
+    }
+
+    /// Forget the mapping after the given offset in the text.
+    fn truncate(&mut self, offset: usize) {
+        self.shifts.retain(|(start, _)| *start <= offset);
+    }
+
+    /// Remove the mapping of the text before the given offset, so that the
+    /// rest of it starts at 0 in both the text and the input.
+    fn remove_start(&mut self, offset: usize) {
+        let removed_shift = self.shift_at(offset);
+
+        self.shifts = self
+            .shifts
+            .iter()
+            .filter(|(start, _)| *start > offset)
+            .map(|(start, shift)| (start - offset, shift - removed_shift))
+            .collect();
+    }
+}
+
+/// The character that each byte of invalid UTF-8 in the input is replaced
+/// with. It takes up a single byte, the same as the byte it replaces, so that
+/// the byte offsets in the text are the same as in the original input.
+pub const INVALID_BYTE_PLACEHOLDER: char = '?';
+
+/// Convert the given bytes to text, replacing each byte that is not a part
+/// of valid UTF-8 with [INVALID_BYTE_PLACEHOLDER].
+///
+/// Unlike [String::from_utf8_lossy], which replaces the invalid sequences
+/// with the 3 bytes long U+FFFD, this keeps the offsets of everything after
+/// the invalid bytes, e.g. the ones output with `--with-position`.
+pub fn from_utf8_keeping_offsets(bytes: &[u8]) -> Cow<'_, str> {
+    let mut chunks = bytes.utf8_chunks();
+    let Some(first) = chunks.next() else {
+        return Cow::Borrowed("");
+    };
+    if first.invalid().is_empty() {
+        return Cow::Borrowed(first.valid());
+    }
+
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in std::iter::once(first).chain(chunks) {
+        text.push_str(chunk.valid());
+        text.extend(std::iter::repeat_n(
+            INVALID_BYTE_PLACEHOLDER,
+            chunk.invalid().len(),
+        ));
+    }
+
+    Cow::Owned(text)
+}
+
+/// Get the byte offsets of the starts of the lines in the given text,
+/// checking that it is valid UTF-8 on the way, so that the text is only
+/// gone through once.
+fn get_line_starts(text: &[u8]) -> Result<Vec<usize>, Utf8Error> {
+    let mut line_starts = vec![];
+    let mut start = 0;
+
+    // A new line is never a part of another character in UTF-8, so the
+    // text is valid if all of its lines are
+    for line in text.split_inclusive(|byte| *byte == b'\n') {
+        std::str::from_utf8(line)?;
+        line_starts.push(start);
+        start += line.len();
+    }
+
+    Ok(line_starts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, os::unix::fs::FileExt};
+    use test_case::test_case;
+
+    #[test_case("", &[]; "when_text_is_empty")]
+    #[test_case("a\nbc\n", &[0, 2]; "when_text_ends_with_new_line")]
+    #[test_case("a\nbc", &[0, 2]; "when_text_does_not_end_with_new_line")]
+    #[test_case("\n\n", &[0, 1]; "when_lines_are_empty")]
+    fn get_line_starts_returns_expected_value(text: &str, expected: &[usize]) {
+        let line_starts = get_line_starts(text.as_bytes()).unwrap();
+
+        assert_eq!(line_starts, expected);
+        assert_eq!(line_starts.len(), text.lines().count());
+    }
+
+    #[test_case(b"\xff\n"; "when_first_line_is_invalid")]
+    #[test_case(b"a\nb\xe2\x82"; "when_last_line_ends_in_middle_of_character")]
+    fn get_line_starts_returns_error_for_invalid_utf8(text: &[u8]) {
+        assert!(get_line_starts(text).is_err());
+    }
+
+    #[test_case(b"abc", "abc"; "when_text_is_valid")]
+    #[test_case(b"a\xffb", "a?b"; "when_byte_is_invalid")]
+    #[test_case(b"a\xe2\x82b\xff", "a??b?"; "when_character_is_cut_off")]
+    #[test_case(b"\xc3\xa4\xff", "ä?"; "when_valid_text_is_not_ascii")]
+    fn from_utf8_keeping_offsets_keeps_length(bytes: &[u8], expected: &str) {
+        let text = from_utf8_keeping_offsets(bytes);
+
+        assert_eq!(text, expected);
+        assert_eq!(text.len(), bytes.len());
+    }
+
+    #[test_case(0, 3, "a\nbc\nd"; "when_all_lines_are_taken")]
+    #[test_case(1, 1, "bc\n"; "when_line_is_in_middle")]
+    #[test_case(2, 5, "d"; "when_lines_go_past_end")]
+    #[test_case(3, 1, ""; "when_line_does_not_exist")]
+    fn lines_returns_expected_text(first: usize, count: usize, expected: &str) {
+        let text = InputText::from("a\nbc\nd");
+
+        assert_eq!(text.lines(first, count), expected);
+    }
+
+    #[test]
+    fn mapped_text_is_checked_when_used() {
+        let path = env::temp_dir().join(format!("mless-mapped-{}", std::process::id()));
+        fs::write(&path, "a\nbc\nd").unwrap();
+        let file = File::options().read(true).write(true).open(&path).unwrap();
+        let text = InputText::map_file(&file).unwrap();
+
+        // Changed after it was mapped, e.g. by another process
+        file.write_all_at(b"\xff", 2).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(text.lines(1, 1), "?c\n");
+        assert_eq!(text.to_str(), "a\n?c\nd");
+        assert_eq!(text.lines(2, 1), "d");
+    }
+
+    #[test_case(2, "a\nbc\n"; "when_text_is_longer")]
+    #[test_case(5, "a\nbc\nd"; "when_text_is_shorter")]
+    #[test_case(0, ""; "when_no_lines_are_kept")]
+    fn keep_first_lines_keeps_expected_text(count: usize, expected: &str) {
+        let mut text = InputText::from("a\nbc\nd");
+
+        text.keep_first_lines(count);
+
+        assert_eq!(text.to_str(), expected);
+    }
+
+    #[test]
+    fn lines_are_found_while_text_grows_and_lines_are_removed() {
+        let mut text = InputText::default();
+        let steps = [
+            ("a\nb", "a\nb", 2),
+            ("c\n", "a\nbc\n", 2),
+            ("dd\ne\n", "dd\ne\n", 3),
+            ("f", "e\nf", 2),
+        ];
+
+        for (new_text, expected, second_line_start) in steps {
+            text.push_str(new_text, &OffsetMap::default());
+            text.keep_last_lines(2);
+
+            assert_eq!(text.to_str(), expected);
+            assert_eq!(text.line_count(), expected.lines().count());
+            assert_eq!(text.line_start(1), second_line_start);
+            assert_eq!(text.lines(1, 1), &expected[second_line_start..]);
+        }
+    }
+
+    #[test]
+    fn original_offsets_follow_appended_and_removed_lines() {
+        let mut offsets = OffsetMap::default();
+        offsets.push(2, 3);
+        let mut text = InputText::default();
+        text.push_str("a\n", &offsets);
+        text.push_str("b\n", &offsets);
+        text.push_str("c\n", &OffsetMap::default());
+
+        assert_eq!(text.original_offset(2), 3);
+        assert_eq!(text.original_offset(4), 6);
+
+        text.keep_last_lines(2);
+
+        assert_eq!(text.to_str(), "b\nc\n");
+        assert_eq!(text.original_offset(0), 0);
+        assert_eq!(text.original_offset(2), 3);
+    }
+
+    #[test_case(2, "bc\nd\n"; "when_text_is_longer")]
+    #[test_case(5, "a\nbc\nd\n"; "when_text_is_shorter")]
+    #[test_case(0, ""; "when_no_lines_are_kept")]
+    fn keep_last_lines_keeps_expected_text(count: usize, expected: &str) {
+        let mut text = InputText::from("a\nbc\nd\n");
+
+        text.keep_last_lines(count);
+
+        assert_eq!(text.to_str(), expected);
+    }
+}
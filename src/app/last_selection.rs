@@ -0,0 +1,91 @@
+//! Remembering the last selected text between the runs.
+use std::{
+    env::{self, VarError},
+    fs,
+    path::PathBuf,
+};
+
+use snafu::ResultExt;
+
+use crate::error::{LastSelectionLoadSnafu, LastSelectionSaveSnafu, RunError};
+
+/// Implementation of [get_last_selection_path] with additional arguments
+/// to make testing easier.
+///
+/// Arguments:
+///  - `get_env_var`: function to use to get the value of the the given environment variable.
+///    Should return the value of the variable with the given name if the variable can be read
+///    or an error otherwise.
+fn get_last_selection_path_impl(
+    get_env_var: &dyn Fn(&str) -> Result<String, VarError>,
+) -> Option<PathBuf> {
+    match get_env_var("XDG_STATE_HOME") {
+        Ok(directory) => Some(PathBuf::from(format!("{directory}/mless/last_selection"))),
+        Err(_) => get_env_var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(format!("{home}/.local/state/mless/last_selection"))),
+    }
+}
+
+/// Get the path of the file where the last selection is kept.
+///
+/// The file is `$XDG_STATE_HOME/mless/last_selection` or, if `XDG_STATE_HOME`
+/// is not defined, `$HOME/.local/state/mless/last_selection`.
+fn get_last_selection_path() -> Option<PathBuf> {
+    let get_env_var = |var_name: &str| env::var(var_name);
+
+    get_last_selection_path_impl(&get_env_var)
+}
+
+/// Save the given selection so that it can be loaded with [load_last_selection].
+pub fn save_last_selection(selection: &str) -> Result<(), RunError> {
+    let Some(path) = get_last_selection_path() else {
+        return Err(RunError::NoStateDirectory);
+    };
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).context(LastSelectionSaveSnafu { path: &path })?;
+    }
+    fs::write(&path, selection).context(LastSelectionSaveSnafu { path })
+}
+
+/// Load the selection saved with [save_last_selection].
+pub fn load_last_selection() -> Result<String, RunError> {
+    let Some(path) = get_last_selection_path() else {
+        return Err(RunError::NoStateDirectory);
+    };
+
+    fs::read_to_string(&path).context(LastSelectionLoadSnafu { path })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(
+        Ok("/home/user/.xdg_state".to_string()),
+        Ok("/home/user".to_string()),
+        Some(PathBuf::from("/home/user/.xdg_state/mless/last_selection")); "when_xdg_state_is_defined")]
+    #[test_case(
+        Err(VarError::NotPresent),
+        Ok("/home/user".to_string()),
+        Some(PathBuf::from("/home/user/.local/state/mless/last_selection")); "when_xdg_state_is_not_defined")]
+    #[test_case(
+        Err(VarError::NotPresent),
+        Err(VarError::NotPresent),
+        None; "when_nothing_is_defined")]
+    fn get_last_selection_path_returns_expected_value(
+        xdg_state_home: Result<String, VarError>,
+        home: Result<String, VarError>,
+        expected: Option<PathBuf>,
+    ) {
+        let get_env_var = |var_name: &str| match var_name {
+            "XDG_STATE_HOME" => xdg_state_home.clone(),
+            "HOME" => home.clone(),
+            _ => Err(VarError::NotPresent),
+        };
+
+        assert_eq!(get_last_selection_path_impl(&get_env_var), expected);
+    }
+}
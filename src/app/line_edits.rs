@@ -0,0 +1,162 @@
+//! Resolving the cursor movements and erasing within a line of the input,
+//! as used by progress bars and spinners, into the text that a terminal
+//! would end up showing.
+use std::{iter::Peekable, str::Chars};
+
+/// A character written to a line together with the escape sequences
+/// (e.g. colors) that came right before it.
+#[derive(Debug, Clone)]
+struct Cell {
+    sequences: String,
+    char: char,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Self {
+            sequences: String::new(),
+            char: ' ',
+        }
+    }
+}
+
+/// Get the text that is shown after writing the given line, without the
+/// line ending, to a terminal.
+///
+/// Carriage returns, backspaces, erasing (`ESC[K`) and horizontal cursor
+/// movements (`ESC[C`, `ESC[D` and `ESC[G`) are resolved. The colors are
+/// kept, while vertical cursor movements, which would change other lines,
+/// and the other escape sequences, e.g. the window titles (`ESC]0;...`) or
+/// the clipboard contents (`ESC]52;...`), are removed.
+pub fn resolve_line_edits(line: &str) -> String {
+    if !line.contains(['\r', '\x08', '\x1b']) {
+        return line.to_string();
+    }
+
+    let mut cells: Vec<Cell> = vec![];
+    let mut cursor: usize = 0;
+    // Sequences to attach to the next written character
+    let mut pending = String::new();
+
+    let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '\r' => cursor = 0,
+            '\x08' => cursor = cursor.saturating_sub(1),
+            '\x1b' if chars.peek() == Some(&'[') => {
+                chars.next();
+                let (parameters, command) = read_control_sequence(&mut chars);
+                let count = parameters.parse::<usize>().unwrap_or(0);
+
+                match command {
+                    Some('m') => {
+                        pending.push_str("\x1b[");
+                        pending.push_str(&parameters);
+                        pending.push('m');
+                    }
+                    Some('K') => match count {
+                        0 => cells.truncate(cursor),
+                        1 => cells
+                            .iter_mut()
+                            .take(cursor + 1)
+                            .for_each(|cell| *cell = Cell::blank()),
+                        _ => cells.clear(),
+                    },
+                    Some('C') => cursor += count.max(1),
+                    Some('D') => cursor = cursor.saturating_sub(count.max(1)),
+                    Some('G') => cursor = count.saturating_sub(1),
+                    _ => (),
+                }
+            }
+            '\x1b' => match chars.next() {
+                // Operating system commands and the other escape strings
+                Some(']' | 'P' | 'X' | '^' | '_') => skip_escape_string(&mut chars),
+                // Character set selections, which have one more character
+                Some('(' | ')' | '*' | '+' | '#' | '%') => {
+                    chars.next();
+                }
+                _ => (),
+            },
+            _ => {
+                if cells.len() <= cursor {
+                    cells.resize(cursor + 1, Cell::blank());
+                }
+                if let Some(cell) = cells.get_mut(cursor) {
+                    cell.sequences.push_str(&pending);
+                    cell.char = char;
+                }
+                pending.clear();
+                cursor += 1;
+            }
+        }
+    }
+
+    let mut resolved = String::new();
+    for cell in cells {
+        resolved.push_str(&cell.sequences);
+        resolved.push(cell.char);
+    }
+    resolved.push_str(&pending);
+
+    resolved
+}
+
+/// Read the rest of a control sequence whose `ESC[` was already read.
+///
+/// Returns the parameters and the final character, which is [None] if the
+/// line ended before it.
+fn read_control_sequence(chars: &mut Peekable<Chars>) -> (String, Option<char>) {
+    let mut parameters = String::new();
+
+    for char in chars.by_ref() {
+        match char {
+            '@'..='~' => return (parameters, Some(char)),
+            _ => parameters.push(char),
+        }
+    }
+
+    (parameters, None)
+}
+
+/// Skip the rest of an escape string, e.g. an operating system command,
+/// whose start was already read. The string ends with `BEL` or `ESC\`.
+fn skip_escape_string(chars: &mut Peekable<Chars>) {
+    while let Some(char) = chars.next() {
+        match char {
+            '\x07' => return,
+            '\x1b' if chars.peek() == Some(&'\\') => {
+                chars.next();
+                return;
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("plain text", "plain text"; "when_line_has_no_edits")]
+    #[test_case("\x1b[31mred\x1b[0m text", "\x1b[31mred\x1b[0m text"; "when_line_has_colors")]
+    #[test_case("10%\r50%\r100%", "100%"; "when_line_is_rewritten_after_carriage_return")]
+    #[test_case("loading\rdone", "doneing"; "when_rewrite_is_shorter")]
+    #[test_case("loading\r\x1b[Kdone", "done"; "when_rest_of_line_is_erased")]
+    #[test_case("loading\x1b[2K\rdone", "done"; "when_whole_line_is_erased")]
+    #[test_case("ab\x08c", "ac"; "when_line_has_backspace")]
+    #[test_case("abcd\x1b[2Dx", "abxd"; "when_cursor_moves_left")]
+    #[test_case("a\x1b[2Cb", "a  b"; "when_cursor_moves_right")]
+    #[test_case("abcd\x1b[2Gx", "axcd"; "when_cursor_moves_to_column")]
+    #[test_case("a\x1b[1Ab", "ab"; "when_cursor_moves_up")]
+    #[test_case("text\r", "text"; "when_line_ends_with_carriage_return")]
+    #[test_case("50%\r\x1b[32m100%\x1b[0m", "\x1b[32m100%\x1b[0m"; "when_rewrite_has_colors")]
+    #[test_case("\x1b]0;title\x07text", "text"; "when_line_sets_window_title")]
+    #[test_case("a\x1b]52;c;Y29weQ==\x1b\\b", "ab"; "when_line_sets_clipboard")]
+    #[test_case("\x1b]8;;http://a.b\x1b\\link\x1b]8;;\x1b\\", "link"; "when_line_has_hyperlink")]
+    #[test_case("\x1b(Bplain\x1b=", "plain"; "when_line_has_two_character_escapes")]
+    #[test_case("text\x1b]2;unterminated", "text"; "when_escape_string_is_not_terminated")]
+    fn resolve_line_edits_returns_expected_text(line: &str, expected: &str) {
+        assert_eq!(resolve_line_edits(line), expected);
+    }
+}
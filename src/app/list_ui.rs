@@ -0,0 +1,263 @@
+//! The selection from a plain numbered list of the hits, see --list-ui,
+//! which works better with screen readers and braille displays than the
+//! hints drawn over the text.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use textwrap::core::display_width;
+
+/// What the user did with a key in the [ListUi].
+#[derive(Debug, PartialEq)]
+pub enum ListUiEvent {
+    /// Nothing that ends the selection, the list should be drawn again.
+    Continue,
+    /// The entry with the given index was selected.
+    Select(usize),
+    /// The selection was cancelled with Esc or Ctrl-C.
+    Cancel,
+    /// The selection was aborted with q.
+    Abort,
+}
+
+/// The numbered list of the hits, scrolled with the arrow keys, Page Up,
+/// Page Down, Home and End, from which an entry is selected by typing its
+/// number and pressing Enter.
+#[derive(Debug)]
+pub struct ListUi {
+    /// The entries of the list, without the numbers.
+    entries: Vec<String>,
+    /// The index of the first entry shown.
+    first: usize,
+    /// The number typed so far.
+    number: String,
+    /// The message about the last key, e.g. a number that is out of range.
+    message: Option<String>,
+}
+
+impl ListUi {
+    /// Create the list of the given entries, each of them on its own line.
+    pub fn new(entries: Vec<String>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|entry| entry.replace(['\n', '\r'], " "))
+            .collect();
+
+        Self {
+            entries,
+            first: 0,
+            number: String::new(),
+            message: None,
+        }
+    }
+
+    /// Handle the given key on a screen with the given size as (rows,
+    /// columns).
+    pub fn handle_key(&mut self, key: KeyEvent, size: (usize, usize)) -> ListUiEvent {
+        self.message = None;
+        let page_length = self.get_page_length(size).max(1);
+        let last = self.entries.len().saturating_sub(1);
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return ListUiEvent::Cancel
+            }
+            KeyCode::Esc => return ListUiEvent::Cancel,
+            KeyCode::Char('q') => return ListUiEvent::Abort,
+            KeyCode::Char(digit @ '0'..='9') => self.number.push(digit),
+            KeyCode::Backspace => {
+                self.number.pop();
+            }
+            KeyCode::Enter => return self.select_typed(),
+            KeyCode::Down => self.first = (self.first + 1).min(last),
+            KeyCode::Up => self.first = self.first.saturating_sub(1),
+            KeyCode::PageDown => self.first = (self.first + page_length).min(last),
+            KeyCode::PageUp => self.first = self.first.saturating_sub(page_length),
+            KeyCode::Home => self.first = 0,
+            KeyCode::End => self.first = last,
+            _ => (),
+        }
+
+        ListUiEvent::Continue
+    }
+
+    /// Select the entry with the typed number, if there is one.
+    fn select_typed(&mut self) -> ListUiEvent {
+        let number = std::mem::take(&mut self.number);
+
+        match number.parse::<usize>() {
+            Ok(number) if (1..=self.entries.len()).contains(&number) => {
+                ListUiEvent::Select(number - 1)
+            }
+            _ if number.is_empty() => {
+                self.message = Some("Type the number of the entry first".to_string());
+                ListUiEvent::Continue
+            }
+            _ => {
+                self.message = Some(format!(
+                    "There is no entry {number}, type a number from 1 to {}",
+                    self.entries.len()
+                ));
+                ListUiEvent::Continue
+            }
+        }
+    }
+
+    /// Get the entries that fit on a screen with the given size as (rows,
+    /// columns), numbered and one per line, leaving the last row for the
+    /// prompt. Entries wider than the screen take as many rows as they
+    /// wrap into.
+    pub fn get_page(&self, size: (usize, usize)) -> String {
+        let page_length = self.get_page_length(size);
+
+        self.get_numbered_entries()
+            .skip(self.first)
+            .take(page_length)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Get the text of the prompt shown on the last row.
+    pub fn get_prompt(&self) -> String {
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+
+        let first_shown = self.first + 1;
+        format!(
+            "Entries from {first_shown} of {}, type a number and press Enter: {}",
+            self.entries.len(),
+            self.number
+        )
+    }
+
+    /// Get the number of entries, starting with the first one shown, that
+    /// fit on a screen with the given size as (rows, columns).
+    fn get_page_length(&self, (rows, columns): (usize, usize)) -> usize {
+        let mut free_rows = rows.saturating_sub(1);
+        let mut length = 0;
+
+        for entry in self.get_numbered_entries().skip(self.first) {
+            let entry_rows = display_width(&entry).div_ceil(columns.max(1)).max(1);
+            if entry_rows > free_rows {
+                // An entry too long for the whole screen is shown anyway
+                return match length {
+                    0 => 1,
+                    _ => length,
+                };
+            }
+            free_rows -= entry_rows;
+            length += 1;
+        }
+
+        length
+    }
+
+    /// Get the entries with their numbers, counted from 1.
+    fn get_numbered_entries(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| format!("{}. {entry}", index + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn create_list(count: usize) -> ListUi {
+        ListUi::new((1..=count).map(|index| format!("entry {index}")).collect())
+    }
+
+    #[test]
+    fn typed_number_selects_entry() {
+        let mut list = create_list(12);
+
+        list.handle_key(key(KeyCode::Char('1')), (10, 80));
+        list.handle_key(key(KeyCode::Char('2')), (10, 80));
+
+        assert_eq!(
+            list.handle_key(key(KeyCode::Enter), (10, 80)),
+            ListUiEvent::Select(11)
+        );
+    }
+
+    #[test_case("0"; "when_number_is_zero")]
+    #[test_case("13"; "when_number_is_too_large")]
+    #[test_case(""; "when_no_number_is_typed")]
+    fn invalid_number_keeps_selecting(number: &str) {
+        let mut list = create_list(12);
+        for digit in number.chars() {
+            list.handle_key(key(KeyCode::Char(digit)), (10, 80));
+        }
+
+        let event = list.handle_key(key(KeyCode::Enter), (10, 80));
+
+        assert_eq!(event, ListUiEvent::Continue);
+        assert!(!list.get_prompt().contains("press Enter"));
+    }
+
+    #[test]
+    fn backspace_removes_typed_digit() {
+        let mut list = create_list(12);
+        list.handle_key(key(KeyCode::Char('1')), (10, 80));
+        list.handle_key(key(KeyCode::Char('5')), (10, 80));
+        list.handle_key(key(KeyCode::Backspace), (10, 80));
+
+        assert!(list.get_prompt().ends_with(": 1"));
+    }
+
+    #[test]
+    fn page_shows_entries_that_fit_above_prompt() {
+        let list = create_list(12);
+
+        assert_eq!(list.get_page((4, 80)), "1. entry 1\n2. entry 2\n3. entry 3");
+    }
+
+    #[test]
+    fn page_counts_rows_of_wrapped_entries() {
+        let list = ListUi::new(vec!["a".repeat(15), "b".to_string(), "c".to_string()]);
+
+        assert_eq!(
+            list.get_page((4, 10)),
+            format!("1. {}\n2. b", "a".repeat(15))
+        );
+    }
+
+    #[test]
+    fn page_down_scrolls_by_page() {
+        let mut list = create_list(12);
+
+        list.handle_key(key(KeyCode::PageDown), (4, 80));
+
+        assert_eq!(list.get_page((4, 80)), "4. entry 4\n5. entry 5\n6. entry 6");
+    }
+
+    #[test]
+    fn scrolling_stops_at_last_entry() {
+        let mut list = create_list(3);
+
+        list.handle_key(key(KeyCode::End), (4, 80));
+        list.handle_key(key(KeyCode::Down), (4, 80));
+
+        assert_eq!(list.get_page((4, 80)), "3. entry 3");
+    }
+
+    #[test]
+    fn entries_with_newlines_take_single_line() {
+        let list = ListUi::new(vec!["first\nsecond".to_string()]);
+
+        assert_eq!(list.get_page((4, 80)), "1. first second");
+    }
+
+    #[test_case(KeyCode::Esc, ListUiEvent::Cancel; "when_esc_is_pressed")]
+    #[test_case(KeyCode::Char('q'), ListUiEvent::Abort; "when_q_is_pressed")]
+    fn keys_end_selection(code: KeyCode, expected: ListUiEvent) {
+        let mut list = create_list(3);
+
+        assert_eq!(list.handle_key(key(code), (4, 80)), expected);
+    }
+}
@@ -0,0 +1,204 @@
+//! The state of the main loop, which decides where the input goes and what
+//! is drawn.
+use std::mem;
+
+use crate::{
+    app::help::get_help_entries, app::run::SelectionResult, configuration::Config, modes::Mode,
+    rendering::DrawInstruction,
+};
+
+/// What the main loop is doing. The dialogs and the prompt are shown over
+/// the state they were opened from, which is restored once they close.
+#[derive(Debug)]
+pub enum LoopState {
+    /// Showing the input like a pager, without any hints.
+    Scrolling,
+    /// Selecting with the active mode.
+    Selecting,
+    /// Choosing the mode to switch to.
+    ModeSelection,
+    /// Showing the help over the given state.
+    Help(Box<LoopState>),
+    /// Typing the given search query over the given state.
+    Search {
+        query: String,
+        previous: Box<LoopState>,
+    },
+    /// Choosing the action for the given selection over the given state.
+    ActionMenu {
+        result: SelectionResult,
+        previous: Box<LoopState>,
+    },
+}
+
+impl LoopState {
+    /// Get the state under all the dialogs and the prompt.
+    fn get_base(&self) -> &LoopState {
+        match self {
+            LoopState::Help(previous)
+            | LoopState::Search { previous, .. }
+            | LoopState::ActionMenu { previous, .. } => previous.get_base(),
+            base => base,
+        }
+    }
+
+    /// Replace the state under all the dialogs and the prompt, keeping them
+    /// open.
+    pub fn set_base(&mut self, base: LoopState) {
+        match self {
+            LoopState::Help(previous)
+            | LoopState::Search { previous, .. }
+            | LoopState::ActionMenu { previous, .. } => previous.set_base(base),
+            current => *current = base,
+        }
+    }
+
+    /// Open the dialog or the prompt created from the current state, e.g.
+    /// `state.open(LoopState::Help)`.
+    pub fn open(&mut self, create: impl FnOnce(Box<LoopState>) -> LoopState) {
+        let previous = mem::replace(self, LoopState::Scrolling);
+        *self = create(Box::new(previous));
+    }
+
+    /// Close the dialog or the prompt shown on top, returning it. Returns
+    /// [None] and does nothing if there is none.
+    pub fn close(&mut self) -> Option<LoopState> {
+        let previous = match self {
+            LoopState::Help(previous)
+            | LoopState::Search { previous, .. }
+            | LoopState::ActionMenu { previous, .. } => {
+                mem::replace(previous.as_mut(), LoopState::Scrolling)
+            }
+            _ => return None,
+        };
+
+        Some(mem::replace(self, previous))
+    }
+
+    /// Check whether the input is shown like in a pager, possibly under
+    /// a dialog or the prompt.
+    pub fn is_scrolling(&self) -> bool {
+        matches!(self.get_base(), LoopState::Scrolling)
+    }
+
+    /// Check whether text is typed instead of the keys having their usual
+    /// meaning, see [crate::input_handler::InputHandler::set_text_input].
+    pub fn is_text_input(&self) -> bool {
+        matches!(
+            self,
+            LoopState::Search { .. } | LoopState::ActionMenu { .. }
+        )
+    }
+
+    /// Get what to draw in this state, given the active mode and its index,
+    /// and whether only the data is shown while peeking under the hints.
+    pub fn get_draw_instructions(
+        &self,
+        mode: &dyn Mode,
+        mode_index: Option<usize>,
+        peeking: bool,
+        config: &Config,
+    ) -> Vec<DrawInstruction> {
+        match self {
+            LoopState::Scrolling => vec![DrawInstruction::Data],
+            LoopState::Selecting | LoopState::ModeSelection => match peeking {
+                true => vec![DrawInstruction::Data],
+                false => mode.get_draw_instructions(),
+            },
+            LoopState::Help(previous) => {
+                let mut instructions =
+                    previous.get_draw_instructions(mode, mode_index, peeking, config);
+                instructions.push(DrawInstruction::HelpDialog(get_help_entries(
+                    config, mode_index,
+                )));
+                instructions
+            }
+            LoopState::Search { query, previous } => {
+                let mut instructions =
+                    previous.get_draw_instructions(mode, mode_index, peeking, config);
+                instructions.push(DrawInstruction::Prompt(format!("/{query}")));
+                instructions
+            }
+            LoopState::ActionMenu { previous, .. } => {
+                let mut instructions =
+                    previous.get_draw_instructions(mode, mode_index, peeking, config);
+                let actions = config
+                    .action_menu
+                    .iter()
+                    .map(|entry| (entry.hotkey, entry.name.clone()))
+                    .collect();
+                instructions.push(DrawInstruction::ActionMenuDialog(actions));
+                instructions
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::output::OutputTarget;
+
+    fn get_result() -> SelectionResult {
+        ("text".to_string(), None, None, OutputTarget::Stdout)
+    }
+
+    #[test]
+    fn closing_dialogs_restores_previous_states() {
+        let mut state = LoopState::Scrolling;
+        state.open(LoopState::Help);
+        state.open(|previous| LoopState::Search {
+            query: String::new(),
+            previous,
+        });
+
+        assert!(matches!(state.close(), Some(LoopState::Search { .. })));
+        assert!(matches!(state, LoopState::Help(_)));
+        assert!(matches!(state.close(), Some(LoopState::Help(_))));
+        assert!(matches!(state, LoopState::Scrolling));
+        assert!(state.close().is_none());
+    }
+
+    #[test]
+    fn set_base_keeps_dialogs_open() {
+        let mut state = LoopState::Scrolling;
+        state.open(LoopState::Help);
+
+        state.set_base(LoopState::ModeSelection);
+
+        let LoopState::Help(previous) = &state else {
+            panic!("The help was closed");
+        };
+        assert!(matches!(**previous, LoopState::ModeSelection));
+        assert!(!state.is_scrolling());
+    }
+
+    #[test]
+    fn is_scrolling_looks_under_dialogs() {
+        let mut state = LoopState::Scrolling;
+        state.open(|previous| LoopState::Search {
+            query: String::new(),
+            previous,
+        });
+
+        assert!(state.is_scrolling());
+        assert!(state.is_text_input());
+    }
+
+    #[test]
+    fn action_menu_keeps_selection() {
+        let mut state = LoopState::Selecting;
+        state.open(|previous| LoopState::ActionMenu {
+            result: get_result(),
+            previous,
+        });
+
+        assert!(state.is_text_input());
+        assert!(matches!(
+            state.close(),
+            Some(LoopState::ActionMenu { result, .. }) if result.0 == "text"
+        ));
+        assert!(matches!(state, LoopState::Selecting));
+        assert!(!state.is_text_input());
+    }
+}
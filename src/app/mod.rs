@@ -1,5 +1,26 @@
 //! High level app logic like main loop and initialization.
+mod bench;
 mod configuration_handling;
+mod debug_overlay;
+mod event_source;
+mod force_quit;
+mod help;
+mod input_filters;
+mod input_follower;
+mod input_text;
+mod last_selection;
+mod line_edits;
+mod list_ui;
+mod loop_state;
+mod output;
+mod page_cache;
+mod pty;
 
 mod run;
-pub use run::run;
+mod search;
+mod selection_history;
+mod stats;
+mod viewport;
+#[cfg(feature = "ratatui")]
+pub mod widget;
+pub use run::{run, select_from_str, RunOutcome};
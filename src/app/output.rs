@@ -0,0 +1,1016 @@
+//! Processing of the selected text before it is output.
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, Write},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use snafu::ResultExt;
+
+use crate::{
+    configuration::{CancelOutput, Config, SelectionAction},
+    error::{
+        ClipboardWriteSnafu, OutputCommandSnafu, OutputFileSnafu, RunError, TypeSelectionSnafu,
+    },
+};
+
+/// Where the selected text is output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// The standard output.
+    Stdout,
+    /// The standard input of the given shell command.
+    Command(String),
+    /// The clipboard of the terminal, see [copy_to_clipboard].
+    Clipboard,
+    /// The primary selection of the terminal, see [copy_to_clipboard].
+    PrimarySelection,
+    /// A tmux paste buffer, see [load_tmux_buffer].
+    TmuxBuffer,
+    /// The command line in the terminal, see [type_selection].
+    Type,
+    /// The command of the matching [Config::open_handlers].
+    Open,
+    /// The running Neovim or Vim instance, see [open_in_editor].
+    Editor,
+    /// The given command template, see [run_with_selection].
+    Run(String),
+}
+
+/// Get where the text selected in the mode with the given index goes,
+/// according to the action of the mode or [Config::selection_action].
+pub fn get_output_target(config: &Config, mode_index: Option<usize>) -> OutputTarget {
+    let action = mode_index
+        .and_then(|index| config.modes.get(index))
+        .and_then(|mode| mode.action.as_ref())
+        .unwrap_or(&config.selection_action);
+
+    get_action_target(action)
+}
+
+/// Get where the text goes with the given action.
+pub fn get_action_target(action: &SelectionAction) -> OutputTarget {
+    match action {
+        SelectionAction::Output => OutputTarget::Stdout,
+        SelectionAction::Copy => OutputTarget::Clipboard,
+        SelectionAction::CopyPrimary => OutputTarget::PrimarySelection,
+        SelectionAction::TmuxBuffer => OutputTarget::TmuxBuffer,
+        SelectionAction::Type => OutputTarget::Type,
+        SelectionAction::Open => OutputTarget::Open,
+        SelectionAction::Editor => OutputTarget::Editor,
+        SelectionAction::Pipe(command) => OutputTarget::Command(command.clone()),
+        SelectionAction::Run(template) => OutputTarget::Run(template.clone()),
+    }
+}
+
+/// Position of the selected text in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputPosition {
+    /// Index of the line, counting from 1.
+    pub line: usize,
+    /// Index of the byte in the line, counting from 1.
+    pub column: usize,
+    /// Index of the byte in the whole input, counting from 0.
+    pub offset: usize,
+    /// The whole line, without the new line at its end.
+    pub line_text: String,
+}
+
+/// The mode and the regex that produced the selected text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionOrigin {
+    /// Name of the mode the text was selected in.
+    pub mode_name: String,
+    /// Index of the regex of the mode that found the text, if it was
+    /// found by a single one.
+    pub regex_index: Option<usize>,
+    /// The file the text was selected from, if the input is read from files.
+    pub file: Option<PathBuf>,
+}
+
+/// Options that affect how the selected text is output.
+#[derive(Debug, Default)]
+pub struct OutputOptions {
+    /// Remove the whitespace from the start and the end of the selection.
+    pub trim: bool,
+    /// Append a new line to the selection, if anything was selected.
+    pub append_newline: bool,
+    /// Append a NUL byte to the selection instead of a new line, if
+    /// anything was selected.
+    pub append_null: bool,
+    /// Put the position of the selection before it, if it is known.
+    pub with_position: bool,
+    /// Put the origin of the selection before it, if it is known.
+    pub with_origin: bool,
+    /// Put the file the selection was made in before it, if it is known.
+    pub with_file: bool,
+    /// Put the time of the selection before it, see [format_timestamp].
+    pub with_time: bool,
+    /// Put the line the selection starts on after it, separated by a tab,
+    /// if it is known.
+    pub with_line: bool,
+    /// Quote the selection for the shell with [quote_for_shell], if
+    /// anything was selected.
+    pub quote: bool,
+}
+
+/// Convert the selected text into the text that should be output according
+/// to the given options.
+pub fn format_selection(
+    selection: String,
+    position: Option<InputPosition>,
+    origin: Option<&SelectionOrigin>,
+    options: &OutputOptions,
+) -> String {
+    let mut output = if options.trim {
+        selection.trim().to_string()
+    } else {
+        selection
+    };
+
+    if options.quote && !output.is_empty() {
+        output = quote_for_shell(&output);
+    }
+
+    if let Some(position) = position.as_ref().filter(|_| options.with_line) {
+        output = format!("{}\t{}", output, position.line_text);
+    }
+
+    if let Some(position) = position.filter(|_| options.with_position) {
+        output = format!(
+            "{}:{}:{}:{}",
+            position.line, position.column, position.offset, output
+        );
+    }
+
+    if let Some(origin) = origin.filter(|_| options.with_origin) {
+        let regex_index = origin
+            .regex_index
+            .map_or(String::new(), |index| index.to_string());
+        output = format!("{}:{}:{}", origin.mode_name, regex_index, output);
+    }
+
+    let file = origin.and_then(|origin| origin.file.as_ref());
+    if let Some(file) = file.filter(|_| options.with_file) {
+        output = format!("{}:{}", file.display(), output);
+    }
+
+    if options.append_null && !output.is_empty() {
+        output.push('\0');
+    } else if options.append_newline && !output.is_empty() {
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Format the given time as an ISO 8601 timestamp in UTC, e.g.
+/// `2024-05-01T09:30:00Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // Days since 1970-01-01 to the date in the proleptic Gregorian calendar,
+    // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Pipe the given selection through the given shell command and return its
+/// output without the final new line, or the selection itself if the
+/// command fails.
+pub fn filter_selection(selection: String, command: &str) -> String {
+    info!("Filtering the selection through command {command}");
+
+    match run_filter_command(&selection, command) {
+        Ok(output) => output,
+        Err(error) => {
+            warn!("{error}, using the selection as is");
+            selection
+        }
+    }
+}
+
+/// Run the given filter command with the given text as its standard input
+/// and return its standard output.
+fn run_filter_command(text: &str, command: &str) -> Result<String, RunError> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context(OutputCommandSnafu { command })?;
+
+    // The text is written while the output is read, since a filter that
+    // outputs as it reads, e.g. cat, would otherwise block on a full pipe
+    // while the text is still being written to it
+    let stdin = child.stdin.take();
+    let (written, output) = thread::scope(|scope| {
+        let writer = scope.spawn(move || match stdin {
+            Some(mut stdin) => stdin.write_all(text.as_bytes()),
+            None => Ok(()),
+        });
+        let output = child.wait_with_output();
+
+        (writer.join(), output)
+    });
+
+    let output = output.context(OutputCommandSnafu { command })?;
+    match written {
+        // A filter may stop reading once it has what it needs, e.g. head
+        Ok(Err(error)) if error.kind() == io::ErrorKind::BrokenPipe => {
+            info!("Command {command} did not read the whole selection");
+        }
+        Ok(Err(error)) => return Err(error).context(OutputCommandSnafu { command }),
+        Ok(Ok(())) => {}
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+    if !output.status.success() {
+        return Err(RunError::OutputCommandFailed {
+            command: command.to_string(),
+            status: output.status,
+        });
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    Ok(output.strip_suffix('\n').unwrap_or(&output).to_string())
+}
+
+/// Run the given command with the shell and write the given text to its
+/// standard input.
+pub fn output_to_command(command: &str, text: &str) -> Result<(), RunError> {
+    info!("Writing the selection to command {command}");
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context(OutputCommandSnafu { command })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .context(OutputCommandSnafu { command })?;
+    }
+
+    let status = child.wait().context(OutputCommandSnafu { command })?;
+    if !status.success() {
+        return Err(RunError::OutputCommandFailed {
+            command: command.to_string(),
+            status,
+        });
+    }
+
+    Ok(())
+}
+
+/// Write the given text to the file at the given path, after its current
+/// contents if `append` is true, or instead of them otherwise.
+pub fn output_to_file(path: &Path, text: &str, append: bool) -> Result<(), RunError> {
+    info!("Writing the selection to {}", path.display());
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .context(OutputFileSnafu { path })?;
+
+    file.write_all(text.as_bytes())
+        .context(OutputFileSnafu { path })
+}
+
+/// Run the given command template with the shell, with `{}` replaced by
+/// the given text quoted for the shell, and wait for it to finish.
+///
+/// The command is connected to the terminal even if the input or the
+/// output of mless is not, so that it can be e.g. an editor.
+pub fn run_with_selection(template: &str, text: &str) -> Result<(), RunError> {
+    let command = template.replace("{}", &quote_for_shell(text));
+    info!("Running command {command} with the selection");
+
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(&command);
+    if let Ok(terminal) = OpenOptions::new().read(true).write(true).open("/dev/tty") {
+        let terminal_output = terminal
+            .try_clone()
+            .context(OutputCommandSnafu { command: &command })?;
+        shell.stdin(terminal).stdout(terminal_output);
+    }
+
+    let status = shell
+        .status()
+        .context(OutputCommandSnafu { command: &command })?;
+    if !status.success() {
+        return Err(RunError::OutputCommandFailed { command, status });
+    }
+
+    Ok(())
+}
+
+/// Quote the given text so that the shell treats it as a single word,
+/// without expanding anything in it.
+pub fn quote_for_shell(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Load the given text into a new tmux paste buffer, which becomes the one
+/// pasted with `prefix ]`.
+pub fn load_tmux_buffer(text: &str) -> Result<(), RunError> {
+    output_to_command("tmux load-buffer -", text)
+}
+
+/// Type the given text into the tmux pane, the kitty window or the WezTerm
+/// pane mless is started in or, elsewhere, into the terminal, so that the
+/// shell there reads it as if it was typed once mless exits.
+pub fn type_selection(text: &str) -> Result<(), RunError> {
+    if let (Some(_), Ok(pane)) = (env::var_os("TMUX"), env::var("TMUX_PANE")) {
+        return send_keys_to_pane(&pane, text);
+    }
+
+    match get_send_text_command(
+        env::var("KITTY_WINDOW_ID").ok(),
+        env::var("WEZTERM_PANE").ok(),
+    ) {
+        Some(command) => output_to_command(&command, text),
+        None => type_into_terminal(text).context(TypeSelectionSnafu {}),
+    }
+}
+
+/// Get the command that sends the text from its standard input to the given
+/// kitty window or, if there is none, to the given WezTerm pane.
+///
+/// kitty only accepts the command if remote control is enabled with
+/// `allow_remote_control`.
+fn get_send_text_command(
+    kitty_window: Option<String>,
+    wezterm_pane: Option<String>,
+) -> Option<String> {
+    let is_id = |id: &String| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit());
+
+    if let Some(window) = kitty_window.filter(is_id) {
+        return Some(format!("kitty @ send-text --match id:{window} --stdin"));
+    }
+
+    wezterm_pane
+        .filter(is_id)
+        .map(|pane| format!("wezterm cli send-text --pane-id {pane} --no-paste"))
+}
+
+/// Send the given text to the given tmux pane as literal keys.
+fn send_keys_to_pane(pane: &str, text: &str) -> Result<(), RunError> {
+    info!("Typing the selection into tmux pane {pane}");
+
+    let command = "tmux send-keys";
+    let status = Command::new("tmux")
+        .args(["send-keys", "-l", "-t", pane, "--", text])
+        .status()
+        .context(OutputCommandSnafu { command })?;
+    if !status.success() {
+        return Err(RunError::OutputCommandFailed {
+            command: command.to_string(),
+            status,
+        });
+    }
+
+    Ok(())
+}
+
+/// Insert the given text into the input queue of the terminal, which newer
+/// Linux kernels only allow if `dev.tty.legacy_tiocsti` is enabled.
+fn type_into_terminal(text: &str) -> io::Result<()> {
+    info!("Typing the selection into the terminal");
+
+    let terminal = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    for byte in text.as_bytes() {
+        // SAFETY: The file descriptor stays open for the duration of the
+        // call and TIOCSTI only reads the byte behind the pointer
+        let result = unsafe { libc::ioctl(terminal.as_raw_fd(), libc::TIOCSTI, byte as *const u8) };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Open the file in the given `file` or `file:line` text in the Neovim
+/// instance listening at `$NVIM` or, if it is not defined, in the Vim
+/// instance named `$VIM_SERVERNAME`.
+pub fn open_in_editor(text: &str) -> Result<(), RunError> {
+    let (file, line) = parse_file_location(text);
+    // Selected text can come from anywhere, and the file name is sent to
+    // Neovim as the keys of a command, where e.g. a new line would end the
+    // command and start another one
+    if file.contains(char::is_control) {
+        return Err(RunError::UnsafeFileName {
+            file: file.to_string(),
+        });
+    }
+    let Some(args) = get_editor_command(
+        env::var("NVIM").ok(),
+        env::var("VIM_SERVERNAME").ok(),
+        file,
+        line,
+    ) else {
+        return Err(RunError::NoEditorServer);
+    };
+
+    info!("Opening the selection in the editor with {args:?}");
+    let command = args.join(" ");
+    let status = Command::new(&args[0])
+        .args(&args[1..])
+        .status()
+        .context(OutputCommandSnafu { command: &command })?;
+    if !status.success() {
+        return Err(RunError::OutputCommandFailed { command, status });
+    }
+
+    Ok(())
+}
+
+/// Split the given `file:line` or `file:line:column` text into the file and
+/// the line. Anything that doesn't end with a line number is a file.
+fn parse_file_location(text: &str) -> (&str, Option<usize>) {
+    let mut parts = text.splitn(3, ':');
+    match (parts.next(), parts.next().map(str::parse)) {
+        (Some(file), Some(Ok(line))) if !file.is_empty() => (file, Some(line)),
+        _ => (text, None),
+    }
+}
+
+/// Get the program and the arguments that open the given file at the given
+/// line in the Neovim instance listening at `nvim` or in the Vim instance
+/// named `vim`, or [None] if there is neither.
+fn get_editor_command(
+    nvim: Option<String>,
+    vim: Option<String>,
+    file: &str,
+    line: Option<usize>,
+) -> Option<Vec<String>> {
+    let jump = line.map(|line| format!("+{line}"));
+
+    if let Some(server) = nvim.filter(|server| !server.is_empty()) {
+        // Neovim ignores +{line} with --remote, so the file is opened with
+        // the keys of an :edit command instead
+        let file = escape_for_vim(file).replace('<', "<lt>");
+        let keys = match jump {
+            Some(jump) => format!("<C-\\><C-N>:edit {jump} {file}<CR>"),
+            None => format!("<C-\\><C-N>:edit {file}<CR>"),
+        };
+        return Some(vec![
+            "nvim".to_string(),
+            "--server".to_string(),
+            server,
+            "--remote-send".to_string(),
+            keys,
+        ]);
+    }
+
+    let server = vim.filter(|server| !server.is_empty())?;
+    let mut args = vec![
+        "vim".to_string(),
+        "--servername".to_string(),
+        server,
+        "--remote".to_string(),
+    ];
+    args.extend(jump);
+    args.push(file.to_string());
+
+    Some(args)
+}
+
+/// Escape the characters that are special in the file names of Vim's
+/// commands, like `fnameescape()` does.
+fn escape_for_vim(file: &str) -> String {
+    let mut escaped = String::with_capacity(file.len());
+    for character in file.chars() {
+        if " \t\n*?[{`$\\%#'\"|!<".contains(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+/// Copy the given text to the clipboard or, if `primary` is set, to the
+/// primary selection pasted with the middle mouse button, with the given
+/// command or, if it is [None], with the installed clipboard command of the
+/// platform. If there is no such command, the text is copied with
+/// [copy_with_escape_sequence].
+pub fn copy_to_clipboard(text: &str, command: Option<&str>, primary: bool) -> Result<(), RunError> {
+    let wayland = env::var_os("WAYLAND_DISPLAY").is_some();
+    let x11 = env::var_os("DISPLAY").is_some();
+
+    match command.or_else(|| find_clipboard_command(wayland, x11, primary, is_installed)) {
+        Some(command) => output_to_command(command, text),
+        None => copy_with_escape_sequence(text, primary),
+    }
+}
+
+/// Find the first command copying to the clipboard or, if `primary` is set,
+/// to the primary selection, that is usable with the given display servers
+/// and whose program is installed according to `is_installed`.
+fn find_clipboard_command(
+    wayland: bool,
+    x11: bool,
+    primary: bool,
+    is_installed: impl Fn(&str) -> bool,
+) -> Option<&'static str> {
+    let candidates = match primary {
+        true => vec![
+            (wayland, "wl-copy --primary"),
+            (x11, "xclip -selection primary"),
+            (x11, "xsel --primary --input"),
+        ],
+        false => vec![
+            (wayland, "wl-copy"),
+            (x11, "xclip -selection clipboard"),
+            (x11, "xsel --clipboard --input"),
+            (cfg!(target_os = "macos"), "pbcopy"),
+            // Available on Windows Subsystem for Linux
+            (true, "clip.exe"),
+        ],
+    };
+
+    candidates
+        .into_iter()
+        .filter(|(usable, _)| *usable)
+        .map(|(_, command)| command)
+        .find(|command| command.split_whitespace().next().is_some_and(&is_installed))
+}
+
+/// Check whether the given program can be found in one of the directories
+/// in `PATH`.
+fn is_installed(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|directory| directory.join(program).is_file())
+    })
+}
+
+/// Copy the given text to the clipboard or, if `primary` is set, to the
+/// primary selection by writing the OSC 52 escape sequence to the terminal,
+/// which passes it to the clipboard of the machine it runs on, also over SSH.
+fn copy_with_escape_sequence(text: &str, primary: bool) -> Result<(), RunError> {
+    info!("Copying the selection to the clipboard with an escape sequence");
+
+    let in_tmux = env::var_os("TMUX").is_some();
+    let mut terminal = OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .context(ClipboardWriteSnafu {})?;
+
+    terminal
+        .write_all(get_clipboard_sequence(text, in_tmux, primary).as_bytes())
+        .context(ClipboardWriteSnafu {})?;
+    terminal.flush().context(ClipboardWriteSnafu {})
+}
+
+/// Get the OSC 52 escape sequence that copies the given text to the
+/// clipboard or, if `primary` is set, to the primary selection. Inside
+/// tmux, it is wrapped so that tmux passes it through to the outer terminal.
+fn get_clipboard_sequence(text: &str, in_tmux: bool, primary: bool) -> String {
+    let target = if primary { 'p' } else { 'c' };
+    let sequence = format!("\x1b]52;{target};{}\x07", encode_base64(text.as_bytes()));
+
+    match in_tmux {
+        true => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+        false => sequence,
+    }
+}
+
+/// Encode the given bytes with the standard base64 alphabet and padding.
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (u32::from(*byte) << (16 - 8 * index))
+        });
+
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => {
+                    let sextet = (group >> (18 - 6 * index)) & 0x3f;
+                    encoded.push(char::from(ALPHABET[sextet as usize]));
+                }
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Get the text that should be output when the selection is cancelled.
+pub fn get_cancel_output(config: &Config, input_text: &str) -> String {
+    match config.cancel_output {
+        CancelOutput::Nothing => String::new(),
+        CancelOutput::Fallback => config.cancel_fallback.clone(),
+        CancelOutput::Input => input_text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(" text ", false, false, " text "; "without_options")]
+    #[test_case(" text\t\n", true, false, "text"; "with_trim")]
+    #[test_case(" text ", false, true, " text \n"; "with_newline")]
+    #[test_case(" text ", true, true, "text\n"; "with_trim_and_newline")]
+    #[test_case("", true, true, ""; "with_empty_selection")]
+    #[test_case("  ", true, true, ""; "with_whitespace_only_selection")]
+    fn format_selection_returns_expected_output(
+        selection: &str,
+        trim: bool,
+        append_newline: bool,
+        expected: &str,
+    ) {
+        let options = OutputOptions {
+            trim,
+            append_newline,
+            ..OutputOptions::default()
+        };
+
+        assert_eq!(
+            format_selection(selection.to_string(), None, None, &options),
+            expected
+        );
+    }
+
+    #[test_case(false, "text\0"; "without_newline")]
+    #[test_case(true, "text\0"; "with_newline")]
+    fn format_selection_appends_null_instead_of_newline(append_newline: bool, expected: &str) {
+        let options = OutputOptions {
+            append_newline,
+            append_null: true,
+            ..OutputOptions::default()
+        };
+
+        assert_eq!(
+            format_selection("text".to_string(), None, None, &options),
+            expected
+        );
+    }
+
+    #[test_case(" it's ", false, "' it'\\''s '"; "without_trim")]
+    #[test_case(" it's ", true, "'it'\\''s'"; "with_trim")]
+    #[test_case("  ", true, ""; "with_whitespace_only_selection")]
+    fn format_selection_quotes_selection(selection: &str, trim: bool, expected: &str) {
+        let options = OutputOptions {
+            trim,
+            quote: true,
+            ..OutputOptions::default()
+        };
+
+        assert_eq!(
+            format_selection(selection.to_string(), None, None, &options),
+            expected
+        );
+    }
+
+    #[test_case(true, Some((2, 5, 14)), "2:5:14:text\n"; "when_position_is_known")]
+    #[test_case(true, None, "text\n"; "when_position_is_unknown")]
+    #[test_case(false, Some((2, 5, 14)), "text\n"; "when_position_is_not_requested")]
+    fn format_selection_adds_position(
+        with_position: bool,
+        position: Option<(usize, usize, usize)>,
+        expected: &str,
+    ) {
+        let options = OutputOptions {
+            append_newline: true,
+            with_position,
+            ..OutputOptions::default()
+        };
+        let position = position.map(|(line, column, offset)| InputPosition {
+            line,
+            column,
+            offset,
+            line_text: "a text".to_string(),
+        });
+
+        assert_eq!(
+            format_selection("text".to_string(), position, None, &options),
+            expected
+        );
+    }
+
+    #[test_case(true, true, "2:5:14:text\tthe text here\n"; "when_line_is_requested")]
+    #[test_case(true, false, "2:5:14:text\n"; "when_line_is_not_requested")]
+    fn format_selection_adds_line_after_selection(
+        with_position: bool,
+        with_line: bool,
+        expected: &str,
+    ) {
+        let options = OutputOptions {
+            append_newline: true,
+            with_position,
+            with_line,
+            ..OutputOptions::default()
+        };
+        let position = InputPosition {
+            line: 2,
+            column: 5,
+            offset: 14,
+            line_text: "the text here".to_string(),
+        };
+
+        assert_eq!(
+            format_selection("text".to_string(), Some(position), None, &options),
+            expected
+        );
+    }
+
+    #[test_case(Some(1), true, "url:1:2:5:14:text"; "when_regex_is_known")]
+    #[test_case(None, true, "url::2:5:14:text"; "when_regex_is_unknown")]
+    #[test_case(Some(1), false, "2:5:14:text"; "when_origin_is_not_requested")]
+    fn format_selection_adds_origin_before_position(
+        regex_index: Option<usize>,
+        with_origin: bool,
+        expected: &str,
+    ) {
+        let options = OutputOptions {
+            with_position: true,
+            with_origin,
+            ..OutputOptions::default()
+        };
+        let position = InputPosition {
+            line: 2,
+            column: 5,
+            offset: 14,
+            line_text: "a text".to_string(),
+        };
+        let origin = SelectionOrigin {
+            mode_name: "url".to_string(),
+            regex_index,
+            file: None,
+        };
+
+        assert_eq!(
+            format_selection("text".to_string(), Some(position), Some(&origin), &options),
+            expected
+        );
+    }
+
+    #[test_case(true, "src/main.rs:url:1:text"; "when_file_is_requested")]
+    #[test_case(false, "url:1:text"; "when_file_is_not_requested")]
+    fn format_selection_adds_file_before_origin(with_file: bool, expected: &str) {
+        let options = OutputOptions {
+            with_origin: true,
+            with_file,
+            ..OutputOptions::default()
+        };
+        let origin = SelectionOrigin {
+            mode_name: "url".to_string(),
+            regex_index: Some(1),
+            file: Some(PathBuf::from("src/main.rs")),
+        };
+
+        assert_eq!(
+            format_selection("text".to_string(), None, Some(&origin), &options),
+            expected
+        );
+    }
+
+    #[test_case(0, "1970-01-01T00:00:00Z"; "at_epoch")]
+    #[test_case(951786123, "2000-02-29T01:02:03Z"; "on_leap_day")]
+    #[test_case(1700000000, "2023-11-14T22:13:20Z"; "in_november")]
+    #[test_case(4102444799, "2099-12-31T23:59:59Z"; "at_end_of_year")]
+    fn format_timestamp_returns_expected_text(seconds: u64, expected: &str) {
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+
+        assert_eq!(format_timestamp(time), expected);
+    }
+
+    #[test_case("text", "tr a-z A-Z", "TEXT"; "when_command_succeeds")]
+    #[test_case("/dir/file", "xargs basename", "file"; "when_command_ends_output_with_newline")]
+    #[test_case("/dir/file", "false", "/dir/file"; "when_command_fails")]
+    fn filter_selection_returns_expected_text(selection: &str, command: &str, expected: &str) {
+        assert_eq!(filter_selection(selection.to_string(), command), expected);
+    }
+
+    #[test]
+    fn filter_selection_handles_selection_larger_than_pipe() {
+        // Larger than the pipe buffers, which are 64 KiB on Linux
+        let selection = "text\n".repeat(100 * 1024);
+
+        let filtered = filter_selection(selection.clone(), "cat");
+        let head = filter_selection(selection.clone(), "head -c4");
+
+        assert_eq!(filtered, selection.trim_end());
+        assert_eq!(head, "text");
+    }
+
+    #[test_case(true, true, false, &["wl-copy", "xclip"], Some("wl-copy"); "on_wayland")]
+    #[test_case(false, true, false, &["wl-copy", "xclip"], Some("xclip -selection clipboard"); "on_x11")]
+    #[test_case(false, true, false, &["xsel"], Some("xsel --clipboard --input"); "on_x11_without_xclip")]
+    #[test_case(false, false, false, &["wl-copy", "xclip"], None; "without_display")]
+    #[test_case(true, true, false, &[], None; "when_nothing_is_installed")]
+    #[test_case(true, true, true, &["wl-copy", "xclip"], Some("wl-copy --primary"); "for_primary_on_wayland")]
+    #[test_case(false, true, true, &["xsel"], Some("xsel --primary --input"); "for_primary_on_x11")]
+    #[test_case(false, false, true, &["wl-copy", "clip.exe"], None; "for_primary_without_display")]
+    fn find_clipboard_command_returns_expected_command(
+        wayland: bool,
+        x11: bool,
+        primary: bool,
+        installed: &[&str],
+        expected: Option<&str>,
+    ) {
+        let command = find_clipboard_command(wayland, x11, primary, |program| {
+            installed.contains(&program)
+        });
+
+        assert_eq!(command, expected);
+    }
+
+    #[test_case(Some("3"), Some("5"), Some("kitty @ send-text --match id:3 --stdin"); "in_kitty")]
+    #[test_case(None, Some("5"), Some("wezterm cli send-text --pane-id 5 --no-paste"); "in_wezterm")]
+    #[test_case(Some("3; rm"), None, None; "when_window_id_is_not_number")]
+    #[test_case(None, None, None; "in_other_terminal")]
+    fn get_send_text_command_returns_expected_command(
+        kitty_window: Option<&str>,
+        wezterm_pane: Option<&str>,
+        expected: Option<&str>,
+    ) {
+        let command = get_send_text_command(
+            kitty_window.map(str::to_string),
+            wezterm_pane.map(str::to_string),
+        );
+
+        assert_eq!(command.as_deref(), expected);
+    }
+
+    #[test_case("src/main.rs", ("src/main.rs", None); "when_text_is_file")]
+    #[test_case("src/main.rs:12", ("src/main.rs", Some(12)); "when_text_has_line")]
+    #[test_case("src/main.rs:12:5", ("src/main.rs", Some(12)); "when_text_has_column")]
+    #[test_case("C:file", ("C:file", None); "when_text_has_no_line_number")]
+    fn parse_file_location_returns_expected_location(text: &str, expected: (&str, Option<usize>)) {
+        assert_eq!(parse_file_location(text), expected);
+    }
+
+    #[test_case(
+        Some("/tmp/nvim.sock"), None, Some(12),
+        &["nvim", "--server", "/tmp/nvim.sock", "--remote-send", r"<C-\><C-N>:edit +12 my\ file.rs<CR>"];
+        "when_nvim_is_running")]
+    #[test_case(
+        Some("/tmp/nvim.sock"), Some("VIM"), None,
+        &["nvim", "--server", "/tmp/nvim.sock", "--remote-send", r"<C-\><C-N>:edit my\ file.rs<CR>"];
+        "when_both_are_running")]
+    #[test_case(
+        None, Some("VIM"), Some(12),
+        &["vim", "--servername", "VIM", "--remote", "+12", "my file.rs"];
+        "when_vim_is_running")]
+    #[test_case(None, None, Some(12), &[]; "when_nothing_is_running")]
+    fn get_editor_command_returns_expected_command(
+        nvim: Option<&str>,
+        vim: Option<&str>,
+        line: Option<usize>,
+        expected: &[&str],
+    ) {
+        let command = get_editor_command(
+            nvim.map(str::to_string),
+            vim.map(str::to_string),
+            "my file.rs",
+            line,
+        );
+
+        assert_eq!(command.unwrap_or_default(), expected);
+    }
+
+    #[test_case("file\n:!cmd\n"; "when_file_has_new_line")]
+    #[test_case("file\r:!cmd\r:12"; "when_file_with_line_has_carriage_return")]
+    #[test_case("file\x1b:!cmd"; "when_file_has_escape")]
+    fn open_in_editor_rejects_file_with_control_characters(text: &str) {
+        let result = open_in_editor(text);
+
+        assert!(matches!(result, Err(RunError::UnsafeFileName { .. })));
+    }
+
+    #[test]
+    fn output_to_file_replaces_or_appends_to_contents() {
+        let path = env::temp_dir().join(format!("mless-output-{}", std::process::id()));
+
+        output_to_file(&path, "old\n", false).unwrap();
+        output_to_file(&path, "first\n", false).unwrap();
+        output_to_file(&path, "second\n", true).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, "first\nsecond\n");
+    }
+
+    #[test_case("text", "'text'"; "when_text_is_plain")]
+    #[test_case("$HOME `ls` a b", "'$HOME `ls` a b'"; "when_text_has_special_characters")]
+    #[test_case("it's", r"'it'\''s'"; "when_text_has_quote")]
+    fn quote_for_shell_returns_expected_text(text: &str, expected: &str) {
+        assert_eq!(quote_for_shell(text), expected);
+    }
+
+    #[test]
+    fn run_with_selection_substitutes_quoted_selection() {
+        let path = env::temp_dir().join(format!("mless-substitute-{}", std::process::id()));
+        let template = format!("printf '%s|' {{}} > {}", path.display());
+
+        run_with_selection(&template, "a $HOME 'b'").unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, "a $HOME 'b'|");
+    }
+
+    #[test_case("", ""; "when_text_is_empty")]
+    #[test_case("f", "Zg=="; "when_one_byte_is_left")]
+    #[test_case("fo", "Zm8="; "when_two_bytes_are_left")]
+    #[test_case("foo", "Zm9v"; "when_no_bytes_are_left")]
+    #[test_case("foobar", "Zm9vYmFy"; "when_text_has_several_groups")]
+    fn encode_base64_returns_expected_text(text: &str, expected: &str) {
+        assert_eq!(encode_base64(text.as_bytes()), expected);
+    }
+
+    #[test_case(false, false, "\x1b]52;c;Zm9v\x07"; "outside_tmux")]
+    #[test_case(true, false, "\x1bPtmux;\x1b\x1b]52;c;Zm9v\x07\x1b\\"; "inside_tmux")]
+    #[test_case(false, true, "\x1b]52;p;Zm9v\x07"; "for_primary")]
+    fn get_clipboard_sequence_returns_expected_sequence(
+        in_tmux: bool,
+        primary: bool,
+        expected: &str,
+    ) {
+        assert_eq!(get_clipboard_sequence("foo", in_tmux, primary), expected);
+    }
+
+    #[test_case(None, None, SelectionAction::Output, OutputTarget::Stdout; "when_nothing_is_set")]
+    #[test_case(None, None, SelectionAction::Copy, OutputTarget::Clipboard; "when_copying_by_default")]
+    #[test_case(Some(0), Some(SelectionAction::Copy), SelectionAction::Output, OutputTarget::Clipboard; "when_mode_copies")]
+    #[test_case(Some(0), Some(SelectionAction::Output), SelectionAction::Copy, OutputTarget::Stdout; "when_mode_outputs")]
+    #[test_case(None, Some(SelectionAction::Copy), SelectionAction::Output, OutputTarget::Stdout; "when_selecting_mode")]
+    #[test_case(None, None, SelectionAction::TmuxBuffer, OutputTarget::TmuxBuffer; "when_loading_tmux_buffer")]
+    #[test_case(Some(0), Some(SelectionAction::Type), SelectionAction::Output, OutputTarget::Type; "when_mode_types")]
+    #[test_case(None, None, SelectionAction::CopyPrimary, OutputTarget::PrimarySelection; "when_copying_to_primary")]
+    #[test_case(None, None, SelectionAction::Pipe("cliphist store".to_string()), OutputTarget::Command("cliphist store".to_string()); "when_piping")]
+    #[test_case(None, None, SelectionAction::Open, OutputTarget::Open; "when_opening")]
+    #[test_case(None, None, SelectionAction::Editor, OutputTarget::Editor; "when_opening_in_editor")]
+    fn get_output_target_returns_expected_target(
+        mode_index: Option<usize>,
+        mode_action: Option<SelectionAction>,
+        selection_action: SelectionAction,
+        expected: OutputTarget,
+    ) {
+        let mut config = Config {
+            selection_action,
+            ..Config::default()
+        };
+        for mode in &mut config.modes {
+            mode.action = mode_action.clone();
+        }
+
+        assert_eq!(get_output_target(&config, mode_index), expected);
+    }
+
+    #[test_case(CancelOutput::Nothing, ""; "when_nothing_configured")]
+    #[test_case(CancelOutput::Fallback, "fallback"; "when_fallback_configured")]
+    #[test_case(CancelOutput::Input, "input text"; "when_input_configured")]
+    fn get_cancel_output_returns_expected_output(cancel_output: CancelOutput, expected: &str) {
+        let config = Config {
+            cancel_output,
+            cancel_fallback: "fallback".to_string(),
+            ..Config::default()
+        };
+
+        assert_eq!(get_cancel_output(&config, "input text"), expected);
+    }
+}
@@ -0,0 +1,188 @@
+//! Caching of the pages shown while scrolling through the input.
+use std::collections::{HashMap, VecDeque};
+
+use log::trace;
+
+use crate::modes::Hit;
+
+/// Identifies a page of the input by everything that affects its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageKey {
+    /// Index of the input line at the top of the page.
+    pub first_line: usize,
+    pub rows: usize,
+    pub cols: usize,
+    pub wrap_lines: bool,
+    /// Length of the whole input, which changes when more of it is read.
+    pub input_len: usize,
+}
+
+/// A page of the input together with what was found on it.
+#[derive(Debug)]
+pub struct CachedPage {
+    /// The text of the page.
+    pub text: String,
+    /// The hits found on the page, by the index of the mode that found them.
+    pub hits: HashMap<usize, Vec<Hit>>,
+}
+
+/// The hits found on the lines of the pages by one mode, by the text of the
+/// line, with the positions relative to the start of the line.
+pub type LineHits = HashMap<String, Vec<Hit>>;
+
+/// How many lines are kept in [LineHits] of a mode before they are dropped.
+const LINE_HITS_CAPACITY: usize = 4096;
+
+/// Keeps the most recently shown pages, so that scrolling back to them does
+/// not lay out the text and match it again.
+///
+/// It also keeps the hits found on the lines of the pages, so that only the
+/// new lines need to be matched when a new page is shown after scrolling.
+pub struct PageCache {
+    pages: HashMap<PageKey, CachedPage>,
+    /// Keys of [PageCache::pages] from the least to the most recently used.
+    usage: VecDeque<PageKey>,
+    /// The maximum number of the kept pages.
+    capacity: usize,
+    /// The hits found on the lines, by the index of the mode that found them.
+    line_hits: HashMap<usize, LineHits>,
+}
+
+impl PageCache {
+    /// Create a cache that keeps at most the given number of pages.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pages: HashMap::new(),
+            usage: VecDeque::new(),
+            capacity,
+            line_hits: HashMap::new(),
+        }
+    }
+
+    /// Get the page with the given key, creating it from the text returned
+    /// by `get_text` if it is not cached.
+    pub fn get(&mut self, key: PageKey, get_text: impl FnOnce() -> String) -> &mut CachedPage {
+        self.mark_used(key);
+
+        Self::get_or_create(&mut self.pages, key, get_text)
+    }
+
+    /// Get the page with the given key like [PageCache::get], together with
+    /// the hits found on the lines of the pages by the mode with the given
+    /// index.
+    pub fn get_with_line_hits(
+        &mut self,
+        key: PageKey,
+        mode_index: usize,
+        get_text: impl FnOnce() -> String,
+    ) -> (&mut CachedPage, &mut LineHits) {
+        self.mark_used(key);
+
+        let line_hits = self.line_hits.entry(mode_index).or_default();
+        if line_hits.len() > LINE_HITS_CAPACITY {
+            trace!("Dropping the hits of the lines found by mode {mode_index}");
+            line_hits.clear();
+        }
+
+        (
+            Self::get_or_create(&mut self.pages, key, get_text),
+            line_hits,
+        )
+    }
+
+    /// Mark the page with the given key as the most recently used one,
+    /// evicting the least recently used page if there are too many.
+    fn mark_used(&mut self, key: PageKey) {
+        self.usage.retain(|used| *used != key);
+        self.usage.push_back(key);
+
+        if self.usage.len() > self.capacity {
+            if let Some(evicted) = self.usage.pop_front() {
+                trace!("Evicting page {evicted:?} from the cache");
+                self.pages.remove(&evicted);
+            }
+        }
+    }
+
+    /// Get the page with the given key from the pages, creating it from the
+    /// text returned by `get_text` if it is not there.
+    fn get_or_create(
+        pages: &mut HashMap<PageKey, CachedPage>,
+        key: PageKey,
+        get_text: impl FnOnce() -> String,
+    ) -> &mut CachedPage {
+        pages.entry(key).or_insert_with(|| {
+            trace!("Page {key:?} is not cached");
+            CachedPage {
+                text: get_text(),
+                hits: HashMap::new(),
+            }
+        })
+    }
+
+    /// Remove all the pages, e.g. when the input is replaced with another
+    /// one whose pages could have the same keys.
+    pub fn clear(&mut self) {
+        self.pages.clear();
+        self.usage.clear();
+        self.line_hits.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(first_line: usize) -> PageKey {
+        PageKey {
+            first_line,
+            rows: 10,
+            cols: 10,
+            wrap_lines: true,
+            input_len: 100,
+        }
+    }
+
+    #[test]
+    fn cached_page_is_not_created_again() {
+        let mut cache = PageCache::new(2);
+        cache.get(key(0), || "page 0".to_string());
+
+        let page = cache.get(key(0), || panic!("The page should be cached"));
+
+        assert_eq!(page.text, "page 0");
+    }
+
+    #[test]
+    fn least_recently_used_page_is_evicted() {
+        let mut cache = PageCache::new(2);
+        cache.get(key(0), || "page 0".to_string());
+        cache.get(key(1), || "page 1".to_string());
+        cache.get(key(0), || "page 0".to_string());
+
+        cache.get(key(2), || "page 2".to_string());
+
+        assert_eq!(
+            cache.get(key(0), || "new page 0".to_string()).text,
+            "page 0"
+        );
+        assert_eq!(
+            cache.get(key(1), || "new page 1".to_string()).text,
+            "new page 1"
+        );
+    }
+
+    #[test]
+    fn line_hits_are_kept_for_other_pages_of_same_mode() {
+        let mut cache = PageCache::new(2);
+        let (_, line_hits) = cache.get_with_line_hits(key(0), 0, || "page 0".to_string());
+        line_hits.insert("line".to_string(), vec![]);
+
+        let (page, line_hits) = cache.get_with_line_hits(key(1), 0, || "page 1".to_string());
+        assert_eq!(page.text, "page 1");
+        assert!(line_hits.contains_key("line"));
+
+        let (_, line_hits) = cache.get_with_line_hits(key(1), 1, || "page 1".to_string());
+        assert!(line_hits.is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Running commands with their output going to a pseudo-terminal, so that
+//! they behave (e.g. use colors) as if they were run in the terminal.
+use std::{
+    fs::File,
+    io::{self, Read},
+    os::fd::{FromRawFd, OwnedFd},
+    process::{Command, ExitStatus, Stdio},
+    ptr,
+};
+
+use log::debug;
+
+/// Run the given command with its standard output connected to a new
+/// pseudo-terminal with the given size, and return its output and exit
+/// status once it finishes.
+///
+/// Since the terminal turns every new line into `\r\n`, the output has
+/// them turned back into `\n`.
+pub fn get_pty_output(
+    mut command: Command,
+    rows: usize,
+    cols: usize,
+) -> io::Result<(String, ExitStatus)> {
+    let (master, slave) = open_pty(rows, cols)?;
+
+    let mut child = command.stdout(Stdio::from(slave)).spawn()?;
+    // The command keeps the terminal open until it is dropped, which would
+    // prevent the reading below from ever ending
+    drop(command);
+
+    let mut output = vec![];
+    match File::from(master).read_to_end(&mut output) {
+        // Linux reports that the command closed the terminal with EIO
+        Err(error) if error.raw_os_error() == Some(libc::EIO) => {
+            debug!("Pseudo-terminal closed with {error}");
+        }
+        result => {
+            result?;
+        }
+    }
+
+    let status = child.wait()?;
+    let output = String::from_utf8_lossy(&output).replace("\r\n", "\n");
+
+    Ok((output, status))
+}
+
+/// Open a new pseudo-terminal with the given size and return its master
+/// and slave side.
+fn open_pty(rows: usize, cols: usize) -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut master = -1;
+    let mut slave = -1;
+    let size = libc::winsize {
+        ws_row: u16::try_from(rows).unwrap_or(u16::MAX),
+        ws_col: u16::try_from(cols).unwrap_or(u16::MAX),
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+
+    // SAFETY: All the pointers are valid for the duration of the call and
+    // are not kept by openpty
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &size,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: On success, openpty returns two new file descriptors that
+    // nothing else owns
+    let fds = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+    Ok(fds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn command_output_goes_to_terminal() {
+        let (output, status) = get_pty_output(shell("test -t 1 && echo terminal"), 24, 80).unwrap();
+
+        assert!(status.success());
+        assert_eq!(output, "terminal\n");
+    }
+
+    #[test]
+    fn terminal_has_given_size() {
+        let (output, _) = get_pty_output(shell("stty size <&1"), 24, 80).unwrap();
+
+        assert_eq!(output, "24 80\n");
+    }
+}
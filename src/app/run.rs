@@ -1,24 +1,66 @@
 //! Initialization, main loop and similar.
 use std::{
-    fs::{File, OpenOptions},
-    io::{self, BufReader, Read},
-    ops::Deref,
+    collections::{hash_map::Entry, HashMap},
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, IsTerminal, Read, Write},
+    ops::{ControlFlow, Deref},
+    os::{
+        fd::AsFd,
+        unix::{
+            fs::FileTypeExt,
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant, SystemTime},
 };
 
-use crossterm::{event::read, terminal};
+use crossterm::{event::Event, terminal};
 use log::{debug, info, trace, warn};
-use snafu::ResultExt;
+use regex::Regex;
+use snafu::{OptionExt, ResultExt};
 
 use crate::{
-    app::configuration_handling::{get_config_file_location, load_config},
-    configuration::{self, ModeArgs},
-    error::{CouldNotReadInputSnafu, RunError, TerminalHandlingSnafu, TtyOpenSnafu},
-    hints::{HintGenerator, HintPoolGenerator},
+    app::bench::run_benchmarks,
+    app::configuration_handling::{
+        get_config_file_location, get_config_file_locations, load_config, load_merged_config,
+    },
+    app::debug_overlay::DebugOverlay,
+    app::event_source::{EventRecorder, EventReplayer, EventSource, Wakeup},
+    app::force_quit::start_force_quit_handler,
+    app::input_filters::prepare_input,
+    app::input_follower::InputFollower,
+    app::input_text::{from_utf8_keeping_offsets, InputText, OffsetMap},
+    app::last_selection::{load_last_selection, save_last_selection},
+    app::list_ui::{ListUi, ListUiEvent},
+    app::loop_state::LoopState,
+    app::output::{
+        copy_to_clipboard, filter_selection, format_selection, format_timestamp, get_action_target,
+        get_cancel_output, get_output_target, load_tmux_buffer, open_in_editor, output_to_command,
+        output_to_file, quote_for_shell, run_with_selection, type_selection, InputPosition,
+        OutputOptions, OutputTarget, SelectionOrigin,
+    },
+    app::page_cache::PageCache,
+    app::pty::get_pty_output,
+    app::search::find_next_line,
+    app::selection_history::{add_to_history, get_history_mode, load_history},
+    app::stats::{self, Step},
+    app::viewport::Viewport,
+    configuration::{
+        self, parse_color, CancelOutput, Config, HintGeneratorKind, HintOrder, InputFilter,
+        ModeArgs, MouseWheelScroll, OpenHandler, RegexArgs,
+    },
+    error::{
+        CouldNotReadInputSnafu, InputCommandSnafu, InvalidRegexSnafu, NoFreeHotkeySnafu, RunError,
+        SelectionOutputSnafu, ServerSnafu, TerminalHandlingSnafu, TtyOpenSnafu,
+    },
+    hints::{HintGenerator, HintPoolGenerator, NumericGenerator, SyllableGenerator},
     input_handler::{Action, InputHandler},
     logging::initialize_logging,
-    modes::{Mode, ModeEvent, ModeSelectorMode, RegexMode},
-    pager::get_page,
-    rendering::{DrawInstruction, Renderer},
+    modes::{create_registered_mode, Hit, Mode, ModeEvent, ModeSelectorMode, RegexMode},
+    rendering::{DrawInstruction, Renderer, TerminalGuard},
 };
 
 use crate::args::Args;
@@ -32,184 +74,3072 @@ fn create_renderer() -> Result<Renderer<File>, RunError> {
         .open(output_path)
         .context(TtyOpenSnafu {})?;
 
-    let renderer = Renderer { output: tty };
+    let renderer = Renderer::new(tty);
 
     Ok(renderer)
 }
 
-fn create_mode<'a>(
-    input_text: &str,
-    hint_generator: &dyn HintGenerator,
-    config: &'a configuration::Config,
-    mode_args: Option<&ModeArgs>,
-) -> Result<Box<dyn Mode + 'a>, RunError> {
-    let modes = &config.modes;
+/// Run the given command with the shell and return its standard output.
+///
+/// If `pty` is true, the output goes to a pseudo-terminal, so that the
+/// command uses colors as if it was run in the terminal.
+fn get_command_output(command: &str, pty: bool) -> Result<String, RunError> {
+    info!("Reading input from command {command}, with pseudo-terminal: {pty}");
+
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command).stderr(Stdio::inherit());
 
-    match mode_args {
-        Some(mode_args) => {
-            let ModeArgs::RegexMode(args) = mode_args;
-            let mode = Box::new(RegexMode::new(input_text, args, hint_generator, config)?);
+    let (output, status) = match pty {
+        true => {
+            // Commands like git would otherwise start a pager in the terminal
+            shell.env("PAGER", "cat").env("GIT_PAGER", "cat");
+            shell.stdin(Stdio::null());
 
-            Ok(mode)
+            let (rows, cols) = get_terminal_size()?;
+            get_pty_output(shell, rows, cols).context(InputCommandSnafu { command })?
         }
-        None => Ok(Box::new(ModeSelectorMode::new(modes))),
+        false => {
+            let output = shell.output().context(InputCommandSnafu { command })?;
+            let stdout = from_utf8_keeping_offsets(&output.stdout).into_owned();
+            (stdout, output.status)
+        }
+    };
+
+    if !status.success() {
+        return Err(RunError::InputCommandFailed {
+            command: command.to_string(),
+            status,
+        });
+    }
+
+    Ok(output)
+}
+
+/// Get the shell command that outputs the contents of the tmux pane with
+/// the given ID, including the colors, or of the active pane if the ID is
+/// empty.
+fn get_tmux_capture_command(pane: &str) -> String {
+    match pane {
+        "" => "tmux capture-pane -e -p".to_string(),
+        _ => format!("tmux capture-pane -e -p -t {}", quote_for_shell(pane)),
     }
 }
 
-fn get_input_text(args: &Args) -> Result<String, RunError> {
-    let input_text = match &args.file {
-        Some(path) => {
-            std::fs::read_to_string(path) //
-                .context(CouldNotReadInputSnafu {})?
+/// The input text together with the reader of its remaining part.
+struct Input {
+    /// The part of the input that was read so far.
+    text: InputText,
+    /// Reads the rest of the input in the background, if it is not read yet.
+    follower: Option<InputFollower>,
+    /// Whether the screen should stay at the end of the input as it grows.
+    follow: bool,
+    /// The files given as the arguments, empty if the input is not read
+    /// from files.
+    files: Vec<PathBuf>,
+    /// Index of the shown file in [Input::files].
+    file_index: usize,
+    /// Whether the shown file is read again when it changes.
+    watch: bool,
+    /// The modification time and the size of the shown file when it was
+    /// opened, used to notice when it changes.
+    file_version: Option<(SystemTime, u64)>,
+    /// Size in bytes of the file being read, if known, used to show how
+    /// much of it is read.
+    size: Option<u64>,
+    /// The shell command the input is the output of, if any, and whether
+    /// it is run in a pseudo-terminal.
+    command: Option<(String, bool)>,
+    /// The filters applied to the input as it is read.
+    filters: Vec<InputFilter>,
+    /// The number of the first lines of the input that are kept, if limited.
+    max_lines: Option<usize>,
+    /// The number of the last lines of the input that are kept, if limited.
+    tail: Option<usize>,
+    /// The number of the last lines of the input that are kept in memory
+    /// while it is read, if limited.
+    keep_lines: Option<usize>,
+    /// The number of lines dropped from the start of the input so far
+    /// because of [Input::keep_lines].
+    dropped_lines: usize,
+    /// Whether the whole input is read before it is used, since nothing is
+    /// shown until then anyway.
+    read_whole: bool,
+    /// Indexes of the lines that only separate the concatenated inputs and
+    /// cannot be selected from, in ascending order.
+    separator_lines: Vec<usize>,
+}
+
+/// Size in bytes from which the files are mapped into memory instead of
+/// being read.
+const LARGE_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// How long to wait for the first screen of the input before showing
+/// whatever arrived until then.
+const FIRST_SCREEN_TIMEOUT: Duration = Duration::from_millis(500);
+
+impl Input {
+    /// Start reading the input according to the arguments and wait for the
+    /// first screen of it to arrive. The rest is read in the background.
+    fn start(args: &Args, config: &configuration::Config) -> Result<Self, RunError> {
+        let mut input = Input {
+            text: InputText::default(),
+            follower: None,
+            follow: args.follow,
+            files: args.files.clone(),
+            file_index: 0,
+            watch: args.watch,
+            file_version: None,
+            size: None,
+            command: None,
+            filters: config.input_filters.clone(),
+            max_lines: args.max_lines,
+            tail: args.tail,
+            keep_lines: args.keep_lines,
+            dropped_lines: 0,
+            read_whole: args.list_matches
+                || args.select.is_some()
+                || args.select_first
+                || args.list_ui,
+            separator_lines: vec![],
+        };
+
+        if args.history {
+            input.set_text(&load_history()?.join("\n"));
+            return Ok(input);
         }
-        None => {
-            let mut ret = "".to_string();
-            io::stdin()
-                .read_to_string(&mut ret) //
-                .context(CouldNotReadInputSnafu {})?;
-            ret
+
+        let tmux_command = args.tmux_pane.as_deref().map(get_tmux_capture_command);
+        if let Some(command) = args.exec.as_ref().or(tmux_command.as_ref()) {
+            input.set_text(&get_command_output(command, args.pty)?);
+            input.command = Some((command.clone(), args.pty));
+            return Ok(input);
         }
-    };
-    Ok(input_text)
+
+        // The files would be shown instead of the piped input without a word.
+        // It is not an error, since stdin is also a pipe when mless is run
+        // from a script, e.g. in `find | while read f; do mless "$f"; done`
+        let reads_stdin = input.files.iter().any(|path| path.as_os_str() == "-");
+        if !input.files.is_empty() && !reads_stdin && is_stdin_piped() {
+            warn!(
+                "Files were given while text was also piped into mless, the piped text is \
+                 ignored, use --concat with `-` for the piped text to select from both"
+            );
+        }
+
+        if args.concat {
+            input.concatenate_files(&config.input_header)?;
+            return Ok(input);
+        }
+
+        if !input.files.is_empty() {
+            input.open_file(0)?;
+            return Ok(input);
+        }
+
+        match &config.default_input_command {
+            // Nothing is piped in, so reading stdin would just block
+            Some(command) if !args.follow && io::stdin().is_terminal() => {
+                input.set_text(&get_command_output(command, false)?);
+                input.command = Some((command.clone(), false));
+            }
+            // Reading the keyboard as the input would block without explanation
+            _ if io::stdin().is_terminal() => return Err(RunError::NoInput),
+            _ => {
+                input.start_reading(InputFollower::start(io::stdin()))?;
+
+                // An empty screen would not tell why there is nothing to select
+                let has_ended = input
+                    .follower
+                    .as_ref()
+                    .is_some_and(InputFollower::has_ended);
+                if has_ended && !input.follow && input.text.is_empty() {
+                    return Err(RunError::EmptyInput);
+                }
+            }
+        }
+
+        Ok(input)
+    }
+
+    /// Create the input from the given text, e.g. received in a request to
+    /// the server, see [serve].
+    fn from_text(text: &str, config: &configuration::Config) -> Self {
+        let mut input = Input {
+            text: InputText::default(),
+            follower: None,
+            follow: false,
+            files: vec![],
+            file_index: 0,
+            watch: false,
+            file_version: None,
+            size: None,
+            command: None,
+            filters: config.input_filters.clone(),
+            max_lines: None,
+            tail: None,
+            keep_lines: None,
+            dropped_lines: 0,
+            read_whole: false,
+            separator_lines: vec![],
+        };
+        input.set_text(text);
+
+        input
+    }
+
+    /// Replace the current input with the file with the given index in
+    /// [Input::files].
+    fn open_file(&mut self, index: usize) -> Result<(), RunError> {
+        let Some(path) = self.files.get(index) else {
+            return Ok(());
+        };
+
+        info!("Reading input from {}", path.display());
+        let file = File::open(path).context(CouldNotReadInputSnafu {})?;
+        self.file_index = index;
+
+        let metadata = file.metadata().ok();
+        self.file_version = metadata.as_ref().and_then(get_file_version);
+        self.size = metadata
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len());
+        // The mapped text cannot be changed by the filters or the limits,
+        // and the files that change while shown must not be mapped at all
+        let is_unchanged = !self.watch
+            && !self.follow
+            && self.filters.is_empty()
+            && self.max_lines.is_none()
+            && self.tail.is_none()
+            && self.keep_lines.is_none();
+        if self.size.is_some_and(|size| size >= LARGE_FILE_SIZE) && is_unchanged {
+            if let Some(text) = InputText::map_file(&file) {
+                self.text = text;
+                self.follower = None;
+                return Ok(());
+            }
+        }
+
+        self.start_reading(InputFollower::start(file))
+    }
+
+    /// Replace the current input with all of [Input::files], with `-`
+    /// standing for the standard input, one after another. Each of them is
+    /// preceded by a separator line made from the given header, with
+    /// `{name}` replaced by the name of the input.
+    ///
+    /// Unlike a single input, the inputs are read completely before they
+    /// are shown, and cannot be switched between afterwards.
+    fn concatenate_files(&mut self, header: &str) -> Result<(), RunError> {
+        let mut text = InputText::default();
+        let mut line_count = 0;
+
+        for path in std::mem::take(&mut self.files) {
+            let (name, content) = match path.to_str() {
+                Some("-") => (
+                    "standard input".to_string(),
+                    InputFollower::start(io::stdin()).wait_for_end(),
+                ),
+                _ => {
+                    info!("Reading input from {}", path.display());
+                    let file = File::open(&path).context(CouldNotReadInputSnafu {})?;
+                    (
+                        path.display().to_string(),
+                        InputFollower::start(file).wait_for_end(),
+                    )
+                }
+            };
+
+            self.separator_lines.push(line_count);
+            // The offsets are the ones in all the inputs one after another,
+            // which the separator lines are not a part of
+            let header = header.replace("{name}", &name) + "\n";
+            let mut header_offsets = OffsetMap::default();
+            header_offsets.push(header.len(), 0);
+            text.push_str(&header, &header_offsets);
+
+            let (mut prepared, mut offsets) = prepare_input(&self.filters, &content);
+            if !prepared.is_empty() && !prepared.ends_with('\n') {
+                prepared.push('\n');
+                offsets.push(prepared.len(), content.len());
+            }
+            line_count += 1 + prepared.matches('\n').count();
+            text.push_str(&prepared, &offsets);
+        }
+
+        self.text = text;
+        self.limit_lines();
+        Ok(())
+    }
+
+    /// Replace the current input with the one read by the given follower,
+    /// waiting for the first screen of it to arrive.
+    fn start_reading(&mut self, mut follower: InputFollower) -> Result<(), RunError> {
+        // The last lines are known only after the whole input is read
+        if self.tail.is_some() || self.read_whole {
+            self.follower = None;
+            self.set_text(&follower.wait_for_end());
+            return Ok(());
+        }
+
+        let (rows, _) = get_terminal_size()?;
+        self.set_text(&follower.wait_for_lines(rows, Some(FIRST_SCREEN_TIMEOUT)));
+        self.follower = Some(follower);
+
+        Ok(())
+    }
+
+    /// Replace the current input with the file that comes after (or before
+    /// if `forward` is false) the current one in [Input::files].
+    ///
+    /// Returns whether the input changed.
+    fn switch_file(&mut self, forward: bool) -> Result<bool, RunError> {
+        let index = match forward {
+            true => self.file_index.checked_add(1),
+            false => self.file_index.checked_sub(1),
+        };
+        let Some(index) = index.filter(|index| *index < self.files.len()) else {
+            debug!("There is no file to switch to");
+            return Ok(false);
+        };
+
+        match self.open_file(index) {
+            Ok(()) => Ok(true),
+            // The file may have been removed in the meantime, which should
+            // not end the selection in the current one
+            Err(RunError::CouldNotReadInput { source }) => {
+                warn!("Could not open the file with index {index}: {source}");
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Open the shown file again if it is watched and changed since it was
+    /// opened.
+    ///
+    /// Returns whether the input changed.
+    fn reload_if_changed(&mut self) -> Result<bool, RunError> {
+        let Some(path) = self.files.get(self.file_index).filter(|_| self.watch) else {
+            return Ok(false);
+        };
+
+        let version = fs::metadata(path).ok().as_ref().and_then(get_file_version);
+        if version.is_none() || version == self.file_version {
+            return Ok(false);
+        }
+        info!("{} changed, reading it again", path.display());
+
+        match self.open_file(self.file_index) {
+            Ok(()) => Ok(true),
+            // The file may be in the middle of being replaced, in which
+            // case it is read again once it is there
+            Err(RunError::CouldNotReadInput { source }) => {
+                warn!("Could not open the changed file: {source}");
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Run the command the input is the output of again and replace the
+    /// input with its new output.
+    ///
+    /// Returns whether the input changed, which it does not if it is not the
+    /// output of a command or the command failed.
+    fn refresh(&mut self) -> Result<bool, RunError> {
+        let Some((command, pty)) = &self.command else {
+            debug!("The input is not the output of a command, nothing to refresh");
+            return Ok(false);
+        };
+
+        match get_command_output(command, *pty) {
+            Ok(output) => {
+                self.set_text(&output);
+                Ok(true)
+            }
+            // A command that fails only sometimes should not end the selection
+            Err(RunError::InputCommandFailed { command, status }) => {
+                warn!("Command {command} failed with {status}, keeping the previous input");
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Replace the input with the given text, prepared with [prepare_input].
+    fn set_text(&mut self, text: &str) {
+        let (text, offsets) = prepare_input(&self.filters, text);
+        self.text = InputText::new(text, offsets);
+        self.limit_lines();
+    }
+
+    /// Append the given text to the input, prepared with [prepare_input].
+    fn push_text(&mut self, text: &str) {
+        let (text, offsets) = prepare_input(&self.filters, text);
+        self.text.push_str(&text, &offsets);
+        self.limit_lines();
+    }
+
+    /// Keep only the lines allowed by [Input::max_lines], [Input::tail] and
+    /// [Input::keep_lines], and stop reading once there are
+    /// [Input::max_lines] lines.
+    fn limit_lines(&mut self) {
+        if let Some(max_lines) = self.max_lines {
+            if self.text.line_count() > max_lines && self.follower.is_some() {
+                info!("Read {max_lines} lines, not reading the rest of the input");
+                self.follower = None;
+            }
+            self.text.keep_first_lines(max_lines);
+        }
+
+        if let Some(tail) = self.tail {
+            self.text.keep_last_lines(tail);
+        }
+
+        if let Some(keep_lines) = self.keep_lines {
+            let line_count = self.text.line_count();
+            if line_count > keep_lines {
+                self.text.keep_last_lines(keep_lines);
+                self.dropped_lines += line_count - self.text.line_count();
+            }
+        }
+    }
+
+    /// Wait until the given number of lines is read or the input ends.
+    fn read_lines(&mut self, line_count: usize) {
+        let missing = line_count.saturating_sub(self.text.line_count());
+
+        if let Some(follower) = &mut self.follower {
+            let new_text = follower.wait_for_lines(missing, None);
+            self.push_text(&new_text);
+        }
+    }
+
+    /// Wait until the whole input is read.
+    fn read_to_end(&mut self) {
+        if let Some(follower) = &mut self.follower {
+            let new_text = follower.wait_for_end();
+            self.push_text(&new_text);
+        }
+    }
+
+    /// Get the text describing the shown file, if there are several of them,
+    /// and how much of the input is read, if it is still being read.
+    fn get_status(&self) -> Option<String> {
+        let mut parts = vec![];
+
+        if let Some(path) = self
+            .files
+            .get(self.file_index)
+            .filter(|_| self.files.len() > 1)
+        {
+            parts.push(format!(
+                "{} ({}/{})",
+                path.display(),
+                self.file_index + 1,
+                self.files.len()
+            ));
+        }
+
+        let reading = self
+            .follower
+            .as_ref()
+            .is_some_and(|follower| !follower.has_ended());
+        // Followed input never ends, so it is not worth mentioning
+        if reading && !self.follow {
+            let read = self.text.len();
+            parts.push(match self.size {
+                Some(size) => {
+                    let percent = (read as u64).saturating_mul(100) / size.max(1);
+                    format!("reading {}%", percent.min(100))
+                }
+                None => format!("reading {}", format_size(read)),
+            });
+        }
+
+        match parts.is_empty() {
+            true => None,
+            false => Some(parts.join("  ")),
+        }
+    }
+}
+
+/// Get the modification time and the size of a file with the given
+/// metadata, which change whenever the file is changed.
+fn get_file_version(metadata: &fs::Metadata) -> Option<(SystemTime, u64)> {
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Get a readable text for the given number of bytes, e.g. "1.5 MiB".
+fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
 }
 
-fn get_input_page(input_text: &str) -> Result<String, RunError> {
+/// Get the size of the terminal as (rows, columns).
+fn get_terminal_size() -> Result<(usize, usize), RunError> {
     let (cols, rows) = terminal::size() //
         .context(TerminalHandlingSnafu {
             operation: "get size",
         })?;
 
-    let mut input_buffer = BufReader::new(input_text.as_bytes());
-    let input_page = get_page(&mut input_buffer, rows as usize, cols as usize);
+    Ok((rows as usize, cols as usize))
+}
+
+/// How many pages are kept in the [PageCache].
+const PAGE_CACHE_CAPACITY: usize = 32;
 
-    trace!("Input text: {}", input_text);
-    trace!("Input page: {}", input_page);
+/// The text of a shown page together with the mode created for it.
+type ShownPage<'a> = (String, Box<dyn Mode + 'a>);
 
-    Ok(input_page)
+/// The pages of the input shown so far, together with what is needed to
+/// create the modes for them.
+struct Pages<'a> {
+    /// The pages and the hits found on them, see [PageCache].
+    page_cache: PageCache,
+    hint_generator: &'a dyn HintGenerator,
+    config: &'a Config,
 }
 
-fn run_main_loop(
-    input_handler: InputHandler,
-    hint_generator: &dyn HintGenerator,
-    config: &configuration::Config,
-    renderer: &mut Renderer<File>,
-    input_text: String,
-    start_in_mode: Option<&configuration::Mode>,
-) -> Result<String, RunError> {
-    let modes = &config.modes;
-    let mut input_page = get_input_page(&input_text)?;
+impl<'a> Pages<'a> {
+    /// Create the pages, with none shown yet.
+    fn new(hint_generator: &'a dyn HintGenerator, config: &'a Config) -> Self {
+        Self {
+            page_cache: PageCache::new(PAGE_CACHE_CAPACITY),
+            hint_generator,
+            config,
+        }
+    }
 
-    let initial_mode = start_in_mode.unwrap_or(&config.modes[0]);
-    let mut current_mode_args = Some(&initial_mode.args);
-    let mut current_mode = create_mode(&input_text, hint_generator, config, current_mode_args)?;
+    /// Get the page of the input shown by the viewport together with the mode
+    /// with the given index created for it, reusing the page and the found hits
+    /// from the cache if the page was shown before.
+    fn show_page(
+        &mut self,
+        input: &Input,
+        viewport: &Viewport,
+        screen_size: (usize, usize),
+        mode_index: Option<usize>,
+    ) -> Result<ShownPage<'a>, RunError> {
+        self.show_page_with_hints(input, viewport, screen_size, mode_index, &HashMap::new())
+    }
 
-    // Make sure the data is rendered as early as possible to avoid blinking
-    renderer.render(&input_page, &[DrawInstruction::Data], config)?;
+    /// Same as [Pages::show_page], but the hits keep the hints from
+    /// `previous_hints` where possible, see [Mode::get_hint_assignments].
+    fn show_page_with_hints(
+        &mut self,
+        input: &Input,
+        viewport: &Viewport,
+        screen_size: (usize, usize),
+        mode_index: Option<usize>,
+        previous_hints: &HashMap<String, String>,
+    ) -> Result<ShownPage<'a>, RunError> {
+        let (hint_generator, config) = (self.hint_generator, self.config);
+        let input_text = &input.text;
+        let (rows, cols) = screen_size;
+        let key = viewport.get_page_key(input_text, rows, cols);
+        let get_text = || stats::measure(Step::Page, || viewport.get_page(input_text, rows, cols));
 
-    info!("Starting the loop");
-    loop {
-        let draw_instructions = current_mode.get_draw_instructions();
-        renderer.render(&input_page, &draw_instructions, config)?;
+        trace!("Input text: {}", input_text.to_str());
 
-        let action = match read() {
-            Ok(event) => {
-                debug!("Got event {:?}", event);
-                input_handler.get_action(event)
+        let (index, args) = match mode_index.zip(get_mode_args(config, mode_index)) {
+            Some((index, ModeArgs::RegexMode(args))) => (index, args),
+            Some((_, ModeArgs::Registered { name, args })) => {
+                let page = self.page_cache.get(key, get_text);
+                let mode = create_registered_mode(name, &page.text, args, hint_generator, config)?;
+                return Ok((page.text.clone(), mode));
+            }
+            None => {
+                let page = self.page_cache.get(key, get_text);
+                let mode: Box<dyn Mode + 'a> = Box::new(ModeSelectorMode::new(&config.modes));
+                return Ok((page.text.clone(), mode));
             }
-            _ => None,
         };
 
-        debug!("Got input handler action {:?}", action);
+        let (page, line_hits) = self.page_cache.get_with_line_hits(key, index, get_text);
+        trace!("Input page: {}", page.text);
 
-        let mode_action = match action {
-            Some(Action::Exit) => Some(ModeEvent::TextSelected("".to_string())),
-            Some(Action::ForwardKeyPress(keypress)) => current_mode.handle_key_press(keypress),
-            Some(Action::Resize) => {
-                input_page = get_input_page(&input_text)?;
-                current_mode = create_mode(&input_text, hint_generator, config, current_mode_args)?;
-                None
-            }
-            Some(Action::GoToModeSelection) => {
-                current_mode_args = None;
-                current_mode = create_mode(&input_text, hint_generator, config, current_mode_args)?;
-                None
+        let hits = match page.hits.entry(index) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                let mut hits = stats::measure(Step::Match, || {
+                    RegexMode::find_hits_by_line(&page.text, args, config, line_hits)
+                })?;
+                remove_separator_hits(
+                    &mut hits,
+                    &page.text,
+                    viewport.first_line(),
+                    &input.separator_lines,
+                );
+                entry.insert(hits).clone()
             }
-            None => None,
         };
 
-        debug!("Got mode action {:?}", mode_action);
+        let mode: Box<dyn Mode + 'a> = Box::new(stats::measure(Step::Hints, || {
+            RegexMode::with_hits(&page.text, hits, hint_generator, config, previous_hints)
+        }));
 
-        match mode_action {
-            Some(ModeEvent::TextSelected(text)) => {
-                return Ok(text);
-            }
-            Some(ModeEvent::ModeSwitchRequested(mode_index)) => {
-                if modes.get(mode_index).is_some() {
-                    current_mode_args = Some(&config.modes[mode_index].args);
-                    current_mode =
-                        create_mode(&input_text, hint_generator, config, current_mode_args)?;
-                } else {
-                    warn!("Trying to switch to a non existing mode with index {mode_index}");
-                }
+        Ok((page.text.clone(), mode))
+    }
+
+    /// Show the page again after the screen or the input changed, keeping the
+    /// hints of the hits that are still there and continuing the selection in
+    /// progress of the current mode in the mode created for the page.
+    fn refresh_page(
+        &mut self,
+        current_mode: &dyn Mode,
+        input: &Input,
+        viewport: &Viewport,
+        screen_size: (usize, usize),
+        mode_index: Option<usize>,
+    ) -> Result<ShownPage<'a>, RunError> {
+        let (page, mut mode) = self.show_page_with_hints(
+            input,
+            viewport,
+            screen_size,
+            mode_index,
+            &current_mode.get_hint_assignments(),
+        )?;
+        mode.restore_selection_state(current_mode.get_selection_state());
+
+        Ok((page, mode))
+    }
+
+    /// Scroll the viewport page by page in the given direction, negative for
+    /// up, until a page where the mode with the given index has any hits
+    /// is shown.
+    ///
+    /// Returns the found page together with the mode created for it. If there
+    /// is no such page, [None] is returned and the viewport does not move.
+    fn scroll_to_hit_page(
+        &mut self,
+        viewport: &mut Viewport,
+        screen_size: (usize, usize),
+        input: &Input,
+        direction: isize,
+        mode_index: Option<usize>,
+    ) -> Result<Option<ShownPage<'a>>, RunError> {
+        let (rows, cols) = screen_size;
+        let start_line = viewport.first_line();
+
+        while viewport.scroll(direction * rows as isize, &input.text, rows, cols) {
+            let (page, mode) = self.show_page(input, viewport, screen_size, mode_index)?;
+
+            if mode.has_hits() {
+                debug!("Found a page with hits at line {}", viewport.first_line());
+                return Ok(Some((page, mode)));
             }
-            None => (),
         }
+
+        info!("There is no page with hits in this direction");
+        viewport.scroll_to_line(start_line, &input.text, rows, cols);
+
+        Ok(None)
+    }
+
+    /// Show the page for the new size of the screen, keeping the position in
+    /// the input and continuing the selection in progress of the current mode
+    /// in the mode created for the page.
+    fn resize(
+        &mut self,
+        current_mode: &dyn Mode,
+        input: &Input,
+        viewport: &mut Viewport,
+        screen_size: (usize, usize),
+        mode_index: Option<usize>,
+    ) -> Result<ShownPage<'a>, RunError> {
+        // The old position may be past the last page on a taller screen
+        let (rows, cols) = screen_size;
+        viewport.scroll(0, &input.text, rows, cols);
+
+        self.refresh_page(current_mode, input, viewport, screen_size, mode_index)
     }
 }
 
-pub fn run(args: Args) -> Result<String, RunError> {
-    initialize_logging()?;
-    info!("Initializing");
+/// Remove the hits that are on the separator lines of the input, given the
+/// text of the page they were found on and the index of its first line.
+fn remove_separator_hits(
+    hits: &mut Vec<Hit>,
+    page_text: &str,
+    first_line: usize,
+    separator_lines: &[usize],
+) {
+    if separator_lines.is_empty() {
+        return;
+    }
 
-    let config_path = match &args.config {
-        Some(path) => Some(path.clone()),
-        None => get_config_file_location(),
-    };
-    let config = load_config(config_path)?;
+    hits.retain(|hit| {
+        let line = first_line + page_text[..hit.start].matches('\n').count();
+        separator_lines.binary_search(&line).is_err()
+    });
+}
 
-    let start_in_mode = match args.start_in_mode {
-        Some(mode_hotkey) => {
-            let mode = config.modes.iter().find(|mode| mode.hotkey == mode_hotkey);
+/// Get the position in the input of the byte at the given offset in the
+/// page that starts with the given line of the input.
+fn get_input_position(
+    input_text: &InputText,
+    first_line: usize,
+    page_text: &str,
+    page_offset: usize,
+) -> InputPosition {
+    let before = &page_text[..page_offset];
+    let page_line = before.matches('\n').count();
+    // The lines on the page start the same as the lines of the input
+    let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1);
 
-            if mode.is_none() {
-                return Err(RunError::InvalidMode { mode: mode_hotkey });
-            }
+    let line = first_line + page_line;
+    let line_start = input_text.line_start(line);
 
-            mode
+    InputPosition {
+        line: line + 1,
+        column: column + 1,
+        offset: input_text.original_offset(line_start + column),
+        line_text: input_text
+            .lines(line, 1)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Find all the hits that the mode with the given index, or all the modes
+/// if it is [None], find in the given text, together with where they come
+/// from, in the order in which they appear in the text.
+fn find_all_hits(
+    text: &str,
+    config: &Config,
+    mode_index: Option<usize>,
+) -> Result<Vec<(Hit, SelectionOrigin)>, RunError> {
+    let mut hits = vec![];
+    for (index, mode) in config.modes.iter().enumerate() {
+        // Only the regex modes can tell what they find without the user
+        let ModeArgs::RegexMode(args) = &mode.args else {
+            continue;
+        };
+        if mode_index.is_none_or(|mode_index| mode_index == index) {
+            let mode_hits = RegexMode::find_hits(text, args, config)?;
+            hits.extend(mode_hits.into_iter().map(|hit| {
+                let origin = SelectionOrigin {
+                    mode_name: mode.name.clone(),
+                    regex_index: Some(hit.regex_index),
+                    file: None,
+                };
+                (hit, origin)
+            }));
         }
-        None => None,
-    };
+    }
+    hits.sort_by_key(|(hit, _)| hit.start);
+    // The same text can be found by more than one mode
+    hits.dedup_by(|(hit, _), (other, _)| hit.start == other.start && hit.text == other.text);
 
-    let input_handler = InputHandler::from_config(&config);
+    Ok(hits)
+}
+
+/// Let the user select one of the given hits, found with [find_all_hits],
+/// from the numbered list of them, see [ListUi].
+///
+/// Returns [None] if the selection was cancelled.
+fn select_from_list(
+    hits: Vec<(Hit, SelectionOrigin)>,
+    args: &Args,
+    config: &Config,
+) -> Result<Option<(Hit, SelectionOrigin)>, RunError> {
+    let entries = hits.iter().map(|(hit, _)| hit.text.to_string()).collect();
+    let mut list = ListUi::new(entries);
+    let mut event_source = create_event_source(args)?;
     let mut renderer = create_renderer()?;
 
-    // This approach is not ideal since it reads the whole input text
-    // while only using one screen of text but it should be OK for now
-    let input_text = get_input_text(&args)?;
+    // Must be running before the terminal is initialized, see run
+    start_force_quit_handler()?;
+    let mut terminal = TerminalGuard::new(&mut renderer, false).context(TerminalHandlingSnafu {
+        operation: "initialize",
+    })?;
 
-    let hint_generator: Box<dyn HintGenerator> =
-        Box::new(HintPoolGenerator::new(&config.hint_characters));
+    let event = loop {
+        let size = terminal.size()?;
+        let draw_instructions = [
+            DrawInstruction::Data,
+            DrawInstruction::Prompt(list.get_prompt()),
+        ];
+        terminal.render(&list.get_page(size), &draw_instructions, config)?;
 
-    renderer
-        .initialize_terminal()
-        .context(TerminalHandlingSnafu {
-            operation: "initialize",
-        })?;
+        // Anything else, e.g. resizing, only needs the list drawn again
+        let Some(Event::Key(key)) = event_source.next_event(None)? else {
+            continue;
+        };
+        match list.handle_key(key, size) {
+            ListUiEvent::Continue => continue,
+            event => break event,
+        }
+    };
 
-    let ret = run_main_loop(
-        input_handler,
-        hint_generator.deref(),
-        &config,
-        &mut renderer,
-        input_text,
-        start_in_mode,
+    terminal.restore().context(TerminalHandlingSnafu {
+        operation: "uninitialize",
+    })?;
+
+    match event {
+        ListUiEvent::Select(index) => Ok(hits.into_iter().nth(index)),
+        ListUiEvent::Abort => Err(RunError::Aborted),
+        ListUiEvent::Continue | ListUiEvent::Cancel => Ok(None),
+    }
+}
+
+/// Get the outcome of selecting the given hit, found with [find_all_hits]
+/// in the given input, without showing anything more, e.g. with --select.
+fn output_hit(
+    (hit, mut origin): (Hit, SelectionOrigin),
+    input: &Input,
+    args: &Args,
+    output_options: &OutputOptions,
+) -> Result<RunOutcome, RunError> {
+    origin.file = input.files.get(input.file_index).cloned();
+    let text = input.text.to_str();
+    let position = get_input_position(&input.text, 0, &text, hit.start);
+    let output = format_selection(
+        hit.text.to_string(),
+        Some(position),
+        Some(&origin),
+        output_options,
     );
+    if let Some(path) = &args.output {
+        output_to_file(path, &output, args.append)?;
+        return Ok(RunOutcome::Selected(String::new()));
+    }
 
-    renderer
-        .uninitialize_terminal()
-        .context(TerminalHandlingSnafu {
-            operation: "uninitialize",
-        })?;
+    Ok(RunOutcome::Selected(output))
+}
+
+/// List the given hits, found with [find_all_hits] in the given text of the
+/// given input, one per line as LINE:COLUMN:OFFSET:TEXT, preceded by their
+/// origin if requested in the given options.
+fn list_matches(
+    text: &str,
+    input_text: &InputText,
+    hits: Vec<(Hit, SelectionOrigin)>,
+    options: &OutputOptions,
+) -> String {
+    let mut list = String::new();
+    let mut line = 0;
+    let mut line_start = 0;
+    let mut scanned = 0;
+    for (hit, origin) in hits {
+        for (index, _) in text[scanned..hit.start].match_indices('\n') {
+            line += 1;
+            line_start = scanned + index + 1;
+        }
+        scanned = hit.start;
+
+        let position = InputPosition {
+            line: line + 1,
+            column: hit.start - line_start + 1,
+            offset: input_text.original_offset(hit.start),
+            line_text: text[line_start..]
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        };
+        list.push_str(&format_selection(
+            hit.text.to_string(),
+            Some(position),
+            Some(&origin),
+            options,
+        ));
+    }
+
+    list
+}
+
+/// Create the generator of the hints according to the given config.
+pub(super) fn create_hint_generator(config: &Config) -> Box<dyn HintGenerator> {
+    let left_hand_characters = match config.hint_order {
+        HintOrder::Pool => None,
+        HintOrder::AlternatingHands => Some(config.left_hand_characters.as_str()),
+    };
+
+    match config.hint_generator {
+        HintGeneratorKind::Pool => Box::new(HintPoolGenerator::new(
+            &config.hint_characters,
+            config.fixed_hint_length,
+            left_hand_characters,
+        )),
+        HintGeneratorKind::Numeric => Box::new(NumericGenerator),
+        HintGeneratorKind::Syllables => Box::new(SyllableGenerator),
+    }
+}
+
+/// Get the text reporting the given number of hits for --count.
+fn format_match_count(count: usize) -> String {
+    match count {
+        1 => "1 match".to_string(),
+        count => format!("{count} matches"),
+    }
+}
+
+/// Check whether the whole input is read and none of the modes finds
+/// anything to select in it.
+fn has_nothing_to_select(input: &Input, config: &Config) -> Result<bool, RunError> {
+    let is_read = input.follower.as_ref().is_none_or(InputFollower::has_ended);
+    // Finding all the hits in a large input would take too long
+    if !is_read || input.text.len() as u64 >= LARGE_FILE_SIZE {
+        return Ok(false);
+    }
+
+    for mode in &config.modes {
+        // Whether the other modes have anything to select is not known
+        let ModeArgs::RegexMode(args) = &mode.args else {
+            return Ok(false);
+        };
+        if !RegexMode::find_hits(&input.text.to_str(), args, config)?.is_empty() {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Check whether the mode with the given index has nothing to select in the
+/// given text. Only the regex modes can tell that without the user, so the
+/// other modes always have something to select.
+fn mode_has_nothing_to_select(
+    text: &str,
+    config: &Config,
+    mode_index: usize,
+) -> Result<bool, RunError> {
+    let Some(ModeArgs::RegexMode(args)) = config.modes.get(mode_index).map(|mode| &mode.args)
+    else {
+        return Ok(false);
+    };
+
+    Ok(RegexMode::find_hits(text, args, config)?.is_empty())
+}
+
+/// Get the index of the mode with the given hotkey or, if there is none,
+/// the given name.
+fn find_mode(modes: &[configuration::Mode], mode: &str) -> Result<usize, RunError> {
+    let mut chars = mode.chars();
+    let hotkey = match (chars.next(), chars.next()) {
+        (Some(hotkey), None) => Some(hotkey),
+        _ => None,
+    };
+
+    modes
+        .iter()
+        .position(|mode_config| Some(mode_config.hotkey) == hotkey)
+        .or_else(|| {
+            modes
+                .iter()
+                .position(|mode_config| mode_config.name == mode)
+        })
+        .ok_or_else(|| RunError::InvalidMode {
+            mode: mode.to_string(),
+            available: modes
+                .iter()
+                .map(|mode_config| format!("{} ({})", mode_config.hotkey, mode_config.name))
+                .collect(),
+        })
+}
+
+/// Get the mode selecting the matches of the given regexes from the command
+/// line, with a hotkey that none of the modes of the given config uses.
+fn get_command_line_mode(
+    patterns: &[String],
+    config: &Config,
+) -> Result<configuration::Mode, RunError> {
+    let regexes = patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern))
+        .collect::<Result<Vec<Regex>, _>>()
+        .context(InvalidRegexSnafu {})?;
+    let hotkey = ('a'..='z')
+        .chain('0'..='9')
+        .find(|hotkey| config.modes.iter().all(|mode| mode.hotkey != *hotkey))
+        .context(NoFreeHotkeySnafu {})?;
+
+    Ok(configuration::Mode {
+        args: ModeArgs::RegexMode(RegexArgs {
+            regexes,
+            min_length: 0,
+            literal_matchers: Default::default(),
+        }),
+        hotkey,
+        name: "command line".to_string(),
+        keybindings: HashMap::new(),
+        action: None,
+    })
+}
+
+/// Get the message telling that the active mode has nothing to select on
+/// the shown page, if that is the case and the mode is selecting.
+fn get_no_match_message(
+    mode: &dyn Mode,
+    mode_index: Option<usize>,
+    paging: bool,
+    config: &Config,
+) -> Option<String> {
+    if paging || mode.has_hits() {
+        return None;
+    }
+
+    let mode_config = config.modes.get(mode_index?)?;
+    Some(format!("no matches for mode {}", mode_config.name))
+}
+
+/// Add the given text to the collected selections, or remove it if it is
+/// already collected.
+fn toggle_collected(collected: &mut Vec<String>, text: String) {
+    match collected
+        .iter()
+        .position(|collected_text| *collected_text == text)
+    {
+        Some(index) => {
+            info!("Removing {text} from the collected selections");
+            collected.remove(index);
+        }
+        None => {
+            info!("Collecting {text}");
+            collected.push(text);
+        }
+    }
+}
+
+/// Get the status to show at the bottom of the screen, made of the status
+/// of the input, whether there is nothing to select (or the given message
+/// that the active mode has nothing to select, see [get_no_match_message])
+/// and the number of the collected selections, if collecting.
+fn get_status(
+    input: &Input,
+    nothing_to_select: bool,
+    no_match_message: Option<&str>,
+    collected: Option<&[String]>,
+) -> Option<String> {
+    let no_match_message = match nothing_to_select {
+        true => Some("nothing to select".to_string()),
+        false => no_match_message.map(str::to_string),
+    };
+    let parts: Vec<String> = input
+        .get_status()
+        .into_iter()
+        .chain(no_match_message)
+        .chain(collected.map(|collected| format!("{} collected", collected.len())))
+        .collect();
+
+    match parts.is_empty() {
+        true => None,
+        false => Some(parts.join("  ")),
+    }
+}
+
+/// Scroll the viewport by the given number of lines, negative for up.
+///
+/// Returns whether the viewport moved.
+fn scroll_viewport(
+    viewport: &mut Viewport,
+    screen_size: (usize, usize),
+    input_text: &InputText,
+    lines: isize,
+) -> Result<bool, RunError> {
+    let (rows, cols) = screen_size;
+    let moved = viewport.scroll(lines, input_text, rows, cols);
+    debug!("Scrolled by {lines} lines, moved: {moved}");
+
+    Ok(moved)
+}
+
+/// Scroll the viewport to the next line after the top one that contains
+/// the given query, wrapping around at the end of the input.
+///
+/// Returns whether the viewport moved.
+fn search_viewport(
+    viewport: &mut Viewport,
+    screen_size: (usize, usize),
+    input_text: &InputText,
+    query: &str,
+) -> Result<bool, RunError> {
+    let Some(line) = find_next_line(&input_text.to_str(), query, viewport.first_line()) else {
+        info!("No line contains {query:?}");
+        return Ok(false);
+    };
+
+    let (rows, cols) = screen_size;
+    let moved = viewport.scroll_to_line(line, input_text, rows, cols);
+    debug!("Found {query:?} on line {line}, moved: {moved}");
+
+    Ok(moved)
+}
+
+/// Get the arguments of the mode with the given index, if any.
+fn get_mode_args(config: &configuration::Config, mode_index: Option<usize>) -> Option<&ModeArgs> {
+    mode_index
+        .and_then(|index| config.modes.get(index))
+        .map(|mode| &mode.args)
+}
+
+/// Get the index of the mode that comes after (or before if `forward` is
+/// false) the mode with the given index, wrapping around at the ends.
+///
+/// If there is no current mode, the first (or the last) mode is returned.
+pub(super) fn get_cycled_mode_index(
+    current: Option<usize>,
+    mode_count: usize,
+    forward: bool,
+) -> usize {
+    let last = mode_count.saturating_sub(1);
+
+    match (current, forward) {
+        (None, true) => 0,
+        (None, false) => last,
+        (Some(current), true) if current >= last => 0,
+        (Some(current), true) => current + 1,
+        (Some(0), false) => last,
+        (Some(current), false) => current - 1,
+    }
+}
 
-    ret
+/// History of the active modes, used to return to the previous one.
+#[derive(Debug, Default)]
+pub(super) struct ModeHistory {
+    /// The mode that is active, or that was active before the mode selection.
+    last: Option<usize>,
+    /// The mode that was active before [ModeHistory::last].
+    previous: Option<usize>,
+}
+
+impl ModeHistory {
+    /// Record switching to the mode with the given index.
+    pub(super) fn switched_to(&mut self, mode_index: usize) {
+        if self.last != Some(mode_index) {
+            self.previous = self.last;
+            self.last = Some(mode_index);
+        }
+    }
+
+    /// Get the index of the mode to return to from the mode with the given
+    /// index. [None] means that the mode selection is shown.
+    pub(super) fn get_return_mode(&self, current: Option<usize>) -> Option<usize> {
+        match current {
+            Some(_) => self.previous,
+            None => self.last,
+        }
+    }
+}
+
+/// Get the source of the input events according to the arguments.
+fn create_event_source(args: &Args) -> Result<EventSource, RunError> {
+    if let Some(path) = &args.replay {
+        return Ok(EventSource::Replay(EventReplayer::load(path)?));
+    }
+
+    let record_path = args
+        .record
+        .clone()
+        .or_else(|| env::var_os("MLESS_RECORD").map(PathBuf::from));
+
+    let recorder = match record_path {
+        Some(path) => Some(EventRecorder::create(&path)?),
+        None => None,
+    };
+
+    Ok(EventSource::Terminal(recorder))
+}
+
+/// The state the selection starts in.
+#[derive(Debug, Default)]
+struct StartOptions {
+    /// Index of the mode to start in, the first mode if [None].
+    mode_index: Option<usize>,
+    /// Whether to show the last page of the input instead of the first one.
+    at_end: bool,
+    /// Index of the input line to show at the top of the screen, if any.
+    line: Option<usize>,
+    /// Whether to start as a plain pager, without showing any hints.
+    paging: bool,
+    /// How long the selection can take before it is cancelled, if limited.
+    timeout: Option<Duration>,
+}
+
+/// The selected text, its position in the input and origin, and where it
+/// goes, as returned by [run_main_loop].
+pub(super) type SelectionResult = (
+    String,
+    Option<InputPosition>,
+    Option<SelectionOrigin>,
+    OutputTarget,
+);
+
+/// Handles each selection while the selection continues after it, see
+/// [run_main_loop].
+type SelectionHandler<'a> =
+    dyn FnMut(&mut Renderer<File>, SelectionResult) -> Result<(), RunError> + 'a;
+
+/// What the main loop does after handling an action: either it continues,
+/// or it ends with the selection, or with [None] if it is cancelled.
+type LoopFlow<T = ()> = ControlFlow<Option<SelectionResult>, T>;
+
+/// The parts of the selection that the main loop changes while handling the
+/// actions, see [run_main_loop].
+struct MainLoop<'a, 'h> {
+    config: &'a Config,
+    input_handler: InputHandler,
+    event_source: EventSource,
+    renderer: &'a mut Renderer<File>,
+    input: &'a mut Input,
+    on_selection: Option<&'a mut SelectionHandler<'h>>,
+    /// Whether the selection started as a plain pager, see [StartOptions].
+    paging: bool,
+    /// Whether the selection started at the end of the input, see
+    /// [StartOptions].
+    at_end: bool,
+    /// When the selection is cancelled, if it is limited.
+    deadline: Option<Instant>,
+    /// The time the last event (or timeout) was handled, used to keep the
+    /// requested timeouts while waking up to check the followed input.
+    last_event_time: Instant,
+    viewport: Viewport,
+    pages: Pages<'a>,
+    input_page: String,
+    current_mode: Box<dyn Mode + 'a>,
+    /// Index of the active mode, [None] represents the mode selection.
+    current_mode_index: Option<usize>,
+    mode_history: ModeHistory,
+    state: LoopState,
+    /// Whether the input handler was last told that the text is typed and
+    /// that the input is scrolled, see [update_input_handler].
+    input_handler_state: (bool, bool),
+    /// Whether only the data is shown, hiding the hints of the mode.
+    peeking: bool,
+    debug_overlay: DebugOverlay,
+    last_search: Option<String>,
+    /// The texts selected so far, if collecting several selections.
+    collected: Option<Vec<String>>,
+    nothing_to_select: bool,
+    /// The message shown until the next event when the active mode has
+    /// nothing to select, and the mode, the first line and whether paging
+    /// it was last checked for.
+    no_match_message: Option<String>,
+    checked_page: Option<(Option<usize>, usize, bool)>,
+    needs_render: bool,
+    /// The status shown by the last render, to know when it needs updating.
+    shown_status: Option<String>,
+}
+
+impl<'a, 'h> MainLoop<'a, 'h> {
+    /// Show the first page of the input as given by the start options.
+    #[allow(
+        clippy::too_many_arguments,
+        reason = "The loop ties together all the parts of the application"
+    )]
+    fn start(
+        mut input_handler: InputHandler,
+        event_source: EventSource,
+        hint_generator: &'a dyn HintGenerator,
+        config: &'a Config,
+        renderer: &'a mut Renderer<File>,
+        input: &'a mut Input,
+        start: StartOptions,
+        on_selection: Option<&'a mut SelectionHandler<'h>>,
+    ) -> Result<Self, RunError> {
+        let mut viewport = Viewport::new(config.wrap_lines);
+        if input.follow || start.at_end {
+            let (rows, cols) = renderer.size()?;
+            viewport.scroll_to_end(&input.text, rows, cols);
+        }
+        if let Some(line) = start.line {
+            let (rows, cols) = renderer.size()?;
+            viewport.scroll_to_line(line, &input.text, rows, cols);
+        }
+        let mut pages = Pages::new(hint_generator, config);
+
+        let current_mode_index = Some(start.mode_index.unwrap_or(0));
+        let (input_page, current_mode) =
+            pages.show_page(input, &viewport, renderer.size()?, current_mode_index)?;
+        input_handler.set_current_mode(current_mode_index);
+        let mut mode_history = ModeHistory::default();
+        mode_history.switched_to(start.mode_index.unwrap_or(0));
+        let state = match start.paging {
+            true => LoopState::Scrolling,
+            false => LoopState::Selecting,
+        };
+        input_handler.set_paging(state.is_scrolling());
+        let nothing_to_select = has_nothing_to_select(input, config)?;
+
+        // Make sure the data is rendered as early as possible to avoid blinking
+        stats::measure(Step::Render, || {
+            renderer.render(&input_page, &[DrawInstruction::Data], config)
+        })?;
+
+        Ok(Self {
+            config,
+            input_handler,
+            event_source,
+            renderer,
+            input,
+            on_selection,
+            paging: start.paging,
+            at_end: start.at_end,
+            deadline: start.timeout.map(|timeout| Instant::now() + timeout),
+            last_event_time: Instant::now(),
+            viewport,
+            pages,
+            input_page,
+            current_mode,
+            current_mode_index,
+            mode_history,
+            input_handler_state: (false, state.is_scrolling()),
+            state,
+            peeking: false,
+            debug_overlay: DebugOverlay::default(),
+            last_search: None,
+            collected: None,
+            nothing_to_select,
+            no_match_message: None,
+            checked_page: None,
+            needs_render: true,
+            shown_status: None,
+        })
+    }
+
+    /// Draw the screen, if anything changed since it was last drawn.
+    fn draw(&mut self) -> Result<(), RunError> {
+        let shown_page = (
+            self.current_mode_index,
+            self.viewport.first_line(),
+            self.state.is_scrolling(),
+        );
+        if self.checked_page != Some(shown_page) {
+            self.checked_page = Some(shown_page);
+            self.no_match_message = get_no_match_message(
+                self.current_mode.as_ref(),
+                self.current_mode_index,
+                self.state.is_scrolling(),
+                self.config,
+            );
+            self.needs_render = true;
+        }
+
+        if !self.needs_render {
+            return Ok(());
+        }
+
+        if let Some(collected) = &self.collected {
+            self.current_mode.set_collected(collected);
+        }
+        let mut draw_instructions = self.state.get_draw_instructions(
+            self.current_mode.as_ref(),
+            self.current_mode_index,
+            self.peeking,
+            self.config,
+        );
+        self.shown_status = self.get_status();
+        if let Some(status) = &self.shown_status {
+            // The search prompt takes the whole last row
+            let index = draw_instructions
+                .iter()
+                .position(|instruction| matches!(instruction, DrawInstruction::Prompt(_)))
+                .unwrap_or(draw_instructions.len());
+            draw_instructions.insert(index, DrawInstruction::Status(status.clone()));
+        }
+        if self.debug_overlay.is_shown() {
+            let mode_name = self
+                .current_mode_index
+                .and_then(|index| self.config.modes.get(index))
+                .map_or("mode selection", |mode| mode.name.as_str());
+            draw_instructions.push(self.debug_overlay.get_draw_instruction(
+                mode_name,
+                self.current_mode.get_hit_count(),
+                self.viewport.first_line(),
+            ));
+        }
+        let render_start = Instant::now();
+        stats::measure(Step::Render, || {
+            self.renderer
+                .render(&self.input_page, &draw_instructions, self.config)
+        })?;
+        self.debug_overlay.rendered(render_start.elapsed());
+
+        Ok(())
+    }
+
+    /// Get the status shown at the bottom of the screen, see [get_status].
+    fn get_status(&self) -> Option<String> {
+        get_status(
+            self.input,
+            self.nothing_to_select,
+            self.no_match_message.as_deref(),
+            self.collected.as_deref(),
+        )
+    }
+
+    /// Wait for the next event, the timeout of the input handler or the mode,
+    /// or for the followed or watched input to be checked, and get the
+    /// action it results in.
+    ///
+    /// Breaks with [None] once the session times out.
+    fn wait_for_action(&mut self) -> Result<LoopFlow<Option<Action>>, RunError> {
+        let timed_out = || {
+            self.deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        };
+        if timed_out() {
+            info!("Cancelling the selection, the session timed out");
+            return Ok(ControlFlow::Break(None));
+        }
+
+        let remaining = self
+            .input_handler
+            .get_timeout()
+            .or_else(|| self.current_mode.get_key_press_timeout())
+            .map(|timeout| timeout.saturating_sub(self.last_event_time.elapsed()));
+        // The session ends at the deadline, whatever else is waited for
+        let until_deadline = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let remaining = match (remaining, until_deadline) {
+            (Some(remaining), Some(until_deadline)) => Some(remaining.min(until_deadline)),
+            (remaining, until_deadline) => remaining.or(until_deadline),
+        };
+        let polling = self.input.watch
+            || self
+                .input
+                .follower
+                .as_ref()
+                .is_some_and(|follower| !follower.has_ended());
+
+        self.needs_render = true;
+        let action = match self.event_source.wait(remaining, polling)? {
+            Wakeup::Event(event) => {
+                debug!("Got event {:?}", event);
+                self.debug_overlay.event_received(&event);
+                self.no_match_message = None;
+                self.last_event_time = Instant::now();
+                self.input_handler.get_action(event)
+            }
+            // The session timed out, which ends it before waiting again
+            Wakeup::Timeout if timed_out() => None,
+            Wakeup::Timeout => {
+                self.last_event_time = Instant::now();
+                self.input_handler.get_timeout_action()
+            }
+            // Only woke up to check for new input
+            Wakeup::Poll => {
+                self.needs_render = false;
+                None
+            }
+        };
+
+        debug!("Got input handler action {:?}", action);
+        Ok(ControlFlow::Continue(action))
+    }
+
+    /// Handle the given action in the current state. The prompt and the
+    /// dialogs take the keys meant for them, the rest goes to the state under
+    /// them.
+    fn handle_action(&mut self, action: Option<Action>) -> Result<LoopFlow, RunError> {
+        let action = match &self.state {
+            LoopState::Search { .. } => self.handle_search_action(action)?,
+            LoopState::ActionMenu { .. } => self.handle_action_menu_action(action)?,
+            LoopState::Help(_) => self.handle_help_action(action),
+            LoopState::Scrolling | LoopState::Selecting | LoopState::ModeSelection => {
+                ControlFlow::Continue(action)
+            }
+        };
+        let action = match action {
+            ControlFlow::Continue(action) => action,
+            ControlFlow::Break(result) => return Ok(ControlFlow::Break(result)),
+        };
+
+        let action = match self.state.is_scrolling() {
+            true => self.handle_scrolling_action(action),
+            false => action,
+        };
+
+        // Only the action that completes the selection decides where it goes
+        let mut output_target = get_output_target(self.config, self.current_mode_index);
+        let mode_event = match self.handle_base_action(action, &mut output_target)? {
+            ControlFlow::Continue(mode_event) => mode_event,
+            ControlFlow::Break(result) => return Ok(ControlFlow::Break(result)),
+        };
+        debug!("Got mode action {:?}", mode_event);
+
+        let flow = self.handle_mode_event(mode_event, output_target)?;
+        self.input_handler_state = update_input_handler(
+            &mut self.input_handler,
+            &self.state,
+            self.input_handler_state,
+        );
+
+        Ok(flow)
+    }
+
+    /// Handle the given action while the search query is typed, passing on
+    /// the ones the prompt does not take.
+    fn handle_search_action(
+        &mut self,
+        action: Option<Action>,
+    ) -> Result<LoopFlow<Option<Action>>, RunError> {
+        let LoopState::Search { query, .. } = &mut self.state else {
+            return Ok(ControlFlow::Continue(action));
+        };
+
+        match action {
+            Some(Action::Exit) => {
+                info!("Exiting without selection");
+                return Ok(ControlFlow::Break(None));
+            }
+            Some(Action::ForwardKeyPress(keypress)) => query.extend(keypress.typed_char()),
+            Some(Action::EraseKeyPress) => {
+                query.pop();
+            }
+            Some(Action::AcceptFocused) => {
+                if !query.is_empty() {
+                    self.last_search = Some(query.clone());
+                }
+                self.state.close();
+                if let Some(query) = &self.last_search {
+                    let screen_size = self.renderer.size()?;
+                    if search_viewport(&mut self.viewport, screen_size, &self.input.text, query)? {
+                        self.show_page()?;
+                    }
+                }
+            }
+            Some(Action::ClearOrExit) => {
+                debug!("Search cancelled");
+                self.state.close();
+            }
+            // The page still needs to fit the terminal
+            Some(Action::Resize) => return Ok(ControlFlow::Continue(Some(Action::Resize))),
+            _ => (),
+        }
+
+        Ok(ControlFlow::Continue(None))
+    }
+
+    /// Handle the given action while the action for the selection is
+    /// chosen, passing on the ones the menu does not take.
+    fn handle_action_menu_action(
+        &mut self,
+        action: Option<Action>,
+    ) -> Result<LoopFlow<Option<Action>>, RunError> {
+        match action {
+            Some(Action::Exit) => {
+                info!("Exiting without selection");
+                return Ok(ControlFlow::Break(None));
+            }
+            Some(Action::Abort) => {
+                info!("Aborting");
+                return Err(RunError::Aborted);
+            }
+            Some(Action::ForwardKeyPress(keypress)) => {
+                let entry = self
+                    .config
+                    .action_menu
+                    .iter()
+                    .find(|entry| Some(entry.hotkey) == keypress.typed_char());
+                if let Some(entry) = entry {
+                    info!("Chose action {} for the selection", entry.name);
+                    if let Some(LoopState::ActionMenu { mut result, .. }) = self.state.close() {
+                        result.3 = get_action_target(&entry.action);
+                        if let ControlFlow::Break(result) = self.output_selection(result)? {
+                            return Ok(ControlFlow::Break(result));
+                        }
+                    }
+                }
+            }
+            Some(Action::ClearOrExit) => {
+                debug!("Action menu closed");
+                self.state.close();
+            }
+            // The page still needs to fit the terminal
+            Some(Action::Resize) => return Ok(ControlFlow::Continue(Some(Action::Resize))),
+            _ => (),
+        }
+
+        Ok(ControlFlow::Continue(None))
+    }
+
+    /// Handle the given action while the help is shown, passing on the ones
+    /// that do not close it.
+    fn handle_help_action(&mut self, action: Option<Action>) -> LoopFlow<Option<Action>> {
+        match action {
+            Some(Action::ClearOrExit | Action::ToggleHelp) => {
+                self.state.close();
+                ControlFlow::Continue(None)
+            }
+            action => ControlFlow::Continue(action),
+        }
+    }
+
+    /// Handle the given action while the input is scrolled like in a pager.
+    /// Nothing goes to the mode until the selection starts, so the actions
+    /// for the mode are dropped, except the ones that start the selection.
+    fn handle_scrolling_action(&mut self, action: Option<Action>) -> Option<Action> {
+        match action {
+            Some(
+                Action::ForwardKeyPress(_)
+                | Action::RedirectKeyPress(_)
+                | Action::EraseKeyPress
+                | Action::AcceptFocused
+                | Action::MoveFocus(_)
+                | Action::RunModeCommand(_)
+                | Action::KeyPressTimeout
+                | Action::TogglePeek,
+            ) => None,
+            Some(Action::ClearOrExit) => Some(Action::Exit),
+            Some(
+                action @ (Action::GoToModeSelection
+                | Action::SwitchToNextMode
+                | Action::SwitchToPreviousMode
+                | Action::SwitchToReturnMode
+                | Action::StartSelection),
+            ) => {
+                info!("Starting the selection");
+                self.state.set_base(LoopState::Selecting);
+                Some(action)
+            }
+            action => action,
+        }
+    }
+
+    /// Handle the given action in the state under the dialogs and the
+    /// prompt, getting the event of the mode it results in, if any.
+    fn handle_base_action(
+        &mut self,
+        action: Option<Action>,
+        output_target: &mut OutputTarget,
+    ) -> Result<LoopFlow<Option<ModeEvent>>, RunError> {
+        let mode_count = self.config.modes.len();
+
+        let mode_event = match action {
+            Some(Action::Exit) => {
+                info!("Exiting without selection");
+                return Ok(ControlFlow::Break(None));
+            }
+            Some(Action::Abort) => {
+                info!("Aborting");
+                return Err(RunError::Aborted);
+            }
+            Some(Action::ForwardKeyPress(keypress)) => self.current_mode.handle_key_press(keypress),
+            Some(Action::RedirectKeyPress(keypress)) => {
+                if let Some(command) = &self.config.alt_output_command {
+                    *output_target = OutputTarget::Command(command.clone());
+                }
+                self.current_mode.handle_key_press(keypress)
+            }
+            Some(Action::EraseKeyPress) => {
+                self.current_mode.erase_key_press();
+                None
+            }
+            Some(Action::ClearOrExit) => {
+                if !self.current_mode.clear_key_presses() {
+                    if !self.paging {
+                        info!("Nothing to clear, exiting without selection");
+                        return Ok(ControlFlow::Break(None));
+                    }
+                    info!("Nothing to clear, going back to scrolling");
+                    self.state.set_base(LoopState::Scrolling);
+                }
+                None
+            }
+            Some(
+                action @ (Action::Resize
+                | Action::Rematch
+                | Action::ScrollLines(_)
+                | Action::ScrollPages(_)
+                | Action::ScrollToHitPage(_)
+                | Action::ScrollToTop
+                | Action::ScrollToBottom
+                | Action::ToggleWrapLines
+                | Action::SwitchToNextFile
+                | Action::SwitchToPreviousFile
+                | Action::Refresh),
+            ) => {
+                self.handle_page_action(action)?;
+                None
+            }
+            Some(Action::MoveFocus(direction)) => {
+                self.current_mode.move_focus(direction);
+                None
+            }
+            Some(Action::AcceptFocused) => self.current_mode.accept_focused(),
+            Some(Action::SwitchToNextMode) => Some(ModeEvent::ModeSwitchRequested(
+                get_cycled_mode_index(self.current_mode_index, mode_count, true),
+            )),
+            Some(Action::SwitchToPreviousMode) => Some(ModeEvent::ModeSwitchRequested(
+                get_cycled_mode_index(self.current_mode_index, mode_count, false),
+            )),
+            Some(Action::SwitchToReturnMode) => {
+                match self.mode_history.get_return_mode(self.current_mode_index) {
+                    Some(mode_index) => Some(ModeEvent::ModeSwitchRequested(mode_index)),
+                    None => {
+                        debug!("There is no previous mode to return to");
+                        None
+                    }
+                }
+            }
+            Some(Action::RunModeCommand(command)) => self.current_mode.handle_command(&command),
+            Some(Action::KeyPressTimeout) => self.current_mode.handle_key_press_timeout(),
+            Some(Action::StartSearch) => {
+                self.state.open(|previous| LoopState::Search {
+                    query: String::new(),
+                    previous,
+                });
+                None
+            }
+            Some(Action::ToggleHelp) => {
+                self.state.open(LoopState::Help);
+                None
+            }
+            Some(Action::TogglePeek) => {
+                self.peeking = !self.peeking;
+                None
+            }
+            Some(Action::ToggleDebugOverlay) => {
+                self.debug_overlay.toggle();
+                None
+            }
+            // Already handled while scrolling
+            Some(Action::StartSelection) => None,
+            Some(Action::ToggleCollecting) => {
+                if let ControlFlow::Break(result) = self.toggle_collecting(output_target)? {
+                    return Ok(ControlFlow::Break(result));
+                }
+                None
+            }
+            Some(Action::GoToModeSelection) => {
+                self.current_mode_index = None;
+                self.state.set_base(LoopState::ModeSelection);
+                self.input_handler.set_current_mode(self.current_mode_index);
+                self.show_page()?;
+                None
+            }
+            None => None,
+        };
+
+        Ok(ControlFlow::Continue(mode_event))
+    }
+
+    /// Handle the given action that changes the shown page, by moving it in
+    /// the input or by changing the input itself.
+    fn handle_page_action(&mut self, action: Action) -> Result<(), RunError> {
+        let screen_size = self.renderer.size()?;
+        let (rows, cols) = screen_size;
+
+        let moved = match action {
+            Action::Resize => {
+                (self.input_page, self.current_mode) = self.pages.resize(
+                    self.current_mode.as_ref(),
+                    self.input,
+                    &mut self.viewport,
+                    screen_size,
+                    self.current_mode_index,
+                )?;
+                false
+            }
+            Action::Rematch => {
+                (self.input_page, self.current_mode) = self.pages.show_page_with_hints(
+                    self.input,
+                    &self.viewport,
+                    screen_size,
+                    self.current_mode_index,
+                    &self.current_mode.get_hint_assignments(),
+                )?;
+                false
+            }
+            Action::ScrollLines(lines) => {
+                scroll_viewport(&mut self.viewport, screen_size, &self.input.text, lines)?
+            }
+            Action::ScrollPages(page_count) => scroll_viewport(
+                &mut self.viewport,
+                screen_size,
+                &self.input.text,
+                page_count * rows as isize,
+            )?,
+            Action::ScrollToHitPage(direction) => {
+                if let Some(shown_page) = self.pages.scroll_to_hit_page(
+                    &mut self.viewport,
+                    screen_size,
+                    self.input,
+                    direction,
+                    self.current_mode_index,
+                )? {
+                    (self.input_page, self.current_mode) = shown_page;
+                }
+                false
+            }
+            Action::ScrollToTop => self.viewport.scroll_to_start(),
+            Action::ScrollToBottom => self.viewport.scroll_to_end(&self.input.text, rows, cols),
+            Action::ToggleWrapLines => {
+                self.viewport
+                    .toggle_wrap_lines(&self.input.text, rows, cols);
+                true
+            }
+            Action::SwitchToNextFile | Action::SwitchToPreviousFile => {
+                let forward = matches!(action, Action::SwitchToNextFile);
+                let switched = self.input.switch_file(forward)?;
+                if switched {
+                    self.nothing_to_select = has_nothing_to_select(self.input, self.config)?;
+                    self.viewport.scroll_to_start();
+                    self.pages.page_cache.clear();
+                }
+                switched
+            }
+            Action::Refresh => {
+                let refreshed = self.input.refresh()?;
+                if refreshed {
+                    self.nothing_to_select = has_nothing_to_select(self.input, self.config)?;
+                    self.pages.page_cache.clear();
+                    self.viewport.scroll(0, &self.input.text, rows, cols);
+                }
+                refreshed
+            }
+            _ => false,
+        };
+
+        if moved {
+            self.show_page()?;
+        }
+
+        Ok(())
+    }
+
+    /// Start or stop collecting the selections. Stopping with anything
+    /// collected selects all of it at once.
+    fn toggle_collecting(&mut self, output_target: &OutputTarget) -> Result<LoopFlow, RunError> {
+        match self.collected.take() {
+            None => {
+                info!("Starting to collect the selections");
+                self.collected = Some(vec![]);
+            }
+            Some(texts) if texts.is_empty() => {
+                info!("Nothing was collected, stopping collecting");
+            }
+            Some(texts) => {
+                info!("Finishing with {} collected selections", texts.len());
+                let text = texts.join(&self.config.selection_delimiter);
+                return self.select((text, None, None, output_target.clone()));
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Handle the given event of the active mode.
+    fn handle_mode_event(
+        &mut self,
+        mode_event: Option<ModeEvent>,
+        output_target: OutputTarget,
+    ) -> Result<LoopFlow, RunError> {
+        match mode_event {
+            Some(ModeEvent::TextSelected(text, hit_origin)) => {
+                if let Some(collected) = &mut self.collected {
+                    toggle_collected(collected, text);
+                    return Ok(ControlFlow::Continue(()));
+                }
+
+                let position = hit_origin.map(|hit_origin| {
+                    get_input_position(
+                        &self.input.text,
+                        self.viewport.first_line(),
+                        &self.input_page,
+                        hit_origin.start,
+                    )
+                });
+                let origin = self
+                    .current_mode_index
+                    .and_then(|index| self.config.modes.get(index))
+                    .map(|mode| SelectionOrigin {
+                        mode_name: mode.name.clone(),
+                        regex_index: hit_origin.map(|hit_origin| hit_origin.regex_index),
+                        file: self.input.files.get(self.input.file_index).cloned(),
+                    });
+                return self.select((text, position, origin, output_target));
+            }
+            Some(ModeEvent::ModeSwitchRequested(mode_index)) => {
+                if self.config.modes.get(mode_index).is_some() {
+                    info!("Switching to mode with index {mode_index}");
+                    self.current_mode_index = Some(mode_index);
+                    self.state.set_base(LoopState::Selecting);
+                    self.mode_history.switched_to(mode_index);
+                    self.input_handler.set_current_mode(self.current_mode_index);
+                    self.show_page()?;
+                } else {
+                    warn!("Trying to switch to a non existing mode with index {mode_index}");
+                }
+            }
+            None => (),
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Let the user choose the action for the given selection, if there is
+    /// the action menu, or output it otherwise.
+    fn select(&mut self, result: SelectionResult) -> Result<LoopFlow, RunError> {
+        if self.config.action_menu.is_empty() {
+            return self.output_selection(result);
+        }
+
+        self.state
+            .open(|previous| LoopState::ActionMenu { result, previous });
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Pass the given selection to the selection handler, if there is one,
+    /// or end the loop with it otherwise.
+    fn output_selection(&mut self, result: SelectionResult) -> Result<LoopFlow, RunError> {
+        match self.on_selection.as_mut() {
+            Some(on_selection) => {
+                on_selection(self.renderer, result)?;
+                Ok(ControlFlow::Continue(()))
+            }
+            None => Ok(ControlFlow::Break(Some(result))),
+        }
+    }
+
+    /// Show the page of the input at the current position with the active
+    /// mode, see [Pages::show_page].
+    fn show_page(&mut self) -> Result<(), RunError> {
+        (self.input_page, self.current_mode) = self.pages.show_page(
+            self.input,
+            &self.viewport,
+            self.renderer.size()?,
+            self.current_mode_index,
+        )?;
+
+        Ok(())
+    }
+
+    /// Show the page at the current position again if the input changed
+    /// on it, keeping the mode, see [Pages::refresh_page].
+    fn refresh_page_if_changed(&mut self) -> Result<(), RunError> {
+        let (rows, cols) = self.renderer.size()?;
+        if self.viewport.get_page(&self.input.text, rows, cols) == self.input_page {
+            return Ok(());
+        }
+
+        (self.input_page, self.current_mode) = self.pages.refresh_page(
+            self.current_mode.as_ref(),
+            self.input,
+            &self.viewport,
+            (rows, cols),
+            self.current_mode_index,
+        )?;
+        self.needs_render = true;
+
+        Ok(())
+    }
+
+    /// Take the new input that arrived while waiting, if it is followed, and
+    /// read the input again if it is watched and changed.
+    fn update_input(&mut self) -> Result<(), RunError> {
+        let is_following = self
+            .input
+            .follower
+            .as_ref()
+            .is_some_and(|follower| !follower.has_ended());
+        if let Some(new_input) = self
+            .input
+            .follower
+            .as_mut()
+            .and_then(InputFollower::take_new_input)
+        {
+            let (rows, cols) = self.renderer.size()?;
+            let stay_at_end = (self.input.follow || self.at_end)
+                && self.viewport.is_at_end(&self.input.text, rows, cols);
+
+            let dropped_lines = self.input.dropped_lines;
+            self.input.push_text(&new_input);
+            if stay_at_end {
+                self.viewport.scroll_to_end(&self.input.text, rows, cols);
+            }
+
+            // Keep showing the same lines after the first ones were dropped
+            let dropped_lines = self.input.dropped_lines - dropped_lines;
+            if dropped_lines > 0 {
+                self.pages.page_cache.clear();
+                if !stay_at_end {
+                    self.viewport
+                        .scroll(-(dropped_lines as isize), &self.input.text, rows, cols);
+                }
+            }
+
+            // Keep the mode unless the new input shows up on the screen
+            self.refresh_page_if_changed()?;
+        }
+
+        // Whether there is anything to select is only known once the whole
+        // input is read, so the modes go through it once it ends instead of
+        // after each new chunk
+        if is_following
+            && self
+                .input
+                .follower
+                .as_ref()
+                .is_some_and(InputFollower::has_ended)
+        {
+            self.nothing_to_select = has_nothing_to_select(self.input, self.config)?;
+        }
+
+        if self.input.reload_if_changed()? {
+            self.nothing_to_select = has_nothing_to_select(self.input, self.config)?;
+            self.pages.page_cache.clear();
+            let (rows, cols) = self.renderer.size()?;
+            self.viewport.scroll(0, &self.input.text, rows, cols);
+            self.refresh_page_if_changed()?;
+        }
+
+        // Keep the shown reading progress up to date
+        if self.get_status() != self.shown_status {
+            self.needs_render = true;
+        }
+
+        Ok(())
+    }
+}
+
+/// Let the user select the text from the input.
+///
+/// Returns the selection, or [None] if it is cancelled. If `on_selection`
+/// is given, each selection is passed to it instead and the selection
+/// continues until it is cancelled.
+#[allow(
+    clippy::too_many_arguments,
+    reason = "The loop ties together all the parts of the application"
+)]
+fn run_main_loop(
+    input_handler: InputHandler,
+    event_source: EventSource,
+    hint_generator: &dyn HintGenerator,
+    config: &configuration::Config,
+    renderer: &mut Renderer<File>,
+    input: &mut Input,
+    start: StartOptions,
+    on_selection: Option<&mut SelectionHandler>,
+) -> Result<Option<SelectionResult>, RunError> {
+    let mut main_loop = MainLoop::start(
+        input_handler,
+        event_source,
+        hint_generator,
+        config,
+        renderer,
+        input,
+        start,
+        on_selection,
+    )?;
+
+    info!("Starting the loop");
+    loop {
+        main_loop.draw()?;
+
+        let action = match main_loop.wait_for_action()? {
+            ControlFlow::Continue(action) => action,
+            ControlFlow::Break(result) => return Ok(result),
+        };
+        if let ControlFlow::Break(result) = main_loop.handle_action(action)? {
+            return Ok(result);
+        }
+
+        main_loop.update_input()?;
+    }
+}
+
+/// Tell the input handler whether the text is typed and whether the input is
+/// scrolled in the given state, if that changed since the given last values,
+/// since that drops the keys typed so far. Returns the new values.
+fn update_input_handler(
+    input_handler: &mut InputHandler,
+    state: &LoopState,
+    (text_input, scrolling): (bool, bool),
+) -> (bool, bool) {
+    if state.is_text_input() != text_input {
+        input_handler.set_text_input(state.is_text_input());
+    }
+    if state.is_scrolling() != scrolling {
+        input_handler.set_paging(state.is_scrolling());
+    }
+
+    (state.is_text_input(), state.is_scrolling())
+}
+
+/// Check whether the standard input is a pipe, e.g. in `ls | mless`, as
+/// opposed to a terminal, a file or nothing at all.
+fn is_stdin_piped() -> bool {
+    io::stdin()
+        .as_fd()
+        .try_clone_to_owned()
+        .map(File::from)
+        .and_then(|file| file.metadata())
+        .is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+/// Check whether the given path is a socket that no server listens on, e.g.
+/// one left behind by a server that was killed, as opposed to a regular file
+/// or the socket of a running server.
+fn is_stale_socket(path: &Path) -> bool {
+    path.symlink_metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_socket())
+        && UnixStream::connect(path).is_err()
+}
+
+/// How the selection ended, together with the text to output.
+#[derive(Debug)]
+pub enum RunOutcome {
+    /// The text was selected.
+    Selected(String),
+    /// The selection was cancelled, see [Config::cancel_output].
+    Cancelled(String),
+    /// The selection was cancelled when there was nothing to select, see
+    /// [has_nothing_to_select].
+    NothingToSelect(String),
+}
+
+pub fn run(mut args: Args) -> Result<RunOutcome, RunError> {
+    let config_path = match &args.config {
+        Some(path) => Some(path.clone()),
+        None => get_config_file_location(),
+    };
+    let mut config = load_config(config_path)?;
+
+    if args.merge_configs || config.merge_configs {
+        let mut config_paths = get_config_file_locations();
+        if let Some(path) = &args.config {
+            config_paths.insert(0, path.clone());
+        }
+
+        config = load_merged_config(&config_paths)?;
+    }
+
+    if args.selection_filter.is_some() {
+        config.selection_filter = args.selection_filter.clone();
+    }
+    if let Some(delimiter) = &args.delimiter {
+        config.selection_delimiter = Config::parse_delimiter(delimiter);
+    }
+    if args.null {
+        config.selection_delimiter = "\0".to_string();
+    }
+    let color_overrides = [
+        (&args.hint_fg, "--hint-fg", &mut config.hint_fg),
+        (&args.hint_bg, "--hint-bg", &mut config.hint_bg),
+        (
+            &args.highlight_fg,
+            "--highlight-fg",
+            &mut config.highlight_fg,
+        ),
+        (
+            &args.highlight_bg,
+            "--highlight-bg",
+            &mut config.highlight_bg,
+        ),
+    ];
+    for (color, option, setting) in color_overrides {
+        if let Some(color) = color {
+            *setting =
+                parse_color(color, &config.palette).ok_or_else(|| RunError::InvalidColor {
+                    option: option.to_string(),
+                    color: color.clone(),
+                })?;
+        }
+    }
+
+    if args.history {
+        config.modes = vec![get_history_mode()];
+    }
+
+    if !args.regex.is_empty() {
+        let mode = get_command_line_mode(&args.regex, &config)?;
+        // Start in the added mode unless another one is given
+        args.start_in_mode
+            .get_or_insert_with(|| mode.hotkey.to_string());
+        config.modes.insert(0, mode);
+    }
+
+    if let Some(name) = &args.action {
+        let Some(action) = config.find_action(name) else {
+            return Err(RunError::InvalidAction { name: name.clone() });
+        };
+        config.selection_action = action;
+        for mode in &mut config.modes {
+            mode.action = None;
+        }
+    }
+
+    if let Some(fd) = args.output_fd {
+        // Appending keeps what was already written to the descriptor
+        args.output = Some(PathBuf::from(format!("/dev/fd/{fd}")));
+        args.append = true;
+    }
+
+    initialize_logging(
+        &config.logging,
+        args.log_file.as_deref(),
+        args.get_log_level(),
+    )?;
+    info!("Initializing");
+
+    if let Some(lines) = args.bench {
+        info!("Running the benchmarks with {lines} lines");
+        return Ok(RunOutcome::Selected(run_benchmarks(lines, &config)?));
+    }
+
+    let start_in_mode = match &args.start_in_mode {
+        Some(mode) => Some(find_mode(&config.modes, mode)?),
+        None => None,
+    };
+
+    let output_options = OutputOptions {
+        trim: args.trim || config.trim_selection,
+        append_newline: args.newline || config.append_newline,
+        append_null: args.null,
+        with_position: args.with_position,
+        with_origin: args.with_origin,
+        with_file: args.with_file,
+        with_time: args.with_time,
+        with_line: args.with_line,
+        quote: args.quote,
+    };
+
+    if args.list_matches {
+        info!("Listing the matches");
+        let mut input = Input::start(&args, &config)?;
+        input.read_to_end();
+
+        // All the hits are listed, not only the ones on a single page
+        config.max_hints = None;
+        let list_options = OutputOptions {
+            with_position: true,
+            append_newline: true,
+            ..output_options
+        };
+        let text = input.text.to_str();
+        let hits = find_all_hits(&text, &config, start_in_mode)?;
+        if args.count {
+            eprintln!("{}", format_match_count(hits.len()));
+        }
+        let list = list_matches(&text, &input.text, hits, &list_options);
+        if list.is_empty() {
+            return Ok(RunOutcome::NothingToSelect(String::new()));
+        }
+        if let Some(path) = &args.output {
+            output_to_file(path, &list, args.append)?;
+            return Ok(RunOutcome::Selected(String::new()));
+        }
+        return Ok(RunOutcome::Selected(list));
+    }
+
+    if let Some(number) = args.select.or(args.select_first.then_some(1)) {
+        info!("Selecting hit number {number}");
+        let mut input = Input::start(&args, &config)?;
+        input.read_to_end();
+
+        // Hits are counted in the whole input, not only on a single page
+        config.max_hints = None;
+        let text = input.text.to_str();
+        let hits = find_all_hits(&text, &config, start_in_mode)?;
+        if args.count {
+            eprintln!("{}", format_match_count(hits.len()));
+        }
+        let Some(hit) = number
+            .checked_sub(1)
+            .and_then(|index| hits.into_iter().nth(index))
+        else {
+            return Ok(RunOutcome::NothingToSelect(String::new()));
+        };
+
+        return output_hit(hit, &input, &args, &output_options);
+    }
+
+    if args.list_ui {
+        info!("Selecting from the list of the hits");
+        let mut input = Input::start(&args, &config)?;
+        input.read_to_end();
+
+        // All the hits are listed, not only the ones on a single page
+        config.max_hints = None;
+        let text = input.text.to_str();
+        let hits = find_all_hits(&text, &config, start_in_mode)?;
+        if hits.is_empty() {
+            return Ok(RunOutcome::NothingToSelect(String::new()));
+        }
+
+        return match select_from_list(hits, &args, &config)? {
+            Some(hit) => output_hit(hit, &input, &args, &output_options),
+            None => Ok(RunOutcome::Cancelled(get_cancel_output(&config, &text))),
+        };
+    }
+
+    if let Some(socket_path) = &args.server {
+        serve(socket_path, &args, &config, start_in_mode, &output_options)?;
+        return Ok(RunOutcome::Selected(String::new()));
+    }
+
+    if args.repeat_last {
+        info!("Repeating the last selection");
+        let output = format_selection(load_last_selection()?, None, None, &output_options);
+        if let Some(path) = &args.output {
+            output_to_file(path, &output, args.append)?;
+            return Ok(RunOutcome::Selected(String::new()));
+        }
+        return Ok(RunOutcome::Selected(output));
+    }
+
+    let input_handler = InputHandler::from_config(&config);
+    let event_source = create_event_source(&args)?;
+    let mut renderer = create_renderer()?;
+
+    if args.stats {
+        stats::enable();
+    }
+    let mut input = stats::measure(Step::ReadInput, || Input::start(&args, &config))?;
+    if args.exit_on_no_match {
+        input.read_to_end();
+        let mode_index = start_in_mode.unwrap_or(0);
+        if mode_has_nothing_to_select(&input.text.to_str(), &config, mode_index)? {
+            info!("Nothing to select in the start mode, exiting");
+            let output = get_cancel_output(&config, &input.text.to_str());
+            return Ok(RunOutcome::NothingToSelect(output));
+        }
+    }
+    // Line numbers start from 1 for the user
+    let start_line = args.line.map(|line| line.saturating_sub(1));
+    if let Some(line) = start_line {
+        let (rows, _) = get_terminal_size()?;
+        input.read_lines(line + rows);
+    }
+
+    let hint_generator = create_hint_generator(&config);
+
+    // Must be running before the terminal is initialized, so that the
+    // terminal is never left initialized without a way to restore it
+    start_force_quit_handler()?;
+
+    let capture_mouse = config.mouse_wheel_scroll != MouseWheelScroll::Off;
+    let mut terminal =
+        TerminalGuard::new(&mut renderer, capture_mouse).context(TerminalHandlingSnafu {
+            operation: "initialize",
+        })?;
+
+    let mut selection_count = 0;
+    let mut emit_selection = |renderer: &mut Renderer<File>, result: SelectionResult| {
+        selection_count += 1;
+        // The command is run in the terminal as it normally is
+        let runs_command = matches!(result.3, OutputTarget::Run(_) | OutputTarget::Open);
+        if runs_command {
+            renderer
+                .uninitialize_terminal()
+                .context(TerminalHandlingSnafu {
+                    operation: "uninitialize",
+                })?;
+        }
+
+        let output = handle_selection(result, &args, &config, &output_options)?;
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(output.as_bytes())
+            .and_then(|_| stdout.flush())
+            .context(SelectionOutputSnafu {})?;
+
+        if runs_command {
+            renderer
+                .initialize_terminal(capture_mouse)
+                .context(TerminalHandlingSnafu {
+                    operation: "initialize",
+                })?;
+        }
+        Ok(())
+    };
+
+    let ret = run_main_loop(
+        input_handler,
+        event_source,
+        hint_generator.deref(),
+        &config,
+        &mut terminal,
+        &mut input,
+        StartOptions {
+            mode_index: start_in_mode,
+            at_end: args.end,
+            line: start_line,
+            paging: args.pager || config.start_as_pager,
+            timeout: args.timeout_session.map(Duration::from_secs),
+        },
+        match args.keep_selecting {
+            true => Some(&mut emit_selection),
+            false => None,
+        },
+    );
+
+    terminal.restore().context(TerminalHandlingSnafu {
+        operation: "uninitialize",
+    })?;
+
+    if args.stats {
+        eprint!("{}", stats::report());
+    }
+
+    let Some(result) = ret? else {
+        // Everything selected was already output
+        if selection_count > 0 {
+            return Ok(RunOutcome::Selected(pass_input_through(&args, &mut input)));
+        }
+        if config.cancel_output == CancelOutput::Input && !input.follow {
+            input.read_to_end();
+        }
+        let mut output = get_cancel_output(&config, &input.text.to_str());
+        if let (Some(path), Some(_)) = (&args.output, args.output_fd) {
+            output_to_file(path, &output, args.append)?;
+            output = pass_input_through(&args, &mut input);
+        }
+        return match has_nothing_to_select(&input, &config)? {
+            true => Ok(RunOutcome::NothingToSelect(output)),
+            false => Ok(RunOutcome::Cancelled(output)),
+        };
+    };
+
+    let output = handle_selection(result, &args, &config, &output_options)?;
+    match args.output_fd {
+        Some(_) => Ok(RunOutcome::Selected(pass_input_through(&args, &mut input))),
+        None => Ok(RunOutcome::Selected(output)),
+    }
+}
+
+/// Get the text to write to the standard output once the selection is done
+/// and the selection itself is already output. It is the whole input with
+/// --output-fd, so that the input continues down the pipeline, and nothing
+/// otherwise.
+fn pass_input_through(args: &Args, input: &mut Input) -> String {
+    if args.output_fd.is_none() {
+        return String::new();
+    }
+
+    input.read_to_end();
+    input.text.to_str().into_owned()
+}
+
+/// Listen on the unix socket at the given path and, for each client that
+/// connects and sends a text, let the user select from the text on the
+/// terminal and reply with the selection. The reply is empty if the
+/// selection is cancelled or goes elsewhere than the standard output.
+///
+/// Runs until the process is terminated, e.g. with Ctrl-C.
+fn serve(
+    socket_path: &Path,
+    args: &Args,
+    config: &Config,
+    start_in_mode: Option<usize>,
+    output_options: &OutputOptions,
+) -> Result<(), RunError> {
+    // A socket left behind by a previous server would prevent binding,
+    // while anything else at the path makes the binding fail instead
+    if is_stale_socket(socket_path) {
+        fs::remove_file(socket_path).context(ServerSnafu { path: socket_path })?;
+    }
+    let listener = UnixListener::bind(socket_path).context(ServerSnafu { path: socket_path })?;
+    info!("Listening on {}", socket_path.display());
+
+    let hint_generator = create_hint_generator(config);
+    let mut renderer = create_renderer()?;
+    start_force_quit_handler()?;
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!("Could not accept a connection: {error}");
+                continue;
+            }
+        };
+
+        let mut text = String::new();
+        if let Err(error) = stream.read_to_string(&mut text) {
+            warn!("Could not read the request: {error}");
+            continue;
+        }
+        info!("Got a request with {} bytes", text.len());
+
+        let mut input = Input::from_text(&text, config);
+        let capture_mouse = config.mouse_wheel_scroll != MouseWheelScroll::Off;
+        let mut terminal =
+            TerminalGuard::new(&mut renderer, capture_mouse).context(TerminalHandlingSnafu {
+                operation: "initialize",
+            })?;
+
+        let ret = run_main_loop(
+            InputHandler::from_config(config),
+            create_event_source(args)?,
+            hint_generator.deref(),
+            config,
+            &mut terminal,
+            &mut input,
+            StartOptions {
+                mode_index: start_in_mode,
+                paging: args.pager || config.start_as_pager,
+                ..StartOptions::default()
+            },
+            None,
+        );
+
+        terminal.restore().context(TerminalHandlingSnafu {
+            operation: "uninitialize",
+        })?;
+
+        let reply = match ret {
+            Ok(Some(result)) => handle_selection(result, args, config, output_options)?,
+            // Only this request is given up on, not the server
+            Ok(None) | Err(RunError::Aborted) => String::new(),
+            Err(error) => return Err(error),
+        };
+        if let Err(error) = stream.write_all(reply.as_bytes()) {
+            warn!("Could not send the reply: {error}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Let the user select from the given text in the terminal with the given
+/// config, like from the input of [run], and get the selected text, or
+/// [None] if the selection was cancelled.
+///
+/// Unlike [run], the selection is only returned, without running the
+/// selection action or remembering it, and the signals are left to the
+/// caller, which needs to restore the terminal if they end the process.
+pub fn select_from_str(text: &str, config: &Config) -> Result<Option<String>, RunError> {
+    let mut input = Input::from_text(text, config);
+    let hint_generator = create_hint_generator(config);
+    let mut renderer = create_renderer()?;
+
+    let capture_mouse = config.mouse_wheel_scroll != MouseWheelScroll::Off;
+    let mut terminal =
+        TerminalGuard::new(&mut renderer, capture_mouse).context(TerminalHandlingSnafu {
+            operation: "initialize",
+        })?;
+
+    let ret = run_main_loop(
+        InputHandler::from_config(config),
+        EventSource::Terminal(None),
+        hint_generator.deref(),
+        config,
+        &mut terminal,
+        &mut input,
+        StartOptions {
+            paging: config.start_as_pager,
+            ..StartOptions::default()
+        },
+        None,
+    );
+
+    terminal.restore().context(TerminalHandlingSnafu {
+        operation: "uninitialize",
+    })?;
+
+    Ok(ret?.map(|(selection, ..)| selection))
+}
+
+/// Remember, filter and format the given selection and pass it to where it
+/// goes. Returns the text to write to the standard output, if any.
+fn handle_selection(
+    (selection, position, origin, output_target): SelectionResult,
+    args: &Args,
+    config: &Config,
+    output_options: &OutputOptions,
+) -> Result<String, RunError> {
+    if config.remember_last_selection {
+        // Failing to remember the selection should not lose it
+        if let Err(error) = save_last_selection(&selection) {
+            warn!("{error}");
+        }
+    }
+    if config.remember_history {
+        if let Err(error) = add_to_history(&selection, config.history_size) {
+            warn!("{error}");
+        }
+    }
+
+    let selection = match &config.selection_filter {
+        Some(command) => filter_selection(selection, command),
+        None => selection,
+    };
+    // The formatting is meant for the output only, the actions get the
+    // selection itself, e.g. the editor a file name and not its position
+    let format_output = || {
+        let output = format_selection(
+            selection.clone(),
+            position.clone(),
+            origin.as_ref(),
+            output_options,
+        );
+        match output_options.with_time && !output.is_empty() {
+            true => format!("{} {}", format_timestamp(SystemTime::now()), output),
+            false => output,
+        }
+    };
+    match output_target {
+        OutputTarget::Stdout => match &args.output {
+            Some(path) => {
+                output_to_file(path, &format_output(), args.append)?;
+                Ok(String::new())
+            }
+            None => Ok(format_output()),
+        },
+        OutputTarget::Command(command) => {
+            output_to_command(&command, &selection)?;
+            Ok(String::new())
+        }
+        OutputTarget::Clipboard => {
+            copy_to_clipboard(&selection, config.clipboard_command.as_deref(), false)?;
+            Ok(String::new())
+        }
+        OutputTarget::PrimarySelection => {
+            copy_to_clipboard(&selection, config.primary_command.as_deref(), true)?;
+            Ok(String::new())
+        }
+        OutputTarget::TmuxBuffer => {
+            load_tmux_buffer(&selection)?;
+            Ok(String::new())
+        }
+        OutputTarget::Run(template) => {
+            run_with_selection(&template, &selection)?;
+            Ok(String::new())
+        }
+        OutputTarget::Type => {
+            type_selection(&selection)?;
+            Ok(String::new())
+        }
+        OutputTarget::Editor => {
+            open_in_editor(&selection)?;
+            Ok(String::new())
+        }
+        OutputTarget::Open => match OpenHandler::find(&config.open_handlers, &selection) {
+            Some(handler) => {
+                info!("Opening the selection as {}", handler.name);
+                run_with_selection(&handler.command, &selection)?;
+                Ok(String::new())
+            }
+            None => {
+                warn!("No open handler matches the selection, outputting it");
+                Ok(format_output())
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use test_case::test_case;
+
+    #[test_case(Some(0), 3, true, 1; "forward_from_first")]
+    #[test_case(Some(2), 3, true, 0; "forward_from_last")]
+    #[test_case(Some(1), 3, false, 0; "backward_from_middle")]
+    #[test_case(Some(0), 3, false, 2; "backward_from_first")]
+    #[test_case(None, 3, true, 0; "forward_from_mode_selection")]
+    #[test_case(None, 3, false, 2; "backward_from_mode_selection")]
+    #[test_case(Some(0), 1, true, 0; "forward_with_single_mode")]
+    fn get_cycled_mode_index_returns_expected_value(
+        current: Option<usize>,
+        mode_count: usize,
+        forward: bool,
+        expected: usize,
+    ) {
+        assert_eq!(
+            get_cycled_mode_index(current, mode_count, forward),
+            expected
+        );
+    }
+
+    #[test_case(512, "512 B"; "when_size_is_in_bytes")]
+    #[test_case(1536, "1.5 KiB"; "when_size_is_in_kibibytes")]
+    #[test_case(3 * 1024 * 1024, "3.0 MiB"; "when_size_is_in_mebibytes")]
+    fn format_size_returns_expected_text(bytes: usize, expected: &str) {
+        assert_eq!(format_size(bytes), expected);
+    }
+
+    #[test_case(0, 2, 1, 6, "second"; "when_hit_is_on_first_page_line")]
+    #[test_case(8, 3, 2, 14, "third line"; "when_hit_is_on_later_page_line")]
+    fn get_input_position_returns_expected_position(
+        page_offset: usize,
+        line: usize,
+        column: usize,
+        offset: usize,
+        line_text: &str,
+    ) {
+        let input_text = InputText::from("first\nsecond\nthird line\n");
+
+        let position = get_input_position(&input_text, 1, "second\nthird line", page_offset);
+
+        assert_eq!(
+            position,
+            InputPosition {
+                line,
+                column,
+                offset,
+                line_text: line_text.to_string(),
+            }
+        );
+    }
+
+    #[test_case("a b c\n-- --\n", true; "when_nothing_matches")]
+    #[test_case("a b c\nsomething\n", false; "when_something_matches")]
+    fn has_nothing_to_select_returns_expected_value(text: &str, expected: bool) {
+        let input = Input {
+            text: InputText::from(text),
+            follower: None,
+            follow: false,
+            files: vec![],
+            file_index: 0,
+            watch: false,
+            file_version: None,
+            size: None,
+            command: None,
+            filters: vec![],
+            max_lines: None,
+            tail: None,
+            keep_lines: None,
+            dropped_lines: 0,
+            read_whole: false,
+            separator_lines: vec![],
+        };
+
+        let nothing_to_select = has_nothing_to_select(&input, &Config::default()).unwrap();
+
+        assert_eq!(nothing_to_select, expected);
+    }
+
+    #[test_case("a b c\n", 0, true; "when_mode_finds_nothing")]
+    #[test_case("a b c\nsomething\n", 0, false; "when_mode_finds_something")]
+    #[test_case("a b c\n", 10, false; "when_mode_does_not_exist")]
+    fn mode_has_nothing_to_select_returns_expected_value(
+        text: &str,
+        mode_index: usize,
+        expected: bool,
+    ) {
+        let nothing_to_select =
+            mode_has_nothing_to_select(text, &Config::default(), mode_index).unwrap();
+
+        assert_eq!(nothing_to_select, expected);
+    }
+
+    #[test_case("r", Some(0); "when_hotkey_is_given")]
+    #[test_case("default", Some(0); "when_name_is_given")]
+    #[test_case("nope", None; "when_nothing_matches")]
+    fn find_mode_returns_expected_index(mode: &str, expected: Option<usize>) {
+        let config = Config::default();
+
+        let index = find_mode(&config.modes, mode);
+
+        assert_eq!(index.ok(), expected);
+    }
+
+    #[test]
+    fn find_mode_lists_available_modes_when_nothing_matches() {
+        let config = Config::default();
+
+        let error = find_mode(&config.modes, "nope").unwrap_err().to_string();
+
+        assert!(error.contains("nope"));
+        assert!(error.contains("r (default)"));
+    }
+
+    #[test]
+    fn get_command_line_mode_uses_unused_hotkey() {
+        let config = Config::default();
+
+        let mode = get_command_line_mode(&["[0-9]+".to_string()], &config).unwrap();
+
+        assert!(config.modes.iter().all(|other| other.hotkey != mode.hotkey));
+        let ModeArgs::RegexMode(args) = &mode.args else {
+            panic!("The command line mode is not a regex mode");
+        };
+        assert_eq!(args.regexes[0].as_str(), "[0-9]+");
+    }
+
+    #[test]
+    fn get_command_line_mode_rejects_invalid_regex() {
+        let result = get_command_line_mode(&["(".to_string()], &Config::default());
+
+        assert!(matches!(result, Err(RunError::InvalidRegex { .. })));
+    }
+
+    #[test]
+    fn get_command_line_mode_fails_when_all_hotkeys_are_taken() {
+        let modes = ('a'..='z')
+            .chain('0'..='9')
+            .map(|hotkey| {
+                get_command_line_mode(&[], &Config::default())
+                    .map(|mode| configuration::Mode { hotkey, ..mode })
+            })
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let config = Config {
+            modes,
+            ..Config::default()
+        };
+
+        let result = get_command_line_mode(&["[0-9]+".to_string()], &config);
+
+        assert!(matches!(result, Err(RunError::NoFreeHotkey)));
+    }
+
+    #[test_case("a b c\n", false, Some("no matches for mode default"); "when_mode_finds_nothing")]
+    #[test_case("a b c\n", true, None; "when_paging")]
+    #[test_case("something\n", false, None; "when_mode_finds_something")]
+    fn get_no_match_message_returns_expected_message(
+        text: &str,
+        paging: bool,
+        expected: Option<&str>,
+    ) {
+        let config = Config::default();
+        let ModeArgs::RegexMode(args) = &config.modes[0].args else {
+            panic!("The first default mode is not a regex mode");
+        };
+        let hint_generator =
+            HintPoolGenerator::new(&config.hint_characters, config.fixed_hint_length, None);
+        let mode = RegexMode::new(text, args, &hint_generator, &config).unwrap();
+
+        let message = get_no_match_message(&mode, Some(0), paging, &config);
+
+        assert_eq!(message.as_deref(), expected);
+    }
+
+    #[test_case(0, "0 matches"; "when_nothing_is_found")]
+    #[test_case(1, "1 match"; "when_one_hit_is_found")]
+    #[test_case(12, "12 matches"; "when_several_hits_are_found")]
+    fn format_match_count_returns_expected_text(count: usize, expected: &str) {
+        assert_eq!(format_match_count(count), expected);
+    }
+
+    #[test]
+    fn list_matches_lists_hits_with_positions() {
+        let options = OutputOptions {
+            with_position: true,
+            append_newline: true,
+            ..OutputOptions::default()
+        };
+
+        let text = "a first\n\nb second";
+        let hits = find_all_hits(text, &Config::default(), None).unwrap();
+
+        let list = list_matches(text, &InputText::from(text), hits, &options);
+
+        assert_eq!(list, "1:3:2:first\n3:3:11:second\n");
+    }
+
+    #[test]
+    fn list_matches_keeps_offsets_of_invalid_utf8_input() {
+        let options = OutputOptions {
+            with_position: true,
+            append_newline: true,
+            ..OutputOptions::default()
+        };
+
+        let text = InputFollower::start(&b"\xff\xfe first\n\xff second"[..]).wait_for_end();
+        let hits = find_all_hits(&text, &Config::default(), None).unwrap();
+
+        let list = list_matches(&text, &InputText::from(text.as_str()), hits, &options);
+
+        assert_eq!(list, "1:4:3:first\n2:3:11:second\n");
+    }
+
+    #[test]
+    fn list_matches_lists_offsets_in_crlf_input() {
+        let options = OutputOptions {
+            with_position: true,
+            append_newline: true,
+            ..OutputOptions::default()
+        };
+
+        let mut input_text = InputText::default();
+        let (text, offsets) = prepare_input(&[], "a first\r\nb second\r\n");
+        input_text.push_str(&text, &offsets);
+        let hits = find_all_hits(&text, &Config::default(), None).unwrap();
+
+        let list = list_matches(&text, &input_text, hits, &options);
+
+        assert_eq!(list, "1:3:2:first\n2:3:11:second\n");
+    }
+
+    #[test]
+    fn list_matches_lists_origin_of_hits() {
+        let options = OutputOptions {
+            with_origin: true,
+            append_newline: true,
+            ..OutputOptions::default()
+        };
+
+        let text = "a first";
+        let hits = find_all_hits(text, &Config::default(), None).unwrap();
+
+        let list = list_matches(text, &InputText::from(text), hits, &options);
+
+        assert_eq!(list, "default:0:first\n");
+    }
+
+    #[test]
+    fn list_matches_lists_lines_of_hits() {
+        let options = OutputOptions {
+            with_line: true,
+            append_newline: true,
+            ..OutputOptions::default()
+        };
+
+        let text = "a first\nb second";
+        let hits = find_all_hits(text, &Config::default(), None).unwrap();
+
+        let list = list_matches(text, &InputText::from(text), hits, &options);
+
+        assert_eq!(list, "first\ta first\nsecond\tb second\n");
+    }
+
+    #[test]
+    fn handle_selection_runs_command_with_unquoted_selection() {
+        let path = env::temp_dir().join(format!("mless-run-{}", std::process::id()));
+        let template = format!("printf %s {{}} > {}", path.display());
+        let options = OutputOptions {
+            quote: true,
+            ..OutputOptions::default()
+        };
+        let result = ("it's".to_string(), None, None, OutputTarget::Run(template));
+
+        let output = handle_selection(
+            result,
+            &Args::parse_from(["mless"]),
+            &Config::default(),
+            &options,
+        )
+        .unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(output, "");
+        assert_eq!(contents, "it's");
+    }
+
+    #[test]
+    fn handle_selection_opens_selection_without_position_in_editor() {
+        let options = OutputOptions {
+            with_position: true,
+            ..OutputOptions::default()
+        };
+        let position = InputPosition {
+            line: 2,
+            column: 5,
+            offset: 14,
+            line_text: "see notes\x1b".to_string(),
+        };
+        // The file name is refused before any editor is looked for, which
+        // shows the name the editor would get
+        let result = (
+            "notes\x1b".to_string(),
+            Some(position),
+            None,
+            OutputTarget::Editor,
+        );
+
+        let error = handle_selection(
+            result,
+            &Args::parse_from(["mless"]),
+            &Config::default(),
+            &options,
+        )
+        .unwrap_err();
+
+        let RunError::UnsafeFileName { file } = error else {
+            panic!("Unexpected error {error:?}");
+        };
+        assert_eq!(file, "notes\x1b");
+    }
+
+    #[test]
+    fn only_sockets_without_server_are_stale() {
+        let path = env::temp_dir().join(format!("mless-socket-{}", std::process::id()));
+
+        fs::write(&path, "notes").unwrap();
+        let is_file_stale = is_stale_socket(&path);
+        fs::remove_file(&path).unwrap();
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let is_live_socket_stale = is_stale_socket(&path);
+        drop(listener);
+        let is_left_socket_stale = is_stale_socket(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(!is_file_stale);
+        assert!(!is_live_socket_stale);
+        assert!(is_left_socket_stale);
+        assert!(!is_stale_socket(&path));
+    }
+
+    #[test]
+    fn toggle_collected_adds_new_text_and_removes_collected_text() {
+        let mut collected = vec!["a".to_string(), "b".to_string()];
+
+        toggle_collected(&mut collected, "c".to_string());
+        toggle_collected(&mut collected, "a".to_string());
+
+        assert_eq!(collected, ["b", "c"]);
+    }
+
+    #[test_case("", "tmux capture-pane -e -p"; "when_pane_is_not_given")]
+    #[test_case("%3", "tmux capture-pane -e -p -t '%3'"; "when_pane_is_given")]
+    #[test_case("it's", r"tmux capture-pane -e -p -t 'it'\''s'"; "when_pane_has_quote")]
+    fn get_tmux_capture_command_returns_expected_command(pane: &str, expected: &str) {
+        assert_eq!(get_tmux_capture_command(pane), expected);
+    }
+
+    fn hit(start: usize, text: &str) -> Hit {
+        Hit {
+            start,
+            length: text.len(),
+            text: text.into(),
+            regex_index: 0,
+        }
+    }
+
+    #[test]
+    fn hits_on_separator_lines_are_removed() {
+        let page_text = "==> first <==\nfirst text\n==> second <==\nsecond text";
+        let mut hits = vec![hit(4, "first"), hit(14, "first"), hit(29, "second")];
+
+        remove_separator_hits(&mut hits, page_text, 10, &[10, 12]);
+
+        assert_eq!(hits, vec![hit(14, "first")]);
+    }
+
+    #[test]
+    fn mode_history_returns_to_previous_mode() {
+        let mut history = ModeHistory::default();
+        history.switched_to(0);
+
+        assert_eq!(history.get_return_mode(Some(0)), None);
+
+        history.switched_to(2);
+        assert_eq!(history.get_return_mode(Some(2)), Some(0));
+
+        // Switching back allows returning again, like `cd -`
+        history.switched_to(0);
+        assert_eq!(history.get_return_mode(Some(0)), Some(2));
+    }
+
+    #[test]
+    fn mode_history_returns_to_last_mode_from_mode_selection() {
+        let mut history = ModeHistory::default();
+        history.switched_to(0);
+        history.switched_to(1);
+
+        assert_eq!(history.get_return_mode(None), Some(1));
+    }
 }
@@ -0,0 +1,40 @@
+//! Searching the input for the text to select.
+
+/// Find the index of the first line after `from_line` that contains the
+/// given query, wrapping around to the start of the input if needed.
+///
+/// The line `from_line` itself is checked last, so that repeating the
+/// search moves on to the next occurrence.
+pub fn find_next_line(input_text: &str, query: &str, from_line: usize) -> Option<usize> {
+    let lines: Vec<&str> = input_text.lines().collect();
+    let line_count = lines.len();
+
+    (1..=line_count)
+        .map(|offset| (from_line + offset) % line_count)
+        .find(|&index| lines[index].contains(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const TEXT: &str = "error 1\ninfo\nerror 2\ninfo\n";
+
+    #[test_case("error", 0, Some(2); "when_next_occurrence_is_below")]
+    #[test_case("error", 2, Some(0); "when_search_wraps_around")]
+    #[test_case("error 2", 2, Some(2); "when_only_occurrence_is_current_line")]
+    #[test_case("warning", 0, None; "when_there_is_no_occurrence")]
+    fn find_next_line_returns_expected_value(
+        query: &str,
+        from_line: usize,
+        expected: Option<usize>,
+    ) {
+        assert_eq!(find_next_line(TEXT, query, from_line), expected);
+    }
+
+    #[test]
+    fn find_next_line_returns_none_for_empty_input() {
+        assert_eq!(find_next_line("", "error", 0), None);
+    }
+}
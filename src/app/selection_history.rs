@@ -0,0 +1,156 @@
+//! Keeping the history of the selected texts between the runs.
+use std::{
+    collections::HashMap,
+    env::{self, VarError},
+    fs, io,
+    path::PathBuf,
+};
+
+use log::debug;
+use regex::Regex;
+use snafu::ResultExt;
+
+use crate::{
+    configuration::{Mode, ModeArgs, RegexArgs},
+    error::{HistoryLoadSnafu, HistorySaveSnafu, RunError},
+};
+
+/// Implementation of [get_history_path] with additional arguments to make
+/// testing easier.
+///
+/// Arguments:
+///  - `get_env_var`: function to use to get the value of the the given environment variable.
+///    Should return the value of the variable with the given name if the variable can be read
+///    or an error otherwise.
+fn get_history_path_impl(
+    get_env_var: &dyn Fn(&str) -> Result<String, VarError>,
+) -> Option<PathBuf> {
+    match get_env_var("XDG_DATA_HOME") {
+        Ok(directory) => Some(PathBuf::from(format!("{directory}/mless/history"))),
+        Err(_) => get_env_var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(format!("{home}/.local/share/mless/history"))),
+    }
+}
+
+/// Get the path of the file where the history of the selections is kept.
+///
+/// The file is `$XDG_DATA_HOME/mless/history` or, if `XDG_DATA_HOME` is not
+/// defined, `$HOME/.local/share/mless/history`.
+fn get_history_path() -> Option<PathBuf> {
+    let get_env_var = |var_name: &str| env::var(var_name);
+
+    get_history_path_impl(&get_env_var)
+}
+
+/// Load the selections saved with [add_to_history], the most recent first.
+pub fn load_history() -> Result<Vec<String>, RunError> {
+    let Some(path) = get_history_path() else {
+        return Err(RunError::NoDataDirectory);
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(history) => Ok(history.lines().map(str::to_string).collect()),
+        // Nothing was selected yet
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(error) => Err(error).context(HistoryLoadSnafu { path }),
+    }
+}
+
+/// Save the given selection as the most recent one in the history, keeping
+/// at most `size` selections.
+///
+/// The history has one selection per line, so the selections that span
+/// several lines are not saved.
+pub fn add_to_history(selection: &str, size: usize) -> Result<(), RunError> {
+    if selection.is_empty() || selection.contains('\n') {
+        debug!("Not saving selection <{selection}> to the history");
+        return Ok(());
+    }
+
+    let Some(path) = get_history_path() else {
+        return Err(RunError::NoDataDirectory);
+    };
+
+    let history = update_history(load_history()?, selection, size);
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).context(HistorySaveSnafu { path: &path })?;
+    }
+    fs::write(&path, history.join("\n") + "\n").context(HistorySaveSnafu { path })
+}
+
+/// Get the mode used to select from the history, which has a hint for
+/// every selection in it.
+pub fn get_history_mode() -> Mode {
+    Mode {
+        args: ModeArgs::RegexMode(RegexArgs {
+            #[allow(
+                clippy::unwrap_used,
+                reason = "Hardcoded value that is verified to work"
+            )]
+            regexes: vec![Regex::new(r"(?m)^.+$").unwrap()],
+            min_length: 0,
+            literal_matchers: Default::default(),
+        }),
+        hotkey: 'h',
+        name: "history".to_string(),
+        keybindings: HashMap::new(),
+        action: None,
+    }
+}
+
+/// Put the given selection at the start of the given history, removing its
+/// earlier occurrence, and drop the oldest selections above `size`.
+fn update_history(mut history: Vec<String>, selection: &str, size: usize) -> Vec<String> {
+    history.retain(|entry| entry != selection);
+    history.insert(0, selection.to_string());
+    history.truncate(size);
+
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(
+        Ok("/home/user/.xdg_data".to_string()),
+        Ok("/home/user".to_string()),
+        Some(PathBuf::from("/home/user/.xdg_data/mless/history")); "when_xdg_data_is_defined")]
+    #[test_case(
+        Err(VarError::NotPresent),
+        Ok("/home/user".to_string()),
+        Some(PathBuf::from("/home/user/.local/share/mless/history")); "when_xdg_data_is_not_defined")]
+    #[test_case(
+        Err(VarError::NotPresent),
+        Err(VarError::NotPresent),
+        None; "when_nothing_is_defined")]
+    fn get_history_path_returns_expected_value(
+        xdg_data_home: Result<String, VarError>,
+        home: Result<String, VarError>,
+        expected: Option<PathBuf>,
+    ) {
+        let get_env_var = |var_name: &str| match var_name {
+            "XDG_DATA_HOME" => xdg_data_home.clone(),
+            "HOME" => home.clone(),
+            _ => Err(VarError::NotPresent),
+        };
+
+        assert_eq!(get_history_path_impl(&get_env_var), expected);
+    }
+
+    #[test_case(&["a", "b"], "c", 3, &["c", "a", "b"]; "when_selection_is_new")]
+    #[test_case(&["a", "b", "c"], "b", 3, &["b", "a", "c"]; "when_selection_is_already_there")]
+    #[test_case(&["a", "b", "c"], "d", 3, &["d", "a", "b"]; "when_history_is_full")]
+    fn update_history_returns_expected_history(
+        history: &[&str],
+        selection: &str,
+        size: usize,
+        expected: &[&str],
+    ) {
+        let history = history.iter().map(|entry| entry.to_string()).collect();
+
+        assert_eq!(update_history(history, selection, size), expected);
+    }
+}
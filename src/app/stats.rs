@@ -0,0 +1,161 @@
+//! Measuring how long the main steps of the selection take while running,
+//! see --stats.
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    mem,
+    time::{Duration, Instant},
+};
+
+/// The measured steps of the selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// Reading the input until the first screen of it is available.
+    ReadInput,
+    /// Computing the text of a page from the input.
+    Page,
+    /// Matching the regexes of a mode on a page.
+    Match,
+    /// Generating and assigning the hints of a page.
+    Hints,
+    /// Rendering a screen.
+    Render,
+}
+
+impl Step {
+    /// All the steps, in the order they are reported.
+    const ALL: [Step; 5] = [
+        Step::ReadInput,
+        Step::Page,
+        Step::Match,
+        Step::Hints,
+        Step::Render,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Step::ReadInput => "input read",
+            Step::Page => "page computation",
+            Step::Match => "matching",
+            Step::Hints => "hint generation",
+            Step::Render => "render",
+        }
+    }
+}
+
+/// The total time a step took and how many times it was done.
+#[derive(Debug, Default, Clone, Copy)]
+struct StepStats {
+    total: Duration,
+    count: u32,
+}
+
+thread_local! {
+    /// The measurements of the steps, or [None] if they are not measured,
+    /// see [enable].
+    static STATS: RefCell<Option<HashMap<Step, StepStats>>> = const { RefCell::new(None) };
+}
+
+/// Start measuring the steps passed to [measure] on this thread.
+pub fn enable() {
+    STATS.with(|stats| *stats.borrow_mut() = Some(HashMap::new()));
+}
+
+/// Run the given step and record how long it took, if the steps are
+/// measured.
+pub fn measure<T>(step: Step, run: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = run();
+    let elapsed = start.elapsed();
+
+    STATS.with(|stats| {
+        if let Some(stats) = stats.borrow_mut().as_mut() {
+            let step_stats = stats.entry(step).or_default();
+            step_stats.total += elapsed;
+            step_stats.count += 1;
+        }
+    });
+
+    result
+}
+
+/// Get the report of the measured steps, with the total and the average
+/// time of each of them, and the peak memory usage of the process. The
+/// measurements are reset.
+pub fn report() -> String {
+    let stats = STATS
+        .with(|stats| mem::take(&mut *stats.borrow_mut()))
+        .unwrap_or_default();
+
+    let mut report = String::new();
+    for step in Step::ALL {
+        let StepStats { total, count } = stats.get(&step).copied().unwrap_or_default();
+        let average = total.checked_div(count).unwrap_or_default();
+
+        report += &format!(
+            "{:<20}{count:>8} times{:>12.3} ms total{:>12.3} ms average\n",
+            step.name(),
+            total.as_secs_f64() * 1000.0,
+            average.as_secs_f64() * 1000.0,
+        );
+    }
+
+    if let Some(peak_memory) = get_peak_memory() {
+        report += &format!(
+            "{:<20}{:>12.1} MiB\n",
+            "peak memory",
+            peak_memory as f64 / (1024.0 * 1024.0)
+        );
+    }
+
+    report
+}
+
+/// Get the largest amount of memory in bytes the process had resident at
+/// any time, if known.
+fn get_peak_memory() -> Option<u64> {
+    // SAFETY: An all-zero rusage is a valid value of the plain C struct
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    // SAFETY: The pointer is valid for the duration of the call and is not
+    // kept by getrusage
+    let result = unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) };
+    if result != 0 {
+        return None;
+    }
+
+    let max_rss = u64::try_from(usage.ru_maxrss).ok()?;
+    // Reported in bytes on macOS and in kilobytes everywhere else
+    match cfg!(target_os = "macos") {
+        true => Some(max_rss),
+        false => Some(max_rss * 1024),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_contains_measured_steps() {
+        enable();
+        measure(Step::Match, || ());
+        measure(Step::Match, || ());
+
+        let report = report();
+
+        assert!(report
+            .lines()
+            .any(|line| line.starts_with("matching") && line.contains(" 2 times")));
+        assert!(report
+            .lines()
+            .any(|line| line.starts_with("render") && line.contains(" 0 times")));
+    }
+
+    #[test]
+    fn measure_returns_result_of_step_when_not_enabled() {
+        let result = measure(Step::Page, || 42);
+
+        assert_eq!(result, 42);
+        assert!(report().contains(" 0 times"));
+    }
+}
@@ -0,0 +1,201 @@
+//! The part of the input that is shown on the screen.
+use std::io::BufReader;
+
+use crate::{
+    app::{input_text::InputText, page_cache::PageKey},
+    pager::{get_last_page_line_count, get_page},
+};
+
+/// The position of the screen in the input.
+#[derive(Debug)]
+pub struct Viewport {
+    /// Index of the input line shown at the top of the screen.
+    first_line: usize,
+    /// Whether the lines longer than the screen width are wrapped to the
+    /// next rows, or truncated if false.
+    wrap_lines: bool,
+}
+
+impl Viewport {
+    /// Create a [Viewport] showing the start of the input.
+    pub fn new(wrap_lines: bool) -> Self {
+        Self {
+            first_line: 0,
+            wrap_lines,
+        }
+    }
+
+    /// Switch between wrapping and truncating the lines longer than the
+    /// screen width, keeping the position as far as the end of the input
+    /// allows.
+    pub fn toggle_wrap_lines(&mut self, input_text: &InputText, rows: usize, cols: usize) {
+        self.wrap_lines = !self.wrap_lines;
+        self.scroll(0, input_text, rows, cols);
+    }
+
+    /// Get the part of the input that fits on the screen of the given size.
+    pub fn get_page(&self, input_text: &InputText, rows: usize, cols: usize) -> String {
+        // No more lines than rows can fit on the screen
+        let visible_text = input_text.lines(self.first_line, rows);
+
+        let mut input_buffer = BufReader::new(visible_text.as_bytes());
+        get_page(&mut input_buffer, rows, cols, self.wrap_lines)
+    }
+
+    /// Get the key identifying the page returned by [Viewport::get_page]
+    /// with the same arguments.
+    pub fn get_page_key(&self, input_text: &InputText, rows: usize, cols: usize) -> PageKey {
+        PageKey {
+            first_line: self.first_line,
+            rows,
+            cols,
+            wrap_lines: self.wrap_lines,
+            input_len: input_text.len(),
+        }
+    }
+
+    /// Scroll by the given number of lines, negative for up, without going
+    /// past the start or the end of the input.
+    ///
+    /// Returns whether the position changed.
+    pub fn scroll(
+        &mut self,
+        lines: isize,
+        input_text: &InputText,
+        rows: usize,
+        cols: usize,
+    ) -> bool {
+        let last_page_start = self.get_last_page_start(input_text, rows, cols);
+        let first_line = self
+            .first_line
+            .saturating_add_signed(lines)
+            .min(last_page_start);
+
+        let changed = first_line != self.first_line;
+        self.first_line = first_line;
+
+        changed
+    }
+
+    /// Get the index of the first line of the last page of the input, i.e. of
+    /// the page that ends with the last line.
+    fn get_last_page_start(&self, input_text: &InputText, rows: usize, cols: usize) -> usize {
+        let line_count = input_text.line_count();
+        // The last page has no more lines than rows
+        let last_lines = input_text.lines(line_count.saturating_sub(rows), rows);
+        let last_page_line_count =
+            get_last_page_line_count(&last_lines, rows, cols, self.wrap_lines);
+
+        line_count.saturating_sub(last_page_line_count)
+    }
+
+    /// Get the index of the input line shown at the top of the screen.
+    pub fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    /// Scroll so that the given line is shown at the top of the screen of
+    /// the given size, or as close to the top as the end of the input allows.
+    ///
+    /// Returns whether the position changed.
+    pub fn scroll_to_line(
+        &mut self,
+        line: usize,
+        input_text: &InputText,
+        rows: usize,
+        cols: usize,
+    ) -> bool {
+        let first_line = line.min(self.get_last_page_start(input_text, rows, cols));
+
+        let changed = first_line != self.first_line;
+        self.first_line = first_line;
+
+        changed
+    }
+
+    /// Scroll so that the first line of the input is shown at the top.
+    ///
+    /// Returns whether the position changed.
+    pub fn scroll_to_start(&mut self) -> bool {
+        let changed = self.first_line != 0;
+        self.first_line = 0;
+
+        changed
+    }
+
+    /// Check whether the last line of the input is shown on the screen of
+    /// the given size.
+    pub fn is_at_end(&self, input_text: &InputText, rows: usize, cols: usize) -> bool {
+        self.first_line >= self.get_last_page_start(input_text, rows, cols)
+    }
+
+    /// Scroll so that the last line of the input is shown at the bottom of
+    /// the screen of the given size.
+    ///
+    /// Returns whether the position changed.
+    pub fn scroll_to_end(&mut self, input_text: &InputText, rows: usize, cols: usize) -> bool {
+        let first_line = self.get_last_page_start(input_text, rows, cols);
+
+        let changed = first_line != self.first_line;
+        self.first_line = first_line;
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn text() -> InputText {
+        InputText::from("line 1\nline 2\nline 3\nline 4\nline 5\n")
+    }
+
+    #[test_case(1, true, "line 2\nline 3"; "when_scrolling_down")]
+    #[test_case(-1, false, "line 1\nline 2"; "when_scrolling_up_at_the_start")]
+    #[test_case(10, true, "line 4\nline 5"; "when_scrolling_past_the_end")]
+    fn scroll_results_in_expected_page(lines: isize, expected_changed: bool, expected_page: &str) {
+        let mut viewport = Viewport::new(true);
+
+        let changed = viewport.scroll(lines, &text(), 2, 10);
+
+        assert_eq!(changed, expected_changed);
+        assert_eq!(viewport.get_page(&text(), 2, 10), expected_page);
+    }
+
+    #[test]
+    fn scroll_to_end_shows_last_lines() {
+        let mut viewport = Viewport::new(true);
+        assert!(!viewport.is_at_end(&text(), 2, 10));
+
+        assert!(viewport.scroll_to_end(&text(), 2, 10));
+
+        assert!(viewport.is_at_end(&text(), 2, 10));
+        assert_eq!(viewport.get_page(&text(), 2, 10), "line 4\nline 5");
+
+        assert!(viewport.scroll_to_start());
+        assert_eq!(viewport.get_page(&text(), 2, 10), "line 1\nline 2");
+    }
+
+    #[test_case(2, "line 3\nline 4"; "when_line_fits_at_the_top")]
+    #[test_case(4, "line 4\nline 5"; "when_line_is_on_the_last_page")]
+    fn scroll_to_line_results_in_expected_page(line: usize, expected_page: &str) {
+        let mut viewport = Viewport::new(true);
+
+        viewport.scroll_to_line(line, &text(), 2, 10);
+
+        assert_eq!(viewport.get_page(&text(), 2, 10), expected_page);
+    }
+
+    #[test]
+    fn toggle_wrap_lines_truncates_long_lines() {
+        let text = InputText::from("a long line\nline 2\n");
+        let mut viewport = Viewport::new(true);
+        assert_eq!(viewport.get_page(&text, 2, 6), "a long line");
+
+        viewport.toggle_wrap_lines(&text, 2, 6);
+
+        assert_eq!(viewport.get_page(&text, 2, 6), "a long\nline 2");
+    }
+}
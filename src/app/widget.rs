@@ -0,0 +1,484 @@
+//! Embedding the selection in a pane of a [ratatui] application, with the
+//! `ratatui` feature.
+//!
+//! The application keeps a [Selection] for the text shown in the pane,
+//! draws it with [ratatui::Frame::render_widget] and forwards the input
+//! events to [Selection::handle_event] while the pane has the focus:
+//!
+//! ```ignore
+//! let mut selection = Selection::new(&text, &config)?;
+//! loop {
+//!     terminal.draw(|frame| frame.render_widget(&selection, pane))?;
+//!     let event = match selection.get_timeout() {
+//!         Some(timeout) if !event::poll(timeout)? => {
+//!             selection.handle_timeout()?;
+//!             continue;
+//!         }
+//!         _ => event::read()?,
+//!     };
+//!     match selection.handle_event(event)? {
+//!         Some(SelectionEvent::Selected(text)) => break Some(text),
+//!         Some(SelectionEvent::Cancelled) => break None,
+//!         None => (),
+//!     }
+//! }
+//! ```
+//!
+//! Only the selection itself is handled, the scrolling, the help and the
+//! other parts of the user interface are left to the application.
+use std::{collections::VecDeque, time::Duration};
+
+use crossterm::event::Event;
+use log::debug;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::Widget,
+};
+use textwrap::core::display_width;
+
+use super::run::{create_hint_generator, get_cycled_mode_index, ModeHistory};
+use crate::{
+    configuration::{Config, ModeArgs},
+    error::RunError,
+    hints::HintGenerator,
+    input_handler::{Action, InputHandler},
+    modes::{
+        create_registered_mode, get_color_regex, Mode, ModeEvent, ModeSelectorMode, RegexMode,
+    },
+    rendering::{get_style_map, DataOverlay, DrawInstruction, OverlayKind, StyledSegment},
+};
+
+/// How the selection in a [Selection] ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionEvent {
+    /// The given text was selected.
+    Selected(String),
+    /// The user left the selection without selecting anything.
+    Cancelled,
+}
+
+/// The selection from a text in a pane, drawn as a widget with
+/// `frame.render_widget(&selection, area)`.
+///
+/// The colors of the text itself are not shown, only the ones of the hits
+/// and the hints.
+pub struct Selection<'a> {
+    data: String,
+    config: &'a Config,
+    hint_generator: Box<dyn HintGenerator>,
+    input_handler: InputHandler,
+    /// The index of the active mode, or [None] for the mode selection.
+    mode_index: Option<usize>,
+    mode: Box<dyn Mode + 'a>,
+    mode_history: ModeHistory,
+}
+
+impl<'a> Selection<'a> {
+    /// Start selecting from the given text in the first mode of the config.
+    pub fn new(data: &str, config: &'a Config) -> Result<Self, RunError> {
+        let hint_generator = create_hint_generator(config);
+        let mode = create_mode(data, config, hint_generator.as_ref(), Some(0))?;
+        let mut input_handler = InputHandler::from_config(config);
+        input_handler.set_current_mode(Some(0));
+        let mut mode_history = ModeHistory::default();
+        mode_history.switched_to(0);
+
+        Ok(Self {
+            data: data.to_string(),
+            config,
+            hint_generator,
+            input_handler,
+            mode_index: Some(0),
+            mode,
+            mode_history,
+        })
+    }
+
+    /// Replace the text to select from, e.g. after the content of the pane
+    /// changed, and start the selection in the active mode again.
+    pub fn set_data(&mut self, data: &str) -> Result<(), RunError> {
+        self.data = data.to_string();
+        self.switch_mode(self.mode_index)
+    }
+
+    /// Get how long to wait for the next event before calling
+    /// [Selection::handle_timeout], e.g. while a key sequence is typed.
+    /// [None] means waiting indefinitely.
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.input_handler
+            .get_timeout()
+            .or_else(|| self.mode.get_key_press_timeout())
+    }
+
+    /// Handle no event arriving within [Selection::get_timeout].
+    pub fn handle_timeout(&mut self) -> Result<Option<SelectionEvent>, RunError> {
+        let action = self.input_handler.get_timeout_action();
+        self.handle_action(action)
+    }
+
+    /// Handle the given input event, returning how the selection ended if
+    /// it did.
+    pub fn handle_event(&mut self, event: Event) -> Result<Option<SelectionEvent>, RunError> {
+        let action = self.input_handler.get_action(event);
+        self.handle_action(action)
+    }
+
+    fn handle_action(
+        &mut self,
+        action: Option<Action>,
+    ) -> Result<Option<SelectionEvent>, RunError> {
+        let mode_event = match action {
+            Some(Action::Exit | Action::Abort) => return Ok(Some(SelectionEvent::Cancelled)),
+            Some(Action::ForwardKeyPress(key) | Action::RedirectKeyPress(key)) => {
+                self.mode.handle_key_press(key)
+            }
+            Some(Action::EraseKeyPress) => {
+                self.mode.erase_key_press();
+                None
+            }
+            Some(Action::ClearOrExit) => match self.mode.clear_key_presses() {
+                true => None,
+                false => return Ok(Some(SelectionEvent::Cancelled)),
+            },
+            Some(Action::Rematch) => {
+                self.switch_mode(self.mode_index)?;
+                None
+            }
+            Some(Action::GoToModeSelection) => {
+                self.switch_mode(None)?;
+                None
+            }
+            Some(Action::SwitchToNextMode) => Some(ModeEvent::ModeSwitchRequested(
+                get_cycled_mode_index(self.mode_index, self.config.modes.len(), true),
+            )),
+            Some(Action::SwitchToPreviousMode) => Some(ModeEvent::ModeSwitchRequested(
+                get_cycled_mode_index(self.mode_index, self.config.modes.len(), false),
+            )),
+            Some(Action::SwitchToReturnMode) => self
+                .mode_history
+                .get_return_mode(self.mode_index)
+                .map(ModeEvent::ModeSwitchRequested),
+            Some(Action::RunModeCommand(command)) => self.mode.handle_command(&command),
+            Some(Action::KeyPressTimeout) => self.mode.handle_key_press_timeout(),
+            Some(Action::MoveFocus(direction)) => {
+                self.mode.move_focus(direction);
+                None
+            }
+            Some(Action::AcceptFocused) => self.mode.accept_focused(),
+            Some(action) => {
+                debug!("Ignoring {action:?}, it is left to the application");
+                None
+            }
+            None => None,
+        };
+
+        match mode_event {
+            Some(ModeEvent::TextSelected(text, _)) => Ok(Some(SelectionEvent::Selected(text))),
+            Some(ModeEvent::ModeSwitchRequested(mode_index)) => {
+                self.switch_mode(Some(mode_index))?;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn switch_mode(&mut self, mode_index: Option<usize>) -> Result<(), RunError> {
+        self.mode = create_mode(
+            &self.data,
+            self.config,
+            self.hint_generator.as_ref(),
+            mode_index,
+        )?;
+        self.mode_index = mode_index;
+        self.input_handler.set_current_mode(mode_index);
+        if let Some(mode_index) = mode_index {
+            self.mode_history.switched_to(mode_index);
+        }
+
+        Ok(())
+    }
+}
+
+/// Create the mode with the given index in the config for selecting from
+/// the given data, or the mode selection for [None].
+fn create_mode<'a>(
+    data: &str,
+    config: &'a Config,
+    hint_generator: &dyn HintGenerator,
+    mode_index: Option<usize>,
+) -> Result<Box<dyn Mode + 'a>, RunError> {
+    match mode_index.and_then(|index| config.modes.get(index)) {
+        Some(mode) => match &mode.args {
+            ModeArgs::RegexMode(args) => Ok(Box::new(RegexMode::new(
+                data,
+                args,
+                hint_generator,
+                config,
+            )?)),
+            ModeArgs::Registered { name, args } => {
+                create_registered_mode(name, data, args, hint_generator, config)
+            }
+        },
+        None => Ok(Box::new(ModeSelectorMode::new(&config.modes))),
+    }
+}
+
+impl Widget for &Selection<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for instruction in self.mode.get_draw_instructions() {
+            match instruction {
+                DrawInstruction::StyledData {
+                    styled_segments,
+                    text_overlays,
+                } => draw_data(&self.data, &styled_segments, &text_overlays, area, buf),
+                DrawInstruction::Data => draw_data(&self.data, &[], &[], area, buf),
+                DrawInstruction::ModeSelectionDialog(modes)
+                | DrawInstruction::ActionMenuDialog(modes) => {
+                    draw_mode_selection_dialog(&modes, self.config, area, buf)
+                }
+                // Not drawn by the modes
+                DrawInstruction::HelpDialog(_)
+                | DrawInstruction::Prompt(_)
+                | DrawInstruction::Status(_)
+                | DrawInstruction::DebugOverlay(_) => (),
+            }
+        }
+    }
+}
+
+/// Draw the data with the given styled segments and overlays into the
+/// area, wrapping the lines longer than its width like the terminal does.
+fn draw_data(
+    data: &str,
+    styled_segments: &[StyledSegment],
+    text_overlays: &[DataOverlay],
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let data = data.strip_suffix('\n').unwrap_or(data);
+    let styles = get_style_map(styled_segments, data.len());
+    let color_sequences: Vec<_> = get_color_regex()
+        .find_iter(data)
+        .map(|sequence| sequence.range())
+        .collect();
+
+    let mut cursor = Cursor { area, x: 0, y: 0 };
+    let mut overlay_chars = VecDeque::new();
+    for (position, char) in data.char_indices() {
+        let style = match styles.get(position).copied().flatten() {
+            Some(style) => to_ratatui_style(style.foreground, style.background),
+            None => Style::default(),
+        };
+
+        match text_overlays
+            .iter()
+            .find(|overlay| overlay.location == position)
+        {
+            Some(DataOverlay {
+                text,
+                kind: OverlayKind::Replace,
+                ..
+            }) => overlay_chars.extend(text.chars()),
+            Some(DataOverlay {
+                text,
+                kind: OverlayKind::Insert(inserted_style),
+                ..
+            }) => {
+                let inserted_style =
+                    to_ratatui_style(inserted_style.foreground, inserted_style.background);
+                text.chars()
+                    .for_each(|char| cursor.print(char, inserted_style, buf));
+            }
+            None => (),
+        }
+
+        if color_sequences
+            .iter()
+            .any(|sequence| sequence.contains(&position))
+        {
+            continue;
+        }
+
+        match overlay_chars.pop_front().unwrap_or(char) {
+            '\n' => cursor.new_line(),
+            char => cursor.print(char, style, buf),
+        }
+    }
+}
+
+/// The position in an area where the next character of the data is drawn.
+struct Cursor {
+    area: Rect,
+    x: u16,
+    y: u16,
+}
+
+impl Cursor {
+    fn new_line(&mut self) {
+        self.x = 0;
+        self.y += 1;
+    }
+
+    fn print(&mut self, char: char, style: Style, buf: &mut Buffer) {
+        let text = char.to_string();
+        let width = display_width(&text) as u16;
+        if width == 0 {
+            return;
+        }
+        if self.x + width > self.area.width {
+            self.new_line();
+        }
+        if self.y >= self.area.height {
+            return;
+        }
+
+        buf.set_string(self.area.x + self.x, self.area.y + self.y, text, style);
+        self.x += width;
+    }
+}
+
+/// Draw the mode selection dialog on the right side of the area, the same
+/// way as [crate::rendering::Renderer] does on the whole screen.
+fn draw_mode_selection_dialog(
+    modes: &[(char, String)],
+    config: &Config,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let dialog_width = config.mode_switch_width as u16;
+    if area.width <= dialog_width {
+        return;
+    }
+
+    let x = area.right() - dialog_width;
+    let divider_style = to_ratatui_style(
+        config.mode_switch_divider_fg,
+        crossterm::style::Color::Reset,
+    );
+    let hotkey_style =
+        to_ratatui_style(config.mode_switch_hotkey_fg, crossterm::style::Color::Reset);
+    let name_style = to_ratatui_style(
+        config.mode_switch_mode_name_fg,
+        crossterm::style::Color::Reset,
+    );
+
+    let mut modes = modes.iter();
+    for y in area.top()..area.bottom() {
+        buf.set_stringn(
+            x,
+            y,
+            format!("{:width$}", "│", width = dialog_width as usize),
+            dialog_width as usize,
+            divider_style,
+        );
+
+        // Leave the first row empty as a top padding
+        if y == area.top() {
+            continue;
+        }
+        if let Some((hotkey, name)) = modes.next() {
+            let (x, _) = buf.set_stringn(
+                x + 1,
+                y,
+                format!(" [{hotkey}] "),
+                dialog_width as usize - 1,
+                hotkey_style,
+            );
+            buf.set_stringn(x, y, name, (area.right() - x) as usize, name_style);
+        }
+    }
+}
+
+/// Get the ratatui style with the given colors of crossterm.
+fn to_ratatui_style(
+    foreground: crossterm::style::Color,
+    background: crossterm::style::Color,
+) -> Style {
+    Style::new()
+        .fg(to_ratatui_color(foreground))
+        .bg(to_ratatui_color(background))
+}
+
+/// Get the ratatui color that is shown the same as the given one of crossterm.
+fn to_ratatui_color(color: crossterm::style::Color) -> Color {
+    use crossterm::style::Color as C;
+
+    match color {
+        C::Reset => Color::Reset,
+        C::Black => Color::Black,
+        C::DarkRed => Color::Red,
+        C::Red => Color::LightRed,
+        C::DarkGreen => Color::Green,
+        C::Green => Color::LightGreen,
+        C::DarkYellow => Color::Yellow,
+        C::Yellow => Color::LightYellow,
+        C::DarkBlue => Color::Blue,
+        C::Blue => Color::LightBlue,
+        C::DarkMagenta => Color::Magenta,
+        C::Magenta => Color::LightMagenta,
+        C::DarkCyan => Color::Cyan,
+        C::Cyan => Color::LightCyan,
+        C::Grey => Color::Gray,
+        C::DarkGrey => Color::DarkGray,
+        C::White => Color::White,
+        C::Rgb { r, g, b } => Color::Rgb(r, g, b),
+        C::AnsiValue(value) => Color::Indexed(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::*;
+
+    fn get_row(buf: &Buffer, y: u16) -> String {
+        (0..buf.area.width)
+            .map(|x| buf.get(x, y).symbol())
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn selection_is_drawn_into_area() {
+        // No hits, so that no hints are drawn over the text
+        let config: Config = serde_yaml::from_str(
+            "modes: [{mode: regex, hotkey: r, name: none, regexes: ['[^\\s\\S]']}]",
+        )
+        .unwrap();
+        let selection = Selection::new("plain text\nwraps here\n", &config).unwrap();
+        let area = Rect::new(0, 0, 7, 4);
+        let mut buf = Buffer::empty(area);
+
+        (&selection).render(area, &mut buf);
+
+        assert_eq!(get_row(&buf, 0), "plain t");
+        assert_eq!(get_row(&buf, 1), "ext");
+        assert_eq!(get_row(&buf, 2), "wraps h");
+        assert_eq!(get_row(&buf, 3), "ere");
+    }
+
+    #[test]
+    fn escape_cancels_selection() {
+        let config = Config::default();
+        let mut selection = Selection::new("plain text\n", &config).unwrap();
+
+        let event = selection
+            .handle_event(Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)))
+            .unwrap();
+
+        assert_eq!(event, Some(SelectionEvent::Cancelled));
+    }
+
+    #[test]
+    fn colors_keep_their_meaning() {
+        use crossterm::style::Color as C;
+
+        assert_eq!(to_ratatui_color(C::DarkRed), Color::Red);
+        assert_eq!(to_ratatui_color(C::Red), Color::LightRed);
+        assert_eq!(to_ratatui_color(C::AnsiValue(42)), Color::Indexed(42));
+    }
+}
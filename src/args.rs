@@ -1,5 +1,10 @@
 //! Definition of the available application arguments.
-use clap::Parser;
+use std::ffi::OsString;
+
+use clap::{ArgAction, Parser};
+use log::LevelFilter;
+
+use crate::shell_integration::Shell;
 
 const AFTER_HELP: &str = "
 CONFIGURATION
@@ -17,6 +22,10 @@ CONFIGURATION
     If a setting is not present in the config file, the default value is
     used.
 
+    If --merge-configs is provided or merge_configs is set in the config
+    file, all of the files above that exist are merged instead, with the
+    files higher in the list overriding the values from the lower ones.
+
 COLORS
 
     Whenever a color needs to be specified in the configuration, the
@@ -31,25 +40,508 @@ COLORS
      - An RGB color in format \"2;<r>;<g>;<b>\", e.g. \"2;255;255;0\" for
        yellow.
 
+     - A name defined in the palette setting of the config file.
+
      Note that the ability to render different colors depends on your
      terminal emulator and configuration.
+
+EXIT STATUS
+
+     0   The text was selected.
+     1   An error occurred.
+     2   The selection was aborted with the abort key (q by default).
+     3   There was nothing to select from, since no file was given,
+         nothing was piped in and default_input_command is not set.
+     4   The selection was cancelled with Ctrl-C or Esc, in which case the
+         output depends on cancel_output.
+     5   The selection was cancelled with Ctrl-C or Esc while none of the
+         modes found anything to select in the whole input, which is also
+         shown as \"nothing to select\" at the bottom of the screen. With
+         --list-matches, --select or --select-first, nothing was found to
+         list or select.
+     6   The text piped into mless ended without anything in it.
+     7   Files were given while text was also piped into mless, without
+         `-` standing for the piped text among them.
+     128+N
+         The process was terminated by signal N (e.g. 130 for SIGINT), even
+         if the interface stopped responding.
 ";
 
+/// The version shown by --version, with the information captured by the
+/// build script to tell apart the builds the bug reports come from.
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit: ",
+    env!("MLESS_GIT_COMMIT"),
+    "\nbuild date: ",
+    env!("MLESS_BUILD_DATE"),
+    "\nfeatures: ",
+    env!("MLESS_FEATURES"),
+    "\ntarget: ",
+    env!("MLESS_TARGET"),
+);
+
 #[derive(Debug, Parser)]
-#[command(author, version, about, after_help=AFTER_HELP)]
+#[command(author, version, long_version = LONG_VERSION, about, after_help=AFTER_HELP)]
 pub struct Args {
-    /// File to select the text from. Omit to use standard input.
-    pub file: Option<std::path::PathBuf>,
+    /// Files to select the text from, switched between with next_file_key
+    /// and previous_file_key from the config. Omit to use standard input or,
+    /// if nothing is piped in, the output of default_input_command from the
+    /// config.
+    pub files: Vec<std::path::PathBuf>,
+
+    /// Run the shell COMMAND and select from its output instead of reading
+    /// the files or the standard input, e.g. `mless --exec 'git log -50'`.
+    #[arg(short, long, value_name = "COMMAND", conflicts_with_all = ["files", "follow"])]
+    pub exec: Option<String>,
+
+    /// Run the --exec command with its output going to a pseudo-terminal,
+    /// so that it uses colors like when it is run in the terminal.
+    #[arg(long, action, requires = "exec")]
+    pub pty: bool,
+
+    /// Select from the contents of the tmux pane with the given ID, e.g.
+    /// `%3`, or of the active pane if no ID is given, including the colors.
+    #[arg(
+        long,
+        value_name = "ID",
+        num_args = 0..=1,
+        default_missing_value = "",
+        conflicts_with_all = ["files", "follow", "exec"]
+    )]
+    pub tmux_pane: Option<String>,
 
     /// Config file to read.
     #[arg(short, long, value_name = "CONFIG_FILE")]
     pub config: Option<std::path::PathBuf>,
 
+    /// Merge all the found config files instead of using only the first one.
+    #[arg(long, action)]
+    pub merge_configs: bool,
+
+    /// Keep reading the standard input while selecting, e.g. with
+    /// `tail -f app.log | mless --follow`, and stay at the end of it.
+    #[arg(short, long, action, conflicts_with = "files")]
+    pub follow: bool,
+
+    /// Read the shown file again whenever it changes, e.g. when another
+    /// program rewrites it, and match the hits in it again.
+    #[arg(short, long, action, requires = "files", conflicts_with = "concat")]
+    pub watch: bool,
+
+    /// Show all the files one after another instead of switching between
+    /// them, each after a header line set with input_header in the config.
+    /// A file given as - stands for the standard input, e.g. with
+    /// `cmd | mless --concat - other.txt`.
+    #[arg(long, action, requires = "files")]
+    pub concat: bool,
+
+    /// Use only the first N lines of the input and stop reading it after
+    /// them, like `head -n N`.
+    #[arg(long, value_name = "N")]
+    pub max_lines: Option<usize>,
+
+    /// Use only the last N lines of the input, like `tail -n N`. The whole
+    /// input is read before anything is shown.
+    #[arg(long, value_name = "N", conflicts_with_all = ["follow", "concat"])]
+    pub tail: Option<usize>,
+
+    /// Keep only the last N lines of the input in memory while it is read,
+    /// dropping the older ones as new ones arrive, e.g. to follow a log
+    /// that never ends with `--follow --keep-lines 10000`. Unlike with
+    /// --tail, the input is shown while it is read.
+    #[arg(long, value_name = "N", conflicts_with_all = ["tail", "concat"])]
+    pub keep_lines: Option<usize>,
+
+    /// Output the position of the selection in the input before it, as
+    /// LINE:COLUMN:OFFSET:, with the line and the column counted from 1 and
+    /// the column and the byte offset counted in bytes, e.g. to open the
+    /// input in an editor at that position.
+    #[arg(long, action)]
+    pub with_position: bool,
+
+    /// Output the name of the mode and the index of its regex, counted
+    /// from 0, that produced the selection before it, as MODE:REGEX:. The
+    /// index is left empty if the selection was not found by a single
+    /// regex, e.g. with select_all.
+    #[arg(long, action)]
+    pub with_origin: bool,
+
+    /// Output the file the selection was made in before it, as FILE:, if
+    /// the input is read from files, e.g. to tell where each selection
+    /// made with --loop comes from.
+    #[arg(long, action)]
+    pub with_file: bool,
+
+    /// Output the time of the selection before it, in UTC as
+    /// YYYY-MM-DDTHH:MM:SSZ followed by a space, e.g. to tell when each
+    /// selection made with --loop was made.
+    #[arg(long, action)]
+    pub with_time: bool,
+
+    /// Output the whole line the selection starts on after it, separated
+    /// by a tab, for the scripts that need the context of the selection.
+    #[arg(long, action)]
+    pub with_line: bool,
+
+    /// Quote the selection for the shell by wrapping it in single quotes,
+    /// so that it is a single word even if it contains spaces or special
+    /// characters, e.g. when it is used in a command built with eval.
+    #[arg(long, action)]
+    pub quote: bool,
+
+    /// Write the selection to FILE instead of the standard output, replacing
+    /// its contents unless --append is given.
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<std::path::PathBuf>,
+
+    /// Write the selection to the file descriptor N, e.g. 3, instead of
+    /// the standard output, which gets the input passed through unchanged
+    /// once the selection is done, so that mless can be used as a filter,
+    /// e.g. `make | mless --output-fd 3 3>selection | tee build.log`.
+    #[arg(long, value_name = "N", conflicts_with = "output")]
+    pub output_fd: Option<u32>,
+
+    /// Append the selection to the --output file instead of replacing it.
+    #[arg(long, action, requires = "output")]
+    pub append: bool,
+
+    /// Do the action NAME with the selection instead of the action of the
+    /// mode or selection_action from the config, e.g. to copy it only when
+    /// started from a keybinding. NAME is one of output (or print), copy,
+    /// copy_primary, tmux_buffer, type, open, editor or one of actions
+    /// from the config.
+    #[arg(long, value_name = "NAME")]
+    pub action: Option<String>,
+
+    /// Pipe the selection through the shell COMMAND and use its output
+    /// instead, e.g. `--selection-filter "xargs basename"`. Overrides
+    /// selection_filter from the config.
+    #[arg(long, value_name = "COMMAND")]
+    pub selection_filter: Option<String>,
+
+    /// Remove the whitespace from the start and the end of the selection.
+    #[arg(long, action)]
+    pub trim: bool,
+
+    /// Append a new line to the selection.
+    #[arg(long, action)]
+    pub newline: bool,
+
+    /// End each selection with a NUL byte instead, also when several of
+    /// them are output together, e.g. for `xargs -0`.
+    #[arg(short = '0', long, action, conflicts_with_all = ["newline", "delimiter"])]
+    pub null: bool,
+
+    /// Put DELIMITER between the selections when several of them are output
+    /// together, either space, tab, newline or the text itself. Overrides
+    /// selection_delimiter from the config.
+    #[arg(short, long, value_name = "DELIMITER")]
+    pub delimiter: Option<String>,
+
+    /// Foreground color of the hints, in any format the config accepts,
+    /// e.g. red, 5;208 or a name from the palette. Overrides hint_fg from
+    /// the config.
+    #[arg(long, value_name = "COLOR")]
+    pub hint_fg: Option<String>,
+
+    /// Background color of the hints, like --hint-fg. Overrides hint_bg
+    /// from the config.
+    #[arg(long, value_name = "COLOR")]
+    pub hint_bg: Option<String>,
+
+    /// Foreground color of the highlighted hits, like --hint-fg. Overrides
+    /// highlight_fg from the config.
+    #[arg(long, value_name = "COLOR")]
+    pub highlight_fg: Option<String>,
+
+    /// Background color of the highlighted hits, like --hint-fg. Overrides
+    /// highlight_bg from the config.
+    #[arg(long, value_name = "COLOR")]
+    pub highlight_bg: Option<String>,
+
+    /// Print all the hits found in the input by the start mode, or by all
+    /// the modes if -m is not given, without showing anything, one per
+    /// line as LINE:COLUMN:OFFSET:TEXT.
+    #[arg(long, action, conflicts_with_all = ["follow", "repeat_last"])]
+    pub list_matches: bool,
+
+    /// With --list-matches, --select or --select-first, write the number of
+    /// the hits found in the input to the standard error, e.g. "3 matches", so
+    /// that the scripts can tell how many there were to choose from.
+    #[arg(long, action)]
+    pub count: bool,
+
+    /// Select the Nth hit found in the input by the start mode, or by all
+    /// the modes if -m is not given, counting from 1, without showing
+    /// anything.
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["follow", "repeat_last", "list_matches"]
+    )]
+    pub select: Option<usize>,
+
+    /// Select the first hit without showing anything, same as --select 1.
+    #[arg(long, action, conflicts_with_all = ["follow", "repeat_last", "list_matches", "select"])]
+    pub select_first: bool,
+
+    /// Instead of drawing the hints over the text, show the hits found by
+    /// the start mode, or by all the modes if -m is not given, as a plain
+    /// numbered list, one per line, and select one by typing its number and
+    /// pressing Enter. Works better with screen readers and braille
+    /// displays. The list is scrolled with the arrow keys, Page Up, Page
+    /// Down, Home and End.
+    #[arg(
+        long,
+        action,
+        conflicts_with_all = ["follow", "repeat_last", "list_matches", "select", "select_first"]
+    )]
+    pub list_ui: bool,
+
+    /// Exit right away with exit code 5 if the start mode has nothing to
+    /// select in the whole input, instead of showing it. The whole input is
+    /// read before anything is shown, so it cannot be used with the input
+    /// that may never end, followed with --follow or --keep-lines.
+    #[arg(
+        long,
+        action,
+        conflicts_with_all = ["follow", "keep_lines", "repeat_last", "list_matches"]
+    )]
+    pub exit_on_no_match: bool,
+
+    /// Select from the history of the selections instead of the input, with
+    /// a hint for each of them. Requires remember_history to be enabled in
+    /// the config.
+    #[arg(long, action, conflicts_with_all = ["files", "follow", "exec", "tmux_pane", "start_in_mode"])]
+    pub history: bool,
+
+    /// Output each selection, or do what selection_action says with it, as
+    /// soon as it is made and keep selecting until the selection is
+    /// cancelled or aborted, e.g. to pick several files one after another.
+    #[arg(
+        long = "loop",
+        action,
+        conflicts_with_all = ["list_matches", "select", "select_first", "repeat_last"]
+    )]
+    pub keep_selecting: bool,
+
+    /// Listen on the unix socket at SOCKET instead of reading any input.
+    /// Each client that connects sends a text and shuts down its side of
+    /// the connection, e.g. with `socat - UNIX-CONNECT:SOCKET < file`, and
+    /// gets the text selected from it on this terminal as the reply, or
+    /// nothing if the selection is cancelled.
+    #[arg(
+        long,
+        value_name = "SOCKET",
+        conflicts_with_all = [
+            "files", "follow", "exec", "tmux_pane", "history", "list_matches",
+            "select", "select_first", "keep_selecting"
+        ]
+    )]
+    pub server: Option<std::path::PathBuf>,
+
+    /// Output the last selection again without showing anything. Requires
+    /// remember_last_selection to be enabled in the config.
+    #[arg(long, action)]
+    pub repeat_last: bool,
+
+    /// Record all the received input events with their timing to FILE.
+    /// Can also be enabled by setting MLESS_RECORD environment variable to
+    /// the path of the file.
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<std::path::PathBuf>,
+
+    /// Replay the input events recorded with --record from FILE instead
+    /// of reading them from the terminal.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<std::path::PathBuf>,
+
+    /// Cancel the selection once SECONDS pass since it started, whether
+    /// or not anything is pressed, e.g. when started by a script that must
+    /// not wait forever.
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout_session: Option<u64>,
+
+    /// Log into FILE. Takes priority over MLESS_LOG environment variable
+    /// and the path in the logging section of the config.
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<std::path::PathBuf>,
+
+    /// Log at debug level with -v and at trace level with -vv, for all
+    /// modules instead of the levels from the config. Has no effect unless
+    /// logging into a file is turned on. RUST_LOG environment variable
+    /// still takes priority.
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log only the warnings and the errors, like --verbose for the lower
+    /// levels.
+    #[arg(short, long, action)]
+    pub quiet: bool,
+
+    /// Start as a plain pager that shows the input without any hints until
+    /// select_key from the config is pressed. Same as setting start_as_pager
+    /// in the config.
+    #[arg(short, long, action)]
+    pub pager: bool,
+
+    /// Start with the last page of the input shown instead of the first one.
+    /// Can also be given as +G, like in less.
+    #[arg(long, action)]
+    pub end: bool,
+
+    /// Start with the input line with number LINE at the top of the screen.
+    /// Can also be given as +LINE, like in less.
+    #[arg(long, value_name = "LINE", conflicts_with = "end")]
+    pub line: Option<usize>,
+
+    /// After exiting, print to stderr how long reading the input, computing
+    /// the pages, matching, generating the hints and rendering took, and
+    /// the peak memory usage, e.g. to find out what makes an input slow.
+    #[arg(long, action)]
+    pub stats: bool,
+
     /// Show the default config with documentation file and exit.
     #[arg(long, action)]
     pub show_default_config: bool,
 
-    /// Start in selection mode with hotkey MODE instead of the first one specified in config
+    /// Measure how long computing a page, matching it with every mode,
+    /// generating the hints and rendering take on a generated input with
+    /// the given number of lines, print the average times and exit.
+    #[arg(long, value_name = "LINES", hide = true)]
+    pub bench: Option<usize>,
+
+    /// Print the shell code that binds Alt-m to selecting text from the
+    /// tmux pane and inserting it at the cursor, and exit, e.g. with
+    /// `eval "$(mless --init zsh)"` in ~/.zshrc.
+    #[arg(long, value_name = "SHELL", value_enum)]
+    pub init: Option<Shell>,
+
+    /// Select the matches of PATTERN, a regular expression, in a mode that
+    /// is added before the ones from the config and started in. Can be given
+    /// more than once to match several patterns.
+    #[arg(
+        short = 'r',
+        long,
+        value_name = "PATTERN",
+        conflicts_with_all = ["history", "start_in_mode"]
+    )]
+    pub regex: Vec<String>,
+
+    /// Start in the selection mode with hotkey or name MODE instead of the first one specified
+    /// in config
     #[arg(short = 'm', long = "start-in-mode", value_name = "MODE")]
-    pub start_in_mode: Option<char>,
+    pub start_in_mode: Option<String>,
+}
+
+impl Args {
+    /// Get the log level set with --verbose or --quiet, if any.
+    pub fn get_log_level(&self) -> Option<LevelFilter> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some(LevelFilter::Warn),
+            (false, 0) => None,
+            (false, 1) => Some(LevelFilter::Debug),
+            (false, _) => Some(LevelFilter::Trace),
+        }
+    }
+}
+
+/// Translate the less-style `+` arguments (`+G` and `+LINE`) to the options
+/// understood by [Args], leaving everything after `--` untouched.
+pub fn expand_plus_arguments(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut expanded = vec![];
+    let mut options_ended = false;
+
+    for arg in args {
+        if options_ended {
+            expanded.push(arg);
+            continue;
+        }
+
+        match arg.to_str() {
+            Some("--") => {
+                options_ended = true;
+                expanded.push(arg);
+            }
+            Some("+G") => expanded.push("--end".into()),
+            Some(plus_arg) => match plus_arg.strip_prefix('+') {
+                Some(line)
+                    if !line.is_empty() && line.chars().all(|char| char.is_ascii_digit()) =>
+                {
+                    expanded.push("--line".into());
+                    expanded.push(line.into());
+                }
+                _ => expanded.push(arg),
+            },
+            None => expanded.push(arg),
+        }
+    }
+
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use test_case::test_case;
+
+    #[test_case(&["mless", "+G", "file"], &["mless", "--end", "file"]; "when_end_is_given")]
+    #[test_case(&["mless", "+42", "file"], &["mless", "--line", "42", "file"]; "when_line_is_given")]
+    #[test_case(&["mless", "+4x"], &["mless", "+4x"]; "when_line_is_not_a_number")]
+    #[test_case(&["mless", "--", "+G"], &["mless", "--", "+G"]; "when_given_after_double_dash")]
+    #[test_case(&["mless", "file"], &["mless", "file"]; "when_nothing_to_expand")]
+    fn expand_plus_arguments_returns_expected_args(args: &[&str], expected: &[&str]) {
+        let args = args.iter().map(OsString::from);
+        let expected: Vec<OsString> = expected.iter().map(OsString::from).collect();
+
+        assert_eq!(expand_plus_arguments(args), expected);
+    }
+
+    #[test_case(&["mless"], None; "when_no_flag_is_given")]
+    #[test_case(&["mless", "-q"], Some(LevelFilter::Warn); "when_quiet")]
+    #[test_case(&["mless", "-v"], Some(LevelFilter::Debug); "when_verbose")]
+    #[test_case(&["mless", "-vv"], Some(LevelFilter::Trace); "when_very_verbose")]
+    #[test_case(&["mless", "-vvv"], Some(LevelFilter::Trace); "when_more_verbose_than_trace")]
+    fn get_log_level_returns_expected_level(args: &[&str], expected: Option<LevelFilter>) {
+        let args = Args::parse_from(args);
+
+        assert_eq!(args.get_log_level(), expected);
+    }
+
+    #[test]
+    fn long_version_contains_build_information() {
+        let version = Args::command().render_long_version();
+
+        assert!(version.starts_with(&format!("mless {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(version.contains(&format!("target: {}", env!("MLESS_TARGET"))));
+    }
+
+    #[test]
+    fn regex_can_be_given_more_than_once() {
+        let args = Args::parse_from(["mless", "-r", "[0-9]+", "--regex", "v[a-z]+"]);
+
+        assert_eq!(args.regex, ["[0-9]+", "v[a-z]+"]);
+    }
+
+    #[test_case(&["--follow"]; "with_follow")]
+    #[test_case(&["--keep-lines", "100"]; "with_keep_lines")]
+    fn exit_on_no_match_conflicts_with_followed_input(follow_args: &[&str]) {
+        let args = ["mless", "--exit-on-no-match"].iter().chain(follow_args);
+
+        assert!(Args::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn regex_conflicts_with_start_in_mode() {
+        let args = ["mless", "--regex", "[0-9]+", "--start-in-mode", "u"];
+
+        assert!(Args::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn verbose_and_quiet_conflict() {
+        assert!(Args::try_parse_from(["mless", "-v", "-q"]).is_err());
+    }
 }
@@ -1,26 +1,106 @@
+use std::{cell::RefCell, collections::HashMap};
+
 use crossterm::style::Color;
 use serde::{
     de::{self, Unexpected},
     Deserialize, Deserializer,
 };
 
+use super::suggestion::did_you_mean;
+
+/// The names of the colors that can be used in place of color specifications.
+const COLOR_NAMES: &[&str] = &[
+    "black",
+    "dark_grey",
+    "red",
+    "dark_red",
+    "green",
+    "dark_green",
+    "yellow",
+    "dark_yellow",
+    "blue",
+    "dark_blue",
+    "magenta",
+    "dark_magenta",
+    "cyan",
+    "dark_cyan",
+    "white",
+    "grey",
+];
+
+thread_local! {
+    /// Named colors that can be used in place of color specifications
+    /// during deserialization. See [with_palette].
+    static PALETTE: RefCell<HashMap<String, Color>> = RefCell::new(HashMap::new());
+}
+
 pub fn deserialize_color<'de, D>(d: D) -> Result<Color, D::Error>
 where
     D: Deserializer<'de>,
 {
     let color_string = String::deserialize(d)?;
 
-    match string_to_color(&color_string) {
+    let palette_color = PALETTE.with(|palette| palette.borrow().get(&color_string).copied());
+
+    match palette_color.or_else(|| string_to_color(&color_string)) {
         Some(color) => Ok(color),
-        None => Err(de::Error::invalid_value(
-            Unexpected::Str(&color_string),
-            &"be an ANSI color like 5;252, RGB color like 2;50;60;70 or one of black, \
-                  dark_grey, red, dark_red, green, dark_green, yellow, dark_yellow, blue, \
-                  dark_blue, magenta, dark_magenta, cyan, dark_cyan, white, grey",
-        )),
+        None => {
+            let palette_names: Vec<String> =
+                PALETTE.with(|palette| palette.borrow().keys().cloned().collect());
+            let names = palette_names
+                .iter()
+                .map(String::as_str)
+                .chain(COLOR_NAMES.iter().copied());
+            let expected = format!(
+                "be a name from the palette, an ANSI color like 5;252, RGB color like \
+                 2;50;60;70 or one of {}{}",
+                COLOR_NAMES.join(", "),
+                did_you_mean(&color_string, names)
+            );
+
+            Err(de::Error::invalid_value(
+                Unexpected::Str(&color_string),
+                &expected.as_str(),
+            ))
+        }
     }
 }
 
+/// Convert the palette with color specifications into a palette of colors.
+///
+/// Returns the name of the first entry whose color cannot be parsed
+/// as an error.
+pub fn parse_palette(palette: &HashMap<String, String>) -> Result<HashMap<String, Color>, String> {
+    palette
+        .iter()
+        .map(|(name, color)| match string_to_color(color) {
+            Some(color) => Ok((name.clone(), color)),
+            None => Err(name.clone()),
+        })
+        .collect()
+}
+
+/// Convert the given color specification into a color, accepting the same
+/// specifications as [deserialize_color] does with the given palette of
+/// color specifications.
+pub fn parse_color(string: &str, palette: &HashMap<String, String>) -> Option<Color> {
+    let string = palette.get(string).map_or(string, String::as_str);
+
+    string_to_color(string)
+}
+
+/// Run the given function with the palette available to [deserialize_color].
+///
+/// While the function is running, [deserialize_color] accepts the names
+/// from the palette in addition to the usual color specifications.
+pub fn with_palette<T>(palette: HashMap<String, Color>, function: impl FnOnce() -> T) -> T {
+    PALETTE.with(|current| *current.borrow_mut() = palette);
+    let result = function();
+    PALETTE.with(|current| current.borrow_mut().clear());
+
+    result
+}
+
 /// Attempt converting the given string containing a color name or ANSI code into a color.
 fn string_to_color(string: &str) -> Option<Color> {
     // First attempt parsing it as a named color, e.g. dark_red
@@ -37,6 +117,23 @@ mod tests {
     use super::*;
     use test_case::test_case;
 
+    #[test]
+    fn deserialize_color_suggests_closest_name() {
+        let error = deserialize_color(serde_yaml::Value::from("magneta")).unwrap_err();
+
+        assert!(error.to_string().contains("did you mean magenta?"));
+    }
+
+    #[test_case("red", Some(Color::Red); "for color name")]
+    #[test_case("5;208", Some(Color::AnsiValue(208)); "for ansi color")]
+    #[test_case("accent", Some(Color::AnsiValue(100)); "for palette name")]
+    #[test_case("nope", None; "for invalid color")]
+    fn parse_color_returns_expected_color(string: &str, expected: Option<Color>) {
+        let palette = HashMap::from([("accent".to_string(), "5;100".to_string())]);
+
+        assert_eq!(parse_color(string, &palette), expected);
+    }
+
     #[test_case("", None; "for empty string")]
     #[test_case("black", Some(Color::Black); "for input 'black'")]
     #[test_case("dark_grey", Some(Color::DarkGrey); "for input 'dark_grey'")]
@@ -59,4 +156,37 @@ mod tests {
     fn string_to_color_returns_expected_value(string: &str, expected: Option<Color>) {
         assert_eq!(string_to_color(string), expected);
     }
+
+    #[derive(Deserialize)]
+    struct ColorHolder {
+        #[serde(deserialize_with = "deserialize_color")]
+        color: Color,
+    }
+
+    #[test]
+    fn deserialize_color_resolves_names_from_palette() {
+        let palette = HashMap::from([("accent".to_string(), Color::AnsiValue(208))]);
+
+        let holder: ColorHolder =
+            with_palette(palette, || serde_yaml::from_str("color: accent")).unwrap();
+
+        assert_eq!(holder.color, Color::AnsiValue(208));
+    }
+
+    #[test]
+    fn deserialize_color_does_not_resolve_names_outside_of_palette_scope() {
+        let palette = HashMap::from([("accent".to_string(), Color::AnsiValue(208))]);
+        with_palette(palette, || ());
+
+        let result = serde_yaml::from_str::<ColorHolder>("color: accent");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_palette_returns_name_of_invalid_entry() {
+        let palette = HashMap::from([("accent".to_string(), "not_a_color".to_string())]);
+
+        assert_eq!(parse_palette(&palette), Err("accent".to_string()));
+    }
 }
@@ -1,18 +1,214 @@
-use std::{collections::HashSet, fs::File};
+use std::collections::{HashMap, HashSet};
 
-use super::{deserialize_color, modes, DEFAULT_CONFIG_FILE};
+use super::{
+    color::{parse_palette, with_palette},
+    deserialize_color,
+    input_filter::InputFilter,
+    modes,
+    open_handler::OpenHandler,
+    suggestion::{did_you_mean, did_you_mean_in_serde_error},
+    version::{check_version, CURRENT_CONFIG_VERSION},
+    LoggingConfig, DEFAULT_CONFIG_FILE,
+};
 use crossterm::style::Color;
+use log::warn;
 use regex::Regex;
 use serde::{
     de::{self, Unexpected},
     Deserialize, Deserializer,
 };
+use serde_yaml::{Mapping, Value};
 use snafu::{ResultExt, Snafu};
 
 #[derive(Debug, Snafu)]
+#[snafu(visibility(pub(super)))]
 pub enum Error {
-    #[snafu(display("{}", source))]
+    #[snafu(display("{}{}", source, did_you_mean_in_serde_error(&source.to_string())))]
     ParseError { source: serde_yaml::Error },
+
+    #[snafu(display("Unknown setting {name}{suggestion}"))]
+    UnknownSetting { name: String, suggestion: String },
+
+    #[snafu(display(
+        "Config version {version} is newer than the supported version {CURRENT_CONFIG_VERSION}"
+    ))]
+    UnsupportedVersion { version: u64 },
+
+    #[snafu(display(
+        "Config version {version} contains settings that have changed since:\n{}\n\
+         Set version to {CURRENT_CONFIG_VERSION} after migrating them",
+        notes.join("\n")
+    ))]
+    OutdatedSettings { version: u64, notes: Vec<String> },
+
+    #[snafu(display("Palette entry '{name}' does not contain a valid color"))]
+    InvalidPaletteColor { name: String },
+
+    #[snafu(display(
+        "hint_characters contain keys that are bound to actions: {}\n\
+         Remove them from hint_characters or bind the actions to other keys",
+        keys.join(", ")
+    ))]
+    HintCharactersBoundToActions { keys: Vec<String> },
+}
+
+impl Error {
+    /// Get the location in the config text that caused the error, if known.
+    ///
+    /// Only errors of configs parsed from text, see [Config::try_from],
+    /// carry a location.
+    pub fn location(&self) -> Option<serde_yaml::Location> {
+        match self {
+            Error::ParseError { source } => source.location(),
+            _ => None,
+        }
+    }
+}
+
+/// Policy for choosing the hits that get hints when not all of them can.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HintLimitPolicy {
+    /// Prefer the hits closest to the start of the input.
+    #[default]
+    First,
+    /// Prefer the hits with the most characters.
+    Longest,
+    /// Prefer the hits closest to the end of the input.
+    Bottom,
+}
+
+/// Position of the hints relative to the hits they select.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HintPosition {
+    /// The hint is drawn over the first characters of the hit.
+    #[default]
+    Overlay,
+    /// The hint is inserted before the hit, shifting the rest of the text.
+    Insert,
+}
+
+/// The kind of hints given to the hits.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HintGeneratorKind {
+    /// Hints made of [Config::hint_characters].
+    #[default]
+    Pool,
+    /// Numbers of the same length, e.g. `1`-`9` or `01`-`42`.
+    Numeric,
+    /// Pronounceable hints made of syllables, e.g. `ka` or `tobe`.
+    Syllables,
+}
+
+/// The order in which the hints are given to the hits.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HintOrder {
+    /// The hints with the characters earlier in [Config::hint_characters]
+    /// come first.
+    #[default]
+    Pool,
+    /// Like [HintOrder::Pool], but the longer hints typed with both
+    /// hands, according to [Config::left_hand_characters], come before the
+    /// ones typed with a single hand.
+    AlternatingHands,
+}
+
+/// Which hits get the shortest hints.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HintPriority {
+    /// The hits found first, i.e. with the first regex of the mode and
+    /// nearer the top of the screen.
+    #[default]
+    Found,
+    /// The hits nearest the bottom of the screen, which were usually
+    /// printed most recently.
+    Bottom,
+    /// The hits nearest the line of the screen with the given number,
+    /// counting from 1 at the top.
+    Line(usize),
+}
+
+/// What to output when the selection is cancelled.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelOutput {
+    /// Output nothing.
+    #[default]
+    Nothing,
+    /// Output [Config::cancel_fallback].
+    Fallback,
+    /// Output the whole input.
+    Input,
+}
+
+/// What is done with the selected text.
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionAction {
+    /// Write it to the standard output.
+    #[default]
+    #[serde(alias = "print")]
+    Output,
+    /// Copy it to the clipboard with [Config::clipboard_command] or the
+    /// clipboard command of the platform, if any is installed. Otherwise,
+    /// copy it to the clipboard of the terminal with the OSC 52 escape
+    /// sequence, which also works over SSH.
+    Copy,
+    /// Copy it to the primary selection, pasted with the middle mouse
+    /// button, the same way as with [SelectionAction::Copy] but with
+    /// [Config::primary_command].
+    CopyPrimary,
+    /// Load it into a tmux paste buffer, so that it can be pasted anywhere
+    /// in tmux with `prefix ]`.
+    #[serde(alias = "tmux-buffer")]
+    TmuxBuffer,
+    /// Type it into the tmux pane, kitty window or WezTerm pane mless is
+    /// started in or, elsewhere, into the terminal if the system allows it,
+    /// so that it lands on the command line being edited there.
+    Type,
+    /// Run the command of the first of [Config::open_handlers] that
+    /// matches it in the terminal after the selection.
+    Open,
+    /// Open it, as `file` or `file:line`, in the running Neovim or Vim
+    /// instance found with `$NVIM` or `$VIM_SERVERNAME`.
+    Editor,
+    /// Pipe it to the standard input of the given shell command, e.g. to
+    /// add it to the history of a clipboard manager with `cliphist store`
+    /// or `copyq add -` instead of overwriting the clipboard.
+    Pipe(String),
+    /// Run the given shell command in the terminal after the selection,
+    /// with `{}` replaced by the selected text quoted for the shell, e.g.
+    /// `xdg-open {}` or `$EDITOR {}`.
+    Run(String),
+}
+
+/// An action that can be chosen in the menu shown after the selection.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct ActionMenuEntry {
+    /// The key that chooses the action.
+    pub hotkey: char,
+    /// The name of the action shown in the menu.
+    pub name: String,
+    /// What is done with the selected text.
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub action: SelectionAction,
+}
+
+/// What scrolling the mouse wheel does.
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseWheelScroll {
+    /// Nothing, the mouse is not captured at all.
+    #[default]
+    Off,
+    /// Scroll the input by one line.
+    Line,
+    /// Scroll the input by one page.
+    Page,
 }
 
 /// The main configuration struct representing the whole configuration
@@ -23,12 +219,78 @@ pub enum Error {
 /// some of the fields.
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct Config {
+    /// Version of the config file format.
+    ///
+    /// Used only to provide helpful messages when the format changes,
+    /// see [super::version::check_version].
+    #[serde(default = "Config::default_version")]
+    pub version: u64,
+
+    /// The kind of hints given to the hits.
+    ///
+    /// The other hint settings, like [Config::hint_characters], apply only
+    /// to [HintGeneratorKind::Pool].
+    #[serde(default)]
+    pub hint_generator: HintGeneratorKind,
+
     /// Characters that can be used by structs implementing [modes::Mode]
     /// trait.
     #[serde(default = "Config::default_hint_characters")]
     #[serde(deserialize_with = "Config::validate_hint_characters")]
     pub hint_characters: String,
 
+    /// Length that all the hints should have, regardless of the number of hits.
+    ///
+    /// If not specified, the hint length is chosen so that the hits can be
+    /// selected with the fewest key presses.
+    #[serde(default)]
+    #[serde(deserialize_with = "Config::validate_fixed_hint_length")]
+    pub fixed_hint_length: Option<usize>,
+
+    /// The order in which the hints are given to the hits, which get them
+    /// in the order in which they are found.
+    #[serde(default)]
+    pub hint_order: HintOrder,
+
+    /// The characters typed with the left hand, used to tell which hints
+    /// are typed with both hands with [HintOrder::AlternatingHands].
+    #[serde(default = "Config::default_left_hand_characters")]
+    pub left_hand_characters: String,
+
+    /// Which hits get the shortest hints, see [HintPriority].
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub hint_priority: HintPriority,
+
+    /// Whether all the hits with the same text get the same hint. If not,
+    /// every hit gets its own hint, so that it is possible to choose which
+    /// of them is selected, e.g. to output its position.
+    #[serde(default = "Config::default_deduplicate_hits")]
+    pub deduplicate_hits: bool,
+
+    /// The maximum number of hints to show at once. If there are more
+    /// hits than this, [Config::max_hints_policy] decides which of them
+    /// get a hint.
+    #[serde(default)]
+    pub max_hints: Option<usize>,
+
+    /// Decides which hits get hints when there are more than [Config::max_hints].
+    #[serde(default)]
+    pub max_hints_policy: HintLimitPolicy,
+
+    /// Whether the hints are drawn over the hits or inserted before them.
+    #[serde(default)]
+    pub hint_position: HintPosition,
+
+    /// Whether the full text and the line of the focused hit, or of the
+    /// few hits left after typing the start of a hint, are shown on the
+    /// last row of the screen.
+    #[serde(default)]
+    pub show_hit_preview: bool,
+
+    /// Named colors that can be used in all other color settings.
+    #[serde(default)]
+    pub palette: HashMap<String, String>,
+
     /// Foreground color for hints during selection.
     #[serde(deserialize_with = "deserialize_color")]
     #[serde(default = "Config::default_hint_fg")]
@@ -49,6 +311,150 @@ pub struct Config {
     #[serde(default = "Config::default_highlight_bg")]
     pub highlight_bg: Color,
 
+    /// Key that opens the mode switching dialog. If [None], the dialog
+    /// cannot be opened and the key is forwarded to the current mode.
+    #[serde(default = "Config::default_mode_selection_key")]
+    pub mode_selection_key: Option<char>,
+
+    /// Key that toggles the help dialog. If [None], the dialog cannot be
+    /// opened and the key is forwarded to the current mode.
+    #[serde(default = "Config::default_help_key")]
+    pub help_key: Option<char>,
+
+    /// Key that exits without a selection and with a distinct exit code.
+    /// If [None], the key is forwarded to the current mode.
+    #[serde(default = "Config::default_abort_key")]
+    pub abort_key: Option<char>,
+
+    /// Key that hides the hints and highlights until it is pressed again.
+    /// If [None], the key is forwarded to the current mode.
+    #[serde(default = "Config::default_peek_key")]
+    pub peek_key: Option<char>,
+
+    /// Key that switches back to the mode that was active before the
+    /// current one. If [None], the key is forwarded to the current mode.
+    #[serde(default = "Config::default_return_mode_key")]
+    pub return_mode_key: Option<char>,
+
+    /// Key that opens the prompt for searching the input. If [None], the
+    /// key is forwarded to the current mode.
+    #[serde(default = "Config::default_search_key")]
+    pub search_key: Option<char>,
+
+    /// Key that switches to the next file given as an argument. If [None],
+    /// the key is forwarded to the current mode.
+    #[serde(default = "Config::default_next_file_key")]
+    pub next_file_key: Option<char>,
+
+    /// Key that switches to the previous file given as an argument. If
+    /// [None], the key is forwarded to the current mode.
+    #[serde(default = "Config::default_previous_file_key")]
+    pub previous_file_key: Option<char>,
+
+    /// Whether the lines longer than the screen width are wrapped to the next
+    /// rows. If false, they are truncated.
+    #[serde(default = "Config::default_wrap_lines")]
+    pub wrap_lines: bool,
+
+    /// Key that switches between wrapping and truncating the long lines. If
+    /// [None], the key is forwarded to the current mode.
+    #[serde(default = "Config::default_wrap_toggle_key")]
+    pub wrap_toggle_key: Option<char>,
+
+    /// Key that scrolls to the next page with anything to select in the
+    /// current mode. If [None], the key is forwarded to the current mode.
+    #[serde(default = "Config::default_next_hit_page_key")]
+    pub next_hit_page_key: Option<char>,
+
+    /// Key that scrolls to the previous page with anything to select in
+    /// the current mode. If [None], the key is forwarded to the current mode.
+    #[serde(default = "Config::default_previous_hit_page_key")]
+    pub previous_hit_page_key: Option<char>,
+
+    /// Key that runs the command the input came from (`--exec` or
+    /// [Config::default_input_command]) again. If [None], the key is
+    /// forwarded to the current mode.
+    #[serde(default = "Config::default_refresh_key")]
+    pub refresh_key: Option<char>,
+
+    /// Whether to start as a plain pager that shows the input without any
+    /// hints until [Config::select_key] is pressed.
+    #[serde(default)]
+    pub start_as_pager: bool,
+
+    /// Key that starts the selection when paging. If [None], the selection
+    /// cannot be started from the pager.
+    #[serde(default = "Config::default_select_key")]
+    pub select_key: Option<char>,
+
+    /// Key that starts collecting several selections, possibly from
+    /// different pages, and outputs them when pressed again. If [None], the
+    /// key is forwarded to the current mode.
+    #[serde(default = "Config::default_collect_key")]
+    pub collect_key: Option<char>,
+
+    /// Foreground color for the hits whose text is already collected.
+    #[serde(deserialize_with = "deserialize_color")]
+    #[serde(default = "Config::default_collected_fg")]
+    pub collected_fg: Color,
+
+    /// Background color for the hits whose text is already collected.
+    #[serde(deserialize_with = "deserialize_color")]
+    #[serde(default = "Config::default_collected_bg")]
+    pub collected_bg: Color,
+
+    /// Key that scrolls to the start of the input, in addition to Home.
+    #[serde(default)]
+    pub scroll_to_top_key: Option<char>,
+
+    /// Key that scrolls to the end of the input, in addition to End.
+    #[serde(default)]
+    pub scroll_to_bottom_key: Option<char>,
+
+    /// Key that shows or hides the overlay with what the application is
+    /// doing. Only used while logging into a file, otherwise the key is
+    /// forwarded to the current mode, as it is if [None].
+    #[serde(default = "Config::default_debug_overlay_key")]
+    pub debug_overlay_key: Option<char>,
+
+    /// Time in milliseconds to wait for the next key of a key sequence
+    /// bound in the mode keybindings.
+    #[serde(default = "Config::default_key_sequence_timeout_ms")]
+    pub key_sequence_timeout_ms: u64,
+
+    /// What scrolling the mouse wheel does.
+    ///
+    /// Note that capturing the mouse prevents selecting the text with it
+    /// in most terminal emulators.
+    #[serde(default)]
+    pub mouse_wheel_scroll: MouseWheelScroll,
+
+    /// Time in milliseconds after which a typed hint that is also the
+    /// start of a longer hint is selected. If [None], such a hint can
+    /// only be selected with Enter.
+    #[serde(default)]
+    pub ambiguous_hint_timeout_ms: Option<u64>,
+
+    /// Whether h, j, k and l keys move the focus between hits like the
+    /// arrow keys do.
+    #[serde(default)]
+    pub hjkl_navigation: bool,
+
+    /// Foreground color for the hit that has the focus during selection.
+    #[serde(deserialize_with = "deserialize_color")]
+    #[serde(default = "Config::default_focus_fg")]
+    pub focus_fg: Color,
+
+    /// Background color for the hit that has the focus during selection.
+    #[serde(deserialize_with = "deserialize_color")]
+    #[serde(default = "Config::default_focus_bg")]
+    pub focus_bg: Color,
+
+    /// Whether tab and shift+tab switch directly to the next and the
+    /// previous mode.
+    #[serde(default = "Config::default_mode_cycling")]
+    pub mode_cycling: bool,
+
     /// Foreground color of the mode switching divider character
     #[serde(deserialize_with = "deserialize_color")]
     #[serde(default = "Config::default_mode_switch_divider_fg")]
@@ -68,6 +474,113 @@ pub struct Config {
     #[serde(default = "Config::default_mode_switch_width")]
     pub mode_switch_width: usize,
 
+    /// Shell command whose output is used as the input when no file
+    /// is given and nothing is piped to the standard input.
+    #[serde(default)]
+    pub default_input_command: Option<String>,
+
+    /// Steps that change each line of the input before it is shown and
+    /// matched, in the order in which they are applied.
+    #[serde(default)]
+    pub input_filters: Vec<InputFilter>,
+
+    /// Line shown before each input concatenated with `--concat`, with
+    /// `{name}` replaced by the name of the input. Nothing on it can be
+    /// selected.
+    #[serde(default = "Config::default_input_header")]
+    pub input_header: String,
+
+    /// Shell command that receives the selection on its standard input,
+    /// instead of it being output, when the hint is typed with alt held.
+    #[serde(default)]
+    pub alt_output_command: Option<String>,
+
+    /// Whether the selected text should be saved so that it can be output
+    /// again with `--repeat-last`.
+    #[serde(default)]
+    pub remember_last_selection: bool,
+
+    /// Whether the selected texts should be saved to the history, so that
+    /// they can be selected again with `--history`.
+    #[serde(default)]
+    pub remember_history: bool,
+
+    /// The maximum number of the selections kept in the history.
+    #[serde(default = "Config::default_history_size")]
+    pub history_size: usize,
+
+    /// Whether all the found config files should be merged instead of
+    /// using only the one with the highest priority.
+    #[serde(default)]
+    pub merge_configs: bool,
+
+    /// Whether the whitespace should be removed from the start and
+    /// the end of the selection before outputting it.
+    #[serde(default)]
+    pub trim_selection: bool,
+
+    /// Whether a new line should be appended to the selection when
+    /// outputting it.
+    #[serde(default)]
+    pub append_newline: bool,
+
+    /// Text put between the selections when several of them are output
+    /// together, e.g. with the `select_all` command.
+    #[serde(default = "Config::default_selection_delimiter")]
+    #[serde(deserialize_with = "Config::deserialize_selection_delimiter")]
+    pub selection_delimiter: String,
+
+    /// What is done with the selected text, unless the mode it is selected
+    /// in sets its own [modes::Mode::action].
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub selection_action: SelectionAction,
+
+    /// Commands that open the selections of different kinds with
+    /// [SelectionAction::Open], the first one that matches is used.
+    #[serde(default = "Config::default_open_handlers")]
+    pub open_handlers: Vec<OpenHandler>,
+
+    /// Named actions that can be chosen with `--action`, in addition to
+    /// the actions without arguments, which are known by their names.
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub actions: HashMap<String, SelectionAction>,
+
+    /// Actions to choose from in a menu shown after each selection, which
+    /// override the action of the mode. If empty, no menu is shown.
+    #[serde(default)]
+    pub action_menu: Vec<ActionMenuEntry>,
+
+    /// Shell command that the selection is piped through, with its output
+    /// used instead of the selection, e.g. `xargs basename`. If it fails, the
+    /// selection is used as it is.
+    #[serde(default)]
+    pub selection_filter: Option<String>,
+
+    /// Shell command that receives the selection on its standard input
+    /// when it is copied with [SelectionAction::Copy]. If [None], the
+    /// clipboard command of the platform is looked for.
+    #[serde(default)]
+    pub clipboard_command: Option<String>,
+
+    /// Shell command that receives the selection on its standard input
+    /// when it is copied with [SelectionAction::CopyPrimary]. If [None],
+    /// the primary selection command of the platform is looked for.
+    #[serde(default)]
+    pub primary_command: Option<String>,
+
+    /// What to output when the selection is cancelled.
+    #[serde(default)]
+    pub cancel_output: CancelOutput,
+
+    /// Text to output when the selection is cancelled and
+    /// [Config::cancel_output] is [CancelOutput::Fallback].
+    #[serde(default)]
+    pub cancel_fallback: String,
+
+    /// Configuration of the debug logging.
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
     /// List of modes that the user can use.
     ///
     /// Note that it is possible to have multiple instances of the same
@@ -88,8 +601,12 @@ impl Default for Config {
 }
 
 impl Config {
+    fn default_version() -> u64 {
+        CURRENT_CONFIG_VERSION
+    }
+
     fn default_hint_characters() -> String {
-        "fdsajkl;weiocmruvnghqpxztyb".into()
+        "fdsajkl;weiocmruvnghpxztyb".into()
     }
 
     fn validate_hint_characters<'de, D>(d: D) -> Result<String, D::Error>
@@ -108,6 +625,158 @@ impl Config {
         ))
     }
 
+    fn validate_fixed_hint_length<'de, D>(d: D) -> Result<Option<usize>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let length = Option::<usize>::deserialize(d)?;
+
+        if length == Some(0) {
+            return Err(de::Error::invalid_value(
+                Unexpected::Unsigned(0),
+                &"a length of at least one character",
+            ));
+        }
+
+        Ok(length)
+    }
+
+    fn default_selection_delimiter() -> String {
+        "\n".to_string()
+    }
+
+    fn deserialize_selection_delimiter<'de, D>(d: D) -> Result<String, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::parse_delimiter(&String::deserialize(d)?))
+    }
+
+    /// Get the delimiter with the given name, i.e. `space`, `tab` or
+    /// `newline`, or the given text itself if it is not a name.
+    pub fn parse_delimiter(name: &str) -> String {
+        match name {
+            "space" => " ".to_string(),
+            "tab" => "\t".to_string(),
+            "newline" => "\n".to_string(),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Get the action with the given name, i.e. one of [Config::actions]
+    /// or one of the actions without arguments, e.g. `copy`.
+    pub fn find_action(&self, name: &str) -> Option<SelectionAction> {
+        if let Some(action) = self.actions.get(name) {
+            return Some(action.clone());
+        }
+
+        serde_yaml::from_str(name).ok()
+    }
+
+    /// Get the keys that trigger an action while selecting instead of
+    /// being typed as a part of a hint, with the names of the top level
+    /// settings that bind them and descriptions of the bindings.
+    fn get_action_keys(&self) -> Vec<(char, &'static str, String)> {
+        let keys = [
+            (self.mode_selection_key, "mode_selection_key"),
+            (self.help_key, "help_key"),
+            (self.abort_key, "abort_key"),
+            (self.peek_key, "peek_key"),
+            (self.return_mode_key, "return_mode_key"),
+            (self.search_key, "search_key"),
+            (self.next_file_key, "next_file_key"),
+            (self.previous_file_key, "previous_file_key"),
+            (self.wrap_toggle_key, "wrap_toggle_key"),
+            (self.next_hit_page_key, "next_hit_page_key"),
+            (self.previous_hit_page_key, "previous_hit_page_key"),
+            (self.refresh_key, "refresh_key"),
+            (self.collect_key, "collect_key"),
+            (self.scroll_to_top_key, "scroll_to_top_key"),
+            (self.scroll_to_bottom_key, "scroll_to_bottom_key"),
+            (self.debug_overlay_key, "debug_overlay_key"),
+        ];
+        let mut action_keys: Vec<(char, &'static str, String)> = keys
+            .into_iter()
+            .filter_map(|(key, setting)| Some((key?, setting, setting.to_string())))
+            .collect();
+
+        if self.hjkl_navigation {
+            action_keys.extend(
+                "hjkl"
+                    .chars()
+                    .map(|key| (key, "hjkl_navigation", "hjkl_navigation".to_string())),
+            );
+        }
+
+        // The first key of a sequence already waits for the rest of it
+        for mode in &self.modes {
+            action_keys.extend(mode.keybindings.keys().filter_map(|sequence| {
+                let key = *sequence.0.first()?;
+                Some((key, "modes", format!("keybindings of mode {}", mode.name)))
+            }));
+        }
+
+        action_keys
+    }
+
+    /// Check that none of [Config::hint_characters] is bound to an action,
+    /// which would make the hints containing it impossible to type.
+    ///
+    /// Only the keys bound by the given settings, i.e. the ones set in the
+    /// config file, result in an error. The characters bound to actions by
+    /// default are left out of the hints with a warning instead, so that
+    /// the configs written before those keys were added keep working.
+    fn check_hint_characters(&mut self, set_settings: &HashSet<String>) -> Result<(), Error> {
+        // The other generators do not use the hint characters
+        if self.hint_generator != HintGeneratorKind::Pool {
+            return Ok(());
+        }
+
+        let mut keys = vec![];
+        let mut dropped_keys = vec![];
+        for (key, setting, binding) in self.get_action_keys() {
+            if !self.hint_characters.contains(key) {
+                continue;
+            }
+
+            let description = format!("'{key}' ({binding})");
+            if set_settings.contains(setting) {
+                keys.push(description);
+            } else {
+                dropped_keys.push((key, description));
+            }
+        }
+
+        if !dropped_keys.is_empty() {
+            let remaining: String = self
+                .hint_characters
+                .chars()
+                .filter(|character| dropped_keys.iter().all(|(key, _)| key != character))
+                .collect();
+            let descriptions = dropped_keys.into_iter().map(|(_, description)| description);
+
+            // Without any characters left, no hints could be made
+            if remaining.is_empty() {
+                keys.extend(descriptions);
+            } else {
+                warn!(
+                    "Leaving keys bound to actions by default out of hint_characters: {}",
+                    descriptions.collect::<Vec<_>>().join(", ")
+                );
+                self.hint_characters = remaining;
+            }
+        }
+
+        keys.sort();
+        keys.dedup();
+
+        if !keys.is_empty() {
+            return Err(Error::HintCharactersBoundToActions { keys });
+        }
+
+        Ok(())
+    }
+
     fn validate_modes<'de, D>(d: D) -> Result<Vec<modes::Mode>, D::Error>
     where
         D: Deserializer<'de>,
@@ -129,6 +798,20 @@ impl Config {
             ));
         }
 
+        for mode in &modes {
+            let commands = mode.args.commands();
+
+            for command in mode.keybindings.values() {
+                if !commands.contains(&command.as_str()) {
+                    let expected = format!("one of the mode commands: {}", commands.join(", "));
+                    return Err(de::Error::invalid_value(
+                        Unexpected::Str(command),
+                        &expected.as_str(),
+                    ));
+                }
+            }
+        }
+
         Ok(modes)
     }
 
@@ -140,9 +823,13 @@ impl Config {
                     reason = "Hardcoded value that is verified to work"
                 )]
                 regexes: vec![Regex::new(r"[\w._\-~/]{4,}").unwrap()],
+                min_length: 0,
+                literal_matchers: Default::default(),
             }),
             hotkey: 'r',
             name: "default".to_string(),
+            keybindings: HashMap::new(),
+            action: None,
         }]
     }
 
@@ -166,6 +853,118 @@ impl Config {
         Color::parse_ansi("5;252").unwrap()
     }
 
+    fn default_focus_fg() -> Color {
+        #[allow(clippy::unwrap_used, reason = "A literal that's known to be parseable")]
+        Color::parse_ansi("5;232").unwrap()
+    }
+
+    fn default_focus_bg() -> Color {
+        #[allow(clippy::unwrap_used, reason = "A literal that's known to be parseable")]
+        Color::parse_ansi("5;39").unwrap()
+    }
+
+    fn default_mode_selection_key() -> Option<char> {
+        Some(' ')
+    }
+
+    fn default_help_key() -> Option<char> {
+        Some('?')
+    }
+
+    fn default_abort_key() -> Option<char> {
+        Some('q')
+    }
+
+    fn default_peek_key() -> Option<char> {
+        Some('.')
+    }
+
+    fn default_key_sequence_timeout_ms() -> u64 {
+        1000
+    }
+
+    fn default_return_mode_key() -> Option<char> {
+        Some('-')
+    }
+
+    fn default_search_key() -> Option<char> {
+        Some('/')
+    }
+
+    fn default_next_file_key() -> Option<char> {
+        Some(']')
+    }
+
+    fn default_previous_file_key() -> Option<char> {
+        Some('[')
+    }
+
+    fn default_wrap_lines() -> bool {
+        true
+    }
+
+    fn default_wrap_toggle_key() -> Option<char> {
+        Some('|')
+    }
+
+    fn default_debug_overlay_key() -> Option<char> {
+        Some('!')
+    }
+
+    fn default_next_hit_page_key() -> Option<char> {
+        Some('}')
+    }
+
+    fn default_previous_hit_page_key() -> Option<char> {
+        Some('{')
+    }
+
+    fn default_input_header() -> String {
+        "==> {name} <==".to_string()
+    }
+
+    fn default_refresh_key() -> Option<char> {
+        Some('R')
+    }
+
+    fn default_select_key() -> Option<char> {
+        Some('v')
+    }
+
+    fn default_collect_key() -> Option<char> {
+        Some('+')
+    }
+
+    fn default_collected_fg() -> Color {
+        #[allow(clippy::unwrap_used, reason = "A literal that's known to be parseable")]
+        Color::parse_ansi("5;232").unwrap()
+    }
+
+    fn default_collected_bg() -> Color {
+        #[allow(clippy::unwrap_used, reason = "A literal that's known to be parseable")]
+        Color::parse_ansi("5;114").unwrap()
+    }
+
+    fn default_history_size() -> usize {
+        100
+    }
+
+    fn default_open_handlers() -> Vec<OpenHandler> {
+        OpenHandler::defaults()
+    }
+
+    fn default_left_hand_characters() -> String {
+        "12345qwertasdfgzxcvb".to_string()
+    }
+
+    fn default_deduplicate_hits() -> bool {
+        true
+    }
+
+    fn default_mode_cycling() -> bool {
+        true
+    }
+
     fn default_mode_switch_divider_fg() -> Color {
         #[allow(clippy::unwrap_used, reason = "A literal that's known to be parseable")]
         Color::parse_ansi("5;208").unwrap()
@@ -186,20 +985,99 @@ impl Config {
     }
 }
 
-impl TryFrom<File> for Config {
+impl TryFrom<&str> for Config {
     type Error = Error;
 
-    fn try_from(file: File) -> Result<Self, Self::Error> {
-        let config = serde_yaml::from_reader(file) //
+    /// Parse the config from the text of a config file. Unlike the errors of
+    /// [Config::try_from] with a [Value], the parse errors contain the
+    /// location of the problem in the text.
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
+        let value: Value = serde_yaml::from_str(text) //
             .context(ParseSnafu {})?;
 
-        Ok(config)
+        parse_config(value, Some(text))
+    }
+}
+
+impl TryFrom<Value> for Config {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        parse_config(value, None)
     }
 }
 
+/// Parse the config from the given value, deserializing it from the given
+/// text instead, if any, so that the errors point to the problem in it.
+fn parse_config(value: Value, text: Option<&str>) -> Result<Config, Error> {
+    check_version(&value)?;
+
+    // The value loses the location of its entries, the text does not. Empty
+    // texts can't be deserialized into the config, so they use the value.
+    let text = text.filter(|_| value.is_mapping());
+
+    // Empty config files should result in the default config
+    let value = match value {
+        Value::Null => Value::Mapping(Mapping::new()),
+        value => value,
+    };
+
+    check_settings(&value)?;
+    let set_settings: HashSet<String> = value
+        .as_mapping()
+        .into_iter()
+        .flat_map(Mapping::keys)
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect();
+
+    let palette = match value.get("palette") {
+        Some(palette) => serde_yaml::from_value(palette.clone()) //
+            .context(ParseSnafu {})?,
+        None => HashMap::new(),
+    };
+    let palette = parse_palette(&palette) //
+        .map_err(|name| Error::InvalidPaletteColor { name })?;
+
+    let mut config: Config = match text {
+        Some(text) => with_palette(palette, || serde_yaml::from_str(text)),
+        None => with_palette(palette, || serde_yaml::from_value(value)),
+    }
+    .context(ParseSnafu {})?;
+    config.check_hint_characters(&set_settings)?;
+
+    Ok(config)
+}
+
+/// Check that all the top level settings of the given config exist, i.e.
+/// that they are in [DEFAULT_CONFIG_FILE], which lists every setting.
+fn check_settings(value: &Value) -> Result<(), Error> {
+    let Some(settings) = value.as_mapping() else {
+        return Ok(());
+    };
+    let default_value: Value = serde_yaml::from_str(DEFAULT_CONFIG_FILE) //
+        .context(ParseSnafu {})?;
+    let Some(known_settings) = default_value.as_mapping() else {
+        return Ok(());
+    };
+
+    for name in settings.keys().filter_map(Value::as_str) {
+        if !known_settings.contains_key(name) {
+            let known_names = known_settings.keys().filter_map(Value::as_str);
+            return Err(Error::UnknownSetting {
+                name: name.to_string(),
+                suggestion: did_you_mean(name, known_names),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_case::test_case;
 
     #[test]
     fn can_be_deserialized_from_empty_string() {
@@ -219,6 +1097,223 @@ mod tests {
         result.unwrap_err();
     }
 
+    #[test]
+    fn fixed_hint_length_deserialization_returns_error_when_zero() {
+        let result = serde_yaml::from_str::<Config>("fixed_hint_length: 0");
+        result.unwrap_err();
+    }
+
+    #[test]
+    fn fixed_hint_length_can_be_deserialized() {
+        let config: Config = serde_yaml::from_str("fixed_hint_length: 2").unwrap();
+
+        assert_eq!(config.fixed_hint_length, Some(2));
+    }
+
+    #[test]
+    fn max_hints_policy_can_be_deserialized() {
+        let config: Config =
+            serde_yaml::from_str("max_hints: 5\nmax_hints_policy: longest").unwrap();
+
+        assert_eq!(config.max_hints, Some(5));
+        assert_eq!(config.max_hints_policy, HintLimitPolicy::Longest);
+    }
+
+    #[test]
+    fn run_selection_action_can_be_deserialized() {
+        let config: Config =
+            serde_yaml::from_str("selection_action: {run: 'xdg-open {}'}").unwrap();
+
+        assert_eq!(
+            config.selection_action,
+            SelectionAction::Run("xdg-open {}".to_string())
+        );
+    }
+
+    #[test]
+    fn can_be_converted_from_empty_value() {
+        let config = Config::try_from(Value::Null).unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test_case("space", " "; "when_space_is_given")]
+    #[test_case("newline", "\n"; "when_newline_is_given")]
+    #[test_case(", ", ", "; "when_text_is_given")]
+    fn selection_delimiter_can_be_named(delimiter: &str, expected: &str) {
+        let config: Config =
+            serde_yaml::from_str(&format!("selection_delimiter: '{delimiter}'")).unwrap();
+
+        assert_eq!(config.selection_delimiter, expected);
+    }
+
+    #[test_case("tmux_buffer"; "when_snake_case_is_used")]
+    #[test_case("tmux-buffer"; "when_dashes_are_used")]
+    fn tmux_buffer_selection_action_can_be_deserialized(action: &str) {
+        let config: Config = serde_yaml::from_str(&format!("selection_action: {action}")).unwrap();
+
+        assert_eq!(config.selection_action, SelectionAction::TmuxBuffer);
+    }
+
+    #[test]
+    fn pipe_selection_action_can_be_deserialized() {
+        let config: Config =
+            serde_yaml::from_str("selection_action: {pipe: 'cliphist store'}").unwrap();
+
+        assert_eq!(
+            config.selection_action,
+            SelectionAction::Pipe("cliphist store".to_string())
+        );
+    }
+
+    #[test_case("hint_generator: pool", HintGeneratorKind::Pool)]
+    #[test_case("hint_generator: numeric", HintGeneratorKind::Numeric)]
+    #[test_case("hint_generator: syllables", HintGeneratorKind::Syllables)]
+    fn hint_generator_can_be_deserialized(yaml: &str, expected: HintGeneratorKind) {
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(config.hint_generator, expected);
+    }
+
+    #[test_case("hint_priority: found", HintPriority::Found)]
+    #[test_case("hint_priority: bottom", HintPriority::Bottom)]
+    #[test_case("hint_priority: {line: 10}", HintPriority::Line(10))]
+    #[test_case("hint_priority:\n  line: 10", HintPriority::Line(10))]
+    fn hint_priority_can_be_deserialized(yaml: &str, expected: HintPriority) {
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(config.hint_priority, expected);
+    }
+
+    #[test_case("copy", Some(SelectionAction::Copy); "when_action_is_builtin")]
+    #[test_case("print", Some(SelectionAction::Output); "when_action_is_alias")]
+    #[test_case("store", Some(SelectionAction::Pipe("cliphist store".to_string())); "when_action_is_named")]
+    #[test_case("run", None; "when_action_needs_argument")]
+    #[test_case("nothing", None; "when_action_does_not_exist")]
+    fn find_action_returns_expected_action(name: &str, expected: Option<SelectionAction>) {
+        let config: Config =
+            serde_yaml::from_str("actions: {store: {pipe: 'cliphist store'}}").unwrap();
+
+        assert_eq!(config.find_action(name), expected);
+    }
+
+    #[test]
+    fn actions_can_be_deserialized() {
+        // The example from the default config file
+        let string = "
+            actions:
+              edit: {run: '$EDITOR {}'}
+              store: {pipe: 'cliphist store'}
+        ";
+
+        let config: Config = serde_yaml::from_str(string).unwrap();
+
+        assert_eq!(
+            config.actions,
+            HashMap::from([
+                (
+                    "edit".to_string(),
+                    SelectionAction::Run("$EDITOR {}".to_string())
+                ),
+                (
+                    "store".to_string(),
+                    SelectionAction::Pipe("cliphist store".to_string())
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn action_menu_can_be_deserialized() {
+        // The example from the default config file
+        let string = "
+            action_menu:
+              - {hotkey: p, name: print, action: output}
+              - {hotkey: c, name: copy, action: copy}
+              - {hotkey: o, name: open, action: open}
+              - {hotkey: e, name: edit, action: {run: '$EDITOR {}'}}
+        ";
+
+        let config: Config = serde_yaml::from_str(string).unwrap();
+
+        let entry = |hotkey, name: &str, action| ActionMenuEntry {
+            hotkey,
+            name: name.to_string(),
+            action,
+        };
+        assert_eq!(
+            config.action_menu,
+            vec![
+                entry('p', "print", SelectionAction::Output),
+                entry('c', "copy", SelectionAction::Copy),
+                entry('o', "open", SelectionAction::Open),
+                entry('e', "edit", SelectionAction::Run("$EDITOR {}".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn colors_can_reference_palette() {
+        let value: Value =
+            serde_yaml::from_str("palette: {accent: '5;100'}\nhint_bg: accent").unwrap();
+
+        let config = Config::try_from(value).unwrap();
+
+        assert_eq!(config.hint_bg, Color::AnsiValue(100));
+    }
+
+    #[test]
+    fn palette_with_invalid_color_returns_error() {
+        let value: Value = serde_yaml::from_str("palette: {accent: nope}").unwrap();
+
+        let result = Config::try_from(value);
+
+        assert!(matches!(result, Err(Error::InvalidPaletteColor { .. })));
+    }
+
+    #[test_case("abort_key: q\nhint_characters: asdfq"; "when_hint_character_is_abort_key")]
+    #[test_case(
+        "mode_selection_key: ' '\nhint_characters: 'as df'";
+        "when_hint_character_is_mode_selection_key"
+    )]
+    #[test_case("hjkl_navigation: true"; "when_hjkl_navigation_uses_hint_characters")]
+    #[test_case(
+        "modes: [{mode: regex, hotkey: r, name: default, regexes: ['.+'], keybindings: {'g a': select_all}}]\n\
+         hint_characters: asdfg";
+        "when_hint_character_starts_key_sequence"
+    )]
+    #[test_case("hint_characters: ' '"; "when_only_hint_character_is_bound_by_default")]
+    fn hint_characters_bound_to_actions_return_error(string: &str) {
+        let value: Value = serde_yaml::from_str(string).unwrap();
+
+        let result = Config::try_from(value);
+
+        assert!(matches!(
+            result,
+            Err(Error::HintCharactersBoundToActions { .. })
+        ));
+    }
+
+    #[test_case("hint_characters: asdf"; "when_no_hint_character_is_bound")]
+    #[test_case("abort_key: null\nhint_characters: asdfq"; "when_action_key_is_disabled")]
+    #[test_case("hint_generator: numeric\nhint_characters: asdfq"; "when_hint_characters_are_not_used")]
+    fn hint_characters_not_bound_to_actions_are_accepted(string: &str) {
+        let value: Value = serde_yaml::from_str(string).unwrap();
+
+        Config::try_from(value).unwrap();
+    }
+
+    #[test_case("hint_characters: fdsajkl;weiocmruvnghqpxztyb", "fdsajkl;weiocmruvnghpxztyb"; "when_old_default_contains_abort_key")]
+    #[test_case("hint_characters: 'as df'", "asdf"; "when_hint_character_is_mode_selection_key")]
+    #[test_case("mode_selection_key: m\nhint_characters: 'as df'", "as df"; "when_default_key_is_rebound")]
+    fn hint_characters_bound_by_default_are_left_out(string: &str, expected: &str) {
+        let value: Value = serde_yaml::from_str(string).unwrap();
+
+        let config = Config::try_from(value).unwrap();
+
+        assert_eq!(config.hint_characters, expected);
+    }
+
     #[test]
     fn modes_deserialization_returns_error_when_empty() {
         let result = serde_yaml::from_str::<Config>("modes: []");
@@ -245,6 +1340,61 @@ mod tests {
         result.unwrap_err();
     }
 
+    #[test]
+    fn unknown_setting_returns_error_with_suggestion() {
+        let value: Value = serde_yaml::from_str("hint_characers: asdf").unwrap();
+
+        let error = Config::try_from(value).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Unknown setting hint_characers, did you mean hint_characters?"
+        );
+    }
+
+    #[test]
+    fn unknown_variant_returns_error_with_suggestion() {
+        let value: Value = serde_yaml::from_str("max_hints_policy: frist").unwrap();
+
+        let error = Config::try_from(value).unwrap_err();
+
+        assert!(error.to_string().ends_with(", did you mean first?"));
+    }
+
+    #[test]
+    fn error_of_config_text_contains_location() {
+        let text = "hint_characters: asdf\nmax_hints_policy: frist\n";
+
+        let error = Config::try_from(text).unwrap_err();
+
+        let location = error.location().unwrap();
+        assert_eq!((location.line(), location.column()), (2, 19));
+    }
+
+    #[test]
+    fn empty_config_text_returns_default_config() {
+        let config = Config::try_from("").unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn modes_deserialization_returns_error_for_unknown_command() {
+        let result = serde_yaml::from_str::<Config>(
+            "
+        modes:
+          - mode: regex
+            hotkey: r
+            name: default
+            keybindings:
+              s: stage_hunk
+            regexes:
+              - regex1
+        ",
+        );
+        result.unwrap_err();
+    }
+
     #[test]
     // This is necessary to make sure that the user can omit some values in their
     // config and get the default values for the rest
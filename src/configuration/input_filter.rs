@@ -0,0 +1,119 @@
+use regex::Regex;
+use serde::{
+    de::{self, Unexpected},
+    Deserialize, Deserializer,
+};
+
+/// A step that changes each line of the input before it is shown and
+/// matched, applied in the order given in the configuration file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "filter")]
+pub enum InputFilter {
+    /// Replace all the matches of the regex in the line.
+    #[serde(rename = "replace")]
+    Replace {
+        /// The regex whose matches are replaced.
+        #[serde(deserialize_with = "deserialize_regex")]
+        regex: Regex,
+        /// The replacement, which can refer to the capture groups of the
+        /// regex, e.g. `$1`.
+        #[serde(default)]
+        with: String,
+    },
+    /// Keep only the given fields of the line.
+    #[serde(rename = "cut")]
+    Cut {
+        /// Numbers of the fields to keep, starting from 1.
+        fields: Vec<usize>,
+        /// The text that separates the fields. If [None], the fields are
+        /// separated by any whitespace and joined with a single space.
+        #[serde(default)]
+        delimiter: Option<String>,
+    },
+    /// Remove all the ANSI escape sequences, e.g. the colors.
+    #[serde(rename = "strip_ansi")]
+    StripAnsi,
+}
+
+pub(super) fn deserialize_regex<'de, D>(d: D) -> Result<Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let regex_string = String::deserialize(d)?;
+
+    Regex::new(&regex_string).map_err(|_| {
+        de::Error::invalid_value(
+            Unexpected::Str(&regex_string),
+            &"a valid regular expression",
+        )
+    })
+}
+
+impl PartialEq for InputFilter {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                InputFilter::Replace { regex, with },
+                InputFilter::Replace {
+                    regex: other_regex,
+                    with: other_with,
+                },
+            ) => regex.as_str() == other_regex.as_str() && with == other_with,
+            (
+                InputFilter::Cut { fields, delimiter },
+                InputFilter::Cut {
+                    fields: other_fields,
+                    delimiter: other_delimiter,
+                },
+            ) => fields == other_fields && delimiter == other_delimiter,
+            (InputFilter::StripAnsi, InputFilter::StripAnsi) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_can_be_deserialized() {
+        let string = "
+            - filter: replace
+              regex: '(\\d+)ms'
+              with: '$1 ms'
+            - filter: cut
+              fields: [1, 3]
+            - filter: strip_ansi
+        ";
+
+        let filters: Vec<InputFilter> = serde_yaml::from_str(string).unwrap();
+
+        assert_eq!(
+            filters,
+            vec![
+                InputFilter::Replace {
+                    regex: Regex::new("(\\d+)ms").unwrap(),
+                    with: "$1 ms".to_string(),
+                },
+                InputFilter::Cut {
+                    fields: vec![1, 3],
+                    delimiter: None,
+                },
+                InputFilter::StripAnsi,
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_regex_is_rejected() {
+        let string = "
+            - filter: replace
+              regex: '('
+        ";
+
+        let result = serde_yaml::from_str::<Vec<InputFilter>>(string);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use std::{collections::HashMap, str::FromStr};
+
+use log::LevelFilter;
+use serde::{
+    de::{self, Unexpected},
+    Deserialize, Deserializer,
+};
+
+/// Logging related part of the configuration.
+///
+/// See [crate::logging] for details on how this interacts with the
+/// environment variables.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct LoggingConfig {
+    /// Path of the file to log into. If [None], logging is turned off.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// The level to log at for all modules without a specific level.
+    #[serde(default = "LoggingConfig::default_level")]
+    #[serde(deserialize_with = "LoggingConfig::deserialize_level")]
+    pub level: LevelFilter,
+
+    /// Levels for specific modules, e.g. `mless::modes`.
+    #[serde(default)]
+    #[serde(deserialize_with = "LoggingConfig::deserialize_module_levels")]
+    pub modules: HashMap<String, LevelFilter>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            level: Self::default_level(),
+            modules: HashMap::new(),
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn default_level() -> LevelFilter {
+        LevelFilter::Debug
+    }
+
+    fn parse_level<E: de::Error>(level: &str) -> Result<LevelFilter, E> {
+        LevelFilter::from_str(level).map_err(|_| {
+            de::Error::invalid_value(
+                Unexpected::Str(level),
+                &"one of off, error, warn, info, debug, trace",
+            )
+        })
+    }
+
+    fn deserialize_level<'de, D>(d: D) -> Result<LevelFilter, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let level = String::deserialize(d)?;
+        Self::parse_level(&level)
+    }
+
+    fn deserialize_module_levels<'de, D>(d: D) -> Result<HashMap<String, LevelFilter>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        HashMap::<String, String>::deserialize(d)?
+            .into_iter()
+            .map(|(module, level)| Self::parse_level(&level).map(|level| (module, level)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_be_deserialized() {
+        let config: LoggingConfig = serde_yaml::from_str(
+            "
+            path: /tmp/mless.log
+            level: warn
+            modules:
+                mless::modes: trace
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(config.path, Some("/tmp/mless.log".to_string()));
+        assert_eq!(config.level, LevelFilter::Warn);
+        assert_eq!(config.modules["mless::modes"], LevelFilter::Trace);
+    }
+
+    #[test]
+    fn deserialization_fails_for_invalid_level() {
+        let result = serde_yaml::from_str::<LoggingConfig>("level: loud");
+        result.unwrap_err();
+    }
+}
@@ -0,0 +1,84 @@
+//! Merging of multiple config files.
+use serde_yaml::Value;
+use snafu::ResultExt;
+
+use super::config::{Error, ParseSnafu};
+
+/// Read the given text of a config file without interpreting it as
+/// [super::Config].
+///
+/// This is useful when the config needs to be combined with other
+/// configs before it is interpreted.
+pub fn read_config_value(text: &str) -> Result<Value, Error> {
+    serde_yaml::from_str(text).context(ParseSnafu {})
+}
+
+/// Merge the `overrides` into `base`.
+///
+/// Mappings are merged recursively, i.e. the keys present only in `base`
+/// are kept. All other values, including sequences, from `overrides`
+/// replace the ones in `base`. An empty (null) `overrides` leaves `base`
+/// unchanged, so that empty config files don't affect the result.
+pub fn merge_values(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (_, Value::Null) => (),
+        (Value::Mapping(base), Value::Mapping(overrides)) => {
+            for (key, override_value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base_value) if override_value.is_mapping() => {
+                        merge_values(base_value, override_value);
+                    }
+                    _ => {
+                        base.insert(key, override_value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge_strings(base: &str, overrides: &str) -> Value {
+        let mut base: Value = serde_yaml::from_str(base).unwrap();
+        let overrides: Value = serde_yaml::from_str(overrides).unwrap();
+
+        merge_values(&mut base, overrides);
+        base
+    }
+
+    #[test]
+    fn values_from_overrides_replace_values_from_base() {
+        let merged = merge_strings("hint_fg: red\nhint_bg: blue", "hint_fg: green");
+        let expected: Value = serde_yaml::from_str("hint_fg: green\nhint_bg: blue").unwrap();
+
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn sequences_from_overrides_replace_sequences_from_base() {
+        let merged = merge_strings("list: [1, 2, 3]", "list: [4]");
+        let expected: Value = serde_yaml::from_str("list: [4]").unwrap();
+
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn nested_mappings_are_merged() {
+        let merged = merge_strings("outer: {a: 1, b: 2}", "outer: {b: 3}");
+        let expected: Value = serde_yaml::from_str("outer: {a: 1, b: 3}").unwrap();
+
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn empty_overrides_leave_base_unchanged() {
+        let merged = merge_strings("hint_fg: red", "");
+        let expected: Value = serde_yaml::from_str("hint_fg: red").unwrap();
+
+        assert_eq!(merged, expected);
+    }
+}
@@ -1,14 +1,41 @@
 //! Structs and functions for dealing with config files.
 mod config;
+pub use config::CancelOutput;
 pub use config::Config;
 pub use config::Error;
+pub use config::HintGeneratorKind;
+pub use config::HintLimitPolicy;
+pub use config::HintOrder;
+pub use config::HintPosition;
+pub use config::HintPriority;
+pub use config::MouseWheelScroll;
+pub use config::SelectionAction;
 
 mod modes;
+pub use modes::KeySequence;
 pub use modes::Mode;
 pub use modes::ModeArgs;
 pub use modes::RegexArgs;
 
+mod input_filter;
+pub use input_filter::InputFilter;
+
+mod open_handler;
+pub use open_handler::OpenHandler;
+
+mod merge;
+pub use merge::merge_values;
+pub use merge::read_config_value;
+
+mod logging;
+pub use logging::LoggingConfig;
+
+mod version;
+
 mod color;
 pub use color::deserialize_color;
+pub use color::parse_color;
+
+mod suggestion;
 
 pub const DEFAULT_CONFIG_FILE: &str = include_str!("default_config.yaml");
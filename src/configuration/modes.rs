@@ -1,8 +1,14 @@
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+use aho_corasick::{AhoCorasick, MatchKind};
 use regex::Regex;
 use serde::{
     de::{self, Unexpected},
     Deserialize, Deserializer,
 };
+use serde_yaml::{Mapping, Value};
+
+use super::{suggestion::did_you_mean, SelectionAction};
 
 /// Structure describing a mode instance in the configuration file.
 #[derive(Deserialize, Debug, PartialEq)]
@@ -14,14 +20,139 @@ pub struct Mode {
     pub hotkey: char,
     /// Name to use during mode selection
     pub name: String,
+    /// Key sequences that trigger the mode specific commands while in this mode.
+    #[serde(default)]
+    pub keybindings: HashMap<KeySequence, String>,
+    /// What is done with the text selected in this mode. If [None],
+    /// [crate::configuration::Config::selection_action] is used.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub action: Option<SelectionAction>,
+}
+
+/// A sequence of keys that have to be pressed one after another.
+///
+/// In the config file, it is written as the keys separated by spaces, with
+/// the space key itself written as `space`, e.g. `"g g"` or `"space x"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeySequence(pub Vec<char>);
+
+impl KeySequence {
+    /// Parse the key sequence from its config file representation.
+    fn parse(string: &str) -> Option<Self> {
+        let keys = string
+            .split_whitespace()
+            .map(|key| {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    _ if key == "space" => Some(' '),
+                    (Some(char), None) => Some(char),
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<char>>>()?;
+
+        match keys.is_empty() {
+            true => None,
+            false => Some(Self(keys)),
+        }
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self
+            .0
+            .iter()
+            .map(|key| match key {
+                ' ' => "space".to_string(),
+                _ => key.to_string(),
+            })
+            .collect();
+
+        write!(f, "{}", keys.join(" "))
+    }
+}
+
+impl<'de> Deserialize<'de> for KeySequence {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(d)?;
+
+        KeySequence::parse(&string).ok_or_else(|| {
+            de::Error::invalid_value(
+                Unexpected::Str(&string),
+                &"single characters or \"space\" separated by spaces",
+            )
+        })
+    }
 }
 
 /// Arguments that specify the details of the mode.
-#[derive(Deserialize, Debug, PartialEq)]
-#[serde(tag = "mode")]
+#[derive(Debug, PartialEq)]
 pub enum ModeArgs {
-    #[serde(rename = "regex")]
+    /// Arguments of the built in `mode: regex`.
     RegexMode(RegexArgs),
+    /// Arguments of a mode registered with [crate::modes::register_mode],
+    /// with the name it is registered under and the rest of its settings.
+    Registered { name: String, args: Value },
+}
+
+impl ModeArgs {
+    /// Names of the commands supported by the mode with these arguments.
+    pub fn commands(&self) -> &'static [&'static str] {
+        match self {
+            ModeArgs::RegexMode(_) => crate::modes::RegexMode::COMMANDS,
+            ModeArgs::Registered { name, .. } => {
+                crate::modes::get_registered_mode_commands(name).unwrap_or_default()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ModeArgs {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut args = Mapping::deserialize(d)?;
+        let name = match args.remove("mode") {
+            Some(Value::String(name)) => name,
+            Some(_) => {
+                return Err(de::Error::invalid_type(
+                    Unexpected::Other("non-string mode"),
+                    &"the name of a mode",
+                ))
+            }
+            None => return Err(de::Error::missing_field("mode")),
+        };
+
+        if name == "regex" {
+            return RegexArgs::deserialize(Value::Mapping(args))
+                .map(ModeArgs::RegexMode)
+                .map_err(de::Error::custom);
+        }
+
+        if crate::modes::get_registered_mode_commands(&name).is_none() {
+            let mut names = vec!["regex".to_string()];
+            names.extend(crate::modes::get_registered_mode_names());
+            let expected = format!(
+                "one of the modes: {}{}",
+                names.join(", "),
+                did_you_mean(&name, names.iter().map(String::as_str))
+            );
+            return Err(de::Error::invalid_value(
+                Unexpected::Str(&name),
+                &expected.as_str(),
+            ));
+        }
+
+        Ok(ModeArgs::Registered {
+            name,
+            args: Value::Mapping(args),
+        })
+    }
 }
 
 /// Arguments for [crate::modes::RegexMode].
@@ -31,6 +162,16 @@ pub struct RegexArgs {
     #[serde(deserialize_with = "RegexArgs::deserialize_regexes")]
     #[serde(serialize_with = "RegexArgs::serialize_regexes")]
     pub regexes: Vec<Regex>,
+
+    /// The minimum number of characters a match needs to have to be
+    /// selectable. Shorter matches are ignored.
+    #[serde(default)]
+    pub min_length: usize,
+
+    /// The matchers used instead of the regexes that only match literal
+    /// strings, see [RegexArgs::get_literal_matchers].
+    #[serde(skip)]
+    pub literal_matchers: OnceLock<Vec<Option<AhoCorasick>>>,
 }
 
 impl RegexArgs {
@@ -64,11 +205,60 @@ impl RegexArgs {
 
         Ok(regexes)
     }
+
+    /// Get a matcher for every regex, in the order of the regexes, that is
+    /// used instead of it if it only matches literal strings, e.g. a long
+    /// list of host names. The matchers are built the first time they are
+    /// needed.
+    pub fn get_literal_matchers(&self) -> &[Option<AhoCorasick>] {
+        self.literal_matchers.get_or_init(|| {
+            self.regexes
+                .iter()
+                .map(|regex| {
+                    let literals = get_literals(regex.as_str())?;
+                    // Same as the regexes, the first alternative that matches wins
+                    AhoCorasick::builder()
+                        .match_kind(MatchKind::LeftmostFirst)
+                        .build(literals)
+                        .ok()
+                })
+                .collect()
+        })
+    }
+}
+
+/// Get the strings matched by the given regex if it is a literal or an
+/// alternation of literals, e.g. `db-1\.example\.com|db-2\.example\.com`.
+fn get_literals(pattern: &str) -> Option<Vec<String>> {
+    let mut literals = vec![String::new()];
+    let mut characters = pattern.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '|' => literals.push(String::new()),
+            // Escaped letters, digits, < and > are classes or assertions
+            '\\' => match characters.next() {
+                Some(escaped) if escaped.is_ascii_punctuation() && !"<>".contains(escaped) => {
+                    literals.last_mut()?.push(escaped)
+                }
+                _ => return None,
+            },
+            '.' | '^' | '$' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' => return None,
+            _ => literals.last_mut()?.push(character),
+        }
+    }
+
+    // An empty alternative matches everywhere
+    if literals.iter().any(String::is_empty) {
+        return None;
+    }
+
+    Some(literals)
 }
 
 impl PartialEq for RegexArgs {
     fn eq(&self, other: &Self) -> bool {
-        if self.regexes.len() != other.regexes.len() {
+        if self.min_length != other.min_length || self.regexes.len() != other.regexes.len() {
             return false;
         }
 
@@ -95,9 +285,13 @@ mod tests {
                 - regex2
         ";
 
-        let Mode { args, hotkey, name } = serde_yaml::from_str(string).unwrap();
+        let Mode {
+            args, hotkey, name, ..
+        } = serde_yaml::from_str(string).unwrap();
 
-        let ModeArgs::RegexMode(regex_args) = args;
+        let ModeArgs::RegexMode(regex_args) = args else {
+            panic!("Deserialized mode is not a regex mode");
+        };
 
         assert_eq!(hotkey, 'r');
         assert_eq!(name, "default");
@@ -105,6 +299,123 @@ mod tests {
         assert_eq!(regex_args.regexes[1].as_str(), "regex2");
     }
 
+    #[test_case("action: copy", Some(SelectionAction::Copy); "when_action_has_no_argument")]
+    #[test_case(
+        "action: {pipe: 'cliphist store'}",
+        Some(SelectionAction::Pipe("cliphist store".to_string()));
+        "when_action_has_argument"
+    )]
+    #[test_case("action: null", None; "when_action_is_null")]
+    #[test_case("", None; "when_action_is_missing")]
+    fn mode_action_can_be_deserialized(action: &str, expected: Option<SelectionAction>) {
+        let string = format!("mode: regex\nhotkey: r\nname: default\nregexes: [a]\n{action}");
+
+        let mode: Mode = serde_yaml::from_str(&string).unwrap();
+
+        assert_eq!(mode.action, expected);
+    }
+
+    #[test]
+    fn regex_mode_min_length_can_be_deserialized() {
+        let string = "
+            mode: regex
+            hotkey: r
+            name: default
+            min_length: 3
+            regexes:
+                - regex1
+        ";
+
+        let Mode { args, .. } = serde_yaml::from_str(string).unwrap();
+
+        let ModeArgs::RegexMode(regex_args) = args else {
+            panic!("Deserialized mode is not a regex mode");
+        };
+
+        assert_eq!(regex_args.min_length, 3);
+    }
+
+    #[test]
+    fn registered_mode_can_be_deserialized() {
+        crate::modes::register_mode("modes_test_registered", &["run"], |_, _, _, _| {
+            Err(crate::error::RunError::Aborted)
+        });
+        let string = "
+            mode: modes_test_registered
+            hotkey: x
+            name: registered
+            keybindings:
+                r: run
+            setting: 3
+        ";
+
+        let Mode { args, .. } = serde_yaml::from_str(string).unwrap();
+
+        let ModeArgs::Registered { name, args } = args else {
+            panic!("Deserialized mode is not a registered mode");
+        };
+        assert_eq!(name, "modes_test_registered");
+        assert_eq!(args.get("setting"), Some(&Value::from(3)));
+    }
+
+    #[test]
+    fn deserialization_fails_if_mode_is_unknown() {
+        let string = "
+            mode: modes_test_unknown
+            hotkey: x
+            name: unknown
+        ";
+
+        let result = serde_yaml::from_str::<Mode>(string);
+        result.unwrap_err();
+    }
+
+    #[test]
+    fn deserialization_error_suggests_closest_mode() {
+        let string = "
+            mode: regx
+            hotkey: x
+            name: unknown
+        ";
+
+        let error = serde_yaml::from_str::<Mode>(string).unwrap_err();
+
+        assert!(error.to_string().contains("did you mean regex?"));
+    }
+
+    #[test]
+    fn keybindings_can_be_deserialized() {
+        let string = "
+            mode: regex
+            hotkey: r
+            name: default
+            keybindings:
+                A: select_all
+            regexes:
+                - regex1
+        ";
+
+        let mode: Mode = serde_yaml::from_str(string).unwrap();
+
+        assert_eq!(mode.keybindings[&KeySequence(vec!['A'])], "select_all");
+    }
+
+    #[test_case("x", Some(vec!['x']))]
+    #[test_case("g g", Some(vec!['g', 'g']))]
+    #[test_case("space  x", Some(vec![' ', 'x']))]
+    #[test_case("", None)]
+    #[test_case("gg", None)]
+    fn key_sequence_is_parsed_correctly(string: &str, expected: Option<Vec<char>>) {
+        assert_eq!(KeySequence::parse(string), expected.map(KeySequence));
+    }
+
+    #[test]
+    fn key_sequence_is_displayed_like_in_config() {
+        let sequence = KeySequence(vec![' ', 'g']);
+
+        assert_eq!(sequence.to_string(), "space g");
+    }
+
     #[test]
     fn deserialization_fails_if_no_regexes_are_provided() {
         let string = "
@@ -136,10 +447,35 @@ mod tests {
         regexes2: Vec<Regex>,
         expected_equal: bool,
     ) {
-        let args1 = RegexArgs { regexes: regexes1 };
-        let args2 = RegexArgs { regexes: regexes2 };
+        let args1 = RegexArgs {
+            regexes: regexes1,
+            min_length: 0,
+            literal_matchers: Default::default(),
+        };
+        let args2 = RegexArgs {
+            regexes: regexes2,
+            min_length: 0,
+            literal_matchers: Default::default(),
+        };
 
         let equal = args1 == args2;
         assert_eq!(equal, expected_equal);
     }
+
+    #[test_case("error", Some(vec!["error"]); "for_literal")]
+    #[test_case("api|db-1|cache", Some(vec!["api", "db-1", "cache"]); "for_alternation")]
+    #[test_case(r"example\.com|a\|b", Some(vec!["example.com", "a|b"]); "for_escaped_characters")]
+    #[test_case(r"error\d", None; "for_escaped_class")]
+    #[test_case(r"\<error", None; "for_word_boundary")]
+    #[test_case("err(or)?", None; "for_group")]
+    #[test_case("a||b", None; "for_empty_alternative")]
+    #[test_case("", None; "for_empty_regex")]
+    fn get_literals_returns_expected_value(pattern: &str, expected: Option<Vec<&str>>) {
+        let literals = get_literals(pattern);
+
+        assert_eq!(
+            literals,
+            expected.map(|literals| literals.into_iter().map(String::from).collect())
+        );
+    }
 }
@@ -0,0 +1,89 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use super::input_filter::deserialize_regex;
+
+/// A shell command that opens the selections of one kind, used by
+/// [super::SelectionAction::Open].
+#[derive(Deserialize, Debug, Clone)]
+pub struct OpenHandler {
+    /// Name of the kind of the selections, e.g. `url`.
+    pub name: String,
+    /// The regex that the selections of this kind match.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    /// The command that opens the selection, with `{}` replaced by the
+    /// selection quoted for the shell.
+    pub command: String,
+}
+
+impl OpenHandler {
+    /// Create a handler from the given values, with the pattern that is
+    /// known to be a valid regex.
+    fn with_known_pattern(name: &str, pattern: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            #[allow(clippy::unwrap_used, reason = "Only used with hardcoded patterns")]
+            pattern: Regex::new(pattern).unwrap(),
+            command: command.to_string(),
+        }
+    }
+
+    /// The handlers used when none are set in the config file.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::with_known_pattern("url", r"^(https?|ftp|file)://\S+$", "xdg-open {}"),
+            Self::with_known_pattern("commit", r"^[0-9a-f]{7,40}$", "git show {}"),
+            Self::with_known_pattern("path", r"^[\w.~/-]*[/.][\w.~/-]*$", "${EDITOR:-vi} {}"),
+        ]
+    }
+
+    /// Find the first of the given handlers whose pattern matches the given
+    /// selection.
+    pub fn find<'a>(handlers: &'a [OpenHandler], selection: &str) -> Option<&'a OpenHandler> {
+        handlers
+            .iter()
+            .find(|handler| handler.pattern.is_match(selection))
+    }
+}
+
+impl PartialEq for OpenHandler {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.pattern.as_str() == other.pattern.as_str()
+            && self.command == other.command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("https://example.com/page", Some("url"); "when_url_is_selected")]
+    #[test_case("4f29ec5", Some("commit"); "when_commit_is_selected")]
+    #[test_case("src/main.rs", Some("path"); "when_path_is_selected")]
+    #[test_case("word", None; "when_nothing_matches")]
+    fn default_handlers_find_expected_handler(selection: &str, expected: Option<&str>) {
+        let handlers = OpenHandler::defaults();
+
+        let handler = OpenHandler::find(&handlers, selection);
+
+        assert_eq!(handler.map(|handler| handler.name.as_str()), expected);
+    }
+
+    #[test]
+    fn handlers_can_be_deserialized() {
+        let string = "
+            - name: issue
+              pattern: '^#\\d+$'
+              command: 'gh issue view {}'
+        ";
+
+        let handlers: Vec<OpenHandler> = serde_yaml::from_str(string).unwrap();
+
+        assert_eq!(handlers[0].name, "issue");
+        assert_eq!(handlers[0].pattern.as_str(), r"^#\d+$");
+        assert_eq!(handlers[0].command, "gh issue view {}");
+    }
+}
@@ -0,0 +1,122 @@
+//! Suggestions of the valid names for the misspelled ones in the config,
+//! added to the errors as "did you mean ...".
+
+/// Get the one of the given candidates that the given name is most likely
+/// a misspelling of, if any of them is close enough.
+pub fn find_suggestion<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    // Allow about one mistake for every three characters
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (get_edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Get the text to add to an error about the given name, suggesting the
+/// closest of the given candidates, or an empty text if none is close.
+pub fn did_you_mean<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    match find_suggestion(name, candidates) {
+        Some(suggestion) => format!(", did you mean {suggestion}?"),
+        None => String::new(),
+    }
+}
+
+/// Get the suggestion for the error message of serde about an unknown
+/// variant or field, which lists the expected ones, e.g.
+/// "unknown variant `frist`, expected `first` or `last`".
+pub fn did_you_mean_in_serde_error(message: &str) -> String {
+    let Some((_, rest)) = message
+        .split_once("unknown variant `")
+        .or_else(|| message.split_once("unknown field `"))
+    else {
+        return String::new();
+    };
+    let Some((name, expected)) = rest.split_once('`') else {
+        return String::new();
+    };
+
+    // The expected names are the ones in backticks
+    let expected_line = expected.lines().next().unwrap_or_default();
+    let candidates = expected_line.split('`').skip(1).step_by(2);
+
+    did_you_mean(name, candidates)
+}
+
+/// Get the number of characters that need to be inserted, removed or
+/// replaced, or of adjacent characters that need to be swapped, to turn
+/// one text into the other (the optimal string alignment distance).
+///
+/// Swapped characters count as a single mistake, since they are one of
+/// the most common typos, e.g. `frist` for `first`.
+fn get_edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // The row before the previous one is needed for the swaps
+    let mut before_previous_row: Vec<usize> = vec![];
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.iter().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous_row[j] + usize::from(a_char != b_char);
+            let remove = previous_row[j + 1] + 1;
+            let insert = row[j] + 1;
+            let mut distance = replace.min(remove).min(insert);
+
+            let is_swapped = i > 0 && j > 0 && *a_char == b[j - 1] && a[i - 1] == *b_char;
+            if is_swapped {
+                distance = distance.min(before_previous_row[j - 1] + 1);
+            }
+            row.push(distance);
+        }
+        before_previous_row = std::mem::replace(&mut previous_row, row);
+    }
+
+    previous_row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("hint_fg", "hint_fg", 0; "for equal texts")]
+    #[test_case("hint_gf", "hint_fg", 1; "for swapped characters")]
+    #[test_case("ab", "ba", 1; "for only swapped characters")]
+    #[test_case("ca", "abc", 3; "for swapped characters with insertion")]
+    #[test_case("hint_f", "hint_fg", 1; "for missing character")]
+    #[test_case("", "abc", 3; "for empty text")]
+    fn get_edit_distance_returns_expected_value(a: &str, b: &str, expected: usize) {
+        assert_eq!(get_edit_distance(a, b), expected);
+    }
+
+    #[test_case("hint_characers", Some("hint_characters"); "for misspelled name")]
+    #[test_case("reg", None; "for too different name")]
+    #[test_case("regx", Some("regex"); "for short name")]
+    fn find_suggestion_returns_expected_value(name: &str, expected: Option<&str>) {
+        let candidates = ["hint_characters", "hint_fg", "regex"];
+
+        assert_eq!(find_suggestion(name, candidates), expected);
+    }
+
+    #[test_case(
+        "hint_order: unknown variant `frist`, expected `first` or `last` at line 1 column 13",
+        ", did you mean first?";
+        "for unknown variant"
+    )]
+    #[test_case(
+        "unknown field `levl`, expected `level` or `modules`",
+        ", did you mean level?";
+        "for unknown field"
+    )]
+    #[test_case("invalid type: integer `1`, expected a string", ""; "for other errors")]
+    fn did_you_mean_in_serde_error_returns_expected_text(message: &str, expected: &str) {
+        assert_eq!(did_you_mean_in_serde_error(message), expected);
+    }
+}
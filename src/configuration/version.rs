@@ -0,0 +1,101 @@
+//! Config file versioning and migration diagnostics.
+use serde_yaml::Value;
+
+use super::config::Error;
+
+/// The version of the config file format that this build understands.
+///
+/// This should be increased whenever a setting is renamed, removed or
+/// its meaning changes, and the change should be recorded in [SETTING_CHANGES].
+pub const CURRENT_CONFIG_VERSION: u64 = 1;
+
+/// Config files without a `version` are considered to have this version.
+const IMPLICIT_CONFIG_VERSION: u64 = 1;
+
+/// Description of an incompatible change of a top level setting.
+pub struct SettingChange {
+    /// The first config version in which the change is present.
+    pub version: u64,
+    /// The name of the setting as it was before the change.
+    pub setting: &'static str,
+    /// Instructions on how to migrate the setting.
+    pub migration: &'static str,
+}
+
+/// All the incompatible changes of the config file format so far.
+const SETTING_CHANGES: &[SettingChange] = &[];
+
+/// Implementation of [check_version] with the setting changes as an
+/// argument to make testing easier.
+fn check_version_impl(value: &Value, setting_changes: &[SettingChange]) -> Result<(), Error> {
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or(IMPLICIT_CONFIG_VERSION);
+
+    if version > CURRENT_CONFIG_VERSION {
+        return Err(Error::UnsupportedVersion { version });
+    }
+
+    let notes: Vec<String> = setting_changes
+        .iter()
+        .filter(|change| change.version > version)
+        .filter(|change| value.get(change.setting).is_some())
+        .map(|change| format!(" - {}: {}", change.setting, change.migration))
+        .collect();
+
+    if !notes.is_empty() {
+        return Err(Error::OutdatedSettings { version, notes });
+    }
+
+    Ok(())
+}
+
+/// Check whether the given config can be interpreted by this version of
+/// the application.
+///
+/// Returns an error with instructions on how to migrate if the config
+/// contains settings that changed since the config version it declares.
+pub fn check_version(value: &Value) -> Result<(), Error> {
+    check_version_impl(value, SETTING_CHANGES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_CHANGES: &[SettingChange] = &[SettingChange {
+        version: 2,
+        setting: "old_setting",
+        migration: "use new_setting instead",
+    }];
+
+    fn check(config: &str) -> Result<(), Error> {
+        let value: Value = serde_yaml::from_str(config).unwrap();
+        check_version_impl(&value, TEST_CHANGES)
+    }
+
+    #[test]
+    fn passes_for_config_without_changed_settings() {
+        check("hint_characters: asdf").unwrap();
+    }
+
+    #[test]
+    fn fails_with_migration_notes_for_changed_settings() {
+        let error = check("old_setting: 5").unwrap_err();
+
+        let Error::OutdatedSettings { version, notes } = error else {
+            panic!("Unexpected error type");
+        };
+
+        assert_eq!(version, IMPLICIT_CONFIG_VERSION);
+        assert_eq!(notes, [" - old_setting: use new_setting instead"]);
+    }
+
+    #[test]
+    fn fails_for_version_newer_than_supported() {
+        let error = check(&format!("version: {}", CURRENT_CONFIG_VERSION + 1)).unwrap_err();
+
+        assert!(matches!(error, Error::UnsupportedVersion { .. }));
+    }
+}
@@ -3,6 +3,7 @@ use crate::configuration;
 use snafu::prelude::*;
 use std::io;
 use std::path::PathBuf;
+use std::process::ExitStatus;
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub))]
@@ -18,23 +19,40 @@ pub enum RunError {
     },
 
     /// The config file could be opened and read, but it's contents are not as expected.
-    #[snafu(display("Could not parse config file {}\n{}", path.display(), source))]
+    #[snafu(display("Could not parse config file {}\n{}{}", path.display(), source, snippet))]
     ConfigParse {
         /// The source error that caused this [RunError].
         source: configuration::Error,
         /// Path of the config file whose parsing failed.
         path: PathBuf,
+        /// The line of the config file with the error and a caret pointing
+        /// to it, or an empty text if the location is not known.
+        snippet: String,
+    },
+
+    /// The config files could be merged, but the merged result is not as expected.
+    #[snafu(display("Could not parse the merged config files\n{}", source))]
+    ConfigMergeParse {
+        /// The source error that caused this [RunError].
+        source: configuration::Error,
     },
 
     /// Could not open the device used to draw the interface.
-    #[snafu(display("Could not open /dev/tty for writing\n{}", source))]
+    #[snafu(display(
+        "Could not open /dev/tty for writing\n{}{}",
+        source,
+        get_terminal_hint(source)
+    ))]
     TtyOpen {
         /// The source error that caused this [RunError].
         source: io::Error,
     },
 
     /// Terminal operation failed
-    #[snafu(display("Terminal operation '{operation}' failed\n{source}"))]
+    #[snafu(display(
+        "Terminal operation '{operation}' failed\n{source}{}",
+        get_terminal_hint(source)
+    ))]
     TerminalHandling {
         /// The source error that caused this [RunError].
         source: io::Error,
@@ -72,6 +90,276 @@ pub enum RunError {
         source: io::Error,
     },
 
-    #[snafu(display("Invalid mode hotkey {}", mode))]
-    InvalidMode { mode: char },
+    /// The command used to get the input could not be run.
+    #[snafu(display("Could not run input command '{command}'\n{source}"))]
+    InputCommand {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// The command that could not be run.
+        command: String,
+    },
+
+    /// The command used to get the input did not finish successfully.
+    #[snafu(display("Input command '{command}' failed with {status}"))]
+    InputCommandFailed {
+        /// The command that failed.
+        command: String,
+        /// The exit status of the command.
+        status: ExitStatus,
+    },
+
+    /// The command that receives the selection could not be run.
+    #[snafu(display("Could not run output command '{command}'\n{source}"))]
+    OutputCommand {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// The command that could not be run.
+        command: String,
+    },
+
+    /// The command that receives the selection did not finish successfully.
+    #[snafu(display("Output command '{command}' failed with {status}"))]
+    OutputCommandFailed {
+        /// The command that failed.
+        command: String,
+        /// The exit status of the command.
+        status: ExitStatus,
+    },
+
+    /// The selection could not be written to the output file.
+    #[snafu(display("Could not write the selection to {}\n{}", path.display(), source))]
+    OutputFile {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the file the selection was being written to.
+        path: PathBuf,
+    },
+
+    /// The selection could not be written to the terminal to copy it to
+    /// the clipboard.
+    #[snafu(display("Could not copy the selection to the clipboard\n{source}"))]
+    ClipboardWrite {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+    },
+
+    /// The server could not listen on its socket.
+    #[snafu(display("Could not listen on socket {}\n{}", path.display(), source))]
+    Server {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the socket.
+        path: PathBuf,
+    },
+
+    /// The selection could not be written to the standard output.
+    #[snafu(display("Could not output the selection\n{source}"))]
+    SelectionOutput {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+    },
+
+    /// The selection could not be typed into the terminal.
+    #[snafu(display("Could not type the selection into the terminal\n{source}"))]
+    TypeSelection {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+    },
+
+    /// Neither `NVIM` nor `VIM_SERVERNAME` is defined, so there is no
+    /// editor to open the selection in.
+    #[snafu(display(
+        "Could not find a running editor, neither NVIM nor VIM_SERVERNAME is defined"
+    ))]
+    NoEditorServer,
+
+    /// The file to open in the editor has control characters in its name,
+    /// which would be sent to the editor as keys, e.g. to run a command.
+    #[snafu(display(
+        "Not opening {file:?} in the editor, since the control characters in it could run commands in the editor"
+    ))]
+    UnsafeFileName { file: String },
+
+    /// The input events could not be recorded.
+    #[snafu(display("Could not record events to {}\n{}", path.display(), source))]
+    Recording {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the file the events were being recorded to.
+        path: PathBuf,
+    },
+
+    /// The file with the recorded events could not be read.
+    #[snafu(display("Could not read recorded events from {}\n{}", path.display(), source))]
+    ReplayOpen {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the file with the recorded events.
+        path: PathBuf,
+    },
+
+    /// The file with the recorded events could be read, but not parsed.
+    #[snafu(display("Could not parse recorded events from {}\n{}", path.display(), source))]
+    ReplayParse {
+        /// The source error that caused this [RunError].
+        source: serde_yaml::Error,
+        /// Path of the file with the recorded events.
+        path: PathBuf,
+    },
+
+    /// All the recorded events were replayed without the selection finishing.
+    #[snafu(display("Replayed all the recorded events without finishing the selection"))]
+    ReplayFinished,
+
+    /// The handler for the force-quit signals could not be registered.
+    #[snafu(display("Could not register signal handler\n{}", source))]
+    SignalHandler {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+    },
+
+    /// The mode given with `--start-in-mode` is neither the hotkey nor the
+    /// name of any of the modes, which are listed as `hotkey (name)`.
+    #[snafu(display(
+        "Invalid mode {mode}, it is neither the hotkey nor the name of any of the modes: {}",
+        available.join(", ")
+    ))]
+    InvalidMode {
+        mode: String,
+        available: Vec<String>,
+    },
+
+    /// Every hotkey the mode for the `--regex` patterns could use is already
+    /// taken by one of the modes of the config.
+    #[snafu(display(
+        "Could not add a mode for --regex, all the hotkeys a-z and 0-9 are taken by the modes in the config"
+    ))]
+    NoFreeHotkey,
+
+    /// The config uses a mode that is neither built in nor registered with
+    /// [crate::modes::register_mode].
+    #[snafu(display("Unknown mode {name}, it is neither built in nor registered"))]
+    UnknownMode { name: String },
+
+    /// The action given with `--action` is neither built in nor defined in
+    /// the config.
+    #[snafu(display("Invalid action {name}, it is neither built in nor defined in actions"))]
+    InvalidAction { name: String },
+
+    /// The color given with one of the color options, e.g. `--hint-fg`,
+    /// cannot be parsed.
+    #[snafu(display("Invalid color {color} given with {option}"))]
+    InvalidColor { option: String, color: String },
+
+    /// Neither `XDG_STATE_HOME` nor `HOME` is defined, so there is nowhere
+    /// to keep the last selection.
+    #[snafu(display("Could not find where to keep the last selection, neither XDG_STATE_HOME nor HOME is defined"))]
+    NoStateDirectory,
+
+    /// The last selection could not be saved.
+    #[snafu(display("Could not save the last selection to {}\n{}", path.display(), source))]
+    LastSelectionSave {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the file where the selection was being saved.
+        path: PathBuf,
+    },
+
+    /// The last selection could not be loaded.
+    #[snafu(display(
+        "Could not load the last selection from {}, make sure that remember_last_selection is enabled in the config file\n{}",
+        path.display(),
+        source
+    ))]
+    LastSelectionLoad {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the file the selection was being loaded from.
+        path: PathBuf,
+    },
+
+    /// Neither `XDG_DATA_HOME` nor `HOME` is defined, so there is nowhere
+    /// to keep the history of the selections.
+    #[snafu(display("Could not find where to keep the selection history, neither XDG_DATA_HOME nor HOME is defined"))]
+    NoDataDirectory,
+
+    /// The selection could not be saved to the history.
+    #[snafu(display("Could not save the selection to the history in {}\n{}", path.display(), source))]
+    HistorySave {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the history file.
+        path: PathBuf,
+    },
+
+    /// The history of the selections could not be loaded.
+    #[snafu(display("Could not load the selection history from {}\n{}", path.display(), source))]
+    HistoryLoad {
+        /// The source error that caused this [RunError].
+        source: io::Error,
+        /// Path of the history file.
+        path: PathBuf,
+    },
+
+    /// The user aborted the selection with the abort key.
+    #[snafu(display("Aborted by the user"))]
+    Aborted,
+
+    /// No file was given and nothing was piped in, so there is nothing to
+    /// select from.
+    #[snafu(display(
+        "Nothing to select from, give a file or pipe the text into mless, e.g. `ls | mless`\n\
+         Set default_input_command in the config file to use a command's output instead\n\
+         See mless --help for more"
+    ))]
+    NoInput,
+
+    /// The text was piped into mless, but it ended without anything in it,
+    /// e.g. because the command before mless failed.
+    #[snafu(display(
+        "The piped input is empty, so there is nothing to select from\n\
+         Check that the command piped into mless prints something, e.g. on its own"
+    ))]
+    EmptyInput,
+}
+
+/// Get the explanation of the likely cause of the given error from opening
+/// or using the terminal, and what to do about it, starting with a newline.
+/// Empty if there is nothing more to say than the error itself.
+fn get_terminal_hint(source: &io::Error) -> &'static str {
+    match (source.kind(), source.raw_os_error()) {
+        // What opening /dev/tty fails with when there is no controlling
+        // terminal, and what the terminal ioctls fail with on a non-terminal
+        (_, Some(libc::ENXIO | libc::ENOTTY)) | (io::ErrorKind::NotFound, _) => {
+            "\nThe selection is drawn in the terminal mless is started from, but it is not \
+             started from one, e.g. when run by a service, cron or a CI job.\n\
+             Run it from a terminal, or use --list-matches or --select N to select without one"
+        }
+        (io::ErrorKind::PermissionDenied, _) => {
+            "\nThe terminal mless is started from belongs to another user, e.g. after su or \
+             sudo.\nRun it as the user that owns the terminal, or from a new terminal"
+        }
+        _ => {
+            "\nThe terminal may not support what mless needs. If TERM is set to something \
+             unusual, try running it with TERM=xterm-256color"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(libc::ENXIO, "not started from one"; "when_there_is_no_terminal")]
+    #[test_case(libc::ENOTTY, "not started from one"; "when_output_is_not_terminal")]
+    #[test_case(libc::EACCES, "another user"; "when_terminal_is_not_accessible")]
+    #[test_case(libc::EIO, "TERM"; "when_cause_is_unknown")]
+    fn terminal_errors_explain_likely_cause(os_error: i32, expected: &str) {
+        let error = RunError::TtyOpen {
+            source: io::Error::from_raw_os_error(os_error),
+        };
+
+        assert!(error.to_string().contains(expected));
+    }
 }
@@ -1,13 +1,34 @@
 //! Hint generation.
+mod numeric_generator;
+pub use numeric_generator::NumericGenerator;
 mod pool_generator;
 pub use pool_generator::HintPoolGenerator;
+mod syllable_generator;
+pub use syllable_generator::SyllableGenerator;
+
+/// The details of the hits that get the same hint, given to a [HintGenerator]
+/// to decide which hint suits them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HintTarget {
+    /// Byte offset of the start of the first of the hits in the data.
+    pub start: usize,
+    /// Length of the hits in bytes.
+    pub length: usize,
+    /// The number of hits that get the hint, e.g. how many times the same
+    /// text was found.
+    pub frequency: usize,
+}
 
 /// The trait that defines structs that can generate hints.
 #[cfg_attr(test, mockall::automock)]
 pub trait HintGenerator {
-    /// Ask the generator to create the requested number of hints.
+    /// Ask the generator to create a hint for each of the given targets,
+    /// ordered from the one that should get the best hint to the one that
+    /// should get the worst.
     ///
-    /// Note that the generator is allowed to return a different
-    /// number of hints than requested.
-    fn create_hints(&self, hint_count: usize) -> Vec<String>;
+    /// The hints are given to the targets in the returned order, except
+    /// that a target keeps its previous hint and skips a hint its text
+    /// starts with. Note that the generator is allowed to return a
+    /// different number of hints than requested.
+    fn create_hints(&self, targets: &[HintTarget]) -> Vec<String>;
 }
@@ -0,0 +1,43 @@
+//! Hint generation from numbers.
+use crate::hints::{HintGenerator, HintTarget};
+
+/// A [HintGenerator] that generates numbers as hints.
+///
+/// The hints are the numbers from 1 up to the number of requested hints,
+/// padded with zeros to the same length, e.g. `1`-`9` or `01`-`42`, so that
+/// no hint is the start of another.
+pub struct NumericGenerator;
+
+impl HintGenerator for NumericGenerator {
+    fn create_hints(&self, targets: &[HintTarget]) -> Vec<String> {
+        let hint_count = targets.len();
+        let width = hint_count.to_string().len();
+
+        (1..=hint_count)
+            .map(|number| format!("{number:0width$}"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod create_hints_tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0, &[])]
+    #[test_case(3, &["1", "2", "3"])]
+    #[test_case(10, &["01", "02", "03", "04", "05", "06", "07", "08", "09", "10"])]
+    fn returns_expected_hints(hint_count: usize, expected: &[&str]) {
+        let hints = NumericGenerator.create_hints(&vec![HintTarget::default(); hint_count]);
+
+        assert_eq!(hints, expected);
+    }
+
+    #[test]
+    fn returns_hints_of_same_length() {
+        let hints = NumericGenerator.create_hints(&[HintTarget::default(); 120]);
+
+        assert_eq!(hints.len(), 120);
+        assert!(hints.iter().all(|hint| hint.len() == 3));
+    }
+}
@@ -1,31 +1,85 @@
 //! Hint generation from a pool of characters.
-use crate::hints::HintGenerator;
+use crate::hints::{HintGenerator, HintTarget};
 
 /// A [HintGenerator] that generates hints from a character pool.
 ///
 /// It generates hints with one or two characters and attempts to
 /// represent them with the least total number of characters.
-/// The maximum number of hints it can provide is equal to the square
-/// of hint pool size.
+/// If more hints are requested than the square of the hint pool size,
+/// the hints get as many characters as needed to represent them all.
+///
+/// Alternatively, it can be asked to generate hints with a fixed length,
+/// in which case the maximum number of hints is the hint pool size to
+/// the power of the length.
+///
+/// The hints with the characters at the beginning of the pool come first,
+/// so they go to the hits that get their hints first.
 pub struct HintPoolGenerator {
     hint_pool: String,
+    fixed_length: Option<usize>,
+    left_hand_characters: Option<String>,
 }
 
 impl HintPoolGenerator {
     /// Create a new [HintPoolGenerator] with the given character pool.
-    pub fn new(hint_character_pool: &str) -> Self {
+    ///
+    /// If `fixed_length` is provided, all the generated hints will have
+    /// that number of characters. If `left_hand_characters` are provided,
+    /// the longer hints typed with both hands come before the ones typed
+    /// with a single hand.
+    pub fn new(
+        hint_character_pool: &str,
+        fixed_length: Option<usize>,
+        left_hand_characters: Option<&str>,
+    ) -> Self {
         Self {
             hint_pool: hint_character_pool.to_string(),
+            fixed_length,
+            left_hand_characters: left_hand_characters.map(str::to_string),
         }
     }
+
+    /// Create the requested number of hints that all have the given length.
+    ///
+    /// The hints are created in the order of the hint pool, i.e. the hints
+    /// whose characters are at the beginning of the pool come first.
+    fn create_fixed_length_hints(&self, hint_count: usize, length: usize) -> Vec<String> {
+        let pool: Vec<char> = self.hint_pool.chars().collect();
+
+        let representable_hints = u32::try_from(length)
+            .ok()
+            .and_then(|length| pool.len().checked_pow(length))
+            .unwrap_or(usize::MAX);
+
+        // Every hint is the representation of its index in the numeral
+        // system whose digits are the characters of the pool
+        (0..hint_count.min(representable_hints))
+            .map(|mut index| {
+                let mut hint = vec![pool[0]; length];
+
+                for position in (0..length).rev() {
+                    hint[position] = pool[index % pool.len()];
+                    index /= pool.len();
+                }
+
+                hint.into_iter().collect()
+            })
+            .collect()
+    }
 }
 
 impl HintGenerator for HintPoolGenerator {
-    fn create_hints(&self, hint_count: usize) -> Vec<String> {
+    fn create_hints(&self, targets: &[HintTarget]) -> Vec<String> {
+        let hint_count = targets.len();
+
         if self.hint_pool.is_empty() {
             return vec![];
         }
 
+        if let Some(length) = self.fixed_length {
+            return self.create_fixed_length_hints(hint_count, length);
+        }
+
         let hint_pool_size = self.hint_pool.chars().count();
 
         if self.hint_pool.len() >= hint_count {
@@ -37,51 +91,83 @@ impl HintGenerator for HintPoolGenerator {
                 .collect();
         }
 
+        // The longest hints need as many characters as it takes for all
+        // the hints with that many characters to cover the requested number
+        let mut long_hint_length = 2;
+        while hint_pool_size > 1
+            && hint_pool_size
+                .checked_pow(long_hint_length)
+                .is_some_and(|representable_hints| representable_hints < hint_count)
+        {
+            long_hint_length += 1;
+        }
+
+        // All the shorter hints have one character less than the longest ones
+        let prefixes = self.create_fixed_length_hints(usize::MAX, long_hint_length as usize - 1);
+
         // In order for it to be possible to distinguish different hints
-        // while the user is typing the characters, a characters that is
-        // starting a hint with one character cannot be used to start a
-        // hint with two characters and vice versa.
+        // while the user is typing the characters, a prefix that is a
+        // short hint cannot be used to start a long hint and vice versa.
         //
-        // Using a character for a one string hint allows representing
-        // just one hint. Using a character to start a two character hint
-        // allows representing number of hints equal to the pool size.
+        // Using a prefix for a short hint allows representing just one
+        // hint. Using a prefix to start a long hint allows representing
+        // number of hints equal to the pool size.
         //
-        // Try to use every character to start a one character hint and
-        // decrease the number of one character hints until the requested
-        // number of hints can be represented.
-        let chars_starting_one_char_hint = (0..=hint_pool_size)
+        // Try to use every prefix as a short hint and decrease the number
+        // of short hints until the requested number of hints can be
+        // represented.
+        let short_hint_count = (0..=prefixes.len())
             .rev()
-            .find(|one_char_hints| {
-                let two_char_hints = hint_pool_size - one_char_hints;
-                let representable_hints = one_char_hints + two_char_hints * hint_pool_size;
+            .find(|short_hints| {
+                let long_hint_prefixes = prefixes.len() - short_hints;
+                let representable_hints = short_hints + long_hint_prefixes * hint_pool_size;
 
                 representable_hints >= hint_count
             })
             .unwrap_or(0);
 
-        let one_char_hints = self
-            .hint_pool
-            .chars()
-            .take(chars_starting_one_char_hint)
-            .map(|char| char.to_string());
-
-        let generate_hints_starting_with = |starting_with| {
+        let generate_hints_starting_with = |starting_with: &String| {
             self.hint_pool
                 .chars()
                 .map(move |char| format!("{}{}", starting_with, char))
+                .collect::<Vec<String>>()
         };
 
-        let two_char_hints = self
-            .hint_pool
-            .chars()
-            .skip(chars_starting_one_char_hint)
+        let mut long_hints: Vec<String> = prefixes
+            .iter()
+            .skip(short_hint_count)
             .flat_map(generate_hints_starting_with)
-            .take(hint_count - chars_starting_one_char_hint);
+            .collect();
 
-        one_char_hints.chain(two_char_hints).collect()
+        if let Some(left_hand_characters) = &self.left_hand_characters {
+            // The sort is stable, so the pool order is kept otherwise
+            long_hints.sort_by_key(|hint| is_typed_with_one_hand(hint, left_hand_characters));
+        }
+
+        prefixes
+            .into_iter()
+            .take(short_hint_count)
+            .chain(
+                long_hints
+                    .into_iter()
+                    .take(hint_count.saturating_sub(short_hint_count)),
+            )
+            .collect()
     }
 }
 
+/// Check whether all the characters of the given hint with more than one
+/// character are typed with the same hand, according to the given characters
+/// typed with the left hand.
+fn is_typed_with_one_hand(hint: &str, left_hand_characters: &str) -> bool {
+    let hands: Vec<bool> = hint
+        .chars()
+        .map(|char| left_hand_characters.contains(char.to_ascii_lowercase()))
+        .collect();
+
+    hands.len() > 1 && hands.iter().all(|hand| *hand == hands[0])
+}
+
 #[cfg(test)]
 mod create_hints_tests {
     use super::*;
@@ -90,8 +176,8 @@ mod create_hints_tests {
     #[test_case("", 5)]
     #[test_case("asdfgjkl", 0)]
     fn returns_empty_vector_for_empty_inputs(pool: &str, hint_count: usize) {
-        let generator = HintPoolGenerator::new(pool);
-        let hints = generator.create_hints(hint_count);
+        let generator = HintPoolGenerator::new(pool, None, None);
+        let hints = generator.create_hints(&vec![HintTarget::default(); hint_count]);
 
         assert!(hints.is_empty())
     }
@@ -110,8 +196,8 @@ mod create_hints_tests {
         expected_one_char_hints: usize,
         expected_two_char_hints: usize,
     ) {
-        let generator = HintPoolGenerator::new(pool);
-        let hints = generator.create_hints(hint_count);
+        let generator = HintPoolGenerator::new(pool, None, None);
+        let hints = generator.create_hints(&vec![HintTarget::default(); hint_count]);
 
         assert_eq!(hints.len(), hint_count);
 
@@ -133,14 +219,86 @@ mod create_hints_tests {
         }
     }
 
+    #[test_case("asd", 10, 8, 2, &["aa", "as", "ad", "sa", "ss", "sd", "da", "ds", "dda", "dds"])]
+    #[test_case("asd", 27, 0, 27, &[])]
+    #[test_case("as", 5, 3, 2, &["aa", "as", "sa", "ssa", "sss"])]
+    fn returns_longer_hints_if_needed(
+        pool: &str,
+        hint_count: usize,
+        expected_two_char_hints: usize,
+        expected_three_char_hints: usize,
+        expected_start: &[&str],
+    ) {
+        let generator = HintPoolGenerator::new(pool, None, None);
+        let hints = generator.create_hints(&vec![HintTarget::default(); hint_count]);
+
+        assert_eq!(hints.len(), hint_count);
+        assert_eq!(&hints[..expected_start.len()], expected_start);
+
+        let two_char_hints = hints
+            .iter()
+            .filter(|hint| hint.chars().count() == 2)
+            .count();
+        assert_eq!(two_char_hints, expected_two_char_hints);
+
+        let three_char_hints = hints
+            .iter()
+            .filter(|hint| hint.chars().count() == 3)
+            .count();
+        assert_eq!(three_char_hints, expected_three_char_hints);
+
+        for hint in &hints {
+            let is_prefix_of_other_hint = hints
+                .iter()
+                .any(|other| other != hint && other.starts_with(hint.as_str()));
+            assert!(!is_prefix_of_other_hint);
+        }
+    }
+
     #[test]
     fn returns_fewer_hints_if_not_all_can_be_represented() {
-        let pool = "asd";
-        let max_hints = pool.chars().count() * pool.chars().count();
+        let generator = HintPoolGenerator::new("a", None, None);
+        let hints = generator.create_hints(&[HintTarget::default(); 5]);
 
-        let generator = HintPoolGenerator::new(pool);
-        let hints = generator.create_hints(999);
+        assert_eq!(hints, vec!["aa"]);
+    }
+
+    #[test_case("asd", 1, 2, &["aa"])]
+    #[test_case("asd", 4, 2, &["aa", "as", "ad", "sa"])]
+    #[test_case("asd", 2, 3, &["aaa", "aas"])]
+    #[test_case("asd", 2, 1, &["a", "s"])]
+    fn returns_hints_of_fixed_length_when_requested(
+        pool: &str,
+        hint_count: usize,
+        length: usize,
+        expected: &[&str],
+    ) {
+        let generator = HintPoolGenerator::new(pool, Some(length), None);
+        let hints = generator.create_hints(&vec![HintTarget::default(); hint_count]);
+
+        assert_eq!(hints, expected);
+    }
+
+    #[test_case(10, &["a", "s", "d", "ja", "js", "jd", "ka", "ks", "kd", "jj"]; "when_some_hints_have_one_character")]
+    #[test_case(20, &[
+        "a", "sj", "sk", "dj", "dk", "ja", "js", "jd", "ka", "ks", "kd",
+        "sa", "ss", "sd", "da", "ds", "dd", "jj", "jk", "kj",
+    ]; "when_most_hints_have_two_characters")]
+    fn returns_hints_typed_with_both_hands_first_when_requested(
+        hint_count: usize,
+        expected: &[&str],
+    ) {
+        let generator = HintPoolGenerator::new("asdjk", None, Some("asd"));
+        let hints = generator.create_hints(&vec![HintTarget::default(); hint_count]);
+
+        assert_eq!(hints, expected);
+    }
+
+    #[test]
+    fn returns_fewer_fixed_length_hints_if_not_all_can_be_represented() {
+        let generator = HintPoolGenerator::new("asd", Some(2), None);
+        let hints = generator.create_hints(&[HintTarget::default(); 999]);
 
-        assert_eq!(hints.len(), max_hints);
+        assert_eq!(hints.len(), 9);
     }
 }
@@ -0,0 +1,75 @@
+//! Hint generation from syllables.
+use crate::hints::{HintGenerator, HintTarget};
+
+/// The consonants that start the syllables.
+const CONSONANTS: &str = "kstnmrdbfghjlpvz";
+
+/// The vowels that end the syllables.
+const VOWELS: &str = "aeiou";
+
+/// A [HintGenerator] that generates pronounceable hints.
+///
+/// Every hint is made of syllables with a consonant followed by a vowel,
+/// e.g. `ka` or `tobe`, which makes them easy to read out and remember.
+/// All the hints have the same number of syllables, as few as needed
+/// for the requested number of hints, so no hint is the start of another.
+pub struct SyllableGenerator;
+
+impl SyllableGenerator {
+    /// Get all the syllables in the order they are used.
+    fn syllables() -> Vec<String> {
+        CONSONANTS
+            .chars()
+            .flat_map(|consonant| {
+                VOWELS
+                    .chars()
+                    .map(move |vowel| format!("{consonant}{vowel}"))
+            })
+            .collect()
+    }
+}
+
+impl HintGenerator for SyllableGenerator {
+    fn create_hints(&self, targets: &[HintTarget]) -> Vec<String> {
+        let hint_count = targets.len();
+        let syllables = Self::syllables();
+
+        let mut hints = syllables.clone();
+        while hints.len() < hint_count {
+            hints = hints
+                .iter()
+                .flat_map(|hint| {
+                    syllables
+                        .iter()
+                        .map(move |syllable| format!("{hint}{syllable}"))
+                })
+                .collect();
+        }
+
+        hints.truncate(hint_count);
+        hints
+    }
+}
+
+#[cfg(test)]
+mod create_hints_tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0, &[])]
+    #[test_case(3, &["ka", "ke", "ki"])]
+    fn returns_expected_hints(hint_count: usize, expected: &[&str]) {
+        let hints = SyllableGenerator.create_hints(&vec![HintTarget::default(); hint_count]);
+
+        assert_eq!(hints, expected);
+    }
+
+    #[test]
+    fn returns_hints_of_same_length_when_one_syllable_is_not_enough() {
+        let hints = SyllableGenerator.create_hints(&[HintTarget::default(); 100]);
+
+        assert_eq!(hints.len(), 100);
+        assert_eq!(hints[0], "kaka");
+        assert!(hints.iter().all(|hint| hint.len() == 4));
+    }
+}
@@ -1,25 +1,205 @@
 //! Handling of input events before they are delivered to the current mode.
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::{collections::HashMap, mem, time::Duration};
 
-use crate::configuration::Config;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use log::debug;
+
+use crate::{
+    configuration::{Config, KeySequence, MouseWheelScroll},
+    logging,
+};
 
 /// Handle the input from the user.
 ///
 /// The two main reasons for the existence of this struct are:
 /// 1. To provide a layer of input handling that does not depend on the current mode.
 /// 2. To translate [crossterm] specific events into the the format used in this application.
-pub struct InputHandler {}
+pub struct InputHandler {
+    /// The key that triggers [Action::GoToModeSelection]. If [None], mode
+    /// selection cannot be triggered and the key is forwarded to the mode.
+    mode_selection_key: Option<char>,
+    /// The key that triggers [Action::ToggleHelp]. If [None], help cannot
+    /// be shown and the key is forwarded to the mode.
+    help_key: Option<char>,
+    /// The key that triggers [Action::Abort]. If [None], the key is
+    /// forwarded to the mode.
+    abort_key: Option<char>,
+    /// The key that triggers [Action::TogglePeek]. If [None], the key is
+    /// forwarded to the mode.
+    peek_key: Option<char>,
+    /// The key that triggers [Action::SwitchToReturnMode]. If [None], the
+    /// key is forwarded to the mode.
+    return_mode_key: Option<char>,
+    /// The key that triggers [Action::StartSearch]. If [None], the key is
+    /// forwarded to the mode.
+    search_key: Option<char>,
+    /// The key that triggers [Action::SwitchToNextFile]. If [None], the key
+    /// is forwarded to the mode.
+    next_file_key: Option<char>,
+    /// The key that triggers [Action::SwitchToPreviousFile]. If [None], the
+    /// key is forwarded to the mode.
+    previous_file_key: Option<char>,
+    /// The key that triggers [Action::ToggleWrapLines]. If [None], the key
+    /// is forwarded to the mode.
+    wrap_toggle_key: Option<char>,
+    /// The key that triggers [Action::ScrollToHitPage] forward. If [None],
+    /// the key is forwarded to the mode.
+    next_hit_page_key: Option<char>,
+    /// The key that triggers [Action::ScrollToHitPage] backward. If [None],
+    /// the key is forwarded to the mode.
+    previous_hit_page_key: Option<char>,
+    /// The key that triggers [Action::Refresh]. If [None], the key is
+    /// forwarded to the mode.
+    refresh_key: Option<char>,
+    /// The key that triggers [Action::StartSelection] while paging.
+    select_key: Option<char>,
+    /// The key that triggers [Action::ToggleCollecting]. If [None], the key
+    /// is forwarded to the mode.
+    collect_key: Option<char>,
+    /// The key that triggers [Action::ScrollToTop], in addition to Home.
+    scroll_to_top_key: Option<char>,
+    /// The key that triggers [Action::ScrollToBottom], in addition to End.
+    scroll_to_bottom_key: Option<char>,
+    /// The key that triggers [Action::ToggleDebugOverlay]. If [None], the
+    /// key is forwarded to the mode.
+    debug_overlay_key: Option<char>,
+    /// Whether key presses with alt held result in [Action::RedirectKeyPress].
+    alt_redirects_output: bool,
+    /// What scrolling the mouse wheel does.
+    mouse_wheel_scroll: MouseWheelScroll,
+    /// Whether h, j, k and l keys move the focus like the arrow keys.
+    hjkl_navigation: bool,
+    /// Whether tab and shift+tab switch between the modes.
+    mode_cycling: bool,
+    /// Keybindings of every mode, in the same order as the modes in the config.
+    mode_keybindings: Vec<HashMap<KeySequence, String>>,
+    /// Index of the current mode whose keybindings are used, if any.
+    current_mode: Option<usize>,
+    /// Keys of a key sequence from the keybindings that was started but
+    /// not finished yet.
+    pending_keys: Vec<char>,
+    /// Time to wait for the next key of a started key sequence.
+    key_sequence_timeout: Duration,
+    /// Whether text is being typed (e.g. a search query), in which case
+    /// all the keys without a fixed meaning are forwarded as they are.
+    text_input: bool,
+    /// Whether the input is only being read like in a pager, in which case
+    /// the usual pager keys scroll instead of going to the mode.
+    paging: bool,
+}
+
+/// How the pressed keys match the key sequences in the keybindings.
+enum SequenceMatch {
+    /// The keys form a key sequence bound to the given command, and are
+    /// not the start of any other key sequence.
+    Command(String),
+    /// The keys are the start of at least one key sequence.
+    Pending,
+    /// The keys are not the start of any key sequence.
+    NoMatch,
+}
+
+/// Direction in which to move something on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
 
 /// Representation of a key press that is delivered to the rest of the application.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct KeyPress {
     /// The key that was pressed.
-    ///
-    /// Note that this is currently only convenient for representing keys
-    /// associated with a specific character (e.g. `'a'`, `'A'`). It will need
-    /// to be modified if keys with modifiers (`Ctrl`, `Alt`) or keys that don't have
-    /// a good one-character representation (`F1`, `Backspace`) need to be supported.
-    pub key: char,
+    pub key: Key,
+    /// The modifiers that were held while the key was pressed.
+    pub modifiers: Modifiers,
+}
+
+/// A key that can be delivered to the rest of the application in a [KeyPress].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A key associated with a specific character (e.g. `'a'`, `'A'`).
+    Char(char),
+    /// The tab key.
+    Tab,
+    /// The tab key pressed together with shift.
+    BackTab,
+    /// A function key with the given number (e.g. `1` for F1).
+    F(u8),
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Key {
+    /// Get the [Key] corresponding to the given [crossterm] key code, if
+    /// the key is supported.
+    fn from_key_code(code: KeyCode) -> Option<Self> {
+        let key = match code {
+            KeyCode::Char(char) => Key::Char(char),
+            KeyCode::Tab => Key::Tab,
+            KeyCode::BackTab => Key::BackTab,
+            KeyCode::F(number) => Key::F(number),
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            _ => return None,
+        };
+
+        Some(key)
+    }
+}
+
+/// The modifier keys held during a [KeyPress].
+///
+/// Note that shift is not reported for [Key::Char] consistently across
+/// terminals, the character itself (e.g. `'A'`) should be used instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl KeyPress {
+    /// Create a [KeyPress] of the key with the given character without any modifiers.
+    pub fn from_char(key: char) -> Self {
+        Self {
+            key: Key::Char(key),
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// Get the character of the key if it is a [Key::Char] pressed without
+    /// ctrl or alt, i.e. a key press that would normally type the character.
+    pub fn typed_char(&self) -> Option<char> {
+        match self.key {
+            Key::Char(char) if !self.modifiers.ctrl && !self.modifiers.alt => Some(char),
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyModifiers> for Modifiers {
+    fn from(modifiers: KeyModifiers) -> Self {
+        Self {
+            ctrl: modifiers.contains(KeyModifiers::CONTROL),
+            alt: modifiers.contains(KeyModifiers::ALT),
+            shift: modifiers.contains(KeyModifiers::SHIFT),
+        }
+    }
 }
 
 /// The action that resulted from the input.
@@ -27,34 +207,211 @@ pub struct KeyPress {
 pub enum Action {
     /// Exit the application without providing any selected text.
     Exit,
+    /// Exit the application with an error signaling that the user
+    /// declined to select anything.
+    Abort,
     /// Forward the given [KeyPress] to the active [crate::modes::Mode].
     ForwardKeyPress(KeyPress),
+    /// Forward the given [KeyPress] to the active [crate::modes::Mode] and
+    /// output the text it selects, if any, with the alt output command.
+    RedirectKeyPress(KeyPress),
+    /// Ask the active [crate::modes::Mode] to undo the last [KeyPress].
+    EraseKeyPress,
+    /// Ask the active [crate::modes::Mode] to undo all the key presses
+    /// or exit like [Action::Exit] if there is nothing to undo.
+    ClearOrExit,
     /// Terminal changed size
     Resize,
+    /// Rebuild the active [crate::modes::Mode] against the current page,
+    /// dropping any typed keys and the focus, but keeping the hints.
+    Rematch,
     /// Go to a state where the user can choose to switch the mode
     GoToModeSelection,
+    /// Switch to the mode that comes after the current one in the config.
+    SwitchToNextMode,
+    /// Switch to the mode that comes before the current one in the config.
+    SwitchToPreviousMode,
+    /// Switch back to the mode that was active before the current one.
+    SwitchToReturnMode,
+    /// Show the file that comes after the current one in the arguments.
+    SwitchToNextFile,
+    /// Show the file that comes before the current one in the arguments.
+    SwitchToPreviousFile,
+    /// Ask the active [crate::modes::Mode] to run the command with the given name.
+    RunModeCommand(String),
+    /// No input arrived within the time requested by the active [crate::modes::Mode].
+    KeyPressTimeout,
+    /// Scroll the input by the given number of lines, negative for up.
+    ScrollLines(isize),
+    /// Scroll the input by the given number of pages, negative for up.
+    ScrollPages(isize),
+    /// Open the prompt for searching the input.
+    StartSearch,
+    /// Scroll by pages in the given direction, negative for up, until a page
+    /// with anything to select in the active [crate::modes::Mode] is shown.
+    ScrollToHitPage(isize),
+    /// Scroll to the start of the input.
+    ScrollToTop,
+    /// Scroll to the end of the input.
+    ScrollToBottom,
+    /// Ask the active [crate::modes::Mode] to move the focus in the given direction.
+    MoveFocus(Direction),
+    /// Ask the active [crate::modes::Mode] to select whatever has the focus.
+    AcceptFocused,
+    /// Switch between wrapping and truncating the lines longer than the screen width.
+    ToggleWrapLines,
+    /// Show the help dialog if it is hidden or hide it if it is shown.
+    ToggleHelp,
+    /// Hide everything drawn over the data if it is shown or show it
+    /// again if it is hidden.
+    TogglePeek,
+    /// Run the command the input came from again and use its new output.
+    Refresh,
+    /// Stop paging and start selecting with the active [crate::modes::Mode].
+    StartSelection,
+    /// Start collecting the selections instead of finishing with the first
+    /// one, or finish with all the collected ones if already collecting.
+    ToggleCollecting,
+    /// Show the debug overlay if it is hidden or hide it if it is shown.
+    ToggleDebugOverlay,
 }
 
 impl InputHandler {
     /// Create an [InputHandler] by using the relevant parts of the given config.
-    pub fn from_config(_config: &Config) -> InputHandler {
-        InputHandler {}
+    ///
+    /// The debug overlay key is only used if logging is already initialized.
+    pub fn from_config(config: &Config) -> InputHandler {
+        InputHandler {
+            mode_selection_key: config.mode_selection_key,
+            help_key: config.help_key,
+            abort_key: config.abort_key,
+            peek_key: config.peek_key,
+            return_mode_key: config.return_mode_key,
+            search_key: config.search_key,
+            next_file_key: config.next_file_key,
+            previous_file_key: config.previous_file_key,
+            wrap_toggle_key: config.wrap_toggle_key,
+            next_hit_page_key: config.next_hit_page_key,
+            previous_hit_page_key: config.previous_hit_page_key,
+            refresh_key: config.refresh_key,
+            select_key: config.select_key,
+            collect_key: config.collect_key,
+            scroll_to_top_key: config.scroll_to_top_key,
+            scroll_to_bottom_key: config.scroll_to_bottom_key,
+            debug_overlay_key: config.debug_overlay_key.filter(|_| logging::is_enabled()),
+            alt_redirects_output: config.alt_output_command.is_some(),
+            mouse_wheel_scroll: config.mouse_wheel_scroll,
+            hjkl_navigation: config.hjkl_navigation,
+            mode_cycling: config.mode_cycling,
+            mode_keybindings: config
+                .modes
+                .iter()
+                .map(|mode| mode.keybindings.clone())
+                .collect(),
+            current_mode: None,
+            pending_keys: vec![],
+            key_sequence_timeout: Duration::from_millis(config.key_sequence_timeout_ms),
+            text_input: false,
+            paging: false,
+        }
+    }
+
+    /// Set whether text is being typed. While it is, every key without a
+    /// fixed meaning (e.g. Enter or Esc) results in [Action::ForwardKeyPress],
+    /// regardless of the configured keys and keybindings.
+    pub fn set_text_input(&mut self, text_input: bool) {
+        self.text_input = text_input;
+        self.pending_keys.clear();
+    }
+
+    /// Set whether the input is only being read like in a pager. While it is,
+    /// the keys used for scrolling in less (e.g. Space, b, j or k) scroll
+    /// the input and [Config::select_key] results in [Action::StartSelection].
+    pub fn set_paging(&mut self, paging: bool) {
+        self.paging = paging;
+        self.pending_keys.clear();
+    }
+
+    /// Set the index of the current mode, whose keybindings should be used.
+    /// [None] means that no mode specific keybindings should be used.
+    pub fn set_current_mode(&mut self, mode_index: Option<usize>) {
+        self.current_mode = mode_index;
+        self.pending_keys.clear();
+    }
+
+    /// Get the time to wait for the next input event before calling
+    /// [InputHandler::get_timeout_action]. [None] means that the input
+    /// handler is not waiting for anything.
+    pub fn get_timeout(&self) -> Option<Duration> {
+        match self.pending_keys.is_empty() {
+            true => None,
+            false => Some(self.key_sequence_timeout),
+        }
+    }
+
+    /// Get the [Action] (if any) resulting from no input event arriving in time.
+    ///
+    /// If a key sequence was started, it is finished with the keys pressed
+    /// so far. Otherwise, the timeout is reported with [Action::KeyPressTimeout].
+    pub fn get_timeout_action(&mut self) -> Option<Action> {
+        if self.pending_keys.is_empty() {
+            return Some(Action::KeyPressTimeout);
+        }
+
+        let sequence = KeySequence(mem::take(&mut self.pending_keys));
+        debug!("Key sequence {sequence} timed out");
+
+        self.get_mode_command(&sequence)
+            .map(|command| Action::RunModeCommand(command.clone()))
     }
 
     /// Get the [Action] (if any) resulting from the given input event.
-    pub fn get_action(&self, event: Event) -> Option<Action> {
+    pub fn get_action(&mut self, event: Event) -> Option<Action> {
         match event {
             Event::Resize(_, _) => Some(Action::Resize),
             Event::Key(key) => self.get_key_action(key),
+            Event::Mouse(mouse) => self.get_mouse_action(mouse),
             _ => None,
         }
     }
 
+    /// Get the [Action] (if any) resulting from the given mouse event.
+    fn get_mouse_action(&self, mouse: MouseEvent) -> Option<Action> {
+        let direction = match mouse.kind {
+            MouseEventKind::ScrollUp => -1,
+            MouseEventKind::ScrollDown => 1,
+            _ => return None,
+        };
+
+        match self.mouse_wheel_scroll {
+            MouseWheelScroll::Off => None,
+            MouseWheelScroll::Line => Some(Action::ScrollLines(direction)),
+            MouseWheelScroll::Page => Some(Action::ScrollPages(direction)),
+        }
+    }
+
     /// Get the [Action] (if any) resulting from the given key press.
     ///
     /// This handles specifically key actions and not mouse actions, window
     /// resize or similar.
-    fn get_key_action(&self, key: KeyEvent) -> Option<Action> {
+    fn get_key_action(&mut self, key: KeyEvent) -> Option<Action> {
+        if !self.pending_keys.is_empty() {
+            let mut keys = mem::take(&mut self.pending_keys);
+
+            match key {
+                KeyEvent {
+                    code: KeyCode::Char(char),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    ..
+                } => {
+                    keys.push(char);
+                    return self.continue_key_sequence(keys);
+                }
+                // Any other key cancels the sequence and is handled as usual
+                _ => debug!("Key sequence {} cancelled", KeySequence(keys)),
+            }
+        }
+
         match key {
             KeyEvent {
                 code: KeyCode::Char('c'),
@@ -62,14 +419,807 @@ impl InputHandler {
                 ..
             } => Some(Action::Exit),
             KeyEvent {
-                code: KeyCode::Char(' '),
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => Some(Action::Rematch),
+            KeyEvent {
+                code: KeyCode::Char('e'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => Some(Action::ScrollLines(1)),
+            KeyEvent {
+                code: KeyCode::Char('y'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => Some(Action::ScrollLines(-1)),
+            KeyEvent {
+                code: KeyCode::PageDown,
+                ..
+            } => Some(Action::ScrollPages(1)),
+            KeyEvent {
+                code: KeyCode::PageUp,
                 ..
-            } => Some(Action::GoToModeSelection),
+            } => Some(Action::ScrollPages(-1)),
             KeyEvent {
-                code: KeyCode::Char(key),
+                code: KeyCode::Backspace,
                 ..
-            } => Some(Action::ForwardKeyPress(KeyPress { key })),
+            } => Some(Action::EraseKeyPress),
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            } => Some(Action::ClearOrExit),
+            KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            } => Some(Action::AcceptFocused),
+            KeyEvent {
+                code, modifiers, ..
+            } => self.get_key_press_action(code, modifiers.into()),
+        }
+    }
+
+    /// Get the command bound to the given key sequence in the current mode, if any.
+    fn get_mode_command(&self, sequence: &KeySequence) -> Option<&String> {
+        self.mode_keybindings
+            .get(self.current_mode?)
+            .and_then(|keybindings| keybindings.get(sequence))
+    }
+
+    /// Check how the given keys match the key sequences of the current mode.
+    fn match_key_sequence(&self, keys: &[char]) -> SequenceMatch {
+        let Some(keybindings) = self
+            .current_mode
+            .and_then(|mode| self.mode_keybindings.get(mode))
+        else {
+            return SequenceMatch::NoMatch;
+        };
+
+        let is_prefix = keybindings
+            .keys()
+            .any(|sequence| sequence.0.len() > keys.len() && sequence.0.starts_with(keys));
+        if is_prefix {
+            return SequenceMatch::Pending;
+        }
+
+        match keybindings.get(&KeySequence(keys.to_vec())) {
+            Some(command) => SequenceMatch::Command(command.clone()),
+            None => SequenceMatch::NoMatch,
+        }
+    }
+
+    /// Get the [Action] (if any) resulting from adding a key to a started
+    /// key sequence.
+    fn continue_key_sequence(&mut self, keys: Vec<char>) -> Option<Action> {
+        match self.match_key_sequence(&keys) {
+            SequenceMatch::Command(command) => Some(Action::RunModeCommand(command)),
+            SequenceMatch::Pending => {
+                self.pending_keys = keys;
+                None
+            }
+            SequenceMatch::NoMatch => {
+                debug!(
+                    "Key sequence {} is not bound, discarding",
+                    KeySequence(keys)
+                );
+                None
+            }
+        }
+    }
+
+    /// Get the [Action] resulting from a key press while paging, if the key
+    /// has a meaning in a pager.
+    fn get_paging_action(&self, key_press: KeyPress) -> Option<Action> {
+        match key_press.key {
+            Key::Up => return Some(Action::ScrollLines(-1)),
+            Key::Down => return Some(Action::ScrollLines(1)),
+            _ => (),
+        }
+
+        match key_press.typed_char()? {
+            char if Some(char) == self.select_key => Some(Action::StartSelection),
+            ' ' | 'f' => Some(Action::ScrollPages(1)),
+            'b' => Some(Action::ScrollPages(-1)),
+            'j' | 'e' => Some(Action::ScrollLines(1)),
+            'k' | 'y' => Some(Action::ScrollLines(-1)),
+            'g' => Some(Action::ScrollToTop),
+            'G' => Some(Action::ScrollToBottom),
             _ => None,
         }
     }
+
+    /// Get the [Action] (if any) resulting from a key press that does not
+    /// have a fixed meaning regardless of the configuration.
+    fn get_key_press_action(&mut self, code: KeyCode, modifiers: Modifiers) -> Option<Action> {
+        let key = Key::from_key_code(code)?;
+        let key_press = KeyPress { key, modifiers };
+
+        if self.text_input {
+            return Some(Action::ForwardKeyPress(key_press));
+        }
+
+        if self.paging {
+            if let Some(action) = self.get_paging_action(key_press) {
+                return Some(action);
+            }
+        }
+
+        match key {
+            Key::Tab if self.mode_cycling => return Some(Action::SwitchToNextMode),
+            Key::BackTab if self.mode_cycling => return Some(Action::SwitchToPreviousMode),
+            Key::Home => return Some(Action::ScrollToTop),
+            Key::End => return Some(Action::ScrollToBottom),
+            Key::Up => return Some(Action::MoveFocus(Direction::Up)),
+            Key::Down => return Some(Action::MoveFocus(Direction::Down)),
+            Key::Left => return Some(Action::MoveFocus(Direction::Left)),
+            Key::Right => return Some(Action::MoveFocus(Direction::Right)),
+            _ => (),
+        }
+
+        if let Key::Char(char) = key {
+            if modifiers.alt && !modifiers.ctrl && self.alt_redirects_output {
+                return Some(Action::RedirectKeyPress(KeyPress::from_char(char)));
+            }
+        }
+
+        match key_press.typed_char() {
+            Some(char) if Some(char) == self.mode_selection_key => Some(Action::GoToModeSelection),
+            Some(char) if Some(char) == self.help_key => Some(Action::ToggleHelp),
+            Some(char) if Some(char) == self.abort_key => Some(Action::Abort),
+            Some(char) if Some(char) == self.peek_key => Some(Action::TogglePeek),
+            Some(char) if Some(char) == self.return_mode_key => Some(Action::SwitchToReturnMode),
+            Some(char) if Some(char) == self.search_key => Some(Action::StartSearch),
+            Some(char) if Some(char) == self.next_file_key => Some(Action::SwitchToNextFile),
+            Some(char) if Some(char) == self.previous_file_key => {
+                Some(Action::SwitchToPreviousFile)
+            }
+            Some(char) if Some(char) == self.wrap_toggle_key => Some(Action::ToggleWrapLines),
+            Some(char) if Some(char) == self.next_hit_page_key => Some(Action::ScrollToHitPage(1)),
+            Some(char) if Some(char) == self.previous_hit_page_key => {
+                Some(Action::ScrollToHitPage(-1))
+            }
+            Some(char) if Some(char) == self.refresh_key => Some(Action::Refresh),
+            Some(char) if Some(char) == self.collect_key => Some(Action::ToggleCollecting),
+            Some(char) if Some(char) == self.scroll_to_top_key => Some(Action::ScrollToTop),
+            Some(char) if Some(char) == self.scroll_to_bottom_key => Some(Action::ScrollToBottom),
+            Some(char) if Some(char) == self.debug_overlay_key => Some(Action::ToggleDebugOverlay),
+            Some(char @ ('h' | 'j' | 'k' | 'l')) if self.hjkl_navigation => {
+                let direction = match char {
+                    'h' => Direction::Left,
+                    'j' => Direction::Down,
+                    'k' => Direction::Up,
+                    _ => Direction::Right,
+                };
+                Some(Action::MoveFocus(direction))
+            }
+            Some(char) => match self.match_key_sequence(&[char]) {
+                SequenceMatch::Command(command) => Some(Action::RunModeCommand(command)),
+                SequenceMatch::Pending => {
+                    self.pending_keys = vec![char];
+                    None
+                }
+                SequenceMatch::NoMatch => Some(Action::ForwardKeyPress(key_press)),
+            },
+            None => Some(Action::ForwardKeyPress(key_press)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn default_input_handler() -> InputHandler {
+        InputHandler::from_config(&Config::default())
+    }
+
+    fn key_event(key: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn mode_selection_key_results_in_mode_selection() {
+        let mut input_handler = InputHandler {
+            mode_selection_key: Some('m'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('m'));
+
+        assert!(matches!(action, Some(Action::GoToModeSelection)));
+    }
+
+    #[test]
+    fn space_is_forwarded_when_mode_selection_key_is_different() {
+        let mut input_handler = InputHandler {
+            mode_selection_key: Some('m'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event(' '));
+
+        assert!(matches!(
+            action,
+            Some(Action::ForwardKeyPress(key_press)) if key_press == KeyPress::from_char(' ')
+        ));
+    }
+
+    #[test]
+    fn backspace_results_in_erasing_key_press() {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::EraseKeyPress)));
+    }
+
+    #[test]
+    fn esc_results_in_clearing_or_exiting() {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::ClearOrExit)));
+    }
+
+    fn mouse_event(kind: MouseEventKind) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn mouse_wheel_results_in_configured_scrolling() {
+        let mut input_handler = InputHandler {
+            mouse_wheel_scroll: MouseWheelScroll::Page,
+            ..default_input_handler()
+        };
+
+        let up = input_handler.get_action(mouse_event(MouseEventKind::ScrollUp));
+        let down = input_handler.get_action(mouse_event(MouseEventKind::ScrollDown));
+
+        assert!(matches!(up, Some(Action::ScrollPages(-1))));
+        assert!(matches!(down, Some(Action::ScrollPages(1))));
+    }
+
+    #[test]
+    fn mouse_wheel_is_ignored_when_scrolling_is_off() {
+        let mut input_handler = default_input_handler();
+
+        let action = input_handler.get_action(mouse_event(MouseEventKind::ScrollDown));
+
+        assert!(action.is_none());
+    }
+
+    #[test]
+    fn hjkl_moves_focus_when_enabled() {
+        let mut input_handler = InputHandler {
+            hjkl_navigation: true,
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('j'));
+
+        assert!(matches!(action, Some(Action::MoveFocus(Direction::Down))));
+    }
+
+    #[test]
+    fn hjkl_is_forwarded_when_navigation_is_disabled() {
+        let mut input_handler = default_input_handler();
+
+        let action = input_handler.get_action(key_event('j'));
+
+        assert!(matches!(
+            action,
+            Some(Action::ForwardKeyPress(key_press)) if key_press == KeyPress::from_char('j')
+        ));
+    }
+
+    #[test]
+    fn key_with_modifiers_is_forwarded_with_modifiers() {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT));
+        let action = input_handler.get_action(event);
+
+        let expected = KeyPress {
+            key: Key::Char('a'),
+            modifiers: Modifiers {
+                alt: true,
+                ..Modifiers::default()
+            },
+        };
+        assert!(matches!(
+            action,
+            Some(Action::ForwardKeyPress(key_press)) if key_press == expected
+        ));
+    }
+
+    #[test]
+    fn mode_selection_key_with_ctrl_is_forwarded() {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::CONTROL));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::ForwardKeyPress(_))));
+    }
+
+    #[test]
+    fn tab_and_shift_tab_cycle_modes_when_enabled() {
+        let mut input_handler = InputHandler {
+            mode_cycling: true,
+            ..default_input_handler()
+        };
+
+        let tab = Event::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        let back_tab = Event::Key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT));
+
+        assert!(matches!(
+            input_handler.get_action(tab),
+            Some(Action::SwitchToNextMode)
+        ));
+        assert!(matches!(
+            input_handler.get_action(back_tab),
+            Some(Action::SwitchToPreviousMode)
+        ));
+    }
+
+    #[test]
+    fn tab_is_forwarded_when_mode_cycling_is_disabled() {
+        let mut input_handler = InputHandler {
+            mode_cycling: false,
+            ..default_input_handler()
+        };
+
+        let tab = Event::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+
+        assert!(matches!(
+            input_handler.get_action(tab),
+            Some(Action::ForwardKeyPress(KeyPress { key: Key::Tab, .. }))
+        ));
+    }
+
+    #[test]
+    fn key_bound_in_current_mode_results_in_mode_command() {
+        let mut input_handler = InputHandler {
+            mode_keybindings: vec![
+                HashMap::new(),
+                HashMap::from([(KeySequence(vec!['x']), "command".to_string())]),
+            ],
+            ..default_input_handler()
+        };
+
+        input_handler.set_current_mode(Some(1));
+        let action = input_handler.get_action(key_event('x'));
+
+        assert!(matches!(action, Some(Action::RunModeCommand(command)) if command == "command"));
+    }
+
+    #[test]
+    fn key_bound_in_other_mode_is_forwarded() {
+        let mut input_handler = InputHandler {
+            mode_keybindings: vec![
+                HashMap::new(),
+                HashMap::from([(KeySequence(vec!['x']), "command".to_string())]),
+            ],
+            ..default_input_handler()
+        };
+
+        input_handler.set_current_mode(Some(0));
+        let action = input_handler.get_action(key_event('x'));
+
+        assert!(matches!(action, Some(Action::ForwardKeyPress(_))));
+    }
+
+    #[test]
+    fn key_is_forwarded_when_mode_selection_is_disabled() {
+        let mut input_handler = InputHandler {
+            mode_selection_key: None,
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event(' '));
+
+        assert!(matches!(
+            action,
+            Some(Action::ForwardKeyPress(key_press)) if key_press == KeyPress::from_char(' ')
+        ));
+    }
+
+    #[test]
+    fn arrow_keys_move_focus() {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::MoveFocus(Direction::Left))));
+    }
+
+    #[test_case(KeyCode::F(5), Key::F(5))]
+    fn non_character_key_is_forwarded(code: KeyCode, expected: Key) {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(
+            action,
+            Some(Action::ForwardKeyPress(KeyPress { key, .. })) if key == expected
+        ));
+    }
+
+    #[test]
+    fn unsupported_key_results_in_no_action() {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Insert, KeyModifiers::NONE));
+
+        assert!(input_handler.get_action(event).is_none());
+    }
+
+    #[test]
+    fn help_key_results_in_toggling_help() {
+        let mut input_handler = InputHandler {
+            help_key: Some('?'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('?'));
+
+        assert!(matches!(action, Some(Action::ToggleHelp)));
+    }
+
+    #[test]
+    fn help_key_is_forwarded_when_help_is_disabled() {
+        let mut input_handler = InputHandler {
+            help_key: None,
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('?'));
+
+        assert!(matches!(action, Some(Action::ForwardKeyPress(_))));
+    }
+
+    #[test]
+    fn ctrl_r_results_in_rematch() {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::Rematch)));
+    }
+
+    #[test]
+    fn abort_key_results_in_abort() {
+        let mut input_handler = InputHandler {
+            abort_key: Some('q'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('q'));
+
+        assert!(matches!(action, Some(Action::Abort)));
+    }
+
+    #[test]
+    fn peek_key_results_in_toggling_peek() {
+        let mut input_handler = InputHandler {
+            peek_key: Some('.'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('.'));
+
+        assert!(matches!(action, Some(Action::TogglePeek)));
+    }
+
+    #[test]
+    fn debug_overlay_key_results_in_toggling_debug_overlay() {
+        let mut input_handler = InputHandler {
+            debug_overlay_key: Some('!'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('!'));
+
+        assert!(matches!(action, Some(Action::ToggleDebugOverlay)));
+    }
+
+    fn input_handler_with_sequences() -> InputHandler {
+        let mut input_handler = InputHandler {
+            mode_keybindings: vec![HashMap::from([
+                (KeySequence(vec!['g']), "first".to_string()),
+                (KeySequence(vec!['g', 'g']), "second".to_string()),
+                (KeySequence(vec!['z', 'x']), "third".to_string()),
+            ])],
+            ..default_input_handler()
+        };
+        input_handler.set_current_mode(Some(0));
+
+        input_handler
+    }
+
+    #[test]
+    fn key_sequence_results_in_mode_command() {
+        let mut input_handler = input_handler_with_sequences();
+
+        assert!(input_handler.get_action(key_event('g')).is_none());
+        assert!(input_handler.get_timeout().is_some());
+
+        let action = input_handler.get_action(key_event('g'));
+
+        assert!(matches!(action, Some(Action::RunModeCommand(command)) if command == "second"));
+        assert!(input_handler.get_timeout().is_none());
+    }
+
+    #[test]
+    fn timed_out_key_sequence_results_in_command_of_pressed_keys() {
+        let mut input_handler = input_handler_with_sequences();
+
+        input_handler.get_action(key_event('g'));
+        let action = input_handler.get_timeout_action();
+
+        assert!(matches!(action, Some(Action::RunModeCommand(command)) if command == "first"));
+    }
+
+    #[test]
+    fn unbound_key_sequence_is_discarded() {
+        let mut input_handler = input_handler_with_sequences();
+
+        input_handler.get_action(key_event('z'));
+        let action = input_handler.get_action(key_event('y'));
+
+        assert!(action.is_none());
+        assert!(input_handler.get_timeout().is_none());
+    }
+
+    #[test]
+    fn other_key_cancels_key_sequence() {
+        let mut input_handler = input_handler_with_sequences();
+
+        input_handler.get_action(key_event('g'));
+        let event = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::ClearOrExit)));
+        assert!(input_handler.get_timeout().is_none());
+    }
+
+    #[test]
+    fn return_mode_key_results_in_switching_to_return_mode() {
+        let mut input_handler = InputHandler {
+            return_mode_key: Some('-'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('-'));
+
+        assert!(matches!(action, Some(Action::SwitchToReturnMode)));
+    }
+
+    #[test]
+    fn key_with_alt_is_redirected_when_alt_output_is_enabled() {
+        let mut input_handler = InputHandler {
+            alt_redirects_output: true,
+            ..default_input_handler()
+        };
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(
+            action,
+            Some(Action::RedirectKeyPress(key_press)) if key_press == KeyPress::from_char('a')
+        ));
+    }
+
+    #[test_case(KeyCode::PageDown, 1; "page_down")]
+    #[test_case(KeyCode::PageUp, -1; "page_up")]
+    fn page_keys_result_in_page_scrolling(code: KeyCode, expected: isize) {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::ScrollPages(pages)) if pages == expected));
+    }
+
+    #[test_case('e', 1; "ctrl_e")]
+    #[test_case('y', -1; "ctrl_y")]
+    fn ctrl_e_and_ctrl_y_result_in_line_scrolling(key: char, expected: isize) {
+        let mut input_handler = default_input_handler();
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Char(key), KeyModifiers::CONTROL));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::ScrollLines(lines)) if lines == expected));
+    }
+
+    #[test]
+    fn home_and_end_result_in_scrolling_to_top_and_bottom() {
+        let mut input_handler = default_input_handler();
+
+        let home = Event::Key(KeyEvent::new(KeyCode::Home, KeyModifiers::NONE));
+        let end = Event::Key(KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+
+        assert!(matches!(
+            input_handler.get_action(home),
+            Some(Action::ScrollToTop)
+        ));
+        assert!(matches!(
+            input_handler.get_action(end),
+            Some(Action::ScrollToBottom)
+        ));
+    }
+
+    #[test]
+    fn configured_keys_result_in_scrolling_to_top_and_bottom() {
+        let mut input_handler = InputHandler {
+            scroll_to_top_key: Some('g'),
+            scroll_to_bottom_key: Some('G'),
+            ..default_input_handler()
+        };
+
+        assert!(matches!(
+            input_handler.get_action(key_event('g')),
+            Some(Action::ScrollToTop)
+        ));
+        assert!(matches!(
+            input_handler.get_action(key_event('G')),
+            Some(Action::ScrollToBottom)
+        ));
+    }
+
+    #[test]
+    fn search_key_results_in_starting_search() {
+        let mut input_handler = InputHandler {
+            search_key: Some('/'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('/'));
+
+        assert!(matches!(action, Some(Action::StartSearch)));
+    }
+
+    #[test_case('/'; "when_key_is_search_key")]
+    #[test_case('j'; "when_key_is_navigation_key")]
+    #[test_case('?'; "when_key_is_help_key")]
+    fn configured_key_is_forwarded_during_text_input(key: char) {
+        let mut input_handler = InputHandler {
+            search_key: Some('/'),
+            help_key: Some('?'),
+            hjkl_navigation: true,
+            ..default_input_handler()
+        };
+        input_handler.set_text_input(true);
+
+        let action = input_handler.get_action(key_event(key));
+
+        assert!(matches!(
+            action,
+            Some(Action::ForwardKeyPress(key_press)) if key_press == KeyPress::from_char(key)
+        ));
+    }
+
+    #[test]
+    fn file_keys_result_in_switching_file() {
+        let mut input_handler = InputHandler {
+            next_file_key: Some(']'),
+            previous_file_key: Some('['),
+            ..default_input_handler()
+        };
+
+        let next = input_handler.get_action(key_event(']'));
+        let previous = input_handler.get_action(key_event('['));
+
+        assert!(matches!(next, Some(Action::SwitchToNextFile)));
+        assert!(matches!(previous, Some(Action::SwitchToPreviousFile)));
+    }
+
+    #[test]
+    fn wrap_toggle_key_results_in_toggling_wrap() {
+        let mut input_handler = InputHandler {
+            wrap_toggle_key: Some('|'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('|'));
+
+        assert!(matches!(action, Some(Action::ToggleWrapLines)));
+    }
+
+    #[test_case('}', 1; "when_key_is_next_hit_page_key")]
+    #[test_case('{', -1; "when_key_is_previous_hit_page_key")]
+    fn hit_page_key_results_in_scrolling_to_hit_page(key: char, expected: isize) {
+        let mut input_handler = InputHandler {
+            next_hit_page_key: Some('}'),
+            previous_hit_page_key: Some('{'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event(key));
+
+        assert!(matches!(action, Some(Action::ScrollToHitPage(pages)) if pages == expected));
+    }
+
+    #[test]
+    fn refresh_key_results_in_refresh() {
+        let mut input_handler = InputHandler {
+            refresh_key: Some('R'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('R'));
+
+        assert!(matches!(action, Some(Action::Refresh)));
+    }
+
+    #[test]
+    fn collect_key_results_in_toggling_collecting() {
+        let mut input_handler = InputHandler {
+            collect_key: Some('+'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('+'));
+
+        assert!(matches!(action, Some(Action::ToggleCollecting)));
+    }
+
+    #[test]
+    fn select_key_results_in_starting_selection_while_paging() {
+        let mut input_handler = InputHandler {
+            select_key: Some('v'),
+            ..default_input_handler()
+        };
+        input_handler.set_paging(true);
+
+        let action = input_handler.get_action(key_event('v'));
+
+        assert!(matches!(action, Some(Action::StartSelection)));
+    }
+
+    #[test]
+    fn select_key_is_forwarded_when_not_paging() {
+        let mut input_handler = InputHandler {
+            select_key: Some('v'),
+            ..default_input_handler()
+        };
+
+        let action = input_handler.get_action(key_event('v'));
+
+        assert!(matches!(action, Some(Action::ForwardKeyPress(_))));
+    }
+
+    #[test_case(' ', 1; "when_key_is_space")]
+    #[test_case('b', -1; "when_key_is_b")]
+    fn pager_keys_scroll_pages_while_paging(key: char, expected: isize) {
+        let mut input_handler = default_input_handler();
+        input_handler.set_paging(true);
+
+        let action = input_handler.get_action(key_event(key));
+
+        assert!(matches!(action, Some(Action::ScrollPages(pages)) if pages == expected));
+    }
+
+    #[test]
+    fn down_arrow_scrolls_while_paging() {
+        let mut input_handler = default_input_handler();
+        input_handler.set_paging(true);
+
+        let event = Event::Key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        let action = input_handler.get_action(event);
+
+        assert!(matches!(action, Some(Action::ScrollLines(1))));
+    }
 }
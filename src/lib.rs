@@ -0,0 +1,33 @@
+//! The hint based selection of mless, which the `mless` binary is a
+//! command line interface to. Other terminal programs can embed it with
+//! [select_from_str], or build their own selection from [configuration],
+//! [modes], [hints] and [rendering].
+//!
+//! # Architecture
+//! The diagram below shows the relationship between the most important
+//! components of the system.
+#![doc = include_str!("./docs/architecture_diagram.svg")]
+//!
+//! For convenience, here are links to the mentioned structs/traits/functions:
+//! - [input_handler::InputHandler]
+//! - [input_handler::Action]
+//! - [run]
+//! - [configuration::Config]
+//! - [modes::Mode]
+//! - [rendering::Renderer]
+//! - [rendering::DrawInstruction]
+mod app;
+pub mod args;
+pub mod configuration;
+pub mod error;
+pub mod hints;
+pub mod input_handler;
+mod logging;
+pub mod modes;
+mod pager;
+pub mod rendering;
+pub mod shell_integration;
+
+#[cfg(feature = "ratatui")]
+pub use app::widget;
+pub use app::{run, select_from_str, RunOutcome};
@@ -6,31 +6,54 @@
 //! into a file. By default, the logging is turned off and it can be turned on by setting the
 //! environment varible MLESS_LOG to the path of the file in which to log:
 //!
-//! ```
+//! ```sh
 //! $ MLESS_LOG=/tmp/log.log mless file_to_select_from.txt
 //! ```
 //!
 //! By default, debug level and higher are logged. To customize the log levels, set RUST_LOG
 //! according to [env_logger's documentation](https://docs.rs/env_logger/0.11.3/env_logger/index.html).
-use std::fs::File;
+//!
+//! The log file and the levels can also be set in the `logging` section of the config file,
+//! see [LoggingConfig]. The environment variables take priority over the config file.
+//!
+//! The arguments take priority over both, except for RUST_LOG. The log file can be set with
+//! `--log-file` and the level of all modules with `-v` (debug), `-vv` (trace) or `-q` (warn):
+//!
+//! ```sh
+//! $ mless --log-file /tmp/log.log -vv file_to_select_from.txt
+//! ```
+use std::{fs::File, path::Path};
 
 use env_logger::Env;
-use log::info;
+use log::{info, LevelFilter};
 use snafu::ResultExt;
 
+use crate::configuration::LoggingConfig;
 use crate::error::LoggingStartSnafu;
 use crate::error::RunError;
 
 /// Name for the environment variable containing the path of the log file.
 const LOG_PATH_ENV: &str = "MLESS_LOG";
-/// Default logging level if RUST_LOG is not provided.
-const LOG_DEFAULT_LEVEL: &str = "debug";
 
-/// Initialize the logging according to environment variables.
+/// Check whether anything is logged, i.e. whether [initialize_logging] found
+/// a log file.
+pub fn is_enabled() -> bool {
+    log::max_level() != LevelFilter::Off
+}
+
+/// Initialize the logging according to the given log file and level from the arguments,
+/// environment variables and the given config.
 /// Returns an error if it cannot open the log file for writing.
-pub fn initialize_logging() -> Result<(), RunError> {
-    let Ok(log_path) = std::env::var(LOG_PATH_ENV) else {
-        return Ok(());
+pub fn initialize_logging(
+    config: &LoggingConfig,
+    log_file: Option<&Path>,
+    level: Option<LevelFilter>,
+) -> Result<(), RunError> {
+    let log_path = match (log_file, std::env::var(LOG_PATH_ENV), &config.path) {
+        (Some(log_path), _, _) => log_path.to_string_lossy().into_owned(),
+        (None, Ok(log_path), _) => log_path,
+        (None, Err(_), Some(log_path)) => log_path.clone(),
+        (None, Err(_), None) => return Ok(()),
     };
 
     let log_file = File::create(log_path.clone()) //
@@ -40,7 +63,21 @@ pub fn initialize_logging() -> Result<(), RunError> {
 
     let log_file = Box::new(log_file);
 
-    env_logger::Builder::from_env(Env::default().default_filter_or(LOG_DEFAULT_LEVEL))
+    let mut builder = env_logger::Builder::new();
+    match level {
+        Some(level) => {
+            builder.filter_level(level);
+        }
+        None => {
+            builder.filter_level(config.level);
+            for (module, level) in &config.modules {
+                builder.filter_module(module, *level);
+            }
+        }
+    }
+
+    builder
+        .parse_env(Env::default())
         .target(env_logger::Target::Pipe(log_file))
         .init();
 
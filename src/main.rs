@@ -1,49 +1,56 @@
-//! # Architecture
-//! The diagram below shows the relationship between the most important
-//! components of the system.
-#![doc = include_str!("./docs/architecture_diagram.svg")]
-//!
-//! For convenience, here are links to the mentioned structs/traits/functions:
-//! - [input_handler::InputHandler]
-//! - [input_handler::Action]
-//! - [app::run]
-//! - [configuration::Config]
-//! - [modes::Mode]
-//! - [rendering::Renderer]
-//! - [rendering::DrawInstruction]
-mod app;
-mod args;
-mod configuration;
-mod error;
-mod hints;
-mod input_handler;
-mod logging;
-mod modes;
-mod pager;
-mod rendering;
+//! The `mless` command line tool, see the [mless] library for the
+//! selection itself.
+use std::{env, process::exit};
 
-use std::process::exit;
-
-use app::run;
-use args::Args;
 use clap::Parser;
+use mless::{
+    args::{self, Args},
+    configuration, error, run, shell_integration, RunOutcome,
+};
 
 fn main() {
-    const EXIT_ERROR: i32 = -1;
     const EXIT_SUCCESS: i32 = 0;
+    const EXIT_ERROR: i32 = 1;
+    const EXIT_ABORTED: i32 = 2;
+    const EXIT_NO_INPUT: i32 = 3;
+    const EXIT_CANCELLED: i32 = 4;
+    const EXIT_NOTHING_TO_SELECT: i32 = 5;
+    const EXIT_EMPTY_INPUT: i32 = 6;
 
-    let args = Args::parse();
+    let args = Args::parse_from(args::expand_plus_arguments(env::args_os()));
 
     if args.show_default_config {
         println!("{}", configuration::DEFAULT_CONFIG_FILE);
         exit(EXIT_SUCCESS);
     }
 
+    if let Some(shell) = args.init {
+        print!("{}", shell_integration::get_init_script(shell));
+        exit(EXIT_SUCCESS);
+    }
+
     match run(args) {
-        Ok(selection) => {
+        Ok(RunOutcome::Selected(selection)) => {
             print!("{}", selection);
             exit(EXIT_SUCCESS);
         }
+        Ok(RunOutcome::Cancelled(output)) => {
+            print!("{}", output);
+            exit(EXIT_CANCELLED);
+        }
+        Ok(RunOutcome::NothingToSelect(output)) => {
+            print!("{}", output);
+            exit(EXIT_NOTHING_TO_SELECT);
+        }
+        Err(error::RunError::Aborted) => exit(EXIT_ABORTED),
+        Err(error @ error::RunError::NoInput) => {
+            eprintln!("{}", error);
+            exit(EXIT_NO_INPUT);
+        }
+        Err(error @ error::RunError::EmptyInput) => {
+            eprintln!("{}", error);
+            exit(EXIT_EMPTY_INPUT);
+        }
         Err(error) => {
             eprintln!("{}", error);
             exit(EXIT_ERROR);
@@ -1,12 +1,26 @@
 //! Different selection modes.
-use crate::{input_handler::KeyPress, rendering::DrawInstruction};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::{
+    input_handler::{Direction, KeyPress},
+    rendering::DrawInstruction,
+};
 
 mod regex;
-pub use regex::RegexMode;
+#[cfg(feature = "ratatui")]
+pub(crate) use regex::get_color_regex;
+pub use regex::{Hit, RegexMode};
 
 mod mode_selector;
 pub use mode_selector::ModeSelectorMode;
 
+mod registry;
+pub use registry::{
+    create_registered_mode, get_registered_mode_commands, get_registered_mode_names, register_mode,
+    ModeFactory,
+};
+
 /// The trait that defines all selection modes.
 pub trait Mode {
     /// Handle the key press from the user.
@@ -15,6 +29,73 @@ pub trait Mode {
     /// state of the mode.
     fn handle_key_press(&mut self, key: KeyPress) -> Option<ModeEvent>;
 
+    /// Undo the effect of the last key press, if the mode supports it.
+    fn erase_key_press(&mut self) {}
+
+    /// Undo the effect of all the key presses so far, if the mode supports it.
+    ///
+    /// Returns true if there was anything to undo, false otherwise.
+    fn clear_key_presses(&mut self) -> bool {
+        false
+    }
+
+    /// Move the focus in the given direction, if the mode supports it.
+    fn move_focus(&mut self, _direction: Direction) {}
+
+    /// Handle a mode specific command bound to a key in the config.
+    fn handle_command(&mut self, _command: &str) -> Option<ModeEvent> {
+        None
+    }
+
+    /// Get the time to wait for the next key press before calling
+    /// [Mode::handle_key_press_timeout]. [None] means waiting indefinitely.
+    fn get_key_press_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Handle the situation where no key was pressed within the time
+    /// requested by [Mode::get_key_press_timeout].
+    fn handle_key_press_timeout(&mut self) -> Option<ModeEvent> {
+        None
+    }
+
+    /// Check whether there is anything to select in the data the mode was
+    /// created for.
+    fn has_hits(&self) -> bool {
+        self.get_hit_count() > 0
+    }
+
+    /// Get the number of things to select in the data the mode was created
+    /// for.
+    fn get_hit_count(&self) -> usize {
+        0
+    }
+
+    /// Get the state of the selection in progress, so that it can be moved
+    /// to a new instance of the mode, e.g. after the screen is resized.
+    fn get_selection_state(&self) -> SelectionState {
+        SelectionState::default()
+    }
+
+    /// Get the hint assigned to each of the hit texts, so that a new instance
+    /// of the mode for the same data can keep them.
+    fn get_hint_assignments(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    /// Continue the selection in progress with the given state taken from
+    /// another instance of the mode, as far as it applies to this one.
+    fn restore_selection_state(&mut self, _state: SelectionState) {}
+
+    /// Mark the hits with the given texts as already collected, so that
+    /// they can be told apart while collecting several selections.
+    fn set_collected(&mut self, _texts: &[String]) {}
+
+    /// Select whatever has the focus, if the mode supports it.
+    fn accept_focused(&mut self) -> Option<ModeEvent> {
+        None
+    }
+
     /// Specify the draw instructions for [crate::rendering::Renderer].
     ///
     /// Note that the renderer does not display anything if the returned
@@ -22,11 +103,33 @@ pub trait Mode {
     fn get_draw_instructions(&self) -> Vec<DrawInstruction>;
 }
 
+/// The selection in progress in a mode, see [Mode::get_selection_state].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SelectionState {
+    /// The characters of a hint typed so far.
+    pub typed_hint: String,
+    /// The digits of a hit number typed so far.
+    pub typed_number: String,
+    /// The text of the hit that has the focus.
+    pub focused_text: Option<String>,
+}
+
+/// Where a selected piece of the data comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HitOrigin {
+    /// Byte offset in the data where the piece starts.
+    pub start: usize,
+    /// Index of the regex of the mode that found the piece.
+    pub regex_index: usize,
+}
+
 /// Enum that specifies the events happening inside the mode.
 #[derive(Debug)]
 pub enum ModeEvent {
     /// The test selection has finished and resulted in the given string.
-    TextSelected(String),
+    /// If it is a single piece of the data, where it comes from is also
+    /// given.
+    TextSelected(String, Option<HitOrigin>),
     /// Switch to another selection mode has been requested.
     ModeSwitchRequested(usize),
 }
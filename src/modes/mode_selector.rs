@@ -18,9 +18,11 @@ impl<'a> Mode for ModeSelectorMode<'a> {
         &mut self,
         key: crate::input_handler::KeyPress,
     ) -> Option<super::ModeEvent> {
+        let hotkey = key.typed_char()?;
+
         self.modes
             .iter()
-            .position(|mode| mode.hotkey == key.key)
+            .position(|mode| mode.hotkey == hotkey)
             .map(ModeEvent::ModeSwitchRequested)
     }
 
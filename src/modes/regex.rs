@@ -2,25 +2,34 @@
 //!
 //! The idea behind this mode is to allow the user to provide a list
 //! of regexes, and then select part of the text that matches any of them.
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use std::time::Duration;
+
 use crossterm::style::Color;
-use hint_hit_map::{HintHitMap, Hit};
-use log::{debug, info, trace};
+use hint_hit_map::HintHitMap;
+pub use hint_hit_map::Hit;
+use log::{debug, info, trace, warn};
+use rayon::prelude::*;
 use regex::Regex;
-use snafu::ResultExt;
+use textwrap::core::display_width;
 
-use crate::configuration::Config;
-use crate::error::{InvalidRegexSnafu, RunError};
+use crate::configuration::{Config, HintLimitPolicy, HintPosition, HintPriority};
+use crate::error::RunError;
 use crate::{
     configuration,
     hints::HintGenerator,
-    input_handler::KeyPress,
-    rendering::{DataOverlay, DrawInstruction, StyledSegment, TextStyle},
+    input_handler::{Direction, KeyPress},
+    rendering::{DataOverlay, DrawInstruction, OverlayKind, StyledSegment, TextStyle},
 };
 
-use super::{Mode, ModeEvent};
+use super::{Mode, ModeEvent, SelectionState};
 
 mod hint_hit_map;
 #[cfg(test)]
+mod snapshot_tests;
+#[cfg(test)]
 mod tests;
 
 /// Struct representing the regex selection mode.
@@ -35,13 +44,52 @@ pub struct RegexMode {
     /// two key presses.
     input_buffer: String,
 
+    /// The digits typed so far to select the nth hit with Enter.
+    number_buffer: String,
+
     hint_fg: Color,
     hint_bg: Color,
     highlight_fg: Color,
     highlight_bg: Color,
+    focus_fg: Color,
+    focus_bg: Color,
+    collected_fg: Color,
+    collected_bg: Color,
+
+    /// Index of the pair in [RegexMode::hint_hit_map] whose hit has the focus.
+    focused: Option<usize>,
+
+    /// Screen positions (row, column) of the hits, in the same order as the
+    /// pairs in [RegexMode::hint_hit_map].
+    hit_positions: Vec<(usize, usize)>,
+
+    /// Texts of the hits that are already collected, see
+    /// [Mode::set_collected].
+    collected: Vec<String>,
+
+    /// Text put between the hits when all of them are selected.
+    selection_delimiter: String,
+
+    /// Whether the hints are drawn over the hits or inserted before them.
+    hint_position: HintPosition,
+
+    /// Time after which a typed hint that is also the start of a longer
+    /// hint is selected. If [None], it can be selected only with Enter.
+    ambiguous_hint_timeout: Option<Duration>,
+
+    /// Lines of the hits without the color sequences, in the same order as
+    /// the pairs in [RegexMode::hint_hit_map], if the preview is shown.
+    hit_lines: Option<Vec<String>>,
 }
 
+/// The most hits left after typing the start of a hint that are still
+/// shown in the preview.
+const MAX_PREVIEWED_HITS: usize = 3;
+
 impl RegexMode {
+    /// Names of the commands supported by [Mode::handle_command].
+    pub const COMMANDS: &'static [&'static str] = &["select_all", "select_focused"];
+
     /// Create a new regex mode for selecting from the given data with the given args.
     pub fn new(
         data: &str,
@@ -49,11 +97,76 @@ impl RegexMode {
         hint_generator: &dyn HintGenerator,
         config: &Config,
     ) -> Result<Self, RunError> {
+        let hits = Self::find_hits(data, args, config)?;
+
+        Ok(Self::with_hits(
+            data,
+            hits,
+            hint_generator,
+            config,
+            &HashMap::new(),
+        ))
+    }
+
+    /// Find the hits in the given data with the given args, which is the
+    /// expensive part of [RegexMode::new]. The result can be reused with
+    /// [RegexMode::with_hits] for the same data.
+    pub fn find_hits(
+        data: &str,
+        args: &configuration::RegexArgs,
+        config: &Config,
+    ) -> Result<Vec<Hit>, RunError> {
+        let hits = Self::match_regexes(data, args);
+
+        Ok(limit_hits_for_config(hits, config))
+    }
+
+    /// Find the hits in the given data like [RegexMode::find_hits], but match
+    /// every line of it separately, so that only the lines whose hits are not
+    /// in `line_hits` yet are matched, e.g. the ones that became visible after
+    /// scrolling. The hits of the matched lines are added to `line_hits`, by
+    /// the text of the line.
+    ///
+    /// Unlike with [RegexMode::find_hits], a hit cannot span multiple lines.
+    pub fn find_hits_by_line(
+        data: &str,
+        args: &configuration::RegexArgs,
+        config: &Config,
+        line_hits: &mut HashMap<String, Vec<Hit>>,
+    ) -> Result<Vec<Hit>, RunError> {
         let mut hits = vec![];
+        let mut line_start = 0;
+
+        for line in data.split('\n') {
+            let found = match line_hits.get(line) {
+                Some(found) => found,
+                None => {
+                    trace!("Matching line <{line}>");
+                    line_hits
+                        .entry(line.to_string())
+                        .or_insert_with(|| Self::match_regexes(line, args))
+                }
+            };
+
+            hits.extend(found.iter().map(|hit| Hit {
+                start: line_start + hit.start,
+                ..hit.clone()
+            }));
+            line_start += line.len() + 1;
+        }
+
+        // The sort is stable, so the hits are in the same order as if the
+        // whole data was matched at once
+        hits.sort_by_key(|hit| hit.regex_index);
+
+        Ok(limit_hits_for_config(hits, config))
+    }
 
+    /// Find all the hits of the regexes in the given data, in the order of
+    /// the regexes that found them.
+    fn match_regexes(data: &str, args: &configuration::RegexArgs) -> Vec<Hit> {
         // All ANSI color sequences should be ignored while matching
-        let ignore_regex = Regex::new("\x1b\\[[^m]+m") //
-            .context(InvalidRegexSnafu {})?;
+        let ignore_regex = get_color_regex();
 
         let ignore_ranges = ignore_regex
             .captures_iter(data)
@@ -71,79 +184,401 @@ impl RegexMode {
         // on the resulting data
         let cleaned_data = ignore_regex.replace_all(data, "");
 
-        for regex in &args.regexes {
-            regex
-                .captures_iter(&cleaned_data)
-                .filter_map(|capture| {
-                    // Documentation guarantees non-None for 0
-                    #[allow(
-                        clippy::unwrap_used,
-                        reason = "Documentation guarantees non-None for 0"
-                    )]
-                    let regex_match = capture.get(0).unwrap();
-
-                    if regex_match.is_empty() {
-                        return None;
-                    }
-
-                    // The calculation needs to be performed with indexes of the
-                    // first and the last character in the match, instead of start
-                    // and end because the end s one character after the match and
-                    // can be moved by the ignored data resulting too large length.
-                    let first_in_original_data =
-                        get_original_index(&ignore_ranges, regex_match.start());
-                    let last_in_original_data =
-                        get_original_index(&ignore_ranges, regex_match.end() - 1);
-
-                    Some(Hit {
-                        start: first_in_original_data,
-                        length: last_in_original_data - first_in_original_data + 1,
-                        text: regex_match.as_str().to_string(),
+        // The regexes are matched in parallel, but the hits are collected
+        // in the order of the regexes, so that the result does not change
+        let regex_hits: Vec<Vec<Hit>> = args
+            .regexes
+            .par_iter()
+            .zip(args.get_literal_matchers())
+            .enumerate()
+            .map(|(regex_index, (regex, literal_matcher))| {
+                let matches: Vec<(usize, usize)> = match literal_matcher {
+                    Some(matcher) => matcher
+                        .find_iter(&*cleaned_data)
+                        .map(|found| (found.start(), found.end()))
+                        .collect(),
+                    None => regex
+                        .find_iter(&cleaned_data)
+                        .map(|found| (found.start(), found.end()))
+                        .collect(),
+                };
+
+                matches
+                    .into_iter()
+                    .filter_map(|(start, end)| {
+                        let text = &cleaned_data[start..end];
+
+                        if text.is_empty() {
+                            return None;
+                        }
+
+                        if text.chars().count() < args.min_length {
+                            trace!("Ignoring too short match <{text}>");
+                            return None;
+                        }
+
+                        // The calculation needs to be performed with indexes of the
+                        // first and the last character in the match, instead of start
+                        // and end because the end s one character after the match and
+                        // can be moved by the ignored data resulting too large length.
+                        let first_in_original_data = get_original_index(&ignore_ranges, start);
+                        let last_in_original_data = get_original_index(&ignore_ranges, end - 1);
+
+                        Some(Hit {
+                            start: first_in_original_data,
+                            length: last_in_original_data - first_in_original_data + 1,
+                            text: text.into(),
+                            regex_index,
+                        })
                     })
-                })
-                .for_each(|hit| hits.push(hit));
-        }
+                    .collect()
+            })
+            .collect();
+
+        regex_hits.into_iter().flatten().collect()
+    }
 
-        let hint_hit_map = HintHitMap::new(hits, hint_generator);
+    /// Create a new regex mode for selecting the given hits, found with
+    /// [RegexMode::find_hits], from the given data.
+    ///
+    /// The hits keep the hints from `previous_hints` where possible, see
+    /// [Mode::get_hint_assignments].
+    pub fn with_hits(
+        data: &str,
+        hits: Vec<Hit>,
+        hint_generator: &dyn HintGenerator,
+        config: &Config,
+        previous_hints: &HashMap<String, String>,
+    ) -> Self {
+        let hit_distances = get_hit_distances(data, &hits, config.hint_priority);
+        let hint_hit_map = HintHitMap::new(
+            hits,
+            hint_generator,
+            previous_hints,
+            hit_distances.as_deref(),
+            config.deduplicate_hits,
+        );
 
         trace!("Constructed hint hit map {:#?}", hint_hit_map);
 
-        Ok(Self {
+        let hit_positions = hint_hit_map
+            .pairs
+            .iter()
+            .map(|(_, hit)| get_screen_position(data, hit.start))
+            .collect();
+
+        let hit_lines = config.show_hit_preview.then(|| {
+            hint_hit_map
+                .pairs
+                .iter()
+                .map(|(_, hit)| strip_color_sequences(get_line(data, hit.start)))
+                .collect()
+        });
+
+        Self {
             hint_hit_map,
             input_buffer: String::new(),
+            number_buffer: String::new(),
             hint_fg: config.hint_fg,
             hint_bg: config.hint_bg,
             highlight_fg: config.highlight_fg,
             highlight_bg: config.highlight_bg,
-        })
+            focus_fg: config.focus_fg,
+            focus_bg: config.focus_bg,
+            collected_fg: config.collected_fg,
+            collected_bg: config.collected_bg,
+            focused: None,
+            hit_positions,
+            collected: vec![],
+            selection_delimiter: config.selection_delimiter.clone(),
+            hint_position: config.hint_position,
+            ambiguous_hint_timeout: config.ambiguous_hint_timeout_ms.map(Duration::from_millis),
+            hit_lines,
+        }
+    }
+
+    /// Get the preview of the focused hit, with its whole text and line, or
+    /// of the few hits whose hints start with the typed keys, if the
+    /// preview is shown.
+    fn get_preview(&self) -> Option<String> {
+        let hit_lines = self.hit_lines.as_ref()?;
+
+        if let Some(focused) = self.focused {
+            let (_, hit) = self.hint_hit_map.pairs.get(focused)?;
+            let line = hit_lines.get(focused)?;
+            return Some(format!("{}  │ {}", hit.text, line.trim()));
+        }
+
+        if self.input_buffer.is_empty() {
+            return None;
+        }
+        // The hits with the same text share the hint, one of them is enough
+        let mut seen = HashSet::new();
+        let candidates: Vec<usize> = self
+            .hint_hit_map
+            .pairs
+            .iter()
+            .enumerate()
+            .filter(|(_, (hint, _))| hint.starts_with(&self.input_buffer))
+            .filter(|(_, (hint, _))| seen.insert(hint))
+            .map(|(index, _)| index)
+            .collect();
+
+        match candidates.as_slice() {
+            [index] => {
+                let (_, hit) = self.hint_hit_map.pairs.get(*index)?;
+                let line = hit_lines.get(*index)?;
+                Some(format!("{}  │ {}", hit.text, line.trim()))
+            }
+            indexes if !indexes.is_empty() && indexes.len() <= MAX_PREVIEWED_HITS => Some(
+                indexes
+                    .iter()
+                    .filter_map(|index| self.hint_hit_map.pairs.get(*index))
+                    .map(|(hint, hit)| format!("[{hint}] {}", hit.text))
+                    .collect::<Vec<_>>()
+                    .join("  "),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Select the hit whose hint is fully typed, if any.
+    fn select_typed_hint(&mut self) -> Option<ModeEvent> {
+        let hit = self.hint_hit_map.get_hit(&self.input_buffer)?;
+        let event = ModeEvent::TextSelected(hit.text.to_string(), Some(hit.origin()));
+        info!("Selected text {}", hit.text);
+
+        self.input_buffer.clear();
+        Some(event)
+    }
+
+    /// Select the nth hit in the order of appearance, counting from 1.
+    fn select_nth_hit(&mut self, n: usize) -> Option<ModeEvent> {
+        let mut hits: Vec<&Hit> = self.hint_hit_map.pairs.iter().map(|(_, hit)| hit).collect();
+        hits.sort_by_key(|hit| hit.start);
+
+        let Some(hit) = n.checked_sub(1).and_then(|index| hits.get(index)) else {
+            debug!("There is no hit number {n}, ignoring");
+            return None;
+        };
+        info!("Selected hit number {n} with text {}", hit.text);
+
+        Some(ModeEvent::TextSelected(
+            hit.text.to_string(),
+            Some(hit.origin()),
+        ))
+    }
+
+    /// Check if the given character appears in any of the hints.
+    fn is_hint_character(&self, key: char) -> bool {
+        self.hint_hit_map
+            .pairs
+            .iter()
+            .any(|(hint, _)| hint.contains(key))
     }
 }
 
 impl Mode for RegexMode {
     fn handle_key_press(&mut self, key: KeyPress) -> Option<ModeEvent> {
-        self.input_buffer.push(key.key);
+        let Some(key) = key.typed_char() else {
+            debug!("Ignoring key press {key:?} that does not type a character");
+            return None;
+        };
+
+        // Digits that are not used by the hints select the nth hit on Enter
+        if key.is_ascii_digit() && !self.is_hint_character(key) {
+            self.input_buffer.clear();
+            self.number_buffer.push(key);
+            debug!("Typed hit number {}", self.number_buffer);
+            return None;
+        }
+        self.number_buffer.clear();
+
+        self.input_buffer.push(key);
 
-        // Check for fully matching hints
-        if let Some(hit) = self.hint_hit_map.get_hit(&self.input_buffer) {
-            let selection = hit.text.clone();
-            info!("Selected text {selection}");
+        // A prefix that cannot match anything is discarded, but the pressed
+        // key may still be the start of a different hint
+        if !self.hint_hit_map.has_hint_with_prefix(&self.input_buffer) {
+            debug!(
+                "No hints matched with {}, starting over with the pressed key {}",
+                self.input_buffer, key
+            );
 
             self.input_buffer.clear();
-            Some(ModeEvent::TextSelected(selection))
+            self.input_buffer.push(key);
+        }
+
+        // Check for fully matching hints that are not the start of other hints
+        if self
+            .hint_hit_map
+            .has_longer_hint_with_prefix(&self.input_buffer)
+        {
+            debug!("Typed hint {} is ambiguous, waiting", self.input_buffer);
+            None
+        } else if self.hint_hit_map.get_hit(&self.input_buffer).is_some() {
+            self.select_typed_hint()
         // Check for partially matching hints
         } else if !self.hint_hit_map.has_hint_with_prefix(&self.input_buffer) {
-            debug!(
-                "No hints matched with the pressed key {}, ignoring",
-                key.key
-            );
+            debug!("No hints matched with the pressed key {key}, ignoring");
 
-            self.input_buffer.pop();
+            self.input_buffer.clear();
             None
         } else {
             None
         }
     }
 
+    fn erase_key_press(&mut self) {
+        let erased = match self.number_buffer.is_empty() {
+            true => self.input_buffer.pop(),
+            false => self.number_buffer.pop(),
+        };
+        debug!("Erased {erased:?} from the input buffer");
+    }
+
+    fn clear_key_presses(&mut self) -> bool {
+        debug!("Clearing the input buffer {}", self.input_buffer);
+
+        let had_key_presses = !self.input_buffer.is_empty() || !self.number_buffer.is_empty();
+        self.input_buffer.clear();
+        self.number_buffer.clear();
+
+        had_key_presses
+    }
+
+    fn move_focus(&mut self, direction: Direction) {
+        self.focused = match self.focused {
+            Some(focused) => {
+                find_nearest(&self.hit_positions, focused, direction).or(Some(focused))
+            }
+            None if self.hint_hit_map.pairs.is_empty() => None,
+            None => Some(0),
+        };
+
+        debug!("Moved focus {direction:?} to {:?}", self.focused);
+    }
+
+    fn handle_command(&mut self, command: &str) -> Option<ModeEvent> {
+        match command {
+            "select_all" => {
+                let mut hits: Vec<&Hit> =
+                    self.hint_hit_map.pairs.iter().map(|(_, hit)| hit).collect();
+                hits.sort_by_key(|hit| hit.start);
+
+                let mut seen = HashSet::new();
+                let texts: Vec<&str> = hits
+                    .into_iter()
+                    .map(|hit| hit.text.as_ref())
+                    .filter(|text| seen.insert(*text))
+                    .collect();
+
+                info!("Selected all {} hits", texts.len());
+                Some(ModeEvent::TextSelected(
+                    texts.join(&self.selection_delimiter),
+                    None,
+                ))
+            }
+            "select_focused" => self.accept_focused(),
+            _ => {
+                warn!("Unknown command {command}, ignoring");
+                None
+            }
+        }
+    }
+
+    fn set_collected(&mut self, texts: &[String]) {
+        self.collected = texts.to_vec();
+    }
+
+    fn accept_focused(&mut self) -> Option<ModeEvent> {
+        if !self.number_buffer.is_empty() {
+            let number = std::mem::take(&mut self.number_buffer);
+            // Only digits end up in the buffer, so this fails only on overflow
+            return number.parse().ok().and_then(|n| self.select_nth_hit(n));
+        }
+
+        // A typed ambiguous hint takes priority over the focus
+        if self.hint_hit_map.get_hit(&self.input_buffer).is_some() {
+            return self.select_typed_hint();
+        }
+
+        let (_, hit) = self.hint_hit_map.pairs.get(self.focused?)?;
+        info!("Selected focused text {}", hit.text);
+
+        Some(ModeEvent::TextSelected(
+            hit.text.to_string(),
+            Some(hit.origin()),
+        ))
+    }
+
+    fn get_hit_count(&self) -> usize {
+        self.hint_hit_map.pairs.len()
+    }
+
+    fn get_selection_state(&self) -> SelectionState {
+        SelectionState {
+            typed_hint: self.input_buffer.clone(),
+            typed_number: self.number_buffer.clone(),
+            focused_text: self
+                .focused
+                .and_then(|focused| self.hint_hit_map.pairs.get(focused))
+                .map(|(_, hit)| hit.text.to_string()),
+        }
+    }
+
+    fn get_hint_assignments(&self) -> HashMap<String, String> {
+        self.hint_hit_map.get_assignments()
+    }
+
+    fn restore_selection_state(&mut self, state: SelectionState) {
+        // A typed hint is kept only if it still needs more keys, so that the
+        // hints assigned differently for the new screen are not selected
+        // without the user noticing
+        if self
+            .hint_hit_map
+            .has_longer_hint_with_prefix(&state.typed_hint)
+        {
+            self.input_buffer = state.typed_hint;
+        } else if !state.typed_hint.is_empty() {
+            debug!(
+                "Dropping typed hint {} that no longer applies",
+                state.typed_hint
+            );
+        }
+
+        if !state
+            .typed_number
+            .chars()
+            .any(|key| self.is_hint_character(key))
+        {
+            self.number_buffer = state.typed_number;
+        }
+
+        self.focused = state.focused_text.and_then(|focused_text| {
+            self.hint_hit_map
+                .pairs
+                .iter()
+                .position(|(_, hit)| *hit.text == *focused_text)
+        });
+
+        debug!(
+            "Restored typed hint {:?}, typed number {:?} and focus {:?}",
+            self.input_buffer, self.number_buffer, self.focused
+        );
+    }
+
+    fn get_key_press_timeout(&self) -> Option<Duration> {
+        // Anything that fully matches at this point is ambiguous
+        self.hint_hit_map
+            .get_hit(&self.input_buffer)
+            .and(self.ambiguous_hint_timeout)
+    }
+
+    fn handle_key_press_timeout(&mut self) -> Option<ModeEvent> {
+        debug!("Accepting typed hint {} after timeout", self.input_buffer);
+        self.select_typed_hint()
+    }
+
     fn get_draw_instructions(&self) -> Vec<DrawInstruction> {
         let mut highlights: Vec<StyledSegment> = self
             .hint_hit_map
@@ -153,13 +588,24 @@ impl Mode for RegexMode {
             .map(|hit| StyledSegment {
                 start: hit.start,
                 length: hit.length,
-                style: TextStyle {
-                    foreground: self.highlight_fg,
-                    background: self.highlight_bg,
+                style: match self.collected.iter().any(|text| **text == *hit.text) {
+                    true => TextStyle {
+                        foreground: self.collected_fg,
+                        background: self.collected_bg,
+                    },
+                    false => TextStyle {
+                        foreground: self.highlight_fg,
+                        background: self.highlight_bg,
+                    },
                 },
             })
             .collect();
 
+        let hint_style = TextStyle {
+            foreground: self.hint_fg,
+            background: self.hint_bg,
+        };
+
         let (hint_highlights, overlays): (Vec<StyledSegment>, Vec<DataOverlay>) = self
             .hint_hit_map
             .pairs
@@ -168,28 +614,219 @@ impl Mode for RegexMode {
                 let highlight = StyledSegment {
                     start: hit.start,
                     length: hint.len(),
-                    style: TextStyle {
-                        foreground: self.hint_fg,
-                        background: self.hint_bg,
-                    },
+                    style: hint_style,
+                };
+
+                let kind = match self.hint_position {
+                    HintPosition::Overlay => OverlayKind::Replace,
+                    HintPosition::Insert => OverlayKind::Insert(hint_style),
                 };
 
                 let overlay = DataOverlay {
                     location: hit.start,
-                    text: hint.clone(),
+                    text: hint.to_string(),
+                    kind,
                 };
 
                 (highlight, overlay)
             })
             .unzip();
 
-        highlights.extend(hint_highlights);
+        // The focused hit is highlighted over the regular highlights
+        if let Some((_, hit)) = self
+            .focused
+            .and_then(|focused| self.hint_hit_map.pairs.get(focused))
+        {
+            highlights.push(StyledSegment {
+                start: hit.start,
+                length: hit.length,
+                style: TextStyle {
+                    foreground: self.focus_fg,
+                    background: self.focus_bg,
+                },
+            });
+        }
+
+        // Inserted hints are styled by the overlays themselves
+        if self.hint_position == HintPosition::Overlay {
+            highlights.extend(hint_highlights);
+        }
 
-        vec![DrawInstruction::StyledData {
+        let mut instructions = vec![DrawInstruction::StyledData {
             styled_segments: highlights,
             text_overlays: overlays,
-        }]
+        }];
+        if let Some(preview) = self.get_preview() {
+            instructions.push(DrawInstruction::Prompt(preview));
+        }
+
+        instructions
+    }
+}
+
+/// Get the line of the data that contains the given byte offset, without
+/// the new line at its end.
+fn get_line(data: &str, byte_offset: usize) -> &str {
+    let line_start = data[..byte_offset].rfind('\n').map_or(0, |index| index + 1);
+
+    data[line_start..].lines().next().unwrap_or_default()
+}
+
+/// Remove the ANSI color sequences, e.g. `\x1b[31m`, from the given text.
+fn strip_color_sequences(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char == '\x1b' && chars.peek() == Some(&'[') {
+            // Skip everything up to and including the final m
+            chars.by_ref().find(|&char| char == 'm');
+        } else {
+            stripped.push(char);
+        }
     }
+
+    stripped
+}
+
+/// Get the position (row, column) on the screen of the character at the given
+/// byte offset in the data, not taking line wrapping into account.
+fn get_screen_position(data: &str, byte_offset: usize) -> (usize, usize) {
+    let before = &data[..byte_offset];
+    let row = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    (row, display_width(&before[line_start..]))
+}
+
+/// Get the regex matching the ANSI color sequences, which are ignored while
+/// matching. It is compiled only once, since the hits are found again for
+/// every page and mode.
+#[allow(clippy::unwrap_used, reason = "A literal that's known to be valid")]
+pub(crate) fn get_color_regex() -> &'static Regex {
+    static COLOR_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    COLOR_REGEX.get_or_init(|| Regex::new("\x1b\\[[^m]+m").unwrap())
+}
+
+/// Get how many lines each of the hits is away from the line that gets the
+/// shortest hints with the given priority, or None if the hits get them in
+/// the order they are found.
+fn get_hit_distances(data: &str, hits: &[Hit], priority: HintPriority) -> Option<Vec<usize>> {
+    let anchor_row = match priority {
+        HintPriority::Found => return None,
+        HintPriority::Bottom => data.trim_end_matches('\n').matches('\n').count(),
+        HintPriority::Line(line) => line.saturating_sub(1),
+    };
+
+    Some(
+        hits.iter()
+            .map(|hit| get_screen_position(data, hit.start).0.abs_diff(anchor_row))
+            .collect(),
+    )
+}
+
+/// Find the index of the position nearest to the one at index `from` in
+/// the given direction.
+fn find_nearest(positions: &[(usize, usize)], from: usize, direction: Direction) -> Option<usize> {
+    let (row, column) = *positions.get(from)?;
+
+    positions
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != from)
+        .filter_map(|(index, &(other_row, other_column))| {
+            let row_delta = other_row as isize - row as isize;
+            let column_delta = other_column as isize - column as isize;
+
+            // Terminal cells are about twice as tall as they are wide
+            let (primary, secondary) = match direction {
+                Direction::Up => (-row_delta * 2, column_delta),
+                Direction::Down => (row_delta * 2, column_delta),
+                Direction::Left => (-column_delta, row_delta * 2),
+                Direction::Right => (column_delta, row_delta * 2),
+            };
+
+            if primary <= 0 {
+                return None;
+            }
+
+            Some((primary * primary + secondary * secondary, index))
+        })
+        .min()
+        .map(|(_, index)| index)
+}
+
+/// Keep only the hits that would need at most [Config::max_hints] hints, if
+/// it is set, see [limit_hits].
+fn limit_hits_for_config(hits: Vec<Hit>, config: &Config) -> Vec<Hit> {
+    match config.max_hints {
+        Some(max_hints) => limit_hits(
+            hits,
+            max_hints,
+            config.max_hints_policy,
+            config.deduplicate_hits,
+        ),
+        None => hits,
+    }
+}
+
+/// Keep only the hits that would need at most `max_hints` hints, choosing
+/// them according to the given policy.
+///
+/// If the hits are deduplicated, all the hits with the same text share the
+/// same hint, so the limit applies to the number of unique hit texts. The
+/// order of the kept hits is preserved.
+fn limit_hits(
+    hits: Vec<Hit>,
+    max_hints: usize,
+    policy: HintLimitPolicy,
+    deduplicate: bool,
+) -> Vec<Hit> {
+    // The groups of hits sharing a hint with the length of their text and
+    // the positions of their first and last hits, and the group of each hit
+    let mut groups: Vec<(usize, usize, usize)> = vec![];
+    let mut hit_groups: Vec<usize> = vec![];
+    let mut text_groups: HashMap<&str, usize> = HashMap::new();
+
+    for hit in &hits {
+        match text_groups.get(hit.text.as_ref()) {
+            Some(&group) if deduplicate => {
+                let (_, first, last) = &mut groups[group];
+                *first = (*first).min(hit.start);
+                *last = (*last).max(hit.start);
+                hit_groups.push(group);
+            }
+            _ => {
+                text_groups.insert(hit.text.as_ref(), groups.len());
+                hit_groups.push(groups.len());
+                groups.push((hit.text.chars().count(), hit.start, hit.start));
+            }
+        }
+    }
+
+    if groups.len() <= max_hints {
+        return hits;
+    }
+
+    debug!(
+        "Limiting {} unique hits to {max_hints} with policy {policy:?}",
+        groups.len()
+    );
+
+    let mut ordered_groups: Vec<usize> = (0..groups.len()).collect();
+    match policy {
+        HintLimitPolicy::First => ordered_groups.sort_by_key(|group| groups[*group].1),
+        HintLimitPolicy::Longest => ordered_groups.sort_by_key(|group| Reverse(groups[*group].0)),
+        HintLimitPolicy::Bottom => ordered_groups.sort_by_key(|group| Reverse(groups[*group].2)),
+    }
+
+    let kept_groups: HashSet<usize> = ordered_groups.into_iter().take(max_hints).collect();
+
+    hits.into_iter()
+        .zip(hit_groups)
+        .filter(|(_, group)| kept_groups.contains(group))
+        .map(|(hit, _)| hit)
+        .collect()
 }
 
 /// For a sequence from which `removed_ranges` where removed, find the index that
@@ -198,7 +835,7 @@ impl Mode for RegexMode {
 /// `removed_ranges` represents the collection of ranges (a, b) where a is included
 /// in the range and b is not.
 ///
-/// ```
+/// ```ignore
 /// // before removal [0, 1, 2, 3, 4, 5, 6]
 /// // after removal  [0, 2, 3, 6]
 /// let removed_ranges = [(1,2), (4,6)];
@@ -1,11 +1,17 @@
 //! Structs for handling mapping between hints and hits.
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::Arc,
+};
 
 use log::{info, trace};
 
-use crate::hints::HintGenerator;
+use crate::{
+    hints::{HintGenerator, HintTarget},
+    modes::HitOrigin,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 /// Struct that records a hit(match) that can be selected.
 pub struct Hit {
     /// Byte offset of the start of the hit.
@@ -18,8 +24,23 @@ pub struct Hit {
 
     /// The text of the hit.
     ///
-    /// This will be returned to the user if this hit is selected.
-    pub text: String,
+    /// This will be returned to the user if this hit is selected. It is
+    /// shared by the copies of the hit, e.g. the ones kept for the lines
+    /// that were already matched.
+    pub text: Arc<str>,
+
+    /// Index of the regex of the mode that found the hit.
+    pub regex_index: usize,
+}
+
+impl Hit {
+    /// Get where the hit starts and which regex found it.
+    pub fn origin(&self) -> HitOrigin {
+        HitOrigin {
+            start: self.start,
+            regex_index: self.regex_index,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -28,56 +49,149 @@ pub struct Hit {
 /// possible for multiple hits with the same text but on different locations to be
 /// assigned to the same hint.
 pub struct HintHitMap {
-    pub pairs: Vec<(String, Hit)>,
+    /// The hints with the hits they are assigned to. The hits that share a
+    /// hint share its text.
+    pub pairs: Vec<(Arc<str>, Hit)>,
 }
 
 impl HintHitMap {
     /// Create a mapping of hints to hits from the given collection of hits and the generator.
-    pub fn new(hits: Vec<Hit>, hint_generator: &dyn HintGenerator) -> Self {
-        let unique_hit_count = hits
+    ///
+    /// If `deduplicate` is true, all the hits with the same text get the same hint,
+    /// otherwise every hit gets its own.
+    ///
+    /// The hits whose texts are in `previous_hints` keep the hints they were assigned
+    /// there, as long as the generator still provides them. This keeps the hints stable
+    /// when the same data is matched again.
+    ///
+    /// The other hits get the hints in the order they are generated, so usually the
+    /// shortest ones first, in the order of the hits. If `hit_distances` are given,
+    /// the hits with the lowest distances get them first instead. A hit skips the hints
+    /// its text starts with for another hint of the same length, if there is one, so
+    /// that the hint can not be mistaken for the text under or after it.
+    pub fn new(
+        hits: Vec<Hit>,
+        hint_generator: &dyn HintGenerator,
+        previous_hints: &HashMap<String, String>,
+        hit_distances: Option<&[usize]>,
+        deduplicate: bool,
+    ) -> Self {
+        // Every hit belongs to a group of hits that get the same hint,
+        // identified by the index of its first hit
+        let mut first_hits = HashMap::<&str, usize>::new();
+        let groups: Vec<usize> = hits
+            .iter()
+            .enumerate()
+            .map(|(index, hit)| match deduplicate {
+                true => *first_hits.entry(hit.text.as_ref()).or_insert(index),
+                false => index,
+            })
+            .collect();
+
+        // Every group gets the priority of its hit with the lowest distance
+        let mut group_priorities = HashMap::<usize, usize>::new();
+        let mut group_sizes = HashMap::<usize, usize>::new();
+        for (index, group) in groups.iter().enumerate() {
+            *group_sizes.entry(*group).or_default() += 1;
+
+            let priority = hit_distances
+                .and_then(|distances| distances.get(index).copied())
+                .unwrap_or(index);
+
+            group_priorities
+                .entry(*group)
+                .and_modify(|group_priority| *group_priority = priority.min(*group_priority))
+                .or_insert(priority);
+        }
+        // The groups with the same priority stay in the order of the hits
+        let mut ordered_groups: Vec<usize> = group_priorities.keys().copied().collect();
+        ordered_groups.sort_by_key(|group| (group_priorities[group], *group));
+
+        let targets: Vec<HintTarget> = ordered_groups
+            .iter()
+            .map(|group| HintTarget {
+                start: hits[*group].start,
+                length: hits[*group].length,
+                frequency: group_sizes[group],
+            })
+            .collect();
+        info!("Number of unique hits {}", targets.len());
+        let hints: Vec<Arc<str>> = hint_generator
+            .create_hints(&targets)
+            .into_iter()
+            .map(Arc::from)
+            .collect();
+
+        let mut group_hints = HashMap::<usize, Arc<str>>::new();
+        let mut kept_hints = HashSet::<&str>::new();
+
+        for group in ordered_groups.iter() {
+            let text = &hits[*group].text;
+            let Some(previous_hint) = previous_hints.get(text.as_ref()) else {
+                continue;
+            };
+            let hint = hints
+                .iter()
+                .find(|hint| hint.as_ref() == previous_hint.as_str());
+            if let Some(hint) = hint.filter(|hint| !kept_hints.contains(hint.as_ref())) {
+                trace!("Keeping previous hint {} for hit <{}>", hint, text);
+                kept_hints.insert(hint.as_ref());
+                group_hints.insert(*group, hint.clone());
+            }
+        }
+        let mut available_hints: Vec<&Arc<str>> = hints
             .iter()
-            .map(|hit| hit.text.clone())
-            .collect::<HashSet<String>>()
-            .len();
-        info!("Number of unique hits {unique_hit_count}");
-        let hints = hint_generator.create_hints(unique_hit_count);
-        let mut hint_iter = hints.iter();
-
-        let mut hit_hint_map = HashMap::<String, String>::new();
-        let mut pairs: Vec<(String, Hit)> = vec![];
-
-        for hit in hits.into_iter() {
-            let hint = if hit_hint_map.contains_key(&hit.text) {
-                trace!("Hit <{}> already in hit_hint_map", hit.text);
-                hit_hint_map[&hit.text].clone()
-            } else if let Some(hint) = hint_iter.next() {
-                trace!("Using new hint {} for hit <{}>", hint, hit.text);
-                hit_hint_map.insert(hit.text.clone(), hint.clone());
-                hint.clone()
+            .filter(|hint| !kept_hints.contains(hint.as_ref()))
+            .collect();
+
+        for group in ordered_groups {
+            let text = &hits[group].text;
+            let Entry::Vacant(entry) = group_hints.entry(group) else {
+                trace!("Hit <{}> already has a hint", text);
+                continue;
+            };
+            if let Some(index) = choose_hint(&available_hints, text) {
+                let hint = available_hints.remove(index);
+                trace!("Using new hint {} for hit <{}>", hint, text);
+                entry.insert(hint.clone());
             } else {
                 info!("Not enough hints for all the hits, giving up");
                 break;
-            };
-
-            pairs.push((hint, hit));
+            }
         }
 
+        let pairs = hits
+            .into_iter()
+            .zip(groups)
+            .filter_map(|(hit, group)| group_hints.get(&group).map(|hint| (hint.clone(), hit)))
+            .collect();
+
         Self { pairs }
     }
 
+    /// Get the hint assigned to each of the hit texts, to be passed to
+    /// [HintHitMap::new] when the data is matched again. If the texts are
+    /// not deduplicated, this is the hint of their first hit.
+    pub fn get_assignments(&self) -> HashMap<String, String> {
+        // The later hits are overwritten by the earlier ones
+        self.pairs
+            .iter()
+            .rev()
+            .map(|(hint, hit)| (hit.text.to_string(), hint.to_string()))
+            .collect()
+    }
+
     /// Get the first [Hit] associated with the given hint string.
     pub fn get_hit(&self, hint: &str) -> Option<&Hit> {
         self.pairs //
             .iter()
-            .find_map(
-                |(pair_hint, hit)| {
-                    if pair_hint == hint {
-                        Some(hit)
-                    } else {
-                        None
-                    }
-                },
-            )
+            .find_map(|(pair_hint, hit)| {
+                if pair_hint.as_ref() == hint {
+                    Some(hit)
+                } else {
+                    None
+                }
+            })
     }
 
     /// Check if the map contains a hint beginning with the given prefix.
@@ -87,21 +201,55 @@ impl HintHitMap {
             .iter()
             .any(|(pair_hint, _)| pair_hint.starts_with(prefix))
     }
+
+    /// Check if the map contains a hint that begins with the given prefix
+    /// and is longer than it.
+    pub fn has_longer_hint_with_prefix(&self, prefix: &str) -> bool {
+        self.pairs
+            .iter()
+            .any(|(pair_hint, _)| pair_hint.len() > prefix.len() && pair_hint.starts_with(prefix))
+    }
+}
+
+/// Choose the index of the hint to give to the hit with the given text from
+/// the available hints, preferring the first one that the text does not start
+/// with among the ones with the same length as the first one.
+fn choose_hint(available_hints: &[&Arc<str>], text: &str) -> Option<usize> {
+    let first_length = available_hints.first()?.chars().count();
+    let text = text.to_lowercase();
+
+    let index = available_hints
+        .iter()
+        .take_while(|hint| hint.chars().count() == first_length)
+        .position(|hint| !text.starts_with(&hint.to_lowercase()))
+        .unwrap_or(0);
+
+    Some(index)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::hints::MockHintGenerator;
     use test_case::test_case;
 
+    fn create_hit(start: usize, text: &str) -> Hit {
+        Hit {
+            start,
+            length: text.len(),
+            text: text.into(),
+            regex_index: 0,
+        }
+    }
+
     #[test]
     fn get_hit_returns_some_hit_when_exists() {
         let hint_hit_map = HintHitMap {
             #[rustfmt::skip]
             pairs: vec![
-                ("a".to_string(), Hit {start: 0, length: 5, text: "stuff".to_string() }),
-                ("b".to_string(), Hit {start: 5, length: 6, text: "things".to_string() }),
-                ("c".to_string(), Hit {start: 9, length: 7, text: "fidgets".to_string() }),
+                ("a".into(), Hit {start: 0, length: 5, text: "stuff".into(), regex_index: 0 }),
+                ("b".into(), Hit {start: 5, length: 6, text: "things".into(), regex_index: 0 }),
+                ("c".into(), Hit {start: 9, length: 7, text: "fidgets".into(), regex_index: 0 }),
             ],
         };
 
@@ -114,9 +262,9 @@ mod test {
         let hint_hit_map = HintHitMap {
             #[rustfmt::skip]
             pairs: vec![
-                ("a".to_string(), Hit {start: 0, length: 5, text: "stuff".to_string() }),
-                ("b".to_string(), Hit {start: 5, length: 6, text: "things".to_string() }),
-                ("c".to_string(), Hit {start: 9, length: 7, text: "fidgets".to_string() }),
+                ("a".into(), Hit {start: 0, length: 5, text: "stuff".into(), regex_index: 0 }),
+                ("b".into(), Hit {start: 5, length: 6, text: "things".into(), regex_index: 0 }),
+                ("c".into(), Hit {start: 9, length: 7, text: "fidgets".into(), regex_index: 0 }),
             ],
         };
 
@@ -130,13 +278,226 @@ mod test {
         let hint_hit_map = HintHitMap {
             #[rustfmt::skip]
             pairs: vec![
-                ("aa".to_string(), Hit {start: 0, length: 5, text: "stuff".to_string() }),
-                ("ab".to_string(), Hit {start: 5, length: 6, text: "things".to_string() }),
-                ("ac".to_string(), Hit {start: 9, length: 7, text: "fidgets".to_string() }),
+                ("aa".into(), Hit {start: 0, length: 5, text: "stuff".into(), regex_index: 0 }),
+                ("ab".into(), Hit {start: 5, length: 6, text: "things".into(), regex_index: 0 }),
+                ("ac".into(), Hit {start: 9, length: 7, text: "fidgets".into(), regex_index: 0 }),
             ],
         };
 
         let has = hint_hit_map.has_hint_with_prefix(prefix);
         assert_eq!(has, expected);
     }
+
+    #[test_case("a", true)]
+    #[test_case("ab", false)]
+    #[test_case("x", false)]
+    fn has_longer_hint_with_prefix_returns_expected_value(prefix: &str, expected: bool) {
+        let hint_hit_map = HintHitMap {
+            #[rustfmt::skip]
+            pairs: vec![
+                ("a".into(), Hit {start: 0, length: 5, text: "stuff".into(), regex_index: 0 }),
+                ("ab".into(), Hit {start: 5, length: 6, text: "things".into(), regex_index: 0 }),
+            ],
+        };
+
+        let has = hint_hit_map.has_longer_hint_with_prefix(prefix);
+        assert_eq!(has, expected);
+    }
+
+    #[test]
+    fn new_keeps_previous_hints_that_are_still_available() {
+        let mut hint_generator = MockHintGenerator::new();
+        hint_generator.expect_create_hints().return_const(vec![
+            "a".to_string(),
+            "b".to_string(),
+            "c".to_string(),
+        ]);
+        let previous_hints = HashMap::from([
+            ("things".to_string(), "c".to_string()),
+            ("gone".to_string(), "a".to_string()),
+            ("stuff".to_string(), "x".to_string()),
+        ]);
+
+        let hint_hit_map = HintHitMap::new(
+            vec![
+                create_hit(0, "stuff"),
+                create_hit(6, "widgets"),
+                create_hit(14, "things"),
+                create_hit(21, "stuff"),
+            ],
+            &hint_generator,
+            &previous_hints,
+            None,
+            true,
+        );
+
+        let hints = hint_hit_map
+            .pairs
+            .iter()
+            .map(|(hint, hit)| (hint.as_ref(), hit.text.as_ref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hints,
+            vec![
+                ("a", "stuff"),
+                ("b", "widgets"),
+                ("c", "things"),
+                ("a", "stuff")
+            ]
+        );
+    }
+
+    #[test]
+    fn new_gives_first_hints_to_hits_with_lowest_distances() {
+        let mut hint_generator = MockHintGenerator::new();
+        hint_generator.expect_create_hints().return_const(vec![
+            "a".to_string(),
+            "b".to_string(),
+            "cc".to_string(),
+        ]);
+
+        let hint_hit_map = HintHitMap::new(
+            vec![
+                create_hit(0, "stuff"),
+                create_hit(6, "widgets"),
+                create_hit(14, "things"),
+                create_hit(21, "stuff"),
+            ],
+            &hint_generator,
+            &HashMap::new(),
+            Some(&[3, 2, 1, 0]),
+            true,
+        );
+
+        let hints = hint_hit_map
+            .pairs
+            .iter()
+            .map(|(hint, hit)| (hint.as_ref(), hit.text.as_ref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hints,
+            vec![
+                ("a", "stuff"),
+                ("cc", "widgets"),
+                ("b", "things"),
+                ("a", "stuff")
+            ]
+        );
+    }
+
+    #[test_case(&["a", "s", "d"], &["s", "a", "d"]; "when_other_hint_has_same_length")]
+    #[test_case(&["a", "ss", "sd"], &["a", "ss", "sd"]; "when_other_hint_is_longer")]
+    fn new_avoids_hints_that_text_starts_with(hints: &[&str], expected: &[&str]) {
+        let mut hint_generator = MockHintGenerator::new();
+        hint_generator.expect_create_hints().return_const(
+            hints
+                .iter()
+                .map(|hint| hint.to_string())
+                .collect::<Vec<_>>(),
+        );
+
+        let hint_hit_map = HintHitMap::new(
+            vec![
+                create_hit(0, "Apple"),
+                create_hit(6, "stuff"),
+                create_hit(12, "widgets"),
+            ],
+            &hint_generator,
+            &HashMap::new(),
+            None,
+            true,
+        );
+
+        let hints = hint_hit_map
+            .pairs
+            .iter()
+            .map(|(hint, _)| hint.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(hints, expected);
+    }
+
+    #[test]
+    fn new_gives_own_hint_to_every_hit_when_not_deduplicating() {
+        let mut hint_generator = MockHintGenerator::new();
+        hint_generator
+            .expect_create_hints()
+            .withf(|targets| targets.len() == 3)
+            .return_const(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+
+        let hint_hit_map = HintHitMap::new(
+            vec![
+                create_hit(0, "stuff"),
+                create_hit(6, "things"),
+                create_hit(13, "stuff"),
+            ],
+            &hint_generator,
+            &HashMap::new(),
+            None,
+            false,
+        );
+
+        let hints = hint_hit_map
+            .pairs
+            .iter()
+            .map(|(hint, hit)| (hint.as_ref(), hit.start))
+            .collect::<Vec<_>>();
+        assert_eq!(hints, vec![("a", 0), ("b", 6), ("c", 13)]);
+        assert_eq!(hint_hit_map.get_hit("c").unwrap().start, 13);
+        assert_eq!(hint_hit_map.get_assignments()["stuff"], "a");
+    }
+
+    #[test]
+    fn new_passes_details_of_hits_to_generator_in_priority_order() {
+        let expected = vec![
+            HintTarget {
+                start: 6,
+                length: 6,
+                frequency: 1,
+            },
+            HintTarget {
+                start: 0,
+                length: 5,
+                frequency: 2,
+            },
+        ];
+        let mut hint_generator = MockHintGenerator::new();
+        hint_generator
+            .expect_create_hints()
+            .withf(move |targets| *targets == expected)
+            .return_const(vec!["a".to_string(), "b".to_string()]);
+
+        let hint_hit_map = HintHitMap::new(
+            vec![
+                create_hit(0, "stuff"),
+                create_hit(6, "things"),
+                create_hit(13, "stuff"),
+            ],
+            &hint_generator,
+            &HashMap::new(),
+            Some(&[2, 0, 1]),
+            true,
+        );
+
+        assert_eq!(hint_hit_map.get_assignments()["things"], "a");
+    }
+
+    #[test]
+    fn get_assignments_returns_hint_of_each_text() {
+        let hint_hit_map = HintHitMap {
+            #[rustfmt::skip]
+            pairs: vec![
+                ("a".into(), Hit {start: 0, length: 5, text: "stuff".into(), regex_index: 0 }),
+                ("b".into(), Hit {start: 5, length: 6, text: "things".into(), regex_index: 0 }),
+                ("a".into(), Hit {start: 9, length: 5, text: "stuff".into(), regex_index: 0 }),
+            ],
+        };
+
+        assert_eq!(
+            hint_hit_map.get_assignments(),
+            HashMap::from([
+                ("stuff".to_string(), "a".to_string()),
+                ("things".to_string(), "b".to_string()),
+            ])
+        );
+    }
 }
@@ -0,0 +1,239 @@
+//! Snapshot tests of the draw instructions of the default regex modes.
+//!
+//! Every mode of the default config is run on every fixture in
+//! `snapshots/fixtures` and the resulting draw instructions are compared
+//! with `snapshots/<fixture>.snap`, both as a text-art drawing that shows
+//! where the highlights and the hints end up, and serialized in full.
+//!
+//! A missing snapshot fails the test like a changed one. To record the
+//! snapshot of a new fixture or to accept the changed output after a
+//! deliberate change, run the tests with `UPDATE_SNAPSHOTS=1` and review the
+//! diff of the snapshots.
+use std::{
+    collections::{HashMap, VecDeque},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    configuration::{Config, ModeArgs},
+    hints::HintPoolGenerator,
+    rendering::get_style_map,
+};
+
+use super::*;
+
+fn get_snapshots_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("src/modes/regex/snapshots")
+}
+
+/// Draw the data the way the renderer shows it, with the styled parts
+/// marked on the row under each line. The parts styled the same way are
+/// marked with the same letter, listed at the top, and the inserted text
+/// is marked with `+`.
+fn draw_text_art(data: &str, instructions: &[DrawInstruction]) -> String {
+    let mut art = String::new();
+
+    for instruction in instructions {
+        let DrawInstruction::StyledData {
+            styled_segments,
+            text_overlays,
+        } = instruction
+        else {
+            art += &format!("{instruction:?}\n");
+            continue;
+        };
+
+        let mut style_letters: Vec<TextStyle> = vec![];
+        for segment in styled_segments {
+            if !style_letters.contains(&segment.style) {
+                art += &format!(
+                    "{}: {:?} on {:?}\n",
+                    get_style_letter(style_letters.len()),
+                    segment.style.foreground,
+                    segment.style.background
+                );
+                style_letters.push(segment.style);
+            }
+        }
+
+        let data = data.strip_suffix('\n').unwrap_or(data);
+        let styles = get_style_map(styled_segments, data.len());
+        let mut overlays = HashMap::new();
+        for overlay in text_overlays {
+            overlays.entry(overlay.location).or_insert(overlay);
+        }
+
+        let mut overlay_chars = VecDeque::new();
+        let mut line = String::new();
+        let mut marks = String::new();
+        for (position, char) in data.char_indices() {
+            match overlays.get(&position) {
+                Some(DataOverlay {
+                    text,
+                    kind: OverlayKind::Replace,
+                    ..
+                }) => overlay_chars.extend(text.chars()),
+                Some(DataOverlay {
+                    text,
+                    kind: OverlayKind::Insert(_),
+                    ..
+                }) => {
+                    line += text;
+                    marks += &"+".repeat(text.chars().count());
+                }
+                None => (),
+            }
+
+            let shown = overlay_chars.pop_front().unwrap_or(char);
+            if shown == '\n' {
+                push_line(&mut art, &line, &marks);
+                line.clear();
+                marks.clear();
+                continue;
+            }
+
+            line.push(shown);
+            marks.push(match styles[position] {
+                Some(style) => {
+                    let index = style_letters.iter().position(|known| *known == style);
+                    get_style_letter(index.unwrap_or_default())
+                }
+                None => ' ',
+            });
+        }
+        push_line(&mut art, &line, &marks);
+    }
+
+    art
+}
+
+fn get_style_letter(index: usize) -> char {
+    (b'a' + index as u8) as char
+}
+
+fn push_line(art: &mut String, line: &str, marks: &str) {
+    *art += &format!("| {line}\n");
+    let marks = marks.trim_end();
+    if !marks.is_empty() {
+        *art += &format!("  {marks}\n");
+    }
+}
+
+/// Get the snapshot of every regex mode of the default config run on the
+/// given data.
+fn get_snapshot(data: &str) -> String {
+    let config = Config::default();
+    let hint_generator =
+        HintPoolGenerator::new(&config.hint_characters, config.fixed_hint_length, None);
+
+    let mut snapshot = String::new();
+    for mode_config in &config.modes {
+        let ModeArgs::RegexMode(args) = &mode_config.args else {
+            continue;
+        };
+
+        let mode = RegexMode::new(data, args, &hint_generator, &config).unwrap();
+        let instructions = mode.get_draw_instructions();
+
+        snapshot += &format!("### {}\n", mode_config.name);
+        snapshot += &draw_text_art(data, &instructions);
+        snapshot += "---\n";
+        snapshot += &serde_yaml::to_string(&instructions).unwrap();
+        snapshot += "\n";
+    }
+
+    snapshot
+}
+
+/// Compare the snapshot with the given name with the actual one, see the
+/// module documentation. Returns whether they are the same, which a missing
+/// snapshot is not.
+fn check_snapshot(name: &str, actual: &str) -> bool {
+    let path = get_snapshots_dir().join(format!("{name}.snap"));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, actual).unwrap();
+        return true;
+    }
+
+    let Ok(expected) = fs::read_to_string(&path) else {
+        eprintln!(
+            "Snapshot {} is missing, the actual one is:\n{actual}",
+            path.display()
+        );
+        return false;
+    };
+    if expected != actual {
+        eprintln!(
+            "Snapshot {} changed, the actual one is:\n{actual}",
+            path.display()
+        );
+        return false;
+    }
+
+    true
+}
+
+#[test]
+fn draw_instructions_match_snapshots() {
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(get_snapshots_dir().join("fixtures"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty());
+
+    let mut changed = vec![];
+    for fixture in fixtures {
+        let name = fixture.file_stem().unwrap().to_string_lossy().into_owned();
+        let data = fs::read_to_string(&fixture).unwrap();
+
+        if !check_snapshot(&name, &get_snapshot(&data)) {
+            changed.push(name);
+        }
+    }
+
+    assert!(
+        changed.is_empty(),
+        "Changed or missing snapshots: {changed:?}, run with UPDATE_SNAPSHOTS=1 to accept \
+         the changes"
+    );
+}
+
+#[test]
+fn text_art_marks_styled_parts_and_overlays() {
+    let style = TextStyle {
+        foreground: Color::Red,
+        background: Color::Reset,
+    };
+    let instructions = [DrawInstruction::StyledData {
+        styled_segments: vec![StyledSegment {
+            start: 6,
+            length: 5,
+            style,
+        }],
+        text_overlays: vec![
+            DataOverlay {
+                text: "ab".to_string(),
+                location: 6,
+                kind: OverlayKind::Replace,
+            },
+            DataOverlay {
+                text: "x".to_string(),
+                location: 12,
+                kind: OverlayKind::Insert(style),
+            },
+        ],
+    }];
+
+    let art = draw_text_art("hello world\nagain\n", &instructions);
+
+    assert_eq!(
+        art,
+        "a: Red on Reset\n\
+         | hello abrld\n        \
+           aaaaa\n\
+         | xagain\n  \
+         +\n"
+    );
+}
@@ -3,6 +3,7 @@ use std::ops::Deref;
 use crate::{
     configuration::{Config, RegexArgs},
     hints::MockHintGenerator,
+    modes::HitOrigin,
 };
 use test_case::test_case;
 
@@ -27,13 +28,25 @@ fn get_draw_instructions(
         .into_iter()
         .map(|regex| Regex::new(&regex).unwrap())
         .collect();
-    let args = RegexArgs { regexes };
+    let args = RegexArgs {
+        regexes,
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
 
+    get_draw_instructions_for_args(text, &args, hints)
+}
+
+fn get_draw_instructions_for_args(
+    text: &str,
+    args: &RegexArgs,
+    hints: Vec<String>,
+) -> (Vec<DataOverlay>, Vec<StyledSegment>) {
     let mut hint_generator = Box::new(MockHintGenerator::new());
     hint_generator.expect_create_hints().return_const(hints);
 
     let config = Config::default();
-    let mode = RegexMode::new(text, &args, hint_generator.deref(), &config).unwrap();
+    let mode = RegexMode::new(text, args, hint_generator.deref(), &config).unwrap();
     match mode.get_draw_instructions().into_iter().next().unwrap() {
         DrawInstruction::StyledData {
             styled_segments,
@@ -110,6 +123,360 @@ fn produces_no_highlights_or_overlays_for_zero_length_match() {
     assert_eq!(styled_segments.len(), 0);
 }
 
+#[test]
+fn produces_no_highlights_or_overlays_for_matches_shorter_than_min_length() {
+    let args = RegexArgs {
+        regexes: vec![Regex::new(r"[a-z]+").unwrap()],
+        min_length: 4,
+        literal_matchers: Default::default(),
+    };
+
+    let (text_overlays, styled_segments) =
+        get_draw_instructions_for_args("a bb ccc dddd", &args, vec!["a".into(), "b".into()]);
+
+    assert_eq!(text_overlays.len(), 1);
+    assert!(has_overlay_at_location(&text_overlays, 9));
+
+    assert_eq!(styled_segments.len(), 2);
+    assert!(has_highlight(&styled_segments, 9, 4));
+}
+
+#[test]
+fn produces_inserted_overlays_without_hint_highlights_in_insert_position() {
+    let args = RegexArgs {
+        regexes: vec![Regex::new(r"[a-z]{4,}").unwrap()],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+    let config = Config {
+        hint_position: HintPosition::Insert,
+        ..Config::default()
+    };
+
+    let mut hint_generator = MockHintGenerator::new();
+    hint_generator
+        .expect_create_hints()
+        .return_const(vec!["a".to_string(), "b".to_string()]);
+
+    let mode = RegexMode::new("things and stuff", &args, &hint_generator, &config).unwrap();
+    let Some(DrawInstruction::StyledData {
+        styled_segments,
+        text_overlays,
+    }) = mode.get_draw_instructions().into_iter().next()
+    else {
+        panic!("RegexMode::get_draw_instructions() returned unexpected type")
+    };
+
+    assert_eq!(text_overlays.len(), 2);
+    assert!(text_overlays
+        .iter()
+        .all(|overlay| matches!(overlay.kind, OverlayKind::Insert(_))));
+
+    // Only the highlights for the hits themselves
+    assert_eq!(styled_segments.len(), 2);
+    assert!(has_highlight(&styled_segments, 0, 6));
+    assert!(has_highlight(&styled_segments, 11, 5));
+}
+
+fn create_mode_with_hints(text: &str, hints: &[&str]) -> RegexMode {
+    let args = RegexArgs {
+        regexes: vec![Regex::new(r"[a-z]{4,}").unwrap()],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+    let hints: Vec<String> = hints.iter().map(|hint| hint.to_string()).collect();
+
+    let mut hint_generator = MockHintGenerator::new();
+    hint_generator.expect_create_hints().return_const(hints);
+
+    RegexMode::new(text, &args, &hint_generator, &Config::default()).unwrap()
+}
+
+fn press(mode: &mut RegexMode, key: char) -> Option<ModeEvent> {
+    mode.handle_key_press(KeyPress::from_char(key))
+}
+
+#[test]
+fn selects_hit_after_erasing_mistyped_key() {
+    let mut mode = create_mode_with_hints("things and stuff", &["ab", "ba"]);
+
+    assert!(press(&mut mode, 'a').is_none());
+    mode.erase_key_press();
+    assert!(press(&mut mode, 'b').is_none());
+
+    let event = press(&mut mode, 'a');
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "stuff"));
+}
+
+#[test]
+fn selected_hit_comes_with_its_start() {
+    let mut mode = create_mode_with_hints("things and stuff", &["ab", "ba"]);
+
+    press(&mut mode, 'b');
+    let event = press(&mut mode, 'a');
+
+    assert!(matches!(
+        event,
+        Some(ModeEvent::TextSelected(
+            _,
+            Some(HitOrigin { start: 11, .. })
+        ))
+    ));
+}
+
+#[test]
+fn hits_come_with_index_of_regex_that_found_them() {
+    let args = RegexArgs {
+        regexes: vec![
+            Regex::new(r"[a-z]{4,}").unwrap(),
+            Regex::new(r"[0-9]+").unwrap(),
+        ],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+
+    let hits = RegexMode::find_hits("stuff 42", &args, &Config::default()).unwrap();
+
+    let regex_indexes: Vec<usize> = hits.iter().map(|hit| hit.regex_index).collect();
+    assert_eq!(regex_indexes, [0, 1]);
+}
+
+#[test]
+fn starts_over_when_prefix_cannot_match_any_hint() {
+    let mut mode = create_mode_with_hints("things and stuff", &["ab", "ba"]);
+
+    assert!(press(&mut mode, 'a').is_none());
+    // "ax" cannot match anything, so the typed prefix is discarded
+    assert!(press(&mut mode, 'x').is_none());
+    assert!(press(&mut mode, 'b').is_none());
+
+    let event = press(&mut mode, 'a');
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "stuff"));
+}
+
+#[test]
+fn clear_key_presses_reports_whether_anything_was_cleared() {
+    let mut mode = create_mode_with_hints("things and stuff", &["ab", "ba"]);
+
+    assert!(!mode.clear_key_presses());
+
+    press(&mut mode, 'a');
+    assert!(mode.clear_key_presses());
+    assert!(!mode.clear_key_presses());
+}
+
+#[test]
+fn accepts_hit_that_has_the_focus() {
+    let mut mode = create_mode_with_hints("things and\nstuff here", &["a", "b", "c"]);
+
+    assert!(mode.accept_focused().is_none());
+
+    // The focus starts at the first hit
+    mode.move_focus(Direction::Right);
+    mode.move_focus(Direction::Down);
+    mode.move_focus(Direction::Right);
+
+    let event = mode.accept_focused();
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "here"));
+}
+
+#[test_case(Direction::Right, Some(1))]
+#[test_case(Direction::Left, None)]
+#[test_case(Direction::Down, Some(2))]
+#[test_case(Direction::Up, None)]
+fn find_nearest_returns_expected_index(direction: Direction, expected: Option<usize>) {
+    let positions = [(0, 0), (0, 4), (2, 1), (3, 0)];
+
+    assert_eq!(find_nearest(&positions, 0, direction), expected);
+}
+
+#[test_case("abc", 2, (0, 2))]
+#[test_case("abc\ndef", 5, (1, 1))]
+#[test_case("😀😀\nab😀a", 15, (1, 4))]
+fn get_screen_position_returns_expected_value(
+    data: &str,
+    byte_offset: usize,
+    expected: (usize, usize),
+) {
+    assert_eq!(get_screen_position(data, byte_offset), expected);
+}
+
+#[test]
+fn select_all_command_selects_unique_hits_in_order_of_appearance() {
+    let mut mode = create_mode_with_hints("things and stuff and things", &["a", "b"]);
+
+    let event = mode.handle_command("select_all");
+
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "things\nstuff"));
+}
+
+#[test_case(&['2'], Some("stuff"))]
+#[test_case(&['1', '2'], None)]
+#[test_case(&['0'], None)]
+#[test_case(&['3', 'a', '1'], Some("things"))]
+fn selects_nth_hit_after_typing_number(keys: &[char], expected: Option<&str>) {
+    let mut mode = create_mode_with_hints("things and\nstuff here", &["ab", "ba", "ca"]);
+
+    for key in keys {
+        assert!(press(&mut mode, *key).is_none());
+    }
+
+    let selected = match mode.accept_focused() {
+        Some(ModeEvent::TextSelected(text, _)) => Some(text),
+        _ => None,
+    };
+    assert_eq!(selected.as_deref(), expected);
+}
+
+#[test]
+fn waits_for_confirmation_when_typed_hint_is_ambiguous() {
+    let mut mode = create_mode_with_hints("things and stuff", &["a", "ab"]);
+
+    assert!(press(&mut mode, 'a').is_none());
+    assert!(mode.get_key_press_timeout().is_none());
+
+    let event = mode.accept_focused();
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "things"));
+}
+
+#[test]
+fn selects_ambiguous_typed_hint_after_timeout() {
+    let mut mode = create_mode_with_hints("things and stuff", &["a", "ab"]);
+    mode.ambiguous_hint_timeout = Some(Duration::from_millis(500));
+
+    press(&mut mode, 'a');
+    assert_eq!(
+        mode.get_key_press_timeout(),
+        Some(Duration::from_millis(500))
+    );
+
+    let event = mode.handle_key_press_timeout();
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "things"));
+}
+
+#[test]
+fn selects_longer_hint_when_ambiguous_hint_is_continued() {
+    let mut mode = create_mode_with_hints("things and stuff", &["a", "ab"]);
+
+    press(&mut mode, 'a');
+    let event = press(&mut mode, 'b');
+
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "stuff"));
+}
+
+fn hit(start: usize, text: &str) -> Hit {
+    Hit {
+        start,
+        length: text.len(),
+        text: text.into(),
+        regex_index: 0,
+    }
+}
+
+#[test_case(HintLimitPolicy::First, &["aa", "bbbb", "aa"]; "with_first_policy")]
+#[test_case(HintLimitPolicy::Longest, &["bbbb", "ccc"]; "with_longest_policy")]
+#[test_case(HintLimitPolicy::Bottom, &["ccc", "d"]; "with_bottom_policy")]
+fn limit_hits_keeps_expected_hits(policy: HintLimitPolicy, expected: &[&str]) {
+    let hits = vec![
+        hit(0, "aa"),
+        hit(3, "bbbb"),
+        hit(8, "aa"),
+        hit(11, "ccc"),
+        hit(15, "d"),
+    ];
+
+    let hits = limit_hits(hits, 2, policy, true);
+    let texts: Vec<&str> = hits.iter().map(|hit| hit.text.as_ref()).collect();
+
+    assert_eq!(texts, expected);
+}
+
+#[test]
+fn find_hits_by_line_finds_same_hits_as_find_hits() {
+    let args = RegexArgs {
+        regexes: vec![
+            Regex::new(r"[a-z]{4,}").unwrap(),
+            Regex::new(r"\d+").unwrap(),
+        ],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+    let text = "things 12 and\nstuff 345 here";
+    let mut line_hits = HashMap::new();
+
+    let hits =
+        RegexMode::find_hits_by_line(text, &args, &Config::default(), &mut line_hits).unwrap();
+
+    assert_eq!(
+        hits,
+        RegexMode::find_hits(text, &args, &Config::default()).unwrap()
+    );
+    assert_eq!(line_hits.len(), 2);
+}
+
+#[test]
+fn literal_regexes_find_same_hits_as_regex_engine() {
+    let literal_args = RegexArgs {
+        regexes: vec![Regex::new(r"db-1\.local|db|api").unwrap()],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+    let regex_args = RegexArgs {
+        regexes: vec![Regex::new(r"(?:db-1\.local|db|api)").unwrap()],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+    let text = "api \x1b[31mdb-1.local\x1b[0m db-2.local apidb";
+
+    let hits = RegexMode::find_hits(text, &literal_args, &Config::default()).unwrap();
+
+    assert!(literal_args.get_literal_matchers()[0].is_some());
+    assert!(regex_args.get_literal_matchers()[0].is_none());
+    assert_eq!(
+        hits,
+        RegexMode::find_hits(text, &regex_args, &Config::default()).unwrap()
+    );
+}
+
+#[test]
+fn find_hits_by_line_reuses_hits_of_matched_lines() {
+    let args = RegexArgs {
+        regexes: vec![Regex::new(r"[a-z]{4,}").unwrap()],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+    let mut line_hits = HashMap::from([("stuff here".to_string(), vec![hit(0, "cached")])]);
+
+    let hits = RegexMode::find_hits_by_line(
+        "things and\nstuff here",
+        &args,
+        &Config::default(),
+        &mut line_hits,
+    )
+    .unwrap();
+
+    assert_eq!(hits, vec![hit(0, "things"), hit(11, "cached")]);
+}
+
+#[test]
+fn limit_hits_keeps_all_hits_when_under_limit() {
+    let hits = vec![hit(0, "aa"), hit(3, "bb"), hit(6, "aa")];
+
+    let hits = limit_hits(hits, 2, HintLimitPolicy::First, true);
+
+    assert_eq!(hits.len(), 3);
+}
+
+#[test]
+fn limit_hits_counts_every_hit_when_not_deduplicating() {
+    let hits = vec![hit(0, "aa"), hit(3, "bb"), hit(6, "aa")];
+
+    let hits = limit_hits(hits, 2, HintLimitPolicy::Bottom, false);
+    let starts: Vec<usize> = hits.iter().map(|hit| hit.start).collect();
+
+    assert_eq!(starts, vec![3, 6]);
+}
+
 #[test_case(&[(2,4), (6, 8)], 0, 0)]
 #[test_case(&[(2,4), (6, 8)], 1, 1)]
 #[test_case(&[(2,4), (6, 8)], 2, 4)]
@@ -123,3 +490,114 @@ fn get_original_index_returns_correct_value(
 ) {
     assert_eq!(get_original_index(removed_ranges, index), expected);
 }
+
+#[test_case("some words here", true; "when_text_has_hits")]
+#[test_case("a b c", false; "when_text_has_no_hits")]
+fn has_hits_returns_expected_value(text: &str, expected: bool) {
+    let mode = create_mode_with_hints(text, &["a", "b", "c"]);
+
+    assert_eq!(mode.has_hits(), expected);
+}
+
+#[test]
+fn get_hit_count_counts_all_hits() {
+    let mode = create_mode_with_hints("some words here", &["a", "b", "c"]);
+
+    assert_eq!(mode.get_hit_count(), 3);
+}
+
+fn create_mode_with_preview(text: &str, hints: &[&str]) -> RegexMode {
+    let args = RegexArgs {
+        regexes: vec![Regex::new(r"[a-z]{4,}").unwrap()],
+        min_length: 0,
+        literal_matchers: Default::default(),
+    };
+    let hints: Vec<String> = hints.iter().map(|hint| hint.to_string()).collect();
+
+    let mut hint_generator = MockHintGenerator::new();
+    hint_generator.expect_create_hints().return_const(hints);
+    let config = Config {
+        show_hit_preview: true,
+        ..Config::default()
+    };
+
+    RegexMode::new(text, &args, &hint_generator, &config).unwrap()
+}
+
+fn get_preview(mode: &RegexMode) -> Option<String> {
+    mode.get_draw_instructions()
+        .into_iter()
+        .find_map(|instruction| match instruction {
+            DrawInstruction::Prompt(text) => Some(text),
+            _ => None,
+        })
+}
+
+#[test]
+fn preview_shows_focused_hit_with_its_line() {
+    let mut mode = create_mode_with_preview(
+        "a things
+  \x1b[31mb stuff\x1b[0m c",
+        &["a", "b"],
+    );
+    mode.move_focus(Direction::Down);
+    mode.move_focus(Direction::Down);
+
+    assert_eq!(get_preview(&mode).unwrap(), "stuff  │ b stuff c");
+}
+
+#[test]
+fn preview_shows_hits_left_after_typed_keys() {
+    let mut mode = create_mode_with_preview("things and stuff here", &["aa", "ab", "ba"]);
+
+    assert_eq!(get_preview(&mode), None);
+    press(&mut mode, 'a');
+    assert_eq!(get_preview(&mode).unwrap(), "[aa] things  [ab] stuff");
+    press(&mut mode, 'b');
+    press(&mut mode, 'x');
+    assert_eq!(get_preview(&mode), None);
+}
+
+#[test]
+fn preview_is_not_shown_by_default() {
+    let mut mode = create_mode_with_hints("things and stuff", &["a", "b"]);
+    mode.move_focus(Direction::Down);
+
+    assert_eq!(get_preview(&mode), None);
+}
+
+#[test]
+fn restored_selection_state_continues_typed_hint() {
+    let mut mode = create_mode_with_hints("things and stuff", &["ab", "ba"]);
+    press(&mut mode, 'a');
+    let mut new_mode = create_mode_with_hints("things and stuff", &["ab", "ba"]);
+
+    new_mode.restore_selection_state(mode.get_selection_state());
+
+    let event = press(&mut new_mode, 'b');
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "things"));
+}
+
+#[test]
+fn restored_selection_state_drops_typed_hint_that_would_be_complete() {
+    let mut mode = create_mode_with_hints("things and stuff", &["ab", "ba"]);
+    press(&mut mode, 'a');
+    let mut new_mode = create_mode_with_hints("things and stuff", &["a", "b"]);
+
+    new_mode.restore_selection_state(mode.get_selection_state());
+
+    assert_eq!(new_mode.get_selection_state().typed_hint, "");
+}
+
+#[test]
+fn restored_selection_state_keeps_focus_on_same_text() {
+    let mut mode = create_mode_with_hints("things and\nstuff here", &["a", "b", "c"]);
+    mode.move_focus(Direction::Right);
+    mode.move_focus(Direction::Down);
+    let mut new_mode = create_mode_with_hints("stuff here", &["a", "b"]);
+
+    new_mode.restore_selection_state(mode.get_selection_state());
+
+    let event = new_mode.accept_focused();
+    assert!(matches!(event, Some(ModeEvent::TextSelected(text, _)) if text == "stuff"));
+}
@@ -0,0 +1,149 @@
+//! Modes that are not built in, registered under a name that the config
+//! refers to them with.
+use std::{
+    collections::BTreeMap,
+    sync::{PoisonError, RwLock},
+};
+
+use serde_yaml::Value;
+
+use crate::{configuration::Config, error::RunError, hints::HintGenerator, modes::Mode};
+
+/// Function that creates a mode for selecting from the given data, with the
+/// settings of the mode from the config other than `mode`, `hotkey`, `name`,
+/// `keybindings` and `action`.
+pub type ModeFactory = fn(
+    data: &str,
+    args: &Value,
+    hint_generator: &dyn HintGenerator,
+    config: &Config,
+) -> Result<Box<dyn Mode>, RunError>;
+
+/// A mode registered with [register_mode].
+#[derive(Clone, Copy)]
+struct RegisteredMode {
+    commands: &'static [&'static str],
+    factory: ModeFactory,
+}
+
+/// The registered modes by their names.
+static REGISTERED_MODES: RwLock<BTreeMap<String, RegisteredMode>> = RwLock::new(BTreeMap::new());
+
+/// Register a mode that can be used in the config with `mode: <name>`,
+/// along with the names of the commands that its keybindings can trigger,
+/// see [Mode::handle_command]. A mode registered under the same name
+/// before is replaced.
+///
+/// The modes need to be registered before the config is loaded, since the
+/// config is checked to contain only the known ones.
+pub fn register_mode(name: &str, commands: &'static [&'static str], factory: ModeFactory) {
+    REGISTERED_MODES
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(name.to_string(), RegisteredMode { commands, factory });
+}
+
+/// Get the names of the registered modes, in alphabetical order.
+pub fn get_registered_mode_names() -> Vec<String> {
+    REGISTERED_MODES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Get the commands of the mode registered under the given name, or [None]
+/// if there is no such mode.
+pub fn get_registered_mode_commands(name: &str) -> Option<&'static [&'static str]> {
+    get_registered_mode(name).map(|mode| mode.commands)
+}
+
+/// Create the mode registered under the given name for selecting from the
+/// given data, see [ModeFactory].
+pub fn create_registered_mode(
+    name: &str,
+    data: &str,
+    args: &Value,
+    hint_generator: &dyn HintGenerator,
+    config: &Config,
+) -> Result<Box<dyn Mode>, RunError> {
+    let Some(mode) = get_registered_mode(name) else {
+        return Err(RunError::UnknownMode {
+            name: name.to_string(),
+        });
+    };
+
+    (mode.factory)(data, args, hint_generator, config)
+}
+
+fn get_registered_mode(name: &str) -> Option<RegisteredMode> {
+    REGISTERED_MODES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(name)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input_handler::KeyPress, modes::ModeEvent, rendering::DrawInstruction};
+
+    struct WholeDataMode(String);
+
+    impl Mode for WholeDataMode {
+        fn handle_key_press(&mut self, _key: KeyPress) -> Option<ModeEvent> {
+            Some(ModeEvent::TextSelected(self.0.clone(), None))
+        }
+
+        fn get_draw_instructions(&self) -> Vec<DrawInstruction> {
+            vec![DrawInstruction::Data]
+        }
+    }
+
+    fn create_whole_data_mode(
+        data: &str,
+        _args: &Value,
+        _hint_generator: &dyn HintGenerator,
+        _config: &Config,
+    ) -> Result<Box<dyn Mode>, RunError> {
+        Ok(Box::new(WholeDataMode(data.to_string())))
+    }
+
+    #[test]
+    fn registered_mode_can_be_created() {
+        register_mode("registry_test_whole", &["select"], create_whole_data_mode);
+
+        let mut mode = create_registered_mode(
+            "registry_test_whole",
+            "some data",
+            &Value::Null,
+            &crate::hints::NumericGenerator,
+            &Config::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_registered_mode_commands("registry_test_whole"),
+            Some(&["select"][..])
+        );
+        assert!(matches!(
+            mode.handle_key_press(KeyPress::from_char('a')),
+            Some(ModeEvent::TextSelected(text, None)) if text == "some data"
+        ));
+    }
+
+    #[test]
+    fn creating_unregistered_mode_returns_error() {
+        let result = create_registered_mode(
+            "registry_test_missing",
+            "some data",
+            &Value::Null,
+            &crate::hints::NumericGenerator,
+            &Config::default(),
+        );
+
+        assert!(matches!(result, Err(RunError::UnknownMode { .. })));
+    }
+}
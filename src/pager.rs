@@ -40,7 +40,8 @@ fn clip_line(line: &str, rows: usize, row_width: usize) -> (String, usize) {
 }
 
 // Get largest substring from the source that can be rendered in the space of the given size.
-pub fn get_page(source: &mut dyn BufRead, rows: usize, cols: usize) -> String {
+// If `wrap_lines` is false, every line is truncated to a single row instead of wrapping.
+pub fn get_page(source: &mut dyn BufRead, rows: usize, cols: usize, wrap_lines: bool) -> String {
     let mut output_lines = vec![];
     let mut output_rows_remaining = rows;
 
@@ -53,7 +54,14 @@ pub fn get_page(source: &mut dyn BufRead, rows: usize, cols: usize) -> String {
             }
         };
 
-        let (line_clipped, line_rows) = clip_line(&line, output_rows_remaining, cols);
+        // Lines already come without CRLF, except for the last one
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+
+        let line_row_limit = match wrap_lines {
+            true => output_rows_remaining,
+            false => 1,
+        };
+        let (line_clipped, line_rows) = clip_line(line, line_row_limit, cols);
 
         output_lines.push(line_clipped);
 
@@ -72,6 +80,31 @@ pub fn get_page(source: &mut dyn BufRead, rows: usize, cols: usize) -> String {
     output_lines.join("\n")
 }
 
+/// Get the number of lines on the last page of the given text, i.e. on the
+/// page that ends with the last line.
+///
+/// If `wrap_lines` is false, every line is expected to take up a single row.
+pub fn get_last_page_line_count(text: &str, rows: usize, cols: usize, wrap_lines: bool) -> usize {
+    let mut rows_remaining = rows;
+    let mut line_count = 0;
+
+    for line in text.lines().rev() {
+        let line_rows = match wrap_lines {
+            true => clip_line(line, usize::MAX, cols).1,
+            false => 1,
+        };
+
+        if line_rows > rows_remaining {
+            // A line that does not fit on its own still makes up the page
+            return line_count.max(1);
+        }
+        rows_remaining -= line_rows;
+        line_count += 1;
+    }
+
+    line_count
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::BufReader;
@@ -86,13 +119,50 @@ mod tests {
     #[test_case("things and stuff\nstuff and things", 3, 10, "things and stuff\nstuff and "; "when_input_longer_and_wider_than_page")]
     #[test_case("fläder väder", 2, 6, "fläder väder"; "when_input_contains_non_ascii_characters")]
     #[test_case("😀😀abcde", 2, 4, "😀😀abcd"; "when_input_contains_emojis")]
+    #[test_case("test1\r\ntest2\r\n", 10, 10, "test1\ntest2"; "when_input_has_crlf_line_endings")]
+    #[test_case("test1\r\ntest2\r", 10, 10, "test1\ntest2"; "when_last_line_ends_with_carriage_return")]
     fn get_page_returns_expected_output(source: &str, rows: usize, cols: usize, expected: &str) {
         let mut source = Box::new(BufReader::new(source.as_bytes()));
-        let page = get_page(&mut source, rows, cols);
+        let page = get_page(&mut source, rows, cols, true);
+
+        assert_eq!(page, expected);
+    }
+
+    #[test_case("test\ntest", 10, 10, "test\ntest"; "when_input_shorter_than_page")]
+    #[test_case("things and stuff\nstuff and things", 10, 10, "things and\nstuff and "; "when_input_wider_than_page")]
+    #[test_case("things and stuff\nstuff\nthings", 2, 10, "things and\nstuff"; "when_input_longer_and_wider_than_page")]
+    fn get_page_without_wrapping_returns_expected_output(
+        source: &str,
+        rows: usize,
+        cols: usize,
+        expected: &str,
+    ) {
+        let mut source = Box::new(BufReader::new(source.as_bytes()));
+        let page = get_page(&mut source, rows, cols, false);
 
         assert_eq!(page, expected);
     }
 
+    #[test_case("", 10, 10, 0; "when_input_is_empty")]
+    #[test_case("a\nb\nc", 10, 10, 3; "when_input_shorter_than_page")]
+    #[test_case("a\nb\nc\nd", 2, 10, 2; "when_input_longer_than_page")]
+    #[test_case("a\nb\nlong line", 2, 5, 1; "when_last_line_is_wrapped")]
+    #[test_case("a\nvery long line", 2, 5, 1; "when_last_line_does_not_fit")]
+    #[test_case("abcde\r\nabcde\r\n", 2, 5, 2; "when_input_has_crlf_line_endings")]
+    fn get_last_page_line_count_returns_expected_value(
+        text: &str,
+        rows: usize,
+        cols: usize,
+        expected: usize,
+    ) {
+        assert_eq!(get_last_page_line_count(text, rows, cols, true), expected);
+    }
+
+    #[test]
+    fn get_last_page_line_count_without_wrapping_counts_lines() {
+        assert_eq!(get_last_page_line_count("a\nb\nlong line", 2, 5, false), 2);
+    }
+
     #[test_case("", 1, 5, ("", 1); "when_input_empty")]
     #[test_case("test", 1, 5, ("test", 1); "when_input_shorter_than_width")]
     #[test_case("testing", 1, 5, ("testi", 1); "when_input_longer_than_width")]
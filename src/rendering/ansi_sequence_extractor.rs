@@ -9,6 +9,7 @@ use snafu::ResultExt;
 use crate::error::{InvalidRegexSnafu, RunError};
 
 /// A struct to extract and store all ANSI sequences in a string
+#[derive(Default)]
 pub struct AnsiSequenceExtractor {
     ansi_sequences: Vec<AnsiSequenceEntry>,
 }
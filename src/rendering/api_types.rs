@@ -1,10 +1,11 @@
 //!Types used in the the renderer API
 use crossterm::style::Color;
+use serde::Serialize;
 
 /// Struct to describe text style.
 ///
 /// Used in [DrawInstruction].
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
 pub struct TextStyle {
     pub foreground: Color,
     pub background: Color,
@@ -13,7 +14,7 @@ pub struct TextStyle {
 /// Struct to describe a styled segment of data.
 ///
 /// Used in [DrawInstruction::StyledData].
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct StyledSegment {
     /// Byte offset of the start of the segment from the start of data.
     pub start: usize,
@@ -25,16 +26,28 @@ pub struct StyledSegment {
 /// Struct to describe text that is drawn over the data.
 ///
 /// Used in [DrawInstruction::StyledData].
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DataOverlay {
     // The text to draw.
     pub text: String,
     /// Byte offset from the start of data where to start drawing the text.
     pub location: usize,
+    /// How the text is combined with the data.
+    pub kind: OverlayKind,
+}
+
+/// Describes how the text of a [DataOverlay] is combined with the data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub enum OverlayKind {
+    /// The text replaces the data, taking over the style of the data.
+    Replace,
+    /// The text is inserted before the data, shifting it, and drawn with
+    /// the given style.
+    Insert(TextStyle),
 }
 
 /// Instruction to [super::Renderer] about what should be drawn to the screen.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum DrawInstruction {
     /// Draw the data without any additional processing.
     /// Has the same effect as [DrawInstruction::StyledData] without any
@@ -53,4 +66,19 @@ pub enum DrawInstruction {
     /// Draw the mode selection dialog where each mode is represented by a tuple
     /// (mode_hotkey, mode_description).
     ModeSelectionDialog(Vec<(char, String)>),
+    /// Draw the menu of the actions for the selection, where each action is
+    /// represented by a tuple (action_hotkey, action_name), the same way as
+    /// [DrawInstruction::ModeSelectionDialog].
+    ActionMenuDialog(Vec<(char, String)>),
+    /// Draw the help dialog where each entry is represented by a tuple
+    /// (keys, description).
+    HelpDialog(Vec<(String, String)>),
+    /// Draw the given text, e.g. a search prompt, on the last row of the screen.
+    Prompt(String),
+    /// Draw the given text, e.g. the name of the shown file, at the end of
+    /// the last row of the screen.
+    Status(String),
+    /// Draw the state of the application at the top right corner of the
+    /// screen, where each entry is represented by a tuple (name, value).
+    DebugOverlay(Vec<(String, String)>),
 }
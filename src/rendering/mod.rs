@@ -5,4 +5,13 @@ pub use renderer::*;
 mod api_types;
 pub use api_types::*;
 
+mod screen;
+pub use screen::*;
+
+mod text_screen;
+pub use text_screen::*;
+
+mod terminal_guard;
+pub use terminal_guard::*;
+
 mod ansi_sequence_extractor;
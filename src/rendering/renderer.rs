@@ -1,37 +1,67 @@
 //!Renderer struct that performs the actual rendering to the terminal.
-use std::{collections::VecDeque, io::Write};
+use std::collections::{HashMap, VecDeque};
 
 use crossterm::{
     cursor::{self, MoveTo},
+    event::{DisableMouseCapture, EnableMouseCapture},
     style::{self, Attribute, Print, ResetColor, SetAttribute, SetForegroundColor},
     terminal::{
-        self, disable_raw_mode, enable_raw_mode, Clear, ClearType, DisableLineWrap, EnableLineWrap,
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, DisableLineWrap, EnableLineWrap,
         EnterAlternateScreen, LeaveAlternateScreen,
     },
     QueueableCommand,
 };
 use log::trace;
 use snafu::ResultExt;
+use textwrap::core::display_width;
 
 use crate::error::RunError;
-use crate::{configuration, error::IoSnafu};
+use crate::{
+    configuration,
+    error::{IoSnafu, TerminalHandlingSnafu},
+};
 
 use super::ansi_sequence_extractor::AnsiSequenceExtractor;
-use super::{DataOverlay, StyledSegment, TextStyle};
+use super::{DataOverlay, OverlayKind, StyledSegment, TextStyle};
 
-use super::DrawInstruction;
+use super::{DrawInstruction, Screen};
 
 /// The struct intended for rendering everything to the terminal.
 ///
 /// Everything rendered to the terminal should come through the [Renderer::render] method.
-pub struct Renderer<T: Write + ?Sized> {
+/// The size of the screen is taken from the output, see [Screen].
+pub struct Renderer<T: Screen + ?Sized> {
+    /// The data drawn last, which [Renderer::ansi_sequences] were extracted from.
+    ansi_data: String,
+
+    /// The ANSI sequences of [Renderer::ansi_data], kept so that they are not
+    /// extracted again on every render while the same page is shown.
+    ansi_sequences: AnsiSequenceExtractor,
+
     /// The output which the rendering is performed.
-    ///
-    /// The type of this field will likely be replaced with [std::io::Stdout] in the future.
     pub output: T,
 }
 
-impl<T: Write + ?Sized> Renderer<T> {
+impl<T: Screen + ?Sized> Renderer<T> {
+    /// Create a new [Renderer] rendering to the given output.
+    pub fn new(output: T) -> Self
+    where
+        T: Sized,
+    {
+        Self {
+            ansi_data: String::new(),
+            ansi_sequences: AnsiSequenceExtractor::default(),
+            output,
+        }
+    }
+
+    /// Get the size of the screen as (rows, columns).
+    pub fn size(&self) -> Result<(usize, usize), RunError> {
+        self.output.size().context(TerminalHandlingSnafu {
+            operation: "get size",
+        })
+    }
+
     /// Render the given data and draw instructions to the terminal.
     ///
     /// Draw instructions are executed in the given order.
@@ -42,6 +72,7 @@ impl<T: Write + ?Sized> Renderer<T> {
         config: &configuration::Config,
     ) -> Result<(), RunError> {
         trace!("Rendering draw instructions {:#?}", draw_instructions);
+        let size = self.size()?;
 
         // Perform rendering into a buffer first, to avoid any blinking issues
         let mut buffer: Vec<u8> = vec![];
@@ -69,7 +100,18 @@ impl<T: Write + ?Sized> Renderer<T> {
                 }
                 DrawInstruction::Data => self.draw_styled_data(&mut buffer, data, &[], &[])?,
                 DrawInstruction::ModeSelectionDialog(modes) => {
-                    self.draw_mode_selection_dialog(&mut buffer, modes, config)?
+                    self.draw_mode_selection_dialog(&mut buffer, modes, size, config)?
+                }
+                DrawInstruction::ActionMenuDialog(actions) => {
+                    self.draw_mode_selection_dialog(&mut buffer, actions, size, config)?
+                }
+                DrawInstruction::HelpDialog(entries) => {
+                    self.draw_help_dialog(&mut buffer, entries, size, config)?
+                }
+                DrawInstruction::Prompt(text) => self.draw_prompt(&mut buffer, text, size)?,
+                DrawInstruction::Status(text) => self.draw_status(&mut buffer, text, size)?,
+                DrawInstruction::DebugOverlay(entries) => {
+                    self.draw_debug_overlay(&mut buffer, entries, size)?
                 }
             }
         }
@@ -90,9 +132,14 @@ impl<T: Write + ?Sized> Renderer<T> {
         text_overlays: &[DataOverlay],
     ) -> Result<(), RunError> {
         let mut overlay_chars: VecDeque<char> = VecDeque::new();
-        let ansi_sequences = AnsiSequenceExtractor::new(data)?;
         let mut last_intra_segment_style = None;
 
+        if self.ansi_data != data {
+            self.ansi_sequences = AnsiSequenceExtractor::new(data)?;
+            self.ansi_data = data.to_string();
+        }
+        let ansi_sequences = &self.ansi_sequences;
+
         // Ignore the terminating new line if present
         let data_range = match data.as_bytes().last() {
             Some(b'\n') => 0..(data.len() - 1),
@@ -103,31 +150,52 @@ impl<T: Write + ?Sized> Renderer<T> {
             trace!("Styled segment to draw {segment:?}")
         }
 
+        let styles = get_style_map(styled_segments, data_range.len());
+        let mut overlays = HashMap::new();
+        for overlay in text_overlays {
+            overlays.entry(overlay.location).or_insert(overlay);
+        }
+
         for (byte_position, char) in data[data_range].char_indices() {
             // Handle start of overlay
-            let overlay = text_overlays
-                .iter()
-                .find(|overlay| overlay.location == byte_position);
-
-            if let Some(DataOverlay { text, .. }) = overlay {
-                text.chars().for_each(|char| overlay_chars.push_back(char));
+            let overlay = overlays.get(&byte_position).copied();
+
+            match overlay {
+                Some(DataOverlay {
+                    text,
+                    kind: OverlayKind::Replace,
+                    ..
+                }) => {
+                    text.chars().for_each(|char| overlay_chars.push_back(char));
+                }
+                Some(DataOverlay {
+                    text,
+                    kind: OverlayKind::Insert(style),
+                    ..
+                }) => {
+                    // Draw the inserted text as if it was a styled segment of
+                    // its own, the style is restored below when needed
+                    let inserted_style = Some(*style);
+                    self.update_style(
+                        &last_intra_segment_style,
+                        &inserted_style,
+                        ansi_sequences,
+                        buffer,
+                        byte_position,
+                    )?;
+                    buffer.queue(Print(text)).context(IoSnafu {})?;
+                    last_intra_segment_style = inserted_style;
+                }
+                None => (),
             }
 
             // Style from segments
-            let intra_segment_style = styled_segments.iter().rev().find_map(|segment| {
-                if byte_position >= segment.start
-                    && byte_position < (segment.start + segment.length)
-                {
-                    Some(segment.style)
-                } else {
-                    None
-                }
-            });
+            let intra_segment_style = styles.get(byte_position).copied().flatten();
 
             self.update_style(
                 &last_intra_segment_style,
                 &intra_segment_style,
-                &ansi_sequences,
+                ansi_sequences,
                 buffer,
                 byte_position,
             )?;
@@ -153,18 +221,18 @@ impl<T: Write + ?Sized> Renderer<T> {
         Ok(())
     }
 
-    /// Draw the mode selection dialog. The styling of the dialog is completely controled
-    /// by the renderer.
+    /// Draw the mode selection dialog on a screen with the given (rows, columns).
+    /// The styling of the dialog is completely controled by the renderer.
     fn draw_mode_selection_dialog(
         &mut self,
         buffer: &mut Vec<u8>,
         modes: &[(char, String)],
+        (rows, cols): (usize, usize),
         config: &configuration::Config,
     ) -> Result<(), RunError> {
         let dialog_width: usize = config.mode_switch_width;
         let start_row = 1; // to have a top padding
-
-        let (cols, rows) = terminal::size().context(IoSnafu {})?;
+        let (rows, cols) = (rows as u16, cols as u16);
 
         // If there is not enough space to draw the dialog, just don't
         if cols <= dialog_width as u16 {
@@ -214,6 +282,168 @@ impl<T: Write + ?Sized> Renderer<T> {
         Ok(())
     }
 
+    /// Draw the help dialog over the whole screen with the given (rows, columns).
+    /// Like the mode selection dialog, it uses the mode switching colors.
+    fn draw_help_dialog(
+        &mut self,
+        buffer: &mut Vec<u8>,
+        entries: &[(String, String)],
+        (rows, cols): (usize, usize),
+        config: &configuration::Config,
+    ) -> Result<(), RunError> {
+        let rows = rows as u16;
+
+        let keys_width = entries
+            .iter()
+            .map(|(keys, _)| display_width(keys))
+            .max()
+            .unwrap_or(0);
+
+        buffer.queue(DisableLineWrap).context(IoSnafu {})?;
+
+        // Draw the empty rows as well to hide the data underneath
+        let mut entries_iter = entries.iter();
+        for row in 0..rows {
+            buffer
+                .queue(MoveTo(0, row))
+                .context(IoSnafu {})?
+                .queue(ResetColor)
+                .context(IoSnafu {})?
+                .queue(Print(format!("{:cols$}", "")))
+                .context(IoSnafu {})?;
+
+            // Leave the first row empty as a top padding
+            if row == 0 {
+                continue;
+            }
+
+            if let Some((keys, description)) = entries_iter.next() {
+                let padding = keys_width - display_width(keys);
+                buffer
+                    .queue(MoveTo(0, row))
+                    .context(IoSnafu {})?
+                    .queue(SetForegroundColor(config.mode_switch_hotkey_fg))
+                    .context(IoSnafu {})?
+                    .queue(Print(format!(" {:padding$}{keys}  ", "")))
+                    .context(IoSnafu {})?
+                    .queue(SetForegroundColor(config.mode_switch_mode_name_fg))
+                    .context(IoSnafu {})?
+                    .queue(Print(description))
+                    .context(IoSnafu {})?;
+            }
+        }
+
+        buffer.queue(EnableLineWrap).context(IoSnafu {})?;
+        Ok(())
+    }
+
+    /// Draw the given text on the last row of the screen with the given
+    /// (rows, columns), hiding the data underneath.
+    fn draw_prompt(
+        &mut self,
+        buffer: &mut Vec<u8>,
+        text: &str,
+        (rows, cols): (usize, usize),
+    ) -> Result<(), RunError> {
+        let rows = rows as u16;
+
+        buffer
+            .queue(DisableLineWrap)
+            .context(IoSnafu {})?
+            .queue(MoveTo(0, rows.saturating_sub(1)))
+            .context(IoSnafu {})?
+            .queue(ResetColor)
+            .context(IoSnafu {})?
+            .queue(SetAttribute(Attribute::Reset))
+            .context(IoSnafu {})?
+            .queue(Print(format!("{text:cols$}")))
+            .context(IoSnafu {})?
+            .queue(EnableLineWrap)
+            .context(IoSnafu {})?;
+
+        Ok(())
+    }
+
+    /// Draw the given text in reverse video at the end of the last row of
+    /// the screen with the given (rows, columns).
+    fn draw_status(
+        &mut self,
+        buffer: &mut Vec<u8>,
+        text: &str,
+        (rows, cols): (usize, usize),
+    ) -> Result<(), RunError> {
+        let rows = rows as u16;
+        let start_col = cols.saturating_sub(display_width(text));
+
+        buffer
+            .queue(DisableLineWrap)
+            .context(IoSnafu {})?
+            .queue(MoveTo(start_col as u16, rows.saturating_sub(1)))
+            .context(IoSnafu {})?
+            .queue(ResetColor)
+            .context(IoSnafu {})?
+            .queue(SetAttribute(Attribute::Reverse))
+            .context(IoSnafu {})?
+            .queue(Print(text))
+            .context(IoSnafu {})?
+            .queue(SetAttribute(Attribute::Reset))
+            .context(IoSnafu {})?
+            .queue(EnableLineWrap)
+            .context(IoSnafu {})?;
+
+        Ok(())
+    }
+
+    /// Draw the given (name, value) entries in reverse video at the top
+    /// right corner of the screen with the given (rows, columns), one per row.
+    /// The values too long for the screen are cut off.
+    fn draw_debug_overlay(
+        &mut self,
+        buffer: &mut Vec<u8>,
+        entries: &[(String, String)],
+        (rows, cols): (usize, usize),
+    ) -> Result<(), RunError> {
+        let names_width = entries
+            .iter()
+            .map(|(name, _)| display_width(name))
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|(name, value)| {
+                let padding = names_width - display_width(name);
+                format!(" {name}{:padding$}  {value} ", "")
+            })
+            .collect();
+        let width = lines
+            .iter()
+            .map(|line| display_width(line))
+            .max()
+            .unwrap_or(0)
+            .min(cols);
+        let start_col = (cols - width) as u16;
+
+        buffer.queue(DisableLineWrap).context(IoSnafu {})?;
+
+        for (row, line) in lines.iter().take(rows).enumerate() {
+            let padding = width.saturating_sub(display_width(line));
+            buffer
+                .queue(MoveTo(start_col, row as u16))
+                .context(IoSnafu {})?
+                .queue(ResetColor)
+                .context(IoSnafu {})?
+                .queue(SetAttribute(Attribute::Reverse))
+                .context(IoSnafu {})?
+                .queue(Print(format!("{line}{:padding$}", "")))
+                .context(IoSnafu {})?
+                .queue(SetAttribute(Attribute::Reset))
+                .context(IoSnafu {})?;
+        }
+
+        buffer.queue(EnableLineWrap).context(IoSnafu {})?;
+        Ok(())
+    }
+
     /// Update the terminal style when switching in and out of styled segments
     fn update_style(
         &self,
@@ -272,10 +502,17 @@ impl<T: Write + ?Sized> Renderer<T> {
     }
 
     /// Prepare the terminal for the use by the application.
-    pub fn initialize_terminal(&mut self) -> std::io::Result<()> {
+    ///
+    /// If `capture_mouse` is true, the mouse events are reported to the
+    /// application instead of being handled by the terminal. Prefer
+    /// [super::TerminalGuard], which makes sure the terminal is restored.
+    pub fn initialize_terminal(&mut self, capture_mouse: bool) -> std::io::Result<()> {
         self.output
             .queue(cursor::Hide)?
             .queue(EnterAlternateScreen)?;
+        if capture_mouse {
+            self.output.queue(EnableMouseCapture)?;
+        }
         enable_raw_mode()?;
 
         Ok(())
@@ -287,6 +524,7 @@ impl<T: Write + ?Sized> Renderer<T> {
     /// the terminal in an invalid, unusable state.
     pub fn uninitialize_terminal(&mut self) -> std::io::Result<()> {
         self.output
+            .queue(DisableMouseCapture)?
             .queue(cursor::Show)?
             .queue(LeaveAlternateScreen)?;
         disable_raw_mode()?;
@@ -294,3 +532,111 @@ impl<T: Write + ?Sized> Renderer<T> {
         Ok(())
     }
 }
+
+/// Get the style of every byte of the data with the given length, as given
+/// by the styled segments. Where the segments overlap, the later ones win.
+///
+/// This keeps drawing linear in the size of the data and the segments,
+/// instead of looking for the segment of every character.
+pub(crate) fn get_style_map(
+    styled_segments: &[StyledSegment],
+    data_length: usize,
+) -> Vec<Option<TextStyle>> {
+    let mut styles = vec![None; data_length];
+
+    for segment in styled_segments {
+        let end = (segment.start + segment.length).min(data_length);
+        if let Some(segment_styles) = styles.get_mut(segment.start..end) {
+            segment_styles.fill(Some(segment.style));
+        }
+    }
+
+    styles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{configuration::Config, rendering::TestScreen};
+
+    #[test]
+    fn render_draws_mode_selection_dialog_on_test_screen() {
+        let mut renderer = Renderer::new(TestScreen::new(10, 80));
+        let modes = vec![('u', "URL".to_string()), ('p', "path".to_string())];
+
+        renderer
+            .render(
+                "some data\n",
+                &[
+                    DrawInstruction::Data,
+                    DrawInstruction::ModeSelectionDialog(modes),
+                ],
+                &Config::default(),
+            )
+            .unwrap();
+
+        let output = renderer.output.output_text();
+        assert!(output.contains("some data"));
+        assert!(output.contains(" [u] "));
+        assert!(output.contains("URL"));
+        assert!(output.contains(" [p] "));
+        assert!(output.contains("path"));
+        // The dialog covers the rows down to the last one
+        let start_col = 80 - Config::default().mode_switch_width as u16;
+        let mut expected = vec![];
+        expected.queue(MoveTo(start_col, 9)).unwrap();
+        assert!(output.contains(&*String::from_utf8_lossy(&expected)));
+    }
+
+    #[test]
+    fn render_skips_mode_selection_dialog_on_narrow_screen() {
+        let mut renderer = Renderer::new(TestScreen::new(10, 20));
+        let modes = vec![('u', "URL".to_string())];
+
+        renderer
+            .render(
+                "some data\n",
+                &[
+                    DrawInstruction::Data,
+                    DrawInstruction::ModeSelectionDialog(modes),
+                ],
+                &Config::default(),
+            )
+            .unwrap();
+
+        let output = renderer.output.output_text();
+        assert!(output.contains("some data"));
+        assert!(!output.contains(" [u] "));
+    }
+
+    #[test]
+    fn render_draws_status_at_end_of_last_row() {
+        let mut renderer = Renderer::new(TestScreen::new(5, 40));
+
+        renderer
+            .render(
+                "some data\n",
+                &[
+                    DrawInstruction::Data,
+                    DrawInstruction::Status("[3/7]".to_string()),
+                ],
+                &Config::default(),
+            )
+            .unwrap();
+
+        let mut expected = vec![];
+        expected
+            .queue(MoveTo(35, 4))
+            .unwrap()
+            .queue(ResetColor)
+            .unwrap()
+            .queue(SetAttribute(Attribute::Reverse))
+            .unwrap()
+            .queue(Print("[3/7]"))
+            .unwrap();
+        assert!(renderer
+            .output
+            .output_text()
+            .contains(&*String::from_utf8_lossy(&expected)));
+    }
+}
@@ -0,0 +1,66 @@
+//! The screens the [super::Renderer] can draw to.
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+use crossterm::terminal;
+
+/// Output that is shown on a screen of a known size.
+pub trait Screen: Write {
+    /// Get the size of the screen as (rows, columns).
+    fn size(&self) -> io::Result<(usize, usize)>;
+}
+
+/// The terminal opened as a file, e.g. /dev/tty.
+impl Screen for File {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        let (cols, rows) = terminal::size()?;
+
+        Ok((rows as usize, cols as usize))
+    }
+}
+
+/// A screen of a fixed size that collects everything written to it,
+/// for drawing without a terminal, e.g. in tests and benchmarks.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TestScreen {
+    /// Everything written to the screen so far.
+    pub output: Vec<u8>,
+    /// The number of rows of the screen.
+    pub rows: usize,
+    /// The number of columns of the screen.
+    pub cols: usize,
+}
+
+impl TestScreen {
+    /// Create an empty screen with the given size.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            output: vec![],
+            rows,
+            cols,
+        }
+    }
+
+    /// Get everything written to the screen so far as text.
+    pub fn output_text(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+}
+
+impl Write for TestScreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Screen for TestScreen {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        Ok((self.rows, self.cols))
+    }
+}
@@ -0,0 +1,68 @@
+//! Restoring the terminal however the drawing ends.
+use std::{
+    io,
+    ops::{Deref, DerefMut},
+};
+
+use log::warn;
+
+use super::{Renderer, Screen};
+
+/// The [Renderer] with the terminal initialized for drawing, see
+/// [Renderer::initialize_terminal], which is restored once the guard is
+/// dropped, including on early returns and panics.
+///
+/// The guard can be used in place of the renderer it guards.
+pub struct TerminalGuard<'a, T: Screen + ?Sized> {
+    renderer: &'a mut Renderer<T>,
+    /// Whether the terminal was already restored with [TerminalGuard::restore].
+    restored: bool,
+}
+
+impl<'a, T: Screen + ?Sized> TerminalGuard<'a, T> {
+    /// Initialize the terminal of the given renderer, capturing the mouse
+    /// if `capture_mouse` is true.
+    pub fn new(renderer: &'a mut Renderer<T>, capture_mouse: bool) -> io::Result<Self> {
+        // Restore whatever was initialized before the failure
+        let guard = Self {
+            renderer,
+            restored: false,
+        };
+        guard.renderer.initialize_terminal(capture_mouse)?;
+
+        Ok(guard)
+    }
+
+    /// Restore the terminal, like dropping the guard does, but returning
+    /// the error instead of only logging it.
+    pub fn restore(mut self) -> io::Result<()> {
+        self.restored = true;
+        self.renderer.uninitialize_terminal()
+    }
+}
+
+impl<T: Screen + ?Sized> Deref for TerminalGuard<'_, T> {
+    type Target = Renderer<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.renderer
+    }
+}
+
+impl<T: Screen + ?Sized> DerefMut for TerminalGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.renderer
+    }
+}
+
+impl<T: Screen + ?Sized> Drop for TerminalGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.restored {
+            return;
+        }
+
+        if let Err(error) = self.renderer.uninitialize_terminal() {
+            warn!("Could not uninitialize the terminal: {error}");
+        }
+    }
+}
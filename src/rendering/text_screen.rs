@@ -0,0 +1,249 @@
+//! Rendering to a string instead of the terminal.
+use textwrap::core::display_width;
+
+use crate::{configuration, error::RunError};
+
+use super::{DrawInstruction, Renderer, TestScreen};
+
+/// Render the given data and draw instructions the same way as
+/// [Renderer::render] does on a screen with the given (rows, columns).
+///
+/// If `ansi` is true, the output is the text with the escape sequences
+/// written to the terminal. Otherwise, it is the text shown on the screen,
+/// one line per row, without the trailing spaces.
+pub fn render_to_string(
+    data: &str,
+    draw_instructions: &[DrawInstruction],
+    (rows, cols): (usize, usize),
+    ansi: bool,
+    config: &configuration::Config,
+) -> Result<String, RunError> {
+    let mut renderer = Renderer::new(TestScreen::new(rows, cols));
+    renderer.render(data, draw_instructions, config)?;
+
+    let output = renderer.output.output_text();
+    match ansi {
+        true => Ok(output),
+        false => Ok(TextScreen::new(rows, cols).show(&output)),
+    }
+}
+
+/// The contents of a screen after the output of the renderer is shown on it,
+/// understanding just the escape sequences the renderer writes itself.
+struct TextScreen {
+    cells: Vec<Vec<Option<char>>>,
+    cols: usize,
+    row: usize,
+    col: usize,
+    wrap: bool,
+}
+
+impl TextScreen {
+    fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            cells: vec![vec![Some(' '); cols]; rows],
+            cols,
+            row: 0,
+            col: 0,
+            wrap: true,
+        }
+    }
+
+    /// Show the given output and get the text on the screen.
+    fn show(mut self, output: &str) -> String {
+        let mut chars = output.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            match char {
+                '\x1b' => match chars.next() {
+                    Some('[') => {
+                        let mut parameters = String::new();
+                        for char in chars.by_ref() {
+                            if ('\x40'..='\x7e').contains(&char) {
+                                self.handle_control_sequence(&parameters, char);
+                                break;
+                            }
+                            parameters.push(char);
+                        }
+                    }
+                    // Operating system commands, e.g. hyperlinks
+                    Some(']') => {
+                        while let Some(char) = chars.next() {
+                            if char == '\x07'
+                                || (char == '\x1b' && chars.next_if_eq(&'\\').is_some())
+                            {
+                                break;
+                            }
+                        }
+                    }
+                    _ => (),
+                },
+                '\r' => self.col = 0,
+                '\n' => self.row += 1,
+                char if char.is_control() => (),
+                char => self.print(char),
+            }
+        }
+
+        self.cells
+            .iter()
+            .map(|row| {
+                let line: String = row.iter().flatten().collect();
+                format!("{}\n", line.trim_end())
+            })
+            .collect()
+    }
+
+    fn handle_control_sequence(&mut self, parameters: &str, command: char) {
+        match (command, parameters) {
+            ('H', _) => {
+                let mut position = parameters
+                    .split(';')
+                    .map(|number| number.parse::<usize>().unwrap_or(1));
+                self.row = position.next().unwrap_or(1).saturating_sub(1);
+                self.col = position.next().unwrap_or(1).saturating_sub(1);
+            }
+            ('J', "2") => self.cells.iter_mut().for_each(|row| row.fill(Some(' '))),
+            ('h', "?7") => self.wrap = true,
+            ('l', "?7") => self.wrap = false,
+            // Styles do not change the text
+            _ => (),
+        }
+    }
+
+    fn print(&mut self, char: char) {
+        let width = display_width(char.encode_utf8(&mut [0; 4]));
+        if width == 0 || width > self.cols {
+            return;
+        }
+
+        // Like in a terminal, the line only wraps once there is something
+        // to print past its end
+        if self.col + width > self.cols {
+            match self.wrap {
+                true => {
+                    self.row += 1;
+                    self.col = 0;
+                }
+                false => self.col = self.cols - width,
+            }
+        }
+
+        let Some(row) = self.cells.get_mut(self.row) else {
+            return;
+        };
+        row[self.col] = Some(char);
+        // The other columns of a wide character are taken by it
+        for cell in &mut row[self.col + 1..self.col + width] {
+            *cell = None;
+        }
+        self.col += width;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        configuration::Config,
+        rendering::{DataOverlay, OverlayKind, StyledSegment, TextStyle},
+    };
+    use crossterm::style::Color;
+
+    #[test]
+    fn render_to_string_shows_overlays_without_escapes() {
+        let style = TextStyle {
+            foreground: Color::Red,
+            background: Color::Reset,
+        };
+        let instructions = [DrawInstruction::StyledData {
+            styled_segments: vec![StyledSegment {
+                start: 6,
+                length: 5,
+                style,
+            }],
+            text_overlays: vec![DataOverlay {
+                text: "ab".to_string(),
+                location: 6,
+                kind: OverlayKind::Replace,
+            }],
+        }];
+
+        let text = render_to_string(
+            "hello world\nagain\n",
+            &instructions,
+            (3, 20),
+            false,
+            &Config::default(),
+        )
+        .unwrap();
+
+        assert_eq!(text, "hello abrld\nagain\n\n");
+    }
+
+    #[test]
+    fn render_to_string_keeps_escapes_when_requested() {
+        let text = render_to_string(
+            "hello",
+            &[DrawInstruction::Data],
+            (3, 20),
+            true,
+            &Config::default(),
+        )
+        .unwrap();
+
+        assert!(text.contains("hello"));
+        assert!(text.contains("\x1b["));
+    }
+
+    #[test]
+    fn render_to_string_wraps_long_lines() {
+        let text = render_to_string(
+            "0123456789\nabc\n",
+            &[DrawInstruction::Data],
+            (4, 4),
+            false,
+            &Config::default(),
+        )
+        .unwrap();
+
+        assert_eq!(text, "0123\n4567\n89\nabc\n");
+    }
+
+    #[test]
+    fn render_to_string_draws_status_on_last_row() {
+        let text = render_to_string(
+            "data\n",
+            &[
+                DrawInstruction::Data,
+                DrawInstruction::Status("[1/2]".to_string()),
+            ],
+            (3, 20),
+            false,
+            &Config::default(),
+        )
+        .unwrap();
+
+        assert_eq!(text, "data\n\n               [1/2]\n");
+    }
+
+    #[test]
+    fn render_to_string_draws_debug_overlay_at_top_right() {
+        let text = render_to_string(
+            "data\n",
+            &[
+                DrawInstruction::Data,
+                DrawInstruction::DebugOverlay(vec![
+                    ("hits".to_string(), "3".to_string()),
+                    ("mode".to_string(), "urls".to_string()),
+                ]),
+            ],
+            (3, 20),
+            false,
+            &Config::default(),
+        )
+        .unwrap();
+
+        assert_eq!(text, "data     hits  3\n         mode  urls\n\n");
+    }
+}
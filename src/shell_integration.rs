@@ -0,0 +1,33 @@
+//! Shell code that lets the shells run mless with a key.
+use clap::ValueEnum;
+
+/// The shells that mless can be integrated with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Zsh,
+    Bash,
+    Fish,
+}
+
+/// Get the shell code that binds a key to selecting text from the tmux
+/// pane with mless and inserting it at the cursor in the given shell.
+pub fn get_init_script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Zsh => include_str!("shell_integration/mless.zsh"),
+        Shell::Bash => include_str!("shell_integration/mless.bash"),
+        Shell::Fish => include_str!("shell_integration/mless.fish"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Shell::Zsh; "for_zsh")]
+    #[test_case(Shell::Bash; "for_bash")]
+    #[test_case(Shell::Fish; "for_fish")]
+    fn init_script_selects_from_tmux_pane(shell: Shell) {
+        assert!(get_init_script(shell).contains("mless --tmux-pane"));
+    }
+}
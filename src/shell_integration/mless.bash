@@ -0,0 +1,23 @@
+# Shell integration of mless for bash, set up by adding the following
+# line to ~/.bashrc:
+#
+#   eval "$(mless --init bash)"
+#
+# Pressing Alt-m inside tmux selects text from the scrollback of the
+# current pane and inserts it at the cursor. To use another key, bind
+# the _mless_insert_selection function to it with bind -x after the
+# line above.
+
+_mless_insert_selection() {
+    if [[ -z "$TMUX" ]]; then
+        echo "mless: the scrollback can only be captured inside tmux" >&2
+        return 1
+    fi
+
+    local selection
+    selection="$(mless --tmux-pane </dev/tty)"
+    READLINE_LINE="${READLINE_LINE:0:READLINE_POINT}${selection}${READLINE_LINE:READLINE_POINT}"
+    READLINE_POINT=$((READLINE_POINT + ${#selection}))
+}
+
+bind -x '"\em": _mless_insert_selection'
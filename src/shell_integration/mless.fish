@@ -0,0 +1,24 @@
+# Shell integration of mless for fish, set up by adding the following
+# line to ~/.config/fish/config.fish:
+#
+#   mless --init fish | source
+#
+# Pressing Alt-m inside tmux selects text from the scrollback of the
+# current pane and inserts it at the cursor. To use another key, bind
+# the _mless_insert_selection function to it after the line above.
+
+function _mless_insert_selection
+    if not set -q TMUX
+        echo "mless: the scrollback can only be captured inside tmux" >&2
+        commandline -f repaint
+        return 1
+    end
+
+    set -l selection (mless --tmux-pane </dev/tty | string collect)
+    if test -n "$selection"
+        commandline -i -- $selection
+    end
+    commandline -f repaint
+end
+
+bind \em _mless_insert_selection